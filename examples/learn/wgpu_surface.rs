@@ -27,6 +27,8 @@ struct CubeResources {
     bind_group: wgpu::BindGroup,
     vert_buf: wgpu::Buffer,
     vertex_count: u32,
+    // the device generation these were created for; rebuilt after a device loss.
+    device_generation: u64,
 }
 
 struct SurfaceExample {
@@ -124,7 +126,8 @@ fn main() {
                     let (pipeline, uniform_buf, bind_group, vert_buf, vertex_count) =
                         RESOURCES.with(|r| {
                             let mut r = r.borrow_mut();
-                            if r.is_none() {
+                            let device_generation = surface_thread.device_generation();
+                            if r.as_ref().is_none_or(|res| res.device_generation != device_generation) {
                                 // shader and pipeline setup
                                 let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
                                     label: Some("CubeShader"),
@@ -345,6 +348,7 @@ fn fs_main(in: VSOut) -> @location(0) vec4<f32> {
                                     bind_group: bind_group.clone(),
                                     vert_buf: vertex_buf.clone(),
                                     vertex_count,
+                                    device_generation,
                                 });
                             }
                             let res = r.as_ref().unwrap();
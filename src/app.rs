@@ -141,9 +141,10 @@ impl Application {
         ))
     }
 
-    /// Build an app in headless mode. This prevents opening windows,
-    /// but makes it possible to run an application in an context like
-    /// SSH, where GUI applications are not allowed.
+    /// Build an app in headless mode. Windows are rendered into offscreen
+    /// textures instead of being shown, which makes it possible to run an
+    /// application in a context like SSH or CI, where no display server is
+    /// available.
     pub fn headless() -> Self {
         Self(App::new_app(
             current_platform(true),
@@ -617,6 +618,7 @@ pub struct App {
     pub(crate) keystroke_observers: SubscriberSet<(), KeystrokeObserver>,
     pub(crate) keystroke_interceptors: SubscriberSet<(), KeystrokeObserver>,
     pub(crate) keyboard_layout_observers: SubscriberSet<(), Handler>,
+    pub(crate) display_observers: SubscriberSet<(), Handler>,
     pub(crate) release_listeners: SubscriberSet<EntityId, ReleaseListener>,
     pub(crate) global_observers: SubscriberSet<TypeId, Handler>,
     pub(crate) quit_observers: SubscriberSet<(), QuitHandler>,
@@ -635,6 +637,7 @@ pub struct App {
     pub(crate) inspector_element_registry: InspectorElementRegistry,
     #[cfg(any(test, feature = "test-support", debug_assertions))]
     pub(crate) name: Option<&'static str>,
+    next_cursor_image_id: u32,
     quit_mode: QuitMode,
     quitting: bool,
 }
@@ -696,6 +699,7 @@ impl App {
                 keystroke_observers: SubscriberSet::new(),
                 keystroke_interceptors: SubscriberSet::new(),
                 keyboard_layout_observers: SubscriberSet::new(),
+                display_observers: SubscriberSet::new(),
                 global_observers: SubscriberSet::new(),
                 quit_observers: SubscriberSet::new(),
                 restart_observers: SubscriberSet::new(),
@@ -708,6 +712,7 @@ impl App {
                 inspector_renderer: None,
                 #[cfg(any(feature = "inspector", debug_assertions))]
                 inspector_element_registry: InspectorElementRegistry::default(),
+                next_cursor_image_id: 0,
                 quit_mode: QuitMode::default(),
                 quitting: false,
 
@@ -733,6 +738,18 @@ impl App {
             }
         }));
 
+        platform.on_displays_changed(Box::new({
+            let app = Rc::downgrade(&app);
+            move || {
+                if let Some(app) = app.upgrade() {
+                    let cx = &mut app.borrow_mut();
+                    cx.display_observers
+                        .clone()
+                        .retain(&(), move |callback| (callback)(cx));
+                }
+            }
+        }));
+
         platform.on_quit(Box::new({
             let cx = app.clone();
             move || {
@@ -795,6 +812,23 @@ impl App {
         subscription
     }
 
+    /// Invokes a handler when a display is plugged in or unplugged, or when the bounds or scale
+    /// of a display change.
+    pub fn on_displays_changed<F>(&self, mut callback: F) -> Subscription
+    where
+        F: 'static + FnMut(&mut App),
+    {
+        let (subscription, activate) = self.display_observers.insert(
+            (),
+            Box::new(move |cx| {
+                callback(cx);
+                true
+            }),
+        );
+        activate();
+        subscription
+    }
+
     /// Gracefully quit the application via the platform's standard routine.
     pub fn quit(&self) {
         self.platform.quit();
@@ -1078,6 +1112,29 @@ impl App {
         self.platform.read_from_clipboard()
     }
 
+    /// Writes data to the primary selection, which middle-clicking pastes from.
+    /// Only available on Linux and FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn write_to_primary(&self, item: ClipboardItem) {
+        self.platform.write_to_primary(item)
+    }
+
+    /// Reads data from the primary selection.
+    /// Only available on Linux and FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.platform.read_from_primary()
+    }
+
+    /// Registers an image to show as the cursor, and returns the style that shows it.
+    /// Platforms that can't show custom cursors show the arrow instead.
+    pub fn register_cursor_image(&mut self, image: crate::CursorImage) -> CursorStyle {
+        let id = crate::CursorImageId(self.next_cursor_image_id);
+        self.next_cursor_image_id += 1;
+        self.platform.register_cursor_image(id, image);
+        CursorStyle::Image(id)
+    }
+
     /// Writes credentials to the platform keychain.
     pub fn write_credentials(
         &self,
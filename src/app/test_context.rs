@@ -3,11 +3,13 @@ use crate::{
     BackgroundExecutor, BorrowAppContext, Bounds, Capslock, ClipboardItem, DrawPhase, Drawable,
     Element, Empty, EventEmitter, ForegroundExecutor, Global, InputEvent, Keystroke, Modifiers,
     ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels,
-    Platform, Point, Render, Result, Size, Task, TestDispatcher, TestPlatform, TestWindow,
-    TextSystem, VisualContext, Window, WindowBounds, WindowHandle, WindowOptions, app::GpuiMode,
+    Platform, Point, Render, Result, SceneDump, Size, SnapshotTolerance, Task, TestDispatcher,
+    TestPlatform, TestWindow, TextSystem, VisualContext, Window, WindowBounds, WindowHandle,
+    WindowOptions, app::GpuiMode, platform::assert_snapshot,
 };
 use anyhow::{anyhow, bail};
 use futures::{Stream, StreamExt, channel::oneshot};
+use image::RgbaImage;
 use rand::{SeedableRng, rngs::StdRng};
 use std::{
     cell::RefCell, future::Future, ops::Deref, path::PathBuf, rc::Rc, sync::Arc, time::Duration,
@@ -151,6 +153,14 @@ impl TestAppContext {
         self.app.borrow_mut().mode = GpuiMode::Test { skip_drawing: true };
     }
 
+    /// Render windows opened after this call with the wgpu renderer, so their frames can be
+    /// captured with [`VisualTestContext::capture_frame`] and compared against golden images
+    /// with [`VisualTestContext::assert_snapshot`]. Uses a software adapter when no GPU is
+    /// available.
+    pub fn enable_rendering(&mut self) -> Result<()> {
+        self.test_platform.enable_rendering()
+    }
+
     /// Create a single TestAppContext, for non-multi-client tests
     pub fn single() -> Self {
         let dispatcher = TestDispatcher::new(StdRng::seed_from_u64(0));
@@ -306,6 +316,19 @@ impl TestAppContext {
         self.test_platform.read_from_clipboard()
     }
 
+    /// Simulates writing to the primary selection.
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn write_to_primary(&self, item: ClipboardItem) {
+        self.test_platform.write_to_primary(item)
+    }
+
+    /// Simulates reading from the primary selection.
+    /// This will return the most recent value from `write_to_primary`.
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.test_platform.read_from_primary()
+    }
+
     /// Simulates choosing a File in the platform's "Open" dialog.
     pub fn simulate_new_path_selection(
         &self,
@@ -820,6 +843,67 @@ impl VisualTestContext {
         self.update(|window, _| window.rendered_frame.debug_bounds.get(selector).copied())
     }
 
+    /// Renders the window's current frame and reads it back.
+    ///
+    /// Requires [`TestAppContext::enable_rendering`] to be called before the window is opened.
+    /// Text is not rendered, as tests don't load any fonts.
+    pub fn capture_frame(&mut self) -> Result<RgbaImage> {
+        self.run_until_parked();
+        let receiver = self
+            .update(|window, _| {
+                window
+                    .platform_window
+                    .capture_frame(&window.rendered_frame.scene)
+            })
+            .ok_or_else(|| {
+                anyhow!("rendering is disabled, call TestAppContext::enable_rendering first")
+            })?;
+        pollster::block_on(receiver)?
+    }
+
+    /// Returns the primitives of the window's current frame, see [`Window::scene_dump`].
+    pub fn scene_dump(&mut self) -> SceneDump {
+        self.run_until_parked();
+        self.update(|window, _| window.scene_dump())
+    }
+
+    /// Renders a dump taken with [`VisualTestContext::scene_dump`] in place of the window's
+    /// current frame, and reads it back.
+    ///
+    /// Sprites refer to tiles in the window's atlas, so they're only drawn correctly when
+    /// replaying a dump taken from the same window.
+    pub fn replay_scene_dump(&mut self, dump: &SceneDump) -> Result<RgbaImage> {
+        let scene = dump.to_scene();
+        let receiver = self
+            .update(|window, _| window.platform_window.capture_frame(&scene))
+            .ok_or_else(|| {
+                anyhow!("rendering is disabled, call TestAppContext::enable_rendering first")
+            })?;
+        pollster::block_on(receiver)?
+    }
+
+    /// Captures the window's current frame and compares it against the golden image
+    /// `snapshots/<name>.png` of the crate under test, using the default tolerance.
+    ///
+    /// Set `GPUI_UPDATE_SNAPSHOTS=1` to write the captured frame as the new golden image. A
+    /// missing golden image is written too, but the assertion fails so it gets looked at.
+    #[track_caller]
+    pub fn assert_snapshot(&mut self, name: &str) {
+        self.assert_snapshot_with_tolerance(name, SnapshotTolerance::default())
+    }
+
+    /// Like [`VisualTestContext::assert_snapshot`], but allows the frame to differ from the
+    /// golden image by the given tolerance.
+    #[track_caller]
+    pub fn assert_snapshot_with_tolerance(&mut self, name: &str, tolerance: SnapshotTolerance) {
+        let frame = self
+            .capture_frame()
+            .unwrap_or_else(|error| panic!("failed to capture snapshot {name}: {error:#}"));
+        if let Err(error) = assert_snapshot(name, &frame, tolerance) {
+            panic!("{error:#}");
+        }
+    }
+
     /// Draw an element to the window. Useful for simulating events or actions
     pub fn draw<E>(
         &mut self,
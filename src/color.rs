@@ -10,6 +10,8 @@ use std::{
     hash::{Hash, Hasher},
 };
 
+use crate::Point;
+
 /// Convert an RGB hex color code number to a color type
 pub fn rgb(hex: u32) -> Rgba {
     let [_, r, g, b] = hex.to_be_bytes().map(|b| (b as f32) / 255.0);
@@ -658,6 +660,8 @@ pub(crate) enum BackgroundTag {
     Solid = 0,
     LinearGradient = 1,
     PatternSlash = 2,
+    RadialGradient = 3,
+    ConicGradient = 4,
 }
 
 /// A color space for color interpolation.
@@ -684,7 +688,11 @@ impl Display for ColorSpace {
     }
 }
 
-/// A background color, which can be either a solid color or a linear gradient.
+/// The maximum number of color stops a gradient [`Background`] can hold. Backgrounds hold their
+/// stops inline, so they stay `Copy`, and gradients built with more stops drop the rest.
+pub const MAX_GRADIENT_STOPS: usize = 8;
+
+/// A background color, which can be either a solid color or a linear, radial or conic gradient.
 #[derive(Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[repr(C)]
 pub struct Background {
@@ -692,9 +700,10 @@ pub struct Background {
     pub(crate) color_space: ColorSpace,
     pub(crate) solid: Hsla,
     pub(crate) gradient_angle_or_pattern_height: f32,
-    pub(crate) colors: [LinearColorStop; 2],
-    /// Padding for alignment for repr(C) layout.
-    pub(crate) pad: u32,
+    pub(crate) stop_count: u32,
+    /// The center of a radial or conic gradient, relative to the bounds it fills.
+    pub(crate) gradient_center: Point<f32>,
+    pub(crate) colors: [LinearColorStop; MAX_GRADIENT_STOPS],
 }
 
 impl std::fmt::Debug for Background {
@@ -704,8 +713,26 @@ impl std::fmt::Debug for Background {
             BackgroundTag::LinearGradient => {
                 write!(
                     f,
-                    "LinearGradient({}, {:?}, {:?})",
-                    self.gradient_angle_or_pattern_height, self.colors[0], self.colors[1]
+                    "LinearGradient({}, {:?})",
+                    self.gradient_angle_or_pattern_height,
+                    self.gradient_stops()
+                )
+            }
+            BackgroundTag::RadialGradient => {
+                write!(
+                    f,
+                    "RadialGradient({:?}, {:?})",
+                    self.gradient_center,
+                    self.gradient_stops()
+                )
+            }
+            BackgroundTag::ConicGradient => {
+                write!(
+                    f,
+                    "ConicGradient({}, {:?}, {:?})",
+                    self.gradient_angle_or_pattern_height,
+                    self.gradient_center,
+                    self.gradient_stops()
                 )
             }
             BackgroundTag::PatternSlash => {
@@ -727,8 +754,9 @@ impl Default for Background {
             solid: Hsla::default(),
             color_space: ColorSpace::default(),
             gradient_angle_or_pattern_height: 0.0,
-            colors: [LinearColorStop::default(), LinearColorStop::default()],
-            pad: 0,
+            stop_count: 0,
+            gradient_center: Point::default(),
+            colors: [LinearColorStop::default(); MAX_GRADIENT_STOPS],
         }
     }
 }
@@ -767,15 +795,87 @@ pub fn linear_gradient(
     from: impl Into<LinearColorStop>,
     to: impl Into<LinearColorStop>,
 ) -> Background {
-    Background {
-        tag: BackgroundTag::LinearGradient,
+    linear_gradient_with_stops(angle, [from.into(), to.into()])
+}
+
+/// Creates a LinearGradient background color with any number of color stops.
+///
+/// The stops are expected in increasing order of percentage. Only the first
+/// [`MAX_GRADIENT_STOPS`] stops are used, the rest are dropped with a warning. See
+/// [`linear_gradient`] for the meaning of `angle`.
+pub fn linear_gradient_with_stops(
+    angle: f32,
+    stops: impl IntoIterator<Item = impl Into<LinearColorStop>>,
+) -> Background {
+    gradient(
+        BackgroundTag::LinearGradient,
+        angle,
+        Point::default(),
+        stops,
+    )
+}
+
+/// Creates a RadialGradient background color.
+///
+/// The gradient is an ellipse that follows the aspect ratio of the bounds it fills, and reaches
+/// its last stop at the corner farthest from the `center`. The `center` is relative to those
+/// bounds, so `point(0.5, 0.5)` is the middle.
+///
+/// Only the first [`MAX_GRADIENT_STOPS`] stops are used, the rest are dropped with a warning.
+///
+/// <https://developer.mozilla.org/en-US/docs/Web/CSS/gradient/radial-gradient>
+pub fn radial_gradient(
+    center: Point<f32>,
+    stops: impl IntoIterator<Item = impl Into<LinearColorStop>>,
+) -> Background {
+    gradient(BackgroundTag::RadialGradient, 0.0, center, stops)
+}
+
+/// Creates a ConicGradient background color.
+///
+/// The color stops are placed around the `center`, starting at `angle` degrees from the top and
+/// going clockwise, with a percentage of `1.0` being a full turn. The `center` is relative to the
+/// bounds the gradient fills, so `point(0.5, 0.5)` is the middle.
+///
+/// Only the first [`MAX_GRADIENT_STOPS`] stops are used, the rest are dropped with a warning.
+///
+/// <https://developer.mozilla.org/en-US/docs/Web/CSS/gradient/conic-gradient>
+pub fn conic_gradient(
+    angle: f32,
+    center: Point<f32>,
+    stops: impl IntoIterator<Item = impl Into<LinearColorStop>>,
+) -> Background {
+    gradient(BackgroundTag::ConicGradient, angle, center, stops)
+}
+
+fn gradient(
+    tag: BackgroundTag,
+    angle: f32,
+    center: Point<f32>,
+    stops: impl IntoIterator<Item = impl Into<LinearColorStop>>,
+) -> Background {
+    let mut background = Background {
+        tag,
         gradient_angle_or_pattern_height: angle,
-        colors: [from.into(), to.into()],
+        gradient_center: center,
         ..Default::default()
+    };
+    let mut stop_count = 0;
+    for stop in stops {
+        if stop_count == MAX_GRADIENT_STOPS {
+            log::warn!(
+                "gradients support at most {MAX_GRADIENT_STOPS} color stops, ignoring the rest"
+            );
+            break;
+        }
+        background.colors[stop_count] = stop.into();
+        stop_count += 1;
     }
+    background.stop_count = stop_count as u32;
+    background
 }
 
-/// A color stop in a linear gradient.
+/// A color stop in a gradient.
 ///
 /// <https://developer.mozilla.org/en-US/docs/Web/CSS/gradient/linear-gradient#linear-color-stop>
 #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -820,18 +920,25 @@ impl Background {
     pub fn opacity(&self, factor: f32) -> Self {
         let mut background = *self;
         background.solid = background.solid.opacity(factor);
-        background.colors = [
-            self.colors[0].opacity(factor),
-            self.colors[1].opacity(factor),
-        ];
+        background.colors = self.colors.map(|stop| stop.opacity(factor));
         background
     }
 
+    /// The color stops of a gradient background.
+    pub(crate) fn gradient_stops(&self) -> &[LinearColorStop] {
+        &self.colors[..self.stop_count as usize]
+    }
+
     /// Returns whether the background color is transparent.
     pub fn is_transparent(&self) -> bool {
         match self.tag {
             BackgroundTag::Solid => self.solid.is_transparent(),
-            BackgroundTag::LinearGradient => self.colors.iter().all(|c| c.color.is_transparent()),
+            BackgroundTag::LinearGradient
+            | BackgroundTag::RadialGradient
+            | BackgroundTag::ConicGradient => self
+                .gradient_stops()
+                .iter()
+                .all(|c| c.color.is_transparent()),
             BackgroundTag::PatternSlash => self.solid.is_transparent(),
         }
     }
@@ -861,6 +968,7 @@ mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::point;
 
     #[test]
     fn test_deserialize_three_value_hex_to_rgba() {
@@ -931,4 +1039,49 @@ mod tests {
         assert!(!background.is_transparent());
         assert!(background.opacity(0.0).is_transparent());
     }
+
+    #[test]
+    fn test_background_gradient_stops() {
+        let stops = [
+            linear_color_stop(rgba(0xff0000ff), 0.0),
+            linear_color_stop(rgba(0x00ff00ff), 0.5),
+            linear_color_stop(rgba(0x0000ffff), 1.0),
+        ];
+
+        let background = linear_gradient_with_stops(45.0, stops);
+        assert_eq!(background.tag, BackgroundTag::LinearGradient);
+        assert_eq!(background.gradient_stops(), &stops);
+
+        let background = radial_gradient(point(0.5, 0.25), stops);
+        assert_eq!(background.tag, BackgroundTag::RadialGradient);
+        assert_eq!(background.gradient_center, point(0.5, 0.25));
+        assert_eq!(background.gradient_stops(), &stops);
+
+        let background = conic_gradient(90.0, point(0.5, 0.5), stops);
+        assert_eq!(background.tag, BackgroundTag::ConicGradient);
+        assert_eq!(background.gradient_angle_or_pattern_height, 90.0);
+        assert!(!background.is_transparent());
+        assert!(background.opacity(0.0).is_transparent());
+
+        let too_many = (0..MAX_GRADIENT_STOPS + 2)
+            .map(|ix| linear_color_stop(rgba(0xff0000ff), ix as f32 / 10.0));
+        let background = linear_gradient_with_stops(0.0, too_many);
+        assert_eq!(background.gradient_stops().len(), MAX_GRADIENT_STOPS);
+    }
+
+    #[test]
+    fn test_gradients_drop_stops_past_the_limit() {
+        let stops = (0..MAX_GRADIENT_STOPS + 3)
+            .map(|ix| linear_color_stop(rgba(0xff0000ff), ix as f32 / 16.0))
+            .collect::<Vec<_>>();
+        let kept = &stops[..MAX_GRADIENT_STOPS];
+
+        for background in [
+            linear_gradient_with_stops(0.0, stops.iter().copied()),
+            radial_gradient(point(0.5, 0.5), stops.iter().copied()),
+            conic_gradient(0.0, point(0.5, 0.5), stops.iter().copied()),
+        ] {
+            assert_eq!(background.gradient_stops(), kept);
+        }
+    }
 }
@@ -6,6 +6,7 @@ mod div;
 mod image_cache;
 mod img;
 mod list;
+mod shader_quad;
 mod surface;
 mod svg;
 mod text;
@@ -20,6 +21,7 @@ pub use div::*;
 pub use image_cache::*;
 pub use img::*;
 pub use list::*;
+pub use shader_quad::*;
 pub use surface::*;
 pub use svg::*;
 pub use text::*;
@@ -0,0 +1,114 @@
+use refineable::Refineable as _;
+
+use crate::{
+    App, Bounds, Element, ElementId, GlobalElementId, InspectorElementId, IntoElement, LayoutId,
+    Pixels, SharedString, Style, StyleRefinement, Styled, Window,
+};
+
+/// The number of `f32` uniforms a shader quad can be painted with.
+pub const MAX_SHADER_QUAD_UNIFORMS: usize = 16;
+
+/// Construct a quad whose pixels are shaded by the given WGSL source, which has to define
+/// `fn shade(input: ShaderQuadInput) -> vec4<f32>` returning a color with straight alpha.
+///
+/// `ShaderQuadInput` gives the shader the pixel's position within the quad, in device pixels
+/// and relative to the quad's size, the quad's size, the seconds elapsed since the window's
+/// renderer was created, and up to [`MAX_SHADER_QUAD_UNIFORMS`] uniforms as an
+/// `array<vec4<f32>, 4>`. The quad is clipped to the content mask like any other primitive.
+///
+/// Pipelines are compiled the first time a source is drawn and cached by the source's hash.
+/// Sources that fail to compile are logged once and never drawn.
+pub fn shader_quad(source: impl Into<SharedString>, uniforms: &[f32]) -> ShaderQuad {
+    ShaderQuad {
+        source: source.into(),
+        uniforms: uniforms.to_vec(),
+        animated: false,
+        style: StyleRefinement::default(),
+    }
+}
+
+/// A quad shaded by a custom WGSL shader, see [`shader_quad`].
+pub struct ShaderQuad {
+    source: SharedString,
+    uniforms: Vec<f32>,
+    animated: bool,
+    style: StyleRefinement,
+}
+
+impl ShaderQuad {
+    /// Redraw the window on every frame while the quad is painted, so shaders that depend on
+    /// the elapsed time keep moving.
+    pub fn animated(mut self, animated: bool) -> Self {
+        self.animated = animated;
+        self
+    }
+}
+
+impl Element for ShaderQuad {
+    type RequestLayoutState = Style;
+    type PrepaintState = ();
+
+    fn id(&self) -> Option<ElementId> {
+        None
+    }
+
+    fn source_location(&self) -> Option<&'static core::panic::Location<'static>> {
+        None
+    }
+
+    fn request_layout(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> (LayoutId, Self::RequestLayoutState) {
+        let mut style = Style::default();
+        style.refine(&self.style);
+        let layout_id = window.request_layout(style.clone(), [], cx);
+        (layout_id, style)
+    }
+
+    fn prepaint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        _bounds: Bounds<Pixels>,
+        _request_layout: &mut Self::RequestLayoutState,
+        _window: &mut Window,
+        _cx: &mut App,
+    ) -> Self::PrepaintState {
+    }
+
+    fn paint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _inspector_id: Option<&InspectorElementId>,
+        bounds: Bounds<Pixels>,
+        style: &mut Self::RequestLayoutState,
+        _prepaint: &mut Self::PrepaintState,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        style.paint(bounds, window, cx, |window, _cx| {
+            window.paint_shader_quad(bounds, self.source.clone(), &self.uniforms);
+        });
+        if self.animated {
+            window.request_animation_frame();
+        }
+    }
+}
+
+impl IntoElement for ShaderQuad {
+    type Element = Self;
+
+    fn into_element(self) -> Self::Element {
+        self
+    }
+}
+
+impl Styled for ShaderQuad {
+    fn style(&mut self) -> &mut StyleRefinement {
+        &mut self.style
+    }
+}
@@ -13,8 +13,6 @@ use crate::{
 struct WgpuSurfaceHandleInner {
     surface_id: SurfaceId,
     registry: Arc<SurfaceRegistry>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
     present_trigger: Arc<dyn Fn() + Send + Sync>,
     /// Optional direct handle to the winit window.  Having an `Arc` lets
     /// us call `request_redraw()` from another thread without touching the
@@ -54,8 +52,6 @@ impl WgpuSurfaceHandle {
         std::env::var("GPUI_BENCHMARK").is_ok()
     }
     pub(crate) fn new(
-        device: wgpu::Device,
-        queue: wgpu::Queue,
         surface_id: SurfaceId,
         registry: Arc<SurfaceRegistry>,
         present_trigger: Arc<dyn Fn() + Send + Sync>,
@@ -68,8 +64,6 @@ impl WgpuSurfaceHandle {
             inner: Arc::new(WgpuSurfaceHandleInner {
                 surface_id,
                 registry,
-                device,
-                queue,
                 present_trigger,
                 winit_window,
                 size: Mutex::new((width, height)),
@@ -79,13 +73,23 @@ impl WgpuSurfaceHandle {
     }
 
     /// The wgpu `Device` for creating GPU resources and command encoders.
-    pub fn device(&self) -> &wgpu::Device {
-        &self.inner.device
+    ///
+    /// The device is replaced when the GPU is lost, so don't hold on to it across frames.
+    /// Resources created with it have to be recreated whenever
+    /// [`device_generation()`](Self::device_generation) changes.
+    pub fn device(&self) -> wgpu::Device {
+        self.inner.registry.device()
+    }
+
+    /// The wgpu `Queue` for submitting command buffers, replaced along with the device.
+    pub fn queue(&self) -> wgpu::Queue {
+        self.inner.registry.queue()
     }
 
-    /// The wgpu `Queue` for submitting command buffers.
-    pub fn queue(&self) -> &wgpu::Queue {
-        &self.inner.queue
+    /// Incremented every time the device is replaced after the GPU was lost. The surface's
+    /// buffers are recreated on the new device, but their contents are lost.
+    pub fn device_generation(&self) -> u64 {
+        self.inner.registry.generation()
     }
 
     /// Get a `TextureView` of the back buffer for use as a render target.
@@ -182,9 +186,7 @@ impl WgpuSurfaceHandle {
         if size.0 == width && size.1 == height {
             return;
         }
-        self.inner
-            .registry
-            .resize(&self.inner.device, self.inner.surface_id, width, height);
+        self.inner.registry.resize(self.inner.surface_id, width, height);
         *size = (width, height);
     }
 }
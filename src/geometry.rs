@@ -2484,6 +2484,7 @@ impl From<Pixels> for Corners<Pixels> {
     PartialEq,
     Serialize,
     Deserialize,
+    JsonSchema,
     Debug,
 )]
 #[repr(transparent)]
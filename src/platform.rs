@@ -7,21 +7,22 @@ mod test;
 
 pub(crate) mod cross;
 
+use crate::platform::cross::headless_platform::HeadlessPlatform;
 use crate::platform::cross::platform::CrossPlatform;
 use crate::{
     Action, AnyWindowHandle, App, AsyncWindowContext, BackgroundExecutor, Bounds,
     DEFAULT_WINDOW_SIZE, DevicePixels, DispatchEventResult, Font, FontId, FontMetrics, FontRun,
     ForegroundExecutor, GlyphId, GpuSpecs, ImageSource, Keymap, LineLayout, Pixels, PlatformInput,
-    Point, Priority, RealtimePriority, RenderGlyphParams, RenderImage, RenderImageParams,
-    RenderSvgParams, Scene, ShapedGlyph, ShapedRun, SharedString, Size, SvgRenderer,
-    SystemWindowTab, Task, TaskLabel, TaskTiming, ThreadTaskTimings, Window, WindowControlArea,
-    hash, point, px, size,
+    Point, PrimitiveKind, Priority, RealtimePriority, RenderGlyphParams, RenderImage,
+    RenderImageParams, RenderSvgParams, Scene, ShapedGlyph, ShapedRun, SharedString, Size,
+    SvgRenderer, SystemWindowTab, Task, TaskLabel, TaskTiming, ThreadTaskTimings, Window,
+    WindowControlArea, hash, point, px, size,
 };
 use anyhow::Result;
 use async_task::Runnable;
 use futures::channel::oneshot;
 use image::codecs::gif::GifDecoder;
-use image::{AnimationDecoder as _, Frame};
+use image::{AnimationDecoder as _, Frame, RgbaImage};
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
 use schemars::JsonSchema;
 use seahash::SeaHasher;
@@ -50,16 +51,18 @@ pub use keystroke::*;
 pub(crate) use test::*;
 
 #[cfg(any(test, feature = "test-support"))]
-pub use test::TestDispatcher;
+pub use test::{SnapshotTolerance, TestDispatcher};
 
 /// Returns a background executor for the current platform.
 pub fn background_executor() -> BackgroundExecutor {
     current_platform(true).background_executor()
 }
 
-pub(crate) fn current_platform(_headless: bool) -> Rc<dyn Platform> {
-    // TODO(mdeand): Support headless
+pub(crate) fn current_platform(headless: bool) -> Rc<dyn Platform> {
     // TODO(mdeand): Monomorphize Platform and its associated types.
+    if headless {
+        return Rc::new(HeadlessPlatform::new());
+    }
     Rc::new(CrossPlatform::new().expect("Failed to initialize platform"))
 }
 
@@ -138,10 +141,15 @@ pub(crate) trait Platform: 'static {
     fn path_for_auxiliary_executable(&self, name: &str) -> Result<PathBuf>;
 
     fn set_cursor_style(&self, style: CursorStyle);
+    fn register_cursor_image(&self, _id: CursorImageId, _image: CursorImage) {}
     fn should_auto_hide_scrollbars(&self) -> bool;
 
     fn write_to_clipboard(&self, item: ClipboardItem);
     fn read_from_clipboard(&self) -> Option<ClipboardItem>;
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn write_to_primary(&self, item: ClipboardItem);
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn read_from_primary(&self) -> Option<ClipboardItem>;
 
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>>;
     fn read_credentials(&self, url: &str) -> Task<Result<Option<(String, Vec<u8>)>>>;
@@ -150,6 +158,7 @@ pub(crate) trait Platform: 'static {
     fn keyboard_layout(&self) -> Box<dyn PlatformKeyboardLayout>;
     fn keyboard_mapper(&self) -> Rc<dyn PlatformKeyboardMapper>;
     fn on_keyboard_layout_change(&self, callback: Box<dyn FnMut()>);
+    fn on_displays_changed(&self, _callback: Box<dyn FnMut()>) {}
 }
 
 /// A handle to a platform's display, e.g. a monitor or laptop screen.
@@ -171,6 +180,17 @@ pub trait PlatformDisplay: Send + Sync + Debug {
         self.bounds()
     }
 
+    /// Get the number of physical pixels per logical pixel on this display.
+    /// Defaults to 1.0 if not overridden.
+    fn scale_factor(&self) -> f32 {
+        1.0
+    }
+
+    /// Get how many times per second this display refreshes, if it's known.
+    fn refresh_rate(&self) -> Option<f32> {
+        None
+    }
+
     /// Get the default bounds for this display to place a window
     fn default_bounds(&self) -> Bounds<Pixels> {
         let bounds = self.bounds();
@@ -360,6 +380,21 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
     fn on_appearance_changed(&self, callback: Box<dyn FnMut()>);
     fn draw(&self, scene: &Scene);
     fn completed_frame(&self) {}
+
+    /// Renders `scene` offscreen and reads it back into CPU memory.
+    /// Returns `None` on platforms that can't read back what they render.
+    fn capture_frame(&self, _scene: &Scene) -> Option<oneshot::Receiver<Result<RgbaImage>>> {
+        None
+    }
+
+    /// Changes how frames are presented, reconfiguring the window's surface.
+    fn set_present_mode(&self, _present_mode: PresentMode) {}
+
+    /// What it took to draw the last frame that was measured, or `None` on platforms that
+    /// don't measure their frames.
+    fn last_frame_stats(&self) -> Option<FrameStats> {
+        None
+    }
     fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas>;
 
     // macOS specific methods
@@ -688,6 +723,23 @@ pub(crate) trait PlatformAtlas: Send + Sync {
         build: &mut dyn FnMut() -> Result<Option<(Size<DevicePixels>, Cow<'a, [u8]>)>>,
     ) -> Result<Option<AtlasTile>>;
     fn remove(&self, key: &AtlasKey);
+
+    /// Limits how many bytes of textures the atlas keeps before it evicts the tiles that were
+    /// used least recently, or lifts the limit when `None`.
+    fn set_budget(&self, _budget: Option<u64>) {}
+
+    /// Moves the tiles of sparsely used textures into the other textures of their kind, so
+    /// the emptied textures can be freed. Returns whether any tile moved, in which case tiles
+    /// handed out before have to be requested again. Called every frame, so atlases only
+    /// compact once textures became sparse enough to be worth it.
+    fn compact(&self) -> bool {
+        false
+    }
+
+    /// How much memory the atlas uses, or `None` for atlases that don't keep track.
+    fn stats(&self) -> Option<AtlasStats> {
+        None
+    }
 }
 
 struct AtlasTextureList<T> {
@@ -751,8 +803,10 @@ pub(crate) struct AtlasTextureId {
     ),
     allow(dead_code)
 )]
-pub(crate) enum AtlasTextureKind {
+pub enum AtlasTextureKind {
+    /// Single-channel coverage, e.g. of glyphs and SVG icons, which are tinted when drawn.
     Monochrome = 0,
+    /// Full color, e.g. of images and emoji.
     Polychrome = 1,
 }
 
@@ -772,6 +826,62 @@ impl From<TileId> for etagere::AllocId {
     }
 }
 
+/// How much memory a window's sprite atlas uses, see [`Window::atlas_stats`].
+///
+/// [`Window::atlas_stats`]: crate::Window::atlas_stats
+#[derive(Clone, Debug, Default)]
+pub struct AtlasStats {
+    /// The textures holding glyphs and icons.
+    pub monochrome: AtlasTextureStats,
+    /// The textures holding images and emoji.
+    pub polychrome: AtlasTextureStats,
+    /// How many bytes of textures the atlas keeps before it evicts tiles, if limited.
+    pub budget: Option<u64>,
+    /// How many tiles were evicted to stay within the budget so far.
+    pub evicted_tiles: u64,
+    /// How many textures were freed by moving their tiles into others so far.
+    pub compacted_textures: u64,
+}
+
+impl AtlasStats {
+    /// The stats of the textures of the given kind.
+    pub fn kind(&self, kind: AtlasTextureKind) -> &AtlasTextureStats {
+        match kind {
+            AtlasTextureKind::Monochrome => &self.monochrome,
+            AtlasTextureKind::Polychrome => &self.polychrome,
+        }
+    }
+
+    /// How many bytes all of the atlas' textures take.
+    pub fn bytes(&self) -> u64 {
+        self.monochrome.bytes + self.polychrome.bytes
+    }
+}
+
+/// How much memory the atlas textures of one kind use.
+#[derive(Clone, Debug, Default)]
+pub struct AtlasTextureStats {
+    /// How many textures there are.
+    pub textures: usize,
+    /// How many tiles are allocated in them.
+    pub tiles: usize,
+    /// How many bytes the textures take.
+    pub bytes: u64,
+    /// How many of those bytes are taken by tiles.
+    pub used_bytes: u64,
+}
+
+impl AtlasTextureStats {
+    /// The fraction of the textures' bytes taken by tiles, from 0 to 1.
+    pub fn occupancy(&self) -> f32 {
+        if self.bytes == 0 {
+            0.0
+        } else {
+            self.used_bytes as f32 / self.bytes as f32
+        }
+    }
+}
+
 pub(crate) struct PlatformInputHandler {
     cx: AsyncWindowContext,
     handler: Box<dyn InputHandler>,
@@ -1058,6 +1168,17 @@ pub struct WindowOptions {
 
     /// Tab group name, allows opening the window as a native tab on macOS 10.12+. Windows with the same tabbing identifier will be grouped together.
     pub tabbing_identifier: Option<String>,
+
+    /// How frames are presented to the display. Can be changed later with
+    /// [`Window::set_present_mode`](crate::Window::set_present_mode).
+    /// - `None`: Read `GPUI_PRESENT_MODE` or `GPUI_DISABLE_VSYNC` from the environment,
+    ///   falling back to [`PresentMode::Fifo`].
+    pub present_mode: Option<PresentMode>,
+
+    /// The maximum number of frames the GPU may queue up ahead of the display. Lower values
+    /// reduce latency, higher values smooth out frames that take long to render.
+    /// Defaults to 2.
+    pub max_frame_latency: Option<u32>,
 }
 
 /// The variables that can be configured when creating a new window
@@ -1087,7 +1208,18 @@ pub(crate) struct WindowParams {
     pub display_id: Option<DisplayId>,
 
     pub window_min_size: Option<Size<Pixels>>,
+
+    /// The appearance of the window background, which some platforms can only make
+    /// transparent when the window is created
+    pub window_background: WindowBackgroundAppearance,
+
+    /// Application identifier of the window, which some platforms can only set when the
+    /// window is created
+    pub app_id: Option<String>,
+
     pub tabbing_identifier: Option<String>,
+    pub present_mode: Option<PresentMode>,
+    pub max_frame_latency: Option<u32>,
 }
 
 /// Represents the status of how a window should be opened.
@@ -1146,6 +1278,8 @@ impl Default for WindowOptions {
             window_min_size: None,
             window_decorations: None,
             tabbing_identifier: None,
+            present_mode: None,
+            max_frame_latency: None,
         }
     }
 }
@@ -1206,6 +1340,83 @@ pub enum WindowAppearance {
     VibrantDark,
 }
 
+/// How a window's rendered frames are handed over to the display.
+///
+/// Modes that aren't supported by the window's surface fall back to the closest supported
+/// mode, and to [`PresentMode::Fifo`] as a last resort, which is always available.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PresentMode {
+    /// Frames wait for the next vertical blank, so they never tear. Rendering is throttled to
+    /// the display's refresh rate, which saves power.
+    #[default]
+    Fifo,
+    /// Like [`PresentMode::Fifo`], but a frame that misses a vertical blank is presented right
+    /// away, which may tear.
+    FifoRelaxed,
+    /// Frames replace any frame still waiting for the vertical blank. Latency is low and
+    /// frames never tear, but rendering isn't throttled.
+    Mailbox,
+    /// Frames are presented right away. Has the lowest latency, but frames may tear.
+    Immediate,
+}
+
+/// What it took to draw one of a window's frames, for finding the views that are expensive to
+/// render.
+///
+/// GPU times are only measured when the `GPUI_GPU_TIMING` environment variable is set and the
+/// GPU supports timestamp queries. They're read back a frame or two after the frame was drawn,
+/// and frames drawn while an earlier frame's times are still being read back are skipped.
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    /// One entry for each kind of primitive drawn, in the order they were first drawn.
+    pub batches: Vec<BatchStats>,
+    /// The GPU time from when the first batch started drawing until the last one finished.
+    pub gpu_time: Option<Duration>,
+    /// How many bytes of instance data were uploaded to the GPU.
+    pub instance_bytes: u64,
+    /// How many tiles were uploaded into the sprite atlas.
+    pub atlas_uploads: usize,
+    /// How many bytes of tiles were uploaded into the sprite atlas.
+    pub atlas_upload_bytes: u64,
+}
+
+impl FrameStats {
+    /// The stats of the batches of the given kind drawn in the frame, if there were any.
+    pub fn batch(&self, kind: PrimitiveKind) -> Option<&BatchStats> {
+        self.batches.iter().find(|batch| batch.kind == kind)
+    }
+
+    pub(crate) fn batch_mut(&mut self, kind: PrimitiveKind) -> &mut BatchStats {
+        let ix = match self.batches.iter().position(|batch| batch.kind == kind) {
+            Some(ix) => ix,
+            None => {
+                self.batches.push(BatchStats {
+                    kind,
+                    batches: 0,
+                    instances: 0,
+                    gpu_time: None,
+                });
+                self.batches.len() - 1
+            }
+        };
+        &mut self.batches[ix]
+    }
+}
+
+/// What it took to draw the primitives of one kind in a frame.
+#[derive(Clone, Debug)]
+pub struct BatchStats {
+    /// The kind of the primitives.
+    pub kind: PrimitiveKind,
+    /// How many batches they were drawn in.
+    pub batches: usize,
+    /// How many primitives were drawn.
+    pub instances: usize,
+    /// The GPU time spent drawing them. For layers, this doesn't include the primitives
+    /// inside them, which are counted with their own kinds.
+    pub gpu_time: Option<Duration>,
+}
+
 /// The appearance of the background of the window itself, when there is
 /// no content or the content is transparent.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -1398,6 +1609,36 @@ pub enum CursorStyle {
 
     /// Hide the cursor
     None,
+
+    /// A custom image registered with [`App::register_cursor_image`]
+    Image(CursorImageId),
+}
+
+/// An opaque identifier for a cursor image registered with [`App::register_cursor_image`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct CursorImageId(pub(crate) u32);
+
+/// An image to show as the cursor, along with the point in it that the cursor points at
+#[derive(Clone, Debug)]
+pub struct CursorImage {
+    /// The image, of which the first frame is shown
+    pub image: Arc<RenderImage>,
+    /// The point the cursor points at, in pixels from the image's top left corner
+    pub hotspot: Point<DevicePixels>,
+}
+
+impl CursorImage {
+    /// Create a cursor image from the given image and hotspot
+    pub fn new(image: Arc<RenderImage>, hotspot: Point<DevicePixels>) -> Self {
+        Self { image, hotspot }
+    }
+
+    /// Create a cursor image by decoding the given PNG bytes
+    pub fn from_png(bytes: &[u8], hotspot: Point<DevicePixels>) -> Result<Self> {
+        let image = Image::from_bytes(ImageFormat::Png, bytes.to_vec())
+            .to_image_data(SvgRenderer::new(Arc::new(())))?;
+        Ok(Self::new(image, hotspot))
+    }
 }
 
 /// A clipboard item that should be copied to the clipboard
@@ -1,8 +1,21 @@
 pub mod atlas;
+pub mod capture;
+pub mod clipboard;
+pub mod cursor;
 pub mod dispatcher;
+pub mod display;
+pub mod gpu_timer;
+pub mod headless_platform;
+pub mod headless_window;
+pub mod instance_buffer;
 pub mod keyboard;
+pub mod offscreen_renderer;
 pub mod platform;
 pub mod renderer;
+#[cfg(feature = "software-renderer")]
+pub mod software_atlas;
+#[cfg(feature = "software-renderer")]
+pub mod software_renderer;
 pub mod surface_registry;
 pub mod text_system;
 pub mod window;
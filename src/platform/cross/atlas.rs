@@ -1,33 +1,72 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use collections::FxHashMap;
+use collections::{FxHashMap, FxHashSet};
 use etagere::BucketedAtlasAllocator;
 use parking_lot::Mutex;
 use wgpu::util::DeviceExt;
 
 use crate::{
-    AtlasKey, AtlasTextureId, AtlasTextureKind, AtlasTile, Bounds, DevicePixels, PlatformAtlas,
-    Point, Size,
+    AtlasKey, AtlasStats, AtlasTextureId, AtlasTextureKind, AtlasTextureStats, AtlasTile, Bounds,
+    DevicePixels, PlatformAtlas, Point, Scene, Size, TileId,
     platform::{AtlasTextureList, cross::render_context::WgpuContext},
 };
 
+/// How many bytes of textures an atlas keeps before it evicts tiles, unless it's configured
+/// otherwise.
+const DEFAULT_BUDGET: u64 = 256 * 1024 * 1024;
+
+/// Textures whose tiles cover less than this fraction of them are compacted into the other
+/// textures of their kind.
+const COMPACTION_OCCUPANCY: f32 = 0.25;
+
+/// How long to wait after compacting textures before compacting them again, since every
+/// compaction has the window paint everything again.
+const COMPACTION_INTERVAL: Duration = Duration::from_secs(1);
+
+const DEFAULT_ATLAS_SIZE: Size<DevicePixels> = Size {
+    width: DevicePixels(1024),
+    height: DevicePixels(1024),
+};
+
+const TEXTURE_KINDS: [AtlasTextureKind; 2] =
+    [AtlasTextureKind::Monochrome, AtlasTextureKind::Polychrome];
+
 pub(crate) struct WgpuAtlas(Mutex<WgpuAtlasState>);
 
 impl WgpuAtlas {
     pub(crate) fn new(context: Arc<WgpuContext>) -> Self {
-        WgpuAtlas(Mutex::new(WgpuAtlasState {
-            atlas_target: None,
-            atlas_target_view: None,
-            context,
-            storage: WgpuAtlasStorage::default(),
-            tiles_by_key: FxHashMap::default(),
-            initializations: Vec::new(),
-            uploads: Vec::new(),
-        }))
+        WgpuAtlas(Mutex::new(WgpuAtlasState::new(context)))
+    }
+
+    /// Drops every tile along with the textures they live in, and allocates new ones on
+    /// `context` from now on. Used once the device the textures were created on was lost, after
+    /// which tiles handed out before have to be requested again.
+    pub(crate) fn recreate(&self, context: Arc<WgpuContext>) {
+        let mut state = self.0.lock();
+        let budget = state.budget;
+        *state = WgpuAtlasState::new(context);
+        state.budget = budget;
     }
 
-    pub fn before_frame(&self, encoder: &mut wgpu::CommandEncoder) {
-        self.0.lock().flush(encoder);
+    /// Marks the tiles `scene` samples from as used, and uploads the tiles inserted since the
+    /// last frame, returning how many there were and how many bytes they took.
+    pub fn before_frame(&self, scene: &Scene, encoder: &mut wgpu::CommandEncoder) -> (usize, u64) {
+        let mut state = self.0.lock();
+        state.mark_drawn(scene);
+        state.flush(encoder);
+        (
+            std::mem::take(&mut state.upload_count),
+            std::mem::take(&mut state.upload_bytes),
+        )
+    }
+
+    /// How many tiles were removed so far. Removed tiles may be handed out again with other
+    /// contents, so frames drawn with them can't be kept around.
+    pub(crate) fn removed_tile_count(&self) -> u64 {
+        self.0.lock().removed_tile_count
     }
 
     pub fn after_frame(&self) {
@@ -54,14 +93,17 @@ impl PlatformAtlas for WgpuAtlas {
     ) -> anyhow::Result<Option<AtlasTile>> {
         let mut atlas = self.0.lock();
 
-        match atlas.tiles_by_key.get(key) {
-            Some(tile) => Ok(Some(tile.clone())),
+        match atlas.tiles_by_key.get(key).cloned() {
+            Some(tile) => {
+                atlas.mark_used(&tile);
+                Ok(Some(tile))
+            }
             None => Ok({
                 profiling::scope!("new tile");
 
                 match build()? {
                     Some((size, bytes)) => {
-                        let tile = atlas.allocate(size, key.texture_kind());
+                        let tile = atlas.allocate(key, size);
 
                         atlas.upload_texture(tile.texture_id, tile.bounds, &bytes);
                         atlas.tiles_by_key.insert(key.clone(), tile.clone());
@@ -77,28 +119,43 @@ impl PlatformAtlas for WgpuAtlas {
     fn remove(&self, key: &AtlasKey) {
         let mut atlas = self.0.lock();
 
-        let Some(id) = atlas.tiles_by_key.remove(key).map(|x| x.texture_id) else {
-            return;
-        };
-
-        let Some(texture_slot) = atlas.storage[id.kind].textures.get_mut(id.index as usize) else {
+        let Some(tile) = atlas.tiles_by_key.remove(key) else {
             return;
         };
+        atlas.removed_tile_count += 1;
+        atlas.free_tile(tile.texture_id, tile.tile_id);
+    }
 
-        if let Some(mut texture) = texture_slot.take() {
-            texture.decrement_ref_count();
+    fn set_budget(&self, budget: Option<u64>) {
+        let mut atlas = self.0.lock();
+        atlas.budget = budget;
+        atlas.trim();
+    }
 
-            if texture.is_unreferenced() {
-                atlas.storage[id.kind]
-                    .free_list
-                    .push(texture.id.index as usize);
+    fn compact(&self) -> bool {
+        self.0.lock().compact()
+    }
 
-                // TODO(mdeand): Is this even necessary?
-                texture.destroy(&atlas.context);
-            } else {
-                *texture_slot = Some(texture);
+    fn stats(&self) -> Option<AtlasStats> {
+        let atlas = self.0.lock();
+        let texture_stats = |kind: AtlasTextureKind| {
+            let mut stats = AtlasTextureStats::default();
+            for texture in atlas.storage.textures(kind) {
+                stats.textures += 1;
+                stats.tiles += texture.tiles.len();
+                stats.bytes += texture.bytes();
+                stats.used_bytes += texture.used_bytes();
             }
-        }
+            stats
+        };
+
+        Some(AtlasStats {
+            monochrome: texture_stats(AtlasTextureKind::Monochrome),
+            polychrome: texture_stats(AtlasTextureKind::Polychrome),
+            budget: atlas.budget,
+            evicted_tiles: atlas.evicted_tile_count,
+            compacted_textures: atlas.compacted_texture_count,
+        })
     }
 }
 
@@ -110,26 +167,396 @@ struct WgpuAtlasState {
     tiles_by_key: FxHashMap<AtlasKey, AtlasTile>,
     initializations: Vec<AtlasTextureId>,
     uploads: Vec<PendingUpload>,
+    /// How many tiles were uploaded since the last frame, and how many bytes they took.
+    upload_count: usize,
+    upload_bytes: u64,
+    removed_tile_count: u64,
+    /// How many scenes were drawn with the atlas. Tiles remember the last one they were used
+    /// in, and the ones used since the last scene was drawn are never evicted, since the
+    /// window's next scene may reuse them without asking for them again.
+    frame: u64,
+    budget: Option<u64>,
+    evicted_tile_count: u64,
+    compacted_texture_count: u64,
+    compaction: CompactionSchedule,
 }
 
 impl WgpuAtlasState {
-    fn allocate(&mut self, size: Size<DevicePixels>, texture_kind: AtlasTextureKind) -> AtlasTile {
+    fn new(context: Arc<WgpuContext>) -> Self {
+        Self {
+            atlas_target: None,
+            atlas_target_view: None,
+            context,
+            storage: WgpuAtlasStorage::default(),
+            tiles_by_key: FxHashMap::default(),
+            initializations: Vec::new(),
+            uploads: Vec::new(),
+            upload_count: 0,
+            upload_bytes: 0,
+            removed_tile_count: 0,
+            frame: 0,
+            budget: Some(DEFAULT_BUDGET),
+            evicted_tile_count: 0,
+            compacted_texture_count: 0,
+            compaction: CompactionSchedule::default(),
+        }
+    }
+
+    fn allocate(&mut self, key: &AtlasKey, size: Size<DevicePixels>) -> AtlasTile {
+        let texture_kind = key.texture_kind();
+        let tile = self
+            .allocate_in_textures(size, texture_kind)
+            .or_else(|| self.make_room(size, texture_kind))
+            .unwrap_or_else(|| {
+                let texture = self.push_texture(size, texture_kind);
+
+                // TODO(mdeand): Note this unwrap use.
+                texture.allocate(size).unwrap()
+            });
+
+        let last_used = self.frame + 1;
+        self.storage[tile.texture_id].tiles.insert(
+            tile.tile_id,
+            TileEntry {
+                key: key.clone(),
+                bounds: tile.bounds,
+                last_used,
+            },
+        );
+        tile
+    }
+
+    fn allocate_in_textures(
+        &mut self,
+        size: Size<DevicePixels>,
+        texture_kind: AtlasTextureKind,
+    ) -> Option<AtlasTile> {
+        self.storage[texture_kind]
+            .iter_mut()
+            .rev()
+            .find_map(|texture| texture.allocate(size))
+    }
+
+    /// Evicts the least recently used tiles while another texture for a tile of `size` would
+    /// exceed the budget. Returns the tile once it fits into a texture tiles were evicted from,
+    /// or `None` if a texture has to be pushed for it after all.
+    fn make_room(
+        &mut self,
+        size: Size<DevicePixels>,
+        texture_kind: AtlasTextureKind,
+    ) -> Option<AtlasTile> {
+        let budget = self.budget?;
+        let texture_size = size.max(&DEFAULT_ATLAS_SIZE);
+        let texture_bytes = texture_size.width.0 as u64
+            * texture_size.height.0 as u64
+            * bytes_per_pixel(texture_kind) as u64;
+
+        let candidates = self.eviction_candidates();
+        // NOTE(mdeand): Evicting tiles only frees the textures it empties. Past where that
+        // NOTE(mdeand): can bring the textures within the budget, tiles are only evicted to
+        // NOTE(mdeand): make room in the textures the new tile could go into.
+        let evictable_bytes = evictable_bytes(
+            TEXTURE_KINDS
+                .into_iter()
+                .flat_map(|kind| self.storage.textures(kind))
+                .map(|texture| (texture.bytes(), &texture.tiles)),
+            self.frame,
+        );
+        let budget_reachable = self.texture_bytes() - evictable_bytes + texture_bytes <= budget;
+        for (texture_id, tile_id) in candidates {
+            if self.texture_bytes() + texture_bytes <= budget {
+                return None;
+            }
+            if !budget_reachable && texture_id.kind != texture_kind {
+                continue;
+            }
+            self.evict_tile(texture_id, tile_id);
+            if texture_id.kind == texture_kind
+                && let Some(tile) = self
+                    .storage
+                    .get_mut(texture_id)
+                    .and_then(|texture| texture.allocate(size))
+            {
+                return Some(tile);
+            }
+        }
+        None
+    }
+
+    /// Evicts the least recently used tiles until the textures fit into the budget again, as
+    /// far as tiles that weren't used since the last frame allow.
+    fn trim(&mut self) {
+        let Some(budget) = self.budget else {
+            return;
+        };
+        for (texture_id, tile_id) in self.eviction_candidates() {
+            if self.texture_bytes() <= budget {
+                return;
+            }
+            self.evict_tile(texture_id, tile_id);
+        }
+    }
+
+    /// The tiles that weren't used since the last scene was drawn, from the least recently
+    /// used.
+    fn eviction_candidates(&self) -> Vec<(AtlasTextureId, TileId)> {
+        let mut candidates = TEXTURE_KINDS
+            .into_iter()
+            .flat_map(|kind| self.storage.textures(kind))
+            .flat_map(|texture| {
+                texture
+                    .tiles
+                    .iter()
+                    .filter(|(_, entry)| entry.last_used < self.frame)
+                    .map(|(tile_id, entry)| (entry.last_used, texture.id, *tile_id))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(last_used, ..)| *last_used);
+        candidates
+            .into_iter()
+            .map(|(_, texture_id, tile_id)| (texture_id, tile_id))
+            .collect()
+    }
+
+    fn evict_tile(&mut self, texture_id: AtlasTextureId, tile_id: TileId) {
+        let Some(entry) = self.free_tile(texture_id, tile_id) else {
+            return;
+        };
+        self.tiles_by_key.remove(&entry.key);
+        self.removed_tile_count += 1;
+        self.evicted_tile_count += 1;
+    }
+
+    /// Frees a tile's space in its texture, and the texture once it holds no tiles anymore.
+    fn free_tile(&mut self, texture_id: AtlasTextureId, tile_id: TileId) -> Option<TileEntry> {
+        let texture = self.storage.get_mut(texture_id)?;
+        let entry = texture.tiles.remove(&tile_id)?;
+        let used_area = texture.used_area;
+        texture.deallocate(tile_id, entry.bounds.size);
+
+        if texture.tiles.is_empty() {
+            self.free_texture(texture_id);
+        } else {
+            let texture_area = texture.area();
+            self.compaction
+                .tile_freed(texture_area, used_area, texture.used_area);
+        }
+        Some(entry)
+    }
+
+    fn free_texture(&mut self, texture_id: AtlasTextureId) {
+        let textures = &mut self.storage[texture_id.kind];
+        let Some(texture) = textures.textures[texture_id.index as usize].take() else {
+            return;
+        };
+        textures.free_list.push(texture_id.index as usize);
+        self.uploads
+            .retain(|upload| upload.texture_id != texture_id);
+
+        // TODO(mdeand): Is this even necessary?
+        texture.destroy(&self.context);
+    }
+
+    fn texture_bytes(&self) -> u64 {
+        TEXTURE_KINDS
+            .into_iter()
+            .flat_map(|kind| self.storage.textures(kind))
+            .map(WgpuAtlasTexture::bytes)
+            .sum()
+    }
+
+    /// Marks a tile handed out for the frame being painted as used.
+    fn mark_used(&mut self, tile: &AtlasTile) {
+        let frame = self.frame + 1;
+        if let Some(entry) = self
+            .storage
+            .get_mut(tile.texture_id)
+            .and_then(|texture| texture.tiles.get_mut(&tile.tile_id))
         {
-            let textures = &mut self.storage[texture_kind];
+            entry.last_used = frame;
+        }
+    }
+
+    /// Marks the tiles `scene` samples from as used by a new frame, and frees the retired
+    /// textures it doesn't sample from anymore.
+    fn mark_drawn(&mut self, scene: &Scene) {
+        self.frame += 1;
+        let mut sampled_textures = FxHashSet::default();
+        self.mark_sampled(scene, &mut sampled_textures);
+
+        let retired = TEXTURE_KINDS
+            .into_iter()
+            .flat_map(|kind| self.storage.textures(kind))
+            .filter(|texture| texture.retired && !sampled_textures.contains(&texture.id))
+            .map(|texture| texture.id)
+            .collect::<Vec<_>>();
+        for texture_id in retired {
+            self.free_texture(texture_id);
+        }
+    }
+
+    fn mark_sampled(&mut self, scene: &Scene, sampled_textures: &mut FxHashSet<AtlasTextureId>) {
+        let tiles = scene
+            .monochrome_sprites
+            .iter()
+            .map(|sprite| &sprite.tile)
+            .chain(scene.polychrome_sprites.iter().map(|sprite| &sprite.tile));
+        for tile in tiles {
+            sampled_textures.insert(tile.texture_id);
+            if let Some(entry) = self
+                .storage
+                .get_mut(tile.texture_id)
+                .and_then(|texture| texture.tiles.get_mut(&tile.tile_id))
+            {
+                entry.last_used = self.frame;
+            }
+        }
+
+        for layer in &scene.layers {
+            self.mark_sampled(&layer.scene, sampled_textures);
+        }
+    }
+
+    /// Moves the tiles of the sparsest texture of each kind into the other textures of that
+    /// kind, if they fit. The emptied textures are retired rather than freed, since the scene
+    /// drawn last still samples from them, and are freed once a scene was drawn without them.
+    ///
+    /// Textures are only compacted once one became sparse enough since they were last
+    /// compacted, and at most once every [`COMPACTION_INTERVAL`].
+    fn compact(&mut self) -> bool {
+        let now = Instant::now();
+        if !self.compaction.is_due(now)
+            || TEXTURE_KINDS
+                .into_iter()
+                .flat_map(|kind| self.storage.textures(kind))
+                .any(|texture| texture.retired)
+        {
+            return false;
+        }
+        self.compaction.compacted(now);
+
+        let sparse_textures = TEXTURE_KINDS
+            .into_iter()
+            .filter_map(|kind| self.sparse_texture(kind))
+            .collect::<Vec<_>>();
+        if sparse_textures.is_empty() {
+            return false;
+        }
 
-            if let Some(tile) = textures
+        let mut encoder =
+            self.context
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("atlas_compaction"),
+                });
+        // NOTE(mdeand): Tiles inserted since the last frame have to be uploaded before they're
+        // NOTE(mdeand): copied out of their texture.
+        self.flush(&mut encoder);
+
+        let mut compacted = false;
+        for texture_id in sparse_textures {
+            compacted |= self.move_tiles(texture_id, &mut encoder);
+        }
+        self.context.queue.submit(Some(encoder.finish()));
+        compacted
+    }
+
+    /// The texture of the given kind whose tiles cover the least of it, if that's sparse
+    /// enough to compact and there are other textures to move its tiles into.
+    fn sparse_texture(&self, texture_kind: AtlasTextureKind) -> Option<AtlasTextureId> {
+        if self.storage.textures(texture_kind).count() < 2 {
+            return None;
+        }
+        self.storage
+            .textures(texture_kind)
+            .filter(|texture| texture.occupancy() < COMPACTION_OCCUPANCY)
+            .min_by(|a, b| a.occupancy().total_cmp(&b.occupancy()))
+            .map(|texture| texture.id)
+    }
+
+    /// Moves every tile of a texture into the other textures of its kind and retires it, or
+    /// leaves everything as it was if they don't fit.
+    fn move_tiles(
+        &mut self,
+        texture_id: AtlasTextureId,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> bool {
+        let mut tiles = self.storage[texture_id]
+            .tiles
+            .iter()
+            .map(|(tile_id, entry)| (*tile_id, entry.clone()))
+            .collect::<Vec<_>>();
+        // NOTE(mdeand): Placing the largest tiles first packs them more tightly.
+        tiles.sort_unstable_by_key(|(_, entry)| {
+            std::cmp::Reverse(entry.bounds.size.width.0 * entry.bounds.size.height.0)
+        });
+
+        let mut moves = Vec::with_capacity(tiles.len());
+        for (tile_id, entry) in tiles {
+            let destination = self.storage[texture_id.kind]
                 .iter_mut()
                 .rev()
-                .find_map(|texture| texture.allocate(size))
-            {
-                return tile;
+                .filter(|texture| texture.id != texture_id)
+                .find_map(|texture| texture.allocate(entry.bounds.size));
+            match destination {
+                Some(tile) => moves.push((tile_id, entry, tile)),
+                None => {
+                    for (_, _, tile) in moves {
+                        self.storage[tile.texture_id].deallocate(tile.tile_id, tile.bounds.size);
+                    }
+                    return false;
+                }
             }
         }
 
-        let texture = self.push_texture(size, texture_kind);
+        for (_, entry, tile) in &moves {
+            encoder.copy_texture_to_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.storage[texture_id].raw,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: entry.bounds.origin.x.into(),
+                        y: entry.bounds.origin.y.into(),
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.storage[tile.texture_id].raw,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: tile.bounds.origin.x.into(),
+                        y: tile.bounds.origin.y.into(),
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::Extent3d {
+                    width: tile.bounds.size.width.into(),
+                    height: tile.bounds.size.height.into(),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        for (_, entry, tile) in moves {
+            self.tiles_by_key.insert(entry.key.clone(), tile.clone());
+            self.storage[tile.texture_id].tiles.insert(
+                tile.tile_id,
+                TileEntry {
+                    bounds: tile.bounds,
+                    ..entry
+                },
+            );
+        }
 
-        // TODO(mdeand): Note this unwrap use.
-        texture.allocate(size).unwrap()
+        let texture = &mut self.storage[texture_id];
+        texture.tiles.clear();
+        texture.used_area = 0;
+        texture.retired = true;
+        self.removed_tile_count += 1;
+        self.compacted_texture_count += 1;
+        true
     }
 
     fn push_texture(
@@ -137,11 +564,6 @@ impl WgpuAtlasState {
         min_size: Size<DevicePixels>,
         texture_kind: AtlasTextureKind,
     ) -> &mut WgpuAtlasTexture {
-        const DEFAULT_ATLAS_SIZE: Size<DevicePixels> = Size {
-            width: DevicePixels(1024),
-            height: DevicePixels(1024),
-        };
-
         let size = min_size.max(&DEFAULT_ATLAS_SIZE);
 
         let (format, usage) = match texture_kind {
@@ -205,7 +627,9 @@ impl WgpuAtlasState {
             raw: texture_raw,
             raw_view: texture_raw_view,
             format,
-            live_atlas_keys: 0,
+            tiles: FxHashMap::default(),
+            used_area: 0,
+            retired: false,
         };
 
         self.initializations.push(atlas_texture.id);
@@ -281,7 +705,9 @@ impl WgpuAtlasState {
     fn flush(&mut self, encoder: &mut wgpu::CommandEncoder) {
         self.flush_initializations(encoder);
 
+        self.upload_count += self.uploads.len();
         for upload in self.uploads.drain(..) {
+            self.upload_bytes += upload.buffer.size();
             let texture = &self.storage[upload.texture_id];
 
             encoder.copy_buffer_to_texture(
@@ -319,11 +745,28 @@ pub(crate) struct WgpuAtlasTexture {
     raw: wgpu::Texture,
     raw_view: wgpu::TextureView,
     format: wgpu::TextureFormat,
-    live_atlas_keys: u32,
+    /// The tiles allocated in the texture.
+    tiles: FxHashMap<TileId, TileEntry>,
+    /// How many pixels the texture's tiles cover.
+    used_area: u64,
+    /// Whether the texture's tiles were moved into other textures. Nothing is allocated in it
+    /// anymore, and it's freed once no drawn scene samples from it.
+    retired: bool,
+}
+
+#[derive(Clone)]
+struct TileEntry {
+    key: AtlasKey,
+    bounds: Bounds<DevicePixels>,
+    /// The last frame the tile was used in.
+    last_used: u64,
 }
 
 impl WgpuAtlasTexture {
     fn allocate(&mut self, size: Size<DevicePixels>) -> Option<AtlasTile> {
+        if self.retired {
+            return None;
+        }
         let allocation = self.allocator.allocate(size.into())?;
 
         let tile = AtlasTile {
@@ -336,11 +779,34 @@ impl WgpuAtlasTexture {
             },
         };
 
-        self.live_atlas_keys += 1;
+        self.used_area += area(size);
 
         Some(tile)
     }
 
+    fn deallocate(&mut self, tile_id: TileId, size: Size<DevicePixels>) {
+        self.allocator.deallocate(tile_id.into());
+        self.used_area -= area(size);
+    }
+
+    fn bytes(&self) -> u64 {
+        self.raw.width() as u64 * self.raw.height() as u64 * self.bytes_per_pixel() as u64
+    }
+
+    fn used_bytes(&self) -> u64 {
+        self.used_area * self.bytes_per_pixel() as u64
+    }
+
+    /// How many pixels the texture has.
+    fn area(&self) -> u64 {
+        self.raw.width() as u64 * self.raw.height() as u64
+    }
+
+    /// The fraction of the texture covered by tiles.
+    fn occupancy(&self) -> f32 {
+        self.used_area as f32 / self.area() as f32
+    }
+
     fn bytes_per_pixel(&self) -> u8 {
         // TODO(mdeand): There's probably a better way to do this
 
@@ -351,17 +817,67 @@ impl WgpuAtlasTexture {
         }
     }
 
-    fn decrement_ref_count(&mut self) {
-        self.live_atlas_keys = self.live_atlas_keys.saturating_sub(1);
+    fn destroy(self, _context: &WgpuContext) {
+        // NOTE(mdeand): In wgpu, textures are automatically cleaned up when dropped.
+        // NOTE(mdeand): If there were any additional resources to free, they would be handled here.
     }
+}
 
-    fn is_unreferenced(&self) -> bool {
-        self.live_atlas_keys == 0
+fn area(size: Size<DevicePixels>) -> u64 {
+    size.width.0 as u64 * size.height.0 as u64
+}
+
+/// Returns how many bytes evicting every tile that wasn't used since frame `frame` frees, given
+/// each texture's bytes and tiles. Only the textures that are emptied are freed.
+fn evictable_bytes<'a>(
+    textures: impl IntoIterator<Item = (u64, &'a FxHashMap<TileId, TileEntry>)>,
+    frame: u64,
+) -> u64 {
+    textures
+        .into_iter()
+        .filter(|(_, tiles)| {
+            !tiles.is_empty() && tiles.values().all(|entry| entry.last_used < frame)
+        })
+        .map(|(bytes, _)| bytes)
+        .sum()
+}
+
+/// Decides when textures are compacted, which is once a texture became sparse enough since
+/// they were last compacted, and at most once every [`COMPACTION_INTERVAL`].
+#[derive(Default)]
+struct CompactionSchedule {
+    /// Whether a texture became sparse enough to compact since textures were last compacted.
+    pending: bool,
+    last_compacted: Option<Instant>,
+}
+
+impl CompactionSchedule {
+    /// Notes that freeing a tile of a texture with `texture_area` pixels left `used_area` of
+    /// them covered by tiles, out of `previously_used_area`.
+    fn tile_freed(&mut self, texture_area: u64, previously_used_area: u64, used_area: u64) {
+        let threshold = COMPACTION_OCCUPANCY * texture_area as f32;
+        if previously_used_area as f32 >= threshold && (used_area as f32) < threshold {
+            self.pending = true;
+        }
     }
 
-    fn destroy(self, _context: &WgpuContext) {
-        // NOTE(mdeand): In wgpu, textures are automatically cleaned up when dropped.
-        // NOTE(mdeand): If there were any additional resources to free, they would be handled here.
+    fn is_due(&self, now: Instant) -> bool {
+        self.pending
+            && self
+                .last_compacted
+                .is_none_or(|last_compacted| now >= last_compacted + COMPACTION_INTERVAL)
+    }
+
+    fn compacted(&mut self, now: Instant) {
+        self.pending = false;
+        self.last_compacted = Some(now);
+    }
+}
+
+fn bytes_per_pixel(texture_kind: AtlasTextureKind) -> u8 {
+    match texture_kind {
+        AtlasTextureKind::Monochrome => 1,
+        AtlasTextureKind::Polychrome => 4,
     }
 }
 
@@ -396,12 +912,28 @@ impl std::ops::Index<AtlasTextureId> for WgpuAtlasStorage {
     }
 }
 
+impl std::ops::IndexMut<AtlasTextureId> for WgpuAtlasStorage {
+    fn index_mut(&mut self, id: AtlasTextureId) -> &mut Self::Output {
+        self.get_mut(id).unwrap()
+    }
+}
+
 #[derive(Default)]
 struct WgpuAtlasStorage {
     monochrome_textures: AtlasTextureList<WgpuAtlasTexture>,
     polychrome_textures: AtlasTextureList<WgpuAtlasTexture>,
 }
 
+impl WgpuAtlasStorage {
+    fn get_mut(&mut self, id: AtlasTextureId) -> Option<&mut WgpuAtlasTexture> {
+        self[id.kind].textures.get_mut(id.index as usize)?.as_mut()
+    }
+
+    fn textures(&self, kind: AtlasTextureKind) -> impl Iterator<Item = &WgpuAtlasTexture> {
+        self[kind].textures.iter().flatten()
+    }
+}
+
 pub(crate) struct WgpuTextureInfo {
     pub raw_view: wgpu::TextureView,
 }
@@ -446,3 +978,129 @@ impl From<etagere::Rectangle> for Bounds<DevicePixels> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::{ImageId, RenderImageParams, size};
+
+    fn insert(atlas: &WgpuAtlas, id: usize, width: i32, height: i32) -> AtlasTile {
+        let key = AtlasKey::Image(RenderImageParams {
+            image_id: ImageId(id),
+            frame_index: 0,
+        });
+        let bytes = vec![0; width as usize * height as usize * 4];
+        atlas
+            .get_or_insert_with(&key, &mut || {
+                Ok(Some((
+                    size(DevicePixels(width), DevicePixels(height)),
+                    Cow::Borrowed(&bytes),
+                )))
+            })
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_only_textures_of_unused_tiles_are_evictable() {
+        let tiles = |last_used: &[u64]| {
+            last_used
+                .iter()
+                .enumerate()
+                .map(|(id, last_used)| {
+                    let entry = TileEntry {
+                        key: AtlasKey::Image(RenderImageParams {
+                            image_id: ImageId(id),
+                            frame_index: 0,
+                        }),
+                        bounds: Bounds::default(),
+                        last_used: *last_used,
+                    };
+                    (TileId(id as u32), entry)
+                })
+                .collect::<FxHashMap<_, _>>()
+        };
+        let unused = tiles(&[1, 2]);
+        let used = tiles(&[1, 3]);
+        let retired = tiles(&[]);
+        let textures = [(4, &unused), (8, &used), (16, &retired)];
+        assert_eq!(evictable_bytes(textures, 3), 4);
+        assert_eq!(evictable_bytes(textures, 4), 12);
+    }
+
+    #[test]
+    fn test_textures_are_compacted_once_sparse_and_not_too_often() {
+        let mut schedule = CompactionSchedule::default();
+        let now = Instant::now();
+
+        // NOTE(mdeand): Textures that were sparse already don't count, nor do ones that stay
+        // NOTE(mdeand): dense enough.
+        schedule.tile_freed(100, 20, 10);
+        schedule.tile_freed(100, 50, 25);
+        assert!(!schedule.is_due(now));
+
+        schedule.tile_freed(100, 25, 24);
+        assert!(schedule.is_due(now));
+        schedule.compacted(now);
+        assert!(!schedule.is_due(now));
+
+        schedule.tile_freed(100, 30, 10);
+        assert!(!schedule.is_due(now + COMPACTION_INTERVAL / 2));
+        assert!(schedule.is_due(now + COMPACTION_INTERVAL));
+    }
+
+    #[test]
+    fn test_least_recently_used_tiles_are_evicted() {
+        let Some(context) = WgpuContext::for_test() else {
+            return;
+        };
+        let atlas = WgpuAtlas::new(Arc::new(context));
+        // NOTE(mdeand): A polychrome texture takes 4 MiB, so only one fits into the budget.
+        atlas.set_budget(Some(4 * 1024 * 1024));
+
+        let first = insert(&atlas, 0, 1024, 512);
+        let second = insert(&atlas, 1, 1024, 512);
+        atlas.0.lock().mark_drawn(&Scene::default());
+        atlas.0.lock().mark_drawn(&Scene::default());
+        assert_eq!(insert(&atlas, 1, 1024, 512), second);
+
+        let third = insert(&atlas, 2, 1024, 512);
+        assert_eq!(third.bounds, first.bounds);
+        let stats = atlas.stats().unwrap();
+        assert_eq!(stats.polychrome.textures, 1);
+        assert_eq!(stats.polychrome.tiles, 2);
+        assert_eq!(stats.evicted_tiles, 1);
+        assert_eq!(insert(&atlas, 1, 1024, 512), second);
+    }
+
+    #[test]
+    fn test_sparse_textures_are_compacted() {
+        let Some(context) = WgpuContext::for_test() else {
+            return;
+        };
+        let atlas = WgpuAtlas::new(Arc::new(context));
+        atlas.set_budget(None);
+
+        let first = insert(&atlas, 0, 1024, 640);
+        let second = insert(&atlas, 1, 1024, 640);
+        let small = insert(&atlas, 2, 128, 128);
+        assert_eq!(small.texture_id, second.texture_id);
+
+        atlas.remove(&AtlasKey::Image(RenderImageParams {
+            image_id: ImageId(1),
+            frame_index: 0,
+        }));
+        assert!(atlas.compact());
+        assert_eq!(insert(&atlas, 2, 128, 128).texture_id, first.texture_id);
+        assert_eq!(atlas.stats().unwrap().polychrome.textures, 2);
+
+        // NOTE(mdeand): The emptied texture is freed once a scene was drawn without it.
+        atlas.0.lock().mark_drawn(&Scene::default());
+        let stats = atlas.stats().unwrap();
+        assert_eq!(stats.polychrome.textures, 1);
+        assert_eq!(stats.compacted_textures, 1);
+        assert!(!atlas.compact());
+    }
+}
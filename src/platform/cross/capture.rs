@@ -0,0 +1,198 @@
+use anyhow::{Context as _, Result, anyhow};
+use futures::channel::oneshot;
+use image::RgbaImage;
+
+/// Copies `texture` into CPU memory. The returned receiver is resolved once the GPU is done
+/// copying, which is waited for on a background thread rather than the caller's.
+///
+/// `premultiplied` must be set if the texture holds premultiplied colors, so they can be
+/// converted to the straight alpha [`RgbaImage`] expects.
+pub(crate) fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    premultiplied: bool,
+) -> oneshot::Receiver<Result<RgbaImage>> {
+    let (sender, receiver) = oneshot::channel();
+
+    let format = texture.format();
+    let width = texture.width();
+    let height = texture.height();
+    let padded_bytes_per_row = padded_bytes_per_row(width);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("capture_buffer"),
+        size: padded_bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("capture"),
+    });
+    command_encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    let submission_index = queue.submit(Some(command_encoder.finish()));
+
+    let mapped_buffer = buffer.clone();
+    buffer.map_async(wgpu::MapMode::Read, .., move |result| {
+        let image = result
+            .context("failed to map the capture buffer")
+            .and_then(|()| {
+                let bytes = mapped_buffer.get_mapped_range(..);
+                rgba_image_from_rows(
+                    &bytes,
+                    width,
+                    height,
+                    padded_bytes_per_row,
+                    format,
+                    premultiplied,
+                )
+            });
+        mapped_buffer.unmap();
+        let _ = sender.send(image);
+    });
+
+    // NOTE(mdeand): Mapping only completes while the device is polled, and an idle window may
+    // NOTE(mdeand): not draw another frame for a long time. Waiting for just the copy invokes
+    // NOTE(mdeand): the callback above, on a thread of its own so the caller isn't stalled.
+    let device = device.clone();
+    std::thread::spawn(move || {
+        if let Err(error) = device.poll(wgpu::PollType::Wait {
+            submission_index: Some(submission_index),
+            timeout: None,
+        }) {
+            log::error!("failed to wait for frame capture: {error}");
+        }
+    });
+
+    receiver
+}
+
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let bytes_per_row = width * 4;
+    bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// Converts rows of 8-bit RGBA or BGRA texels, or of 10-bit RGB texels with 2-bit alpha, each
+/// padded to `padded_bytes_per_row`, into a straight alpha RGBA image.
+///
+/// Other formats, like the float formats of HDR surfaces, can't be captured.
+fn rgba_image_from_rows(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+    premultiplied: bool,
+) -> Result<RgbaImage> {
+    let to_rgba8: fn([u8; 4]) -> [u8; 4] = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => |texel| texel,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+            |[b, g, r, a]| [r, g, b, a]
+        }
+        wgpu::TextureFormat::Rgb10a2Unorm => |texel| {
+            let texel = u32::from_le_bytes(texel);
+            let channel = |shift: u32| ((((texel >> shift) & 0x3ff) * 255 + 511) / 1023) as u8;
+            let alpha = ((texel >> 30) * 85) as u8;
+            [channel(0), channel(10), channel(20), alpha]
+        },
+        format => return Err(anyhow!("capturing {format:?} textures is not supported")),
+    };
+
+    let row_len = width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_len * height as usize);
+    for row in bytes
+        .chunks(padded_bytes_per_row as usize)
+        .take(height as usize)
+    {
+        for texel in row[..row_len].chunks_exact(4) {
+            let [r, g, b, a] = to_rgba8([texel[0], texel[1], texel[2], texel[3]]);
+            if premultiplied && a > 0 && a < 255 {
+                let unpremultiply =
+                    |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+                pixels.extend([unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+            } else {
+                pixels.extend([r, g, b, a]);
+            }
+        }
+    }
+
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow!("capture buffer is too small for a {width}x{height} image"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_are_unpadded_and_swizzled() {
+        let padded_bytes_per_row = padded_bytes_per_row(1);
+        assert_eq!(padded_bytes_per_row, 256);
+
+        let mut bytes = vec![0; padded_bytes_per_row as usize * 2];
+        bytes[..4].copy_from_slice(&[1, 2, 3, 255]);
+        bytes[256..260].copy_from_slice(&[4, 5, 6, 255]);
+
+        let image = rgba_image_from_rows(
+            &bytes,
+            1,
+            2,
+            padded_bytes_per_row,
+            wgpu::TextureFormat::Bgra8Unorm,
+            false,
+        )
+        .unwrap();
+        assert_eq!(image.as_raw(), &[3, 2, 1, 255, 6, 5, 4, 255]);
+    }
+
+    #[test]
+    fn test_premultiplied_colors_are_unpremultiplied() {
+        let image = rgba_image_from_rows(
+            &[64, 0, 128, 128],
+            1,
+            1,
+            4,
+            wgpu::TextureFormat::Rgba8Unorm,
+            true,
+        )
+        .unwrap();
+        assert_eq!(image.as_raw(), &[128, 0, 255, 128]);
+    }
+
+    #[test]
+    fn test_ten_bit_colors_are_narrowed() {
+        let texel = 1023 | (512 << 10) | (3 << 30);
+        let image = rgba_image_from_rows(
+            &u32::to_le_bytes(texel),
+            1,
+            1,
+            4,
+            wgpu::TextureFormat::Rgb10a2Unorm,
+            false,
+        )
+        .unwrap();
+        assert_eq!(image.as_raw(), &[255, 128, 0, 255]);
+
+        assert!(
+            rgba_image_from_rows(&[0; 8], 1, 1, 8, wgpu::TextureFormat::Rgba16Float, false)
+                .is_err()
+        );
+    }
+}
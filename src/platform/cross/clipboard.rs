@@ -0,0 +1,186 @@
+use crate::{ClipboardEntry, ClipboardItem, ClipboardString, Image, ImageFormat, SvgRenderer};
+use anyhow::{Context as _, Result};
+use collections::FxHashMap;
+use std::{borrow::Cow, cell::RefCell, io::Cursor, sync::Arc};
+
+/// One of the clipboards the system keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum ClipboardKind {
+    /// The clipboard that copying writes to and pasting reads from.
+    Clipboard,
+    /// The selection that selecting text writes to and middle-clicking pastes from.
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    Primary,
+}
+
+/// The system's clipboards, as far as they can hold text and images.
+///
+/// The system clipboards can't carry the metadata of a string, so it's kept here instead and
+/// handed out again for as long as the clipboard still holds the text it was written with.
+/// Images are written in whichever [`ImageFormat`] they come in, but are always read as PNGs.
+#[derive(Default)]
+pub(crate) struct CrossClipboard {
+    /// Opened on first use, since there may be no display server to talk to before.
+    clipboard: RefCell<Option<arboard::Clipboard>>,
+    /// The hash of the text last written to each clipboard, and the metadata it came with.
+    metadata: RefCell<FxHashMap<ClipboardKind, (u64, String)>>,
+}
+
+impl CrossClipboard {
+    pub(crate) fn write(&self, kind: ClipboardKind, item: ClipboardItem) {
+        self.metadata.borrow_mut().remove(&kind);
+
+        // NOTE(mdeand): The clipboard holds either text or an image, so images are only written
+        // NOTE(mdeand): when the item has no text.
+        let image = item.entries().iter().find_map(|entry| match entry {
+            ClipboardEntry::Image(image) => Some(image),
+            _ => None,
+        });
+        let result = match (item.text(), image) {
+            (None, Some(image)) => image_data(image).and_then(|image| {
+                self.with_clipboard(|clipboard| Ok(set(clipboard, kind).image(image)?))
+            }),
+            (text, _) => {
+                let text = text.unwrap_or_default();
+                let result =
+                    self.with_clipboard(|clipboard| Ok(set(clipboard, kind).text(text.as_str())?));
+                if result.is_ok()
+                    && let Some(metadata) = item.metadata()
+                {
+                    self.metadata
+                        .borrow_mut()
+                        .insert(kind, (ClipboardString::text_hash(&text), metadata.clone()));
+                }
+                result
+            }
+        };
+        if let Err(error) = result {
+            log::error!("failed to write to the clipboard: {error:?}");
+        }
+    }
+
+    pub(crate) fn read(&self, kind: ClipboardKind) -> Option<ClipboardItem> {
+        let result = self.with_clipboard(|clipboard| {
+            match get(clipboard, kind).text() {
+                Ok(text) => {
+                    let metadata = self
+                        .metadata
+                        .borrow()
+                        .get(&kind)
+                        .filter(|(hash, _)| *hash == ClipboardString::text_hash(&text))
+                        .map(|(_, metadata)| metadata.clone());
+                    return Ok(Some(match metadata {
+                        Some(metadata) => ClipboardItem::new_string_with_metadata(text, metadata),
+                        None => ClipboardItem::new_string(text),
+                    }));
+                }
+                Err(arboard::Error::ContentNotAvailable) => {}
+                Err(error) => return Err(error.into()),
+            }
+            match get(clipboard, kind).image() {
+                Ok(image) => Ok(Some(ClipboardItem::new_image(&png(image)?))),
+                Err(arboard::Error::ContentNotAvailable) => Ok(None),
+                Err(error) => Err(error.into()),
+            }
+        });
+        result.unwrap_or_else(|error| {
+            log::error!("failed to read from the clipboard: {error:?}");
+            None
+        })
+    }
+
+    fn with_clipboard<R>(&self, f: impl FnOnce(&mut arboard::Clipboard) -> Result<R>) -> Result<R> {
+        let mut clipboard = self.clipboard.borrow_mut();
+        let clipboard = match &mut *clipboard {
+            Some(clipboard) => clipboard,
+            None => clipboard.insert(arboard::Clipboard::new()?),
+        };
+        f(clipboard)
+    }
+}
+
+fn set(clipboard: &mut arboard::Clipboard, kind: ClipboardKind) -> arboard::Set<'_> {
+    match kind {
+        ClipboardKind::Clipboard => clipboard.set(),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        ClipboardKind::Primary => {
+            use arboard::SetExtLinux as _;
+
+            clipboard
+                .set()
+                .clipboard(arboard::LinuxClipboardKind::Primary)
+        }
+    }
+}
+
+fn get(clipboard: &mut arboard::Clipboard, kind: ClipboardKind) -> arboard::Get<'_> {
+    match kind {
+        ClipboardKind::Clipboard => clipboard.get(),
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+        ClipboardKind::Primary => {
+            use arboard::GetExtLinux as _;
+
+            clipboard
+                .get()
+                .clipboard(arboard::LinuxClipboardKind::Primary)
+        }
+    }
+}
+
+/// Decodes `image` into the RGBA pixels the clipboard holds. Animated images keep their first
+/// frame.
+fn image_data(image: &Image) -> Result<arboard::ImageData<'static>> {
+    let svg_renderer = SvgRenderer::new(Arc::new(()));
+    let render_image = match image.format {
+        ImageFormat::Svg => svg_renderer.render_single_frame(&image.bytes, 1.0, true)?,
+        _ => image.to_image_data(svg_renderer)?,
+    };
+    let mut bytes = render_image
+        .as_bytes(0)
+        .context("the image has no frames")?
+        .to_vec();
+    // NOTE(mdeand): Decoded images are BGRA.
+    for pixel in bytes.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    let size = render_image.size(0);
+    Ok(arboard::ImageData {
+        width: size.width.0 as usize,
+        height: size.height.0 as usize,
+        bytes: Cow::Owned(bytes),
+    })
+}
+
+/// Encodes the RGBA pixels read from the clipboard as a PNG.
+fn png(image: arboard::ImageData<'_>) -> Result<Image> {
+    let buffer = image::RgbaImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.bytes.into_owned(),
+    )
+    .context("the clipboard image is smaller than its size")?;
+    let mut bytes = Vec::new();
+    buffer.write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+    Ok(Image::from_bytes(ImageFormat::Png, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_images_round_trip_through_clipboard_pixels() {
+        let pixels = vec![255, 0, 0, 255, 0, 0, 255, 128];
+        let image = png(arboard::ImageData {
+            width: 2,
+            height: 1,
+            bytes: Cow::Borrowed(&pixels),
+        })
+        .unwrap();
+        assert_eq!(image.format, ImageFormat::Png);
+
+        let image_data = image_data(&image).unwrap();
+        assert_eq!((image_data.width, image_data.height), (2, 1));
+        assert_eq!(image_data.bytes, pixels);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{CursorImage, CursorImageId, CursorStyle, RenderImage};
+use anyhow::{Context as _, Result};
+use collections::FxHashMap;
+use std::cell::{Cell, RefCell};
+use winit::{
+    event_loop::ActiveEventLoop,
+    window::{Cursor, CursorIcon, CustomCursor, CustomCursorSource},
+};
+
+/// The cursor images registered with the app. Images move from `sources` to `cursors` once
+/// they're first shown, since handing them to the windowing system takes the event loop.
+#[derive(Default)]
+pub(crate) struct CursorImages {
+    sources: RefCell<FxHashMap<CursorImageId, CustomCursorSource>>,
+    cursors: RefCell<FxHashMap<CursorImageId, CustomCursor>>,
+    /// How many times images were registered, which tells apart the images registered with
+    /// the same id.
+    generation: Cell<u64>,
+}
+
+/// A cursor shown over a window, which is compared with the next one to not hand the same
+/// cursor to winit again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ShownCursor {
+    style: CursorStyle,
+    /// The generation of the registered images, for images.
+    generation: u64,
+}
+
+impl CursorImages {
+    pub(crate) fn register(&self, id: CursorImageId, image: CursorImage) {
+        match custom_cursor_source(&image) {
+            Ok(source) => {
+                self.sources.borrow_mut().insert(id, source);
+                self.cursors.borrow_mut().remove(&id);
+                self.generation.set(self.generation.get() + 1);
+            }
+            Err(error) => log::error!("failed to register a cursor image: {error:?}"),
+        }
+    }
+
+    /// The cursor shown for `style`, which differs from the one shown before for an image
+    /// that was registered again in between.
+    pub(crate) fn shown_cursor(&self, style: CursorStyle) -> ShownCursor {
+        let generation = match style {
+            CursorStyle::Image(_) => self.generation.get(),
+            _ => 0,
+        };
+        ShownCursor { style, generation }
+    }
+
+    /// The cursor showing `style`, or `None` when the cursor is hidden. Images that weren't
+    /// registered show the arrow instead.
+    pub(crate) fn cursor(
+        &self,
+        style: CursorStyle,
+        event_loop: &ActiveEventLoop,
+    ) -> Option<Cursor> {
+        if let CursorStyle::Image(id) = style
+            && let Some(cursor) = self.custom_cursor(id, event_loop)
+        {
+            return Some(cursor.into());
+        }
+        // NOTE(mdeand): Icons the system has no cursor for fall back to a similar one, or to
+        // NOTE(mdeand): the arrow, in winit.
+        cursor_icon(style).map(Cursor::from)
+    }
+
+    fn custom_cursor(
+        &self,
+        id: CursorImageId,
+        event_loop: &ActiveEventLoop,
+    ) -> Option<CustomCursor> {
+        if let Some(cursor) = self.cursors.borrow().get(&id) {
+            return Some(cursor.clone());
+        }
+        let source = self.sources.borrow_mut().remove(&id)?;
+        let cursor = event_loop.create_custom_cursor(source);
+        self.cursors.borrow_mut().insert(id, cursor.clone());
+        Some(cursor)
+    }
+}
+
+/// The system cursor showing `style`, or `None` when the cursor is hidden. Images show the
+/// arrow, for when they weren't registered.
+fn cursor_icon(style: CursorStyle) -> Option<CursorIcon> {
+    Some(match style {
+        CursorStyle::Arrow => CursorIcon::Default,
+        CursorStyle::IBeam => CursorIcon::Text,
+        CursorStyle::Crosshair => CursorIcon::Crosshair,
+        CursorStyle::ClosedHand => CursorIcon::Grabbing,
+        CursorStyle::OpenHand => CursorIcon::Grab,
+        CursorStyle::PointingHand => CursorIcon::Pointer,
+        CursorStyle::ResizeLeft => CursorIcon::WResize,
+        CursorStyle::ResizeRight => CursorIcon::EResize,
+        CursorStyle::ResizeLeftRight => CursorIcon::EwResize,
+        CursorStyle::ResizeUp => CursorIcon::NResize,
+        CursorStyle::ResizeDown => CursorIcon::SResize,
+        CursorStyle::ResizeUpDown => CursorIcon::NsResize,
+        CursorStyle::ResizeUpLeftDownRight => CursorIcon::NwseResize,
+        CursorStyle::ResizeUpRightDownLeft => CursorIcon::NeswResize,
+        CursorStyle::ResizeColumn => CursorIcon::ColResize,
+        CursorStyle::ResizeRow => CursorIcon::RowResize,
+        CursorStyle::IBeamCursorForVerticalLayout => CursorIcon::VerticalText,
+        CursorStyle::OperationNotAllowed => CursorIcon::NotAllowed,
+        CursorStyle::DragLink => CursorIcon::Alias,
+        CursorStyle::DragCopy => CursorIcon::Copy,
+        CursorStyle::ContextualMenu => CursorIcon::ContextMenu,
+        CursorStyle::None => return None,
+        CursorStyle::Image(_) => CursorIcon::Default,
+    })
+}
+
+fn custom_cursor_source(image: &CursorImage) -> Result<CustomCursorSource> {
+    let size = image.image.size(0);
+    Ok(CustomCursor::from_rgba(
+        rgba_pixels(&image.image)?,
+        u16::try_from(size.width.0).context("the cursor image is too wide")?,
+        u16::try_from(size.height.0).context("the cursor image is too tall")?,
+        u16::try_from(image.hotspot.x.0).context("the hotspot is outside the cursor image")?,
+        u16::try_from(image.hotspot.y.0).context("the hotspot is outside the cursor image")?,
+    )?)
+}
+
+/// The pixels of the first frame of `image`, which render images keep as BGRA, as RGBA.
+fn rgba_pixels(image: &RenderImage) -> Result<Vec<u8>> {
+    let mut rgba = image
+        .as_bytes(0)
+        .context("the cursor image has no frames")?
+        .to_vec();
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    Ok(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DevicePixels, point};
+    use image::{Frame, RgbaImage};
+    use std::sync::Arc;
+
+    fn cursor_image(hotspot_x: i32, hotspot_y: i32) -> CursorImage {
+        let pixels = vec![10, 20, 30, 255, 40, 50, 60, 128];
+        let buffer = RgbaImage::from_raw(2, 1, pixels).unwrap();
+        CursorImage::new(
+            Arc::new(RenderImage::new([Frame::new(buffer)])),
+            point(DevicePixels(hotspot_x), DevicePixels(hotspot_y)),
+        )
+    }
+
+    #[test]
+    fn test_cursor_styles_map_to_icons() {
+        let icons = [
+            (CursorStyle::Arrow, Some(CursorIcon::Default)),
+            (CursorStyle::IBeam, Some(CursorIcon::Text)),
+            (CursorStyle::Crosshair, Some(CursorIcon::Crosshair)),
+            (CursorStyle::ClosedHand, Some(CursorIcon::Grabbing)),
+            (CursorStyle::OpenHand, Some(CursorIcon::Grab)),
+            (CursorStyle::PointingHand, Some(CursorIcon::Pointer)),
+            (CursorStyle::ResizeLeft, Some(CursorIcon::WResize)),
+            (CursorStyle::ResizeRight, Some(CursorIcon::EResize)),
+            (CursorStyle::ResizeLeftRight, Some(CursorIcon::EwResize)),
+            (CursorStyle::ResizeUp, Some(CursorIcon::NResize)),
+            (CursorStyle::ResizeDown, Some(CursorIcon::SResize)),
+            (CursorStyle::ResizeUpDown, Some(CursorIcon::NsResize)),
+            (
+                CursorStyle::ResizeUpLeftDownRight,
+                Some(CursorIcon::NwseResize),
+            ),
+            (
+                CursorStyle::ResizeUpRightDownLeft,
+                Some(CursorIcon::NeswResize),
+            ),
+            (CursorStyle::ResizeColumn, Some(CursorIcon::ColResize)),
+            (CursorStyle::ResizeRow, Some(CursorIcon::RowResize)),
+            (
+                CursorStyle::IBeamCursorForVerticalLayout,
+                Some(CursorIcon::VerticalText),
+            ),
+            (
+                CursorStyle::OperationNotAllowed,
+                Some(CursorIcon::NotAllowed),
+            ),
+            (CursorStyle::DragLink, Some(CursorIcon::Alias)),
+            (CursorStyle::DragCopy, Some(CursorIcon::Copy)),
+            (CursorStyle::ContextualMenu, Some(CursorIcon::ContextMenu)),
+            (CursorStyle::None, None),
+            (
+                CursorStyle::Image(CursorImageId(0)),
+                Some(CursorIcon::Default),
+            ),
+        ];
+        for (style, icon) in icons {
+            assert_eq!(cursor_icon(style), icon, "{style:?}");
+        }
+    }
+
+    #[test]
+    fn test_cursor_images_are_swizzled_to_rgba() {
+        let image = cursor_image(0, 0);
+        assert_eq!(
+            rgba_pixels(&image.image).unwrap(),
+            [30, 20, 10, 255, 60, 50, 40, 128]
+        );
+        assert!(custom_cursor_source(&image).is_ok());
+    }
+
+    #[test]
+    fn test_hotspots_outside_cursor_images_are_rejected() {
+        let error = custom_cursor_source(&cursor_image(-1, 0)).unwrap_err();
+        assert_eq!(error.to_string(), "the hotspot is outside the cursor image");
+        assert!(custom_cursor_source(&cursor_image(1, -1)).is_err());
+        assert!(custom_cursor_source(&cursor_image(3, 0)).is_err());
+
+        let images = CursorImages::default();
+        images.register(CursorImageId(0), cursor_image(-1, 0));
+        images.register(CursorImageId(1), cursor_image(1, 0));
+        assert_eq!(
+            images.sources.borrow().keys().collect::<Vec<_>>(),
+            [&CursorImageId(1)]
+        );
+    }
+
+    #[test]
+    fn test_registering_images_again_changes_the_shown_cursor() {
+        let images = CursorImages::default();
+        let image = CursorStyle::Image(CursorImageId(0));
+        images.register(CursorImageId(0), cursor_image(0, 0));
+        let shown = images.shown_cursor(image);
+        let arrow = images.shown_cursor(CursorStyle::Arrow);
+        assert_eq!(images.shown_cursor(image), shown);
+
+        // NOTE(mdeand): Images that fail to register leave the shown cursor as it was.
+        images.register(CursorImageId(0), cursor_image(-1, 0));
+        assert_eq!(images.shown_cursor(image), shown);
+
+        images.register(CursorImageId(0), cursor_image(1, 0));
+        assert_ne!(images.shown_cursor(image), shown);
+        assert_eq!(images.shown_cursor(CursorStyle::Arrow), arrow);
+    }
+}
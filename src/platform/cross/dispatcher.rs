@@ -11,23 +11,39 @@ pub enum CrossEvent {
     SurfacePresent(winit::window::WindowId),
 }
 
+/// How the dispatcher wakes the main thread after queueing work for it.
+pub enum MainThreadWaker {
+    /// The main thread runs a winit event loop.
+    EventLoop(EventLoopProxy<CrossEvent>),
+    /// The main thread runs the headless loop, which parks between frames.
+    Thread(std::thread::Thread),
+}
+
+impl MainThreadWaker {
+    fn wake(&self) {
+        match self {
+            MainThreadWaker::EventLoop(proxy) => {
+                let _ = proxy.send_event(CrossEvent::WakeUp);
+            }
+            MainThreadWaker::Thread(thread) => thread.unpark(),
+        }
+    }
+}
+
 pub struct Dispatcher {
     main_thread_id: ThreadId,
     main_tx: PriorityQueueSender<RunnableVariant>,
     threadpool: ThreadPool<Priority>,
-    proxy: EventLoopProxy<CrossEvent>,
+    waker: MainThreadWaker,
 }
 
 impl Dispatcher {
-    pub fn new(
-        main_tx: PriorityQueueSender<RunnableVariant>,
-        proxy: EventLoopProxy<CrossEvent>,
-    ) -> Self {
+    pub fn new(main_tx: PriorityQueueSender<RunnableVariant>, waker: MainThreadWaker) -> Self {
         Self {
             main_thread_id: std::thread::current().id(),
             main_tx,
             threadpool: ThreadPool::new(num_cpus::get() * 8),
-            proxy,
+            waker,
         }
     }
 }
@@ -73,7 +89,7 @@ impl PlatformDispatcher for Dispatcher {
     fn dispatch_on_main_thread(&self, runnable: RunnableVariant, priority: Priority) {
         match self.main_tx.send(priority, runnable) {
             Ok(_) => {
-                let _ = self.proxy.send_event(CrossEvent::WakeUp);
+                self.waker.wake();
             }
             Err(runnable) => {
                 std::mem::forget(runnable);
@@ -0,0 +1,294 @@
+use crate::{Bounds, DisplayId, Pixels, PlatformDisplay, Point, Size};
+use anyhow::Result;
+use collections::FxHashMap;
+use std::cell::{Cell, RefCell};
+use uuid::Uuid;
+use winit::{event_loop::ActiveEventLoop, monitor::MonitorHandle};
+
+/// A display backed by one of winit's monitors, as it was when the monitors were last listed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CrossDisplay {
+    id: DisplayId,
+    uuid: Uuid,
+    bounds: Bounds<Pixels>,
+    scale_factor: f32,
+    refresh_rate: Option<f32>,
+}
+
+impl CrossDisplay {
+    fn new(id: DisplayId, key: &MonitorKey, monitor: &MonitorHandle) -> Self {
+        Self {
+            id,
+            uuid: key.uuid(),
+            bounds: monitor_bounds(monitor),
+            scale_factor: monitor.scale_factor() as f32,
+            refresh_rate: monitor
+                .refresh_rate_millihertz()
+                .map(|millihertz| millihertz as f32 / 1000.),
+        }
+    }
+}
+
+impl PlatformDisplay for CrossDisplay {
+    fn id(&self) -> DisplayId {
+        self.id
+    }
+
+    fn uuid(&self) -> Result<Uuid> {
+        Ok(self.uuid)
+    }
+
+    fn bounds(&self) -> Bounds<Pixels> {
+        self.bounds
+    }
+
+    /// The whole display, since winit doesn't tell which part of a monitor is taken by panels
+    /// and docks.
+    fn visible_bounds(&self) -> Bounds<Pixels> {
+        self.bounds
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    fn refresh_rate(&self) -> Option<f32> {
+        self.refresh_rate
+    }
+}
+
+/// What tells a monitor apart from the others across hot-plugs: its name, and how many monitors
+/// with the same name were listed before it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct MonitorKey {
+    name: String,
+    index: usize,
+}
+
+impl MonitorKey {
+    /// The keys of `names`, which are listed in the order winit lists the monitors.
+    fn for_names(names: impl IntoIterator<Item = String>) -> Vec<Self> {
+        let mut counts = FxHashMap::<String, usize>::default();
+        names
+            .into_iter()
+            .map(|name| {
+                let count = counts.entry(name.clone()).or_default();
+                let index = *count;
+                *count += 1;
+                Self { name, index }
+            })
+            .collect()
+    }
+
+    fn uuid(&self) -> Uuid {
+        Uuid::new_v5(
+            &Uuid::NAMESPACE_OID,
+            format!("{}#{}", self.name, self.index).as_bytes(),
+        )
+    }
+}
+
+/// Hands out display ids that stay the same for as long as the app runs, even when monitors
+/// are unplugged and plugged in again, and tells when the displays changed.
+#[derive(Default)]
+pub(crate) struct DisplayRegistry {
+    ids: RefCell<FxHashMap<MonitorKey, DisplayId>>,
+    next_id: Cell<u32>,
+    /// The displays as they were when they were last polled, and the id of the primary one.
+    last_displays: RefCell<Option<(Vec<CrossDisplay>, Option<DisplayId>)>>,
+    on_displays_changed: Cell<Option<Box<dyn FnMut()>>>,
+}
+
+impl DisplayRegistry {
+    fn id(&self, key: &MonitorKey) -> DisplayId {
+        *self.ids.borrow_mut().entry(key.clone()).or_insert_with(|| {
+            let id = DisplayId(self.next_id.get());
+            self.next_id.set(id.0 + 1);
+            id
+        })
+    }
+
+    /// The displays of `monitors`, along with the monitor each one is backed by.
+    pub(crate) fn displays(
+        &self,
+        monitors: impl IntoIterator<Item = MonitorHandle>,
+    ) -> Vec<(MonitorHandle, CrossDisplay)> {
+        let monitors = monitors.into_iter().collect::<Vec<_>>();
+        let keys = MonitorKey::for_names(
+            monitors
+                .iter()
+                .map(|monitor| monitor.name().unwrap_or_default()),
+        );
+        monitors
+            .into_iter()
+            .zip(keys)
+            .map(|(monitor, key)| {
+                let display = CrossDisplay::new(self.id(&key), &key, &monitor);
+                (monitor, display)
+            })
+            .collect()
+    }
+
+    /// The display backed by `monitor`, which has to be one of `monitors`.
+    pub(crate) fn display(
+        &self,
+        monitors: impl IntoIterator<Item = MonitorHandle>,
+        monitor: &MonitorHandle,
+    ) -> Option<CrossDisplay> {
+        self.displays(monitors)
+            .into_iter()
+            .find_map(|(candidate, display)| (candidate == *monitor).then_some(display))
+    }
+
+    /// The monitor backing the display with the given id, if it's still connected.
+    pub(crate) fn monitor(
+        &self,
+        monitors: impl IntoIterator<Item = MonitorHandle>,
+        display_id: DisplayId,
+    ) -> Option<MonitorHandle> {
+        self.displays(monitors)
+            .into_iter()
+            .find_map(|(monitor, display)| (display.id == display_id).then_some(monitor))
+    }
+
+    pub(crate) fn on_displays_changed(&self, callback: Box<dyn FnMut()>) {
+        self.on_displays_changed.set(Some(callback));
+    }
+
+    /// The displays as they were when they were last polled, for when the monitors can't be
+    /// listed, which is outside of the event loop's callbacks.
+    pub(crate) fn last_displays(&self) -> Vec<CrossDisplay> {
+        self.last_displays
+            .borrow()
+            .as_ref()
+            .map(|(displays, _)| displays.clone())
+            .unwrap_or_default()
+    }
+
+    /// The primary display as it was when the displays were last polled.
+    pub(crate) fn last_primary_display(&self) -> Option<CrossDisplay> {
+        let last_displays = self.last_displays.borrow();
+        let (displays, primary_id) = last_displays.as_ref()?;
+        displays
+            .iter()
+            .find(|display| Some(display.id) == *primary_id)
+            .cloned()
+    }
+
+    /// Compares `monitors` with the displays seen when this was last called, and invokes the
+    /// callback when a display was added, removed, moved, resized or rescaled in between.
+    pub(crate) fn poll(
+        &self,
+        monitors: impl IntoIterator<Item = MonitorHandle>,
+        primary_monitor: Option<MonitorHandle>,
+    ) {
+        let displays = self.displays(monitors);
+        let primary_id = primary_monitor.and_then(|primary_monitor| {
+            displays
+                .iter()
+                .find_map(|(monitor, display)| (*monitor == primary_monitor).then_some(display.id))
+        });
+        self.update(
+            displays.into_iter().map(|(_, display)| display).collect(),
+            primary_id,
+        );
+    }
+
+    fn update(&self, displays: Vec<CrossDisplay>, primary_id: Option<DisplayId>) {
+        let previous = self
+            .last_displays
+            .replace(Some((displays.clone(), primary_id)));
+        if previous.is_none_or(|(previous, _)| previous == displays) {
+            return;
+        }
+
+        // NOTE(mdeand): The callback asks for the displays again, so nothing may be borrowed
+        // NOTE(mdeand): while it runs.
+        if let Some(mut callback) = self.on_displays_changed.take() {
+            callback();
+            self.on_displays_changed.set(Some(callback));
+        }
+    }
+}
+
+/// The monitor that stands in for the primary display.
+pub(crate) fn primary_monitor(event_loop: &ActiveEventLoop) -> Option<MonitorHandle> {
+    // NOTE(mdeand): Wayland has no primary monitor, so the first one stands in for it.
+    event_loop
+        .primary_monitor()
+        .or_else(|| event_loop.available_monitors().next())
+}
+
+/// The bounds of a monitor in global, logical coordinates.
+pub(crate) fn monitor_bounds(monitor: &MonitorHandle) -> Bounds<Pixels> {
+    let scale_factor = monitor.scale_factor();
+    let position = monitor.position().to_logical::<f32>(scale_factor);
+    let size = monitor.size().to_logical::<f32>(scale_factor);
+    Bounds {
+        origin: Point {
+            x: Pixels(position.x),
+            y: Pixels(position.y),
+        },
+        size: Size {
+            width: Pixels(size.width),
+            height: Pixels(size.height),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_ids_survive_hot_plugs() {
+        let registry = DisplayRegistry::default();
+        let ids = |names: &[&str]| {
+            MonitorKey::for_names(names.iter().map(|name| name.to_string()))
+                .iter()
+                .map(|key| registry.id(key))
+                .collect::<Vec<_>>()
+        };
+
+        let first = ids(&["DP-1", "HDMI-1", "DP-1"]);
+        assert_eq!(first, [DisplayId(0), DisplayId(1), DisplayId(2)]);
+        assert_eq!(ids(&["HDMI-1"]), [DisplayId(1)]);
+        assert_eq!(
+            ids(&["DP-2", "HDMI-1", "DP-1"]),
+            [DisplayId(3), first[1], first[0]]
+        );
+    }
+
+    #[test]
+    fn test_polls_tell_when_displays_were_added_or_removed() {
+        let registry = DisplayRegistry::default();
+        let changes = Rc::new(Cell::new(0));
+        registry.on_displays_changed(Box::new({
+            let changes = changes.clone();
+            move || changes.set(changes.get() + 1)
+        }));
+        let display = |id: u32| CrossDisplay {
+            id: DisplayId(id),
+            uuid: Uuid::nil(),
+            bounds: Bounds::default(),
+            scale_factor: 1.,
+            refresh_rate: None,
+        };
+
+        registry.update(vec![display(0)], Some(DisplayId(0)));
+        assert_eq!(changes.get(), 0);
+        registry.update(vec![display(0)], Some(DisplayId(0)));
+        assert_eq!(changes.get(), 0);
+
+        registry.update(vec![display(0), display(1)], Some(DisplayId(0)));
+        assert_eq!(changes.get(), 1);
+        assert_eq!(registry.last_displays(), [display(0), display(1)]);
+        registry.update(vec![display(0), display(1)], Some(DisplayId(0)));
+        assert_eq!(changes.get(), 1);
+
+        registry.update(vec![display(1)], Some(DisplayId(1)));
+        assert_eq!(changes.get(), 2);
+        assert_eq!(registry.last_primary_display(), Some(display(1)));
+    }
+}
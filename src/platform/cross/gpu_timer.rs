@@ -0,0 +1,224 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{FrameStats, PrimitiveKind};
+
+/// How many timestamps a frame may write. Frames drawing more batches than fit aren't timed.
+const MAX_TIMESTAMPS: u32 = 1024;
+
+/// What a timestamp written while drawing a frame marks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TimestampEvent {
+    /// A pass of batches started drawing into a target, which is either the frame or the
+    /// texture of a layer.
+    PassStart,
+    /// A batch of primitives of the given kind started drawing.
+    Batch(PrimitiveKind),
+    /// The pass that started last finished drawing.
+    PassEnd,
+}
+
+/// Writes GPU timestamps between the batches of a frame and reads them back, to tell how long
+/// each kind of primitive took to draw.
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+    /// What each timestamp written during the current frame marks.
+    events: Vec<TimestampEvent>,
+    recording: bool,
+    /// Whether the current frame isn't timed since the previous frame's timestamps are still
+    /// being read back.
+    skipped: bool,
+    /// Set from when a frame's timestamps are resolved until they were read back.
+    reading: Arc<AtomicBool>,
+}
+
+impl GpuTimer {
+    pub(crate) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let size = MAX_TIMESTAMPS as u64 * wgpu::QUERY_SIZE as u64;
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("gpu_timer"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_TIMESTAMPS,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu_timer_resolve"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("gpu_timer_readback"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            timestamp_period: queue.get_timestamp_period(),
+            events: Vec::new(),
+            recording: false,
+            skipped: false,
+            reading: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Starts timing a frame, unless the previous frame's timestamps are still being read.
+    pub(crate) fn begin_frame(&mut self) {
+        self.events.clear();
+        self.skipped = self.reading.load(Ordering::Acquire);
+        self.recording = !self.skipped;
+    }
+
+    /// Writes a timestamp into `pass`, marking `event`.
+    pub(crate) fn write(&mut self, pass: &mut wgpu::RenderPass<'_>, event: TimestampEvent) {
+        if !self.recording {
+            return;
+        }
+        if self.events.len() as u32 == MAX_TIMESTAMPS {
+            log::warn!("frame draws too many batches to be timed");
+            self.events.clear();
+            self.recording = false;
+            return;
+        }
+        pass.write_timestamp(&self.query_set, self.events.len() as u32);
+        self.events.push(event);
+    }
+
+    /// Stops timing the frame and publishes `stats` once the GPU times measured for it were
+    /// added. Frames that weren't timed are published right away, unless they were skipped.
+    pub(crate) fn end_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mut stats: FrameStats,
+        published: &Arc<Mutex<Option<FrameStats>>>,
+    ) {
+        self.recording = false;
+        if self.skipped {
+            return;
+        }
+        if self.events.is_empty() {
+            *published.lock().unwrap() = Some(stats);
+            return;
+        }
+
+        let count = self.events.len() as u32;
+        let size = count as u64 * wgpu::QUERY_SIZE as u64;
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("gpu_timer"),
+        });
+        command_encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        command_encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            size,
+        );
+        queue.submit(Some(command_encoder.finish()));
+
+        self.reading.store(true, Ordering::Release);
+        let events = std::mem::take(&mut self.events);
+        let timestamp_period = self.timestamp_period;
+        let readback_buffer = self.readback_buffer.clone();
+        let reading = self.reading.clone();
+        let published = published.clone();
+        self.readback_buffer
+            .map_async(wgpu::MapMode::Read, ..size, move |result| {
+                match result {
+                    Ok(()) => {
+                        let timestamps = readback_buffer
+                            .get_mapped_range(..size)
+                            .chunks_exact(wgpu::QUERY_SIZE as usize)
+                            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                            .collect::<Vec<_>>();
+                        readback_buffer.unmap();
+                        add_gpu_times(&mut stats, &events, &timestamps, timestamp_period);
+                        *published.lock().unwrap() = Some(stats);
+                    }
+                    Err(error) => log::error!("failed to read back GPU timestamps: {error}"),
+                }
+                reading.store(false, Ordering::Release);
+            });
+    }
+}
+
+/// Adds the time between each timestamp and the next to the kind of batch that was drawing in
+/// between, so layers don't count the time spent on the primitives inside them.
+fn add_gpu_times(
+    stats: &mut FrameStats,
+    events: &[TimestampEvent],
+    timestamps: &[u64],
+    timestamp_period: f32,
+) {
+    let elapsed = |start: u64, end: u64| {
+        Duration::from_nanos((end.saturating_sub(start) as f64 * timestamp_period as f64) as u64)
+    };
+
+    // NOTE(mdeand): The kind of batch drawing in each pass that's still open, from the pass
+    // NOTE(mdeand): drawing into the frame to the innermost layer's.
+    let mut pass_kinds: Vec<Option<PrimitiveKind>> = Vec::new();
+    for (ix, event) in events.iter().enumerate() {
+        match event {
+            TimestampEvent::PassStart => pass_kinds.push(None),
+            TimestampEvent::Batch(kind) => {
+                if let Some(pass_kind) = pass_kinds.last_mut() {
+                    *pass_kind = Some(*kind);
+                }
+            }
+            TimestampEvent::PassEnd => {
+                pass_kinds.pop();
+            }
+        }
+        let (Some(Some(kind)), Some(&end)) = (pass_kinds.last(), timestamps.get(ix + 1)) else {
+            continue;
+        };
+        let gpu_time = stats.batch_mut(*kind).gpu_time.get_or_insert_default();
+        *gpu_time += elapsed(timestamps[ix], end);
+    }
+
+    if let (Some(&start), Some(&end)) = (timestamps.first(), timestamps.last()) {
+        stats.gpu_time = Some(elapsed(start, end));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_exclude_the_batches_inside_them() {
+        use TimestampEvent::*;
+
+        let mut stats = FrameStats::default();
+        add_gpu_times(
+            &mut stats,
+            &[
+                PassStart,
+                Batch(PrimitiveKind::Quad),
+                Batch(PrimitiveKind::Layer),
+                PassStart,
+                Batch(PrimitiveKind::Quad),
+                PassEnd,
+                Batch(PrimitiveKind::Path),
+                PassEnd,
+            ],
+            &[0, 10, 30, 40, 45, 60, 100, 130],
+            1.0,
+        );
+
+        let gpu_time = |kind| stats.batch(kind).unwrap().gpu_time.unwrap().as_nanos();
+        assert_eq!(gpu_time(PrimitiveKind::Quad), 20 + 15);
+        assert_eq!(gpu_time(PrimitiveKind::Layer), 10 + 40);
+        assert_eq!(gpu_time(PrimitiveKind::Path), 30);
+        assert_eq!(stats.gpu_time, Some(Duration::from_nanos(130)));
+    }
+}
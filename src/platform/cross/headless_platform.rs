@@ -0,0 +1,399 @@
+use crate::{
+    BackgroundExecutor, Bounds, ClipboardItem, DisplayId, DummyKeyboardMapper, ForegroundExecutor,
+    Pixels, Platform, PlatformDisplay, PriorityQueueReceiver, RunnableVariant,
+    platform::cross::{
+        dispatcher::{Dispatcher, MainThreadWaker},
+        headless_window::{HeadlessWindow, HeadlessWindowInner},
+        keyboard::CrossKeyboardLayout,
+        offscreen_renderer::OffscreenBackend,
+        platform::PlatformCallbacks,
+        text_system::CosmicTextSystem,
+    },
+    point, px, size,
+};
+use anyhow::Result;
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    rc::{Rc, Weak},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How often headless windows are asked to render a frame.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// A platform that runs without a windowing system. Windows are rendered offscreen, so apps
+/// can run on machines without a display server, e.g. to render thumbnails on a CI server.
+pub(crate) struct HeadlessPlatform {
+    background_executor: BackgroundExecutor,
+    foreground_executor: ForegroundExecutor,
+    text_system: Arc<CosmicTextSystem>,
+    // NOTE(mdeand): Created on the first `open_window`, so headless apps that never open a
+    // NOTE(mdeand): window don't need a GPU (or even a software adapter) at all.
+    offscreen_backend: OnceCell<OffscreenBackend>,
+    main_rx: RefCell<PriorityQueueReceiver<RunnableVariant>>,
+    windows: RefCell<Vec<Weak<HeadlessWindowInner>>>,
+    display: Rc<HeadlessDisplay>,
+    clipboard: RefCell<Option<ClipboardItem>>,
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    primary: RefCell<Option<ClipboardItem>>,
+    should_quit: Cell<bool>,
+    callbacks: PlatformCallbacks,
+}
+
+impl HeadlessPlatform {
+    pub fn new() -> Self {
+        let (main_tx, main_rx) = PriorityQueueReceiver::new();
+        let dispatcher = Arc::new(Dispatcher::new(
+            main_tx,
+            MainThreadWaker::Thread(std::thread::current()),
+        ));
+
+        Self {
+            background_executor: BackgroundExecutor::new(dispatcher.clone()),
+            foreground_executor: ForegroundExecutor::new(dispatcher),
+            text_system: Arc::new(CosmicTextSystem::new()),
+            offscreen_backend: OnceCell::new(),
+            main_rx: RefCell::new(main_rx),
+            windows: RefCell::default(),
+            display: Rc::new(HeadlessDisplay::new()),
+            clipboard: RefCell::default(),
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            primary: RefCell::default(),
+            should_quit: Cell::new(false),
+            callbacks: PlatformCallbacks::default(),
+        }
+    }
+
+    fn offscreen_backend(&self) -> Result<OffscreenBackend> {
+        if let Some(backend) = self.offscreen_backend.get() {
+            return Ok(backend.clone());
+        }
+        let backend = OffscreenBackend::new()?;
+        Ok(self.offscreen_backend.get_or_init(|| backend).clone())
+    }
+
+    fn drain_main_queue(&self) {
+        loop {
+            // NOTE(mdeand): Don't hold the receiver while running, the runnable may spawn
+            // NOTE(mdeand): more work for the main thread.
+            let runnable = self.main_rx.borrow_mut().try_pop();
+            match runnable {
+                Ok(Some(RunnableVariant::Compat(runnable))) => {
+                    runnable.run();
+                }
+                Ok(Some(RunnableVariant::Meta(runnable))) => {
+                    runnable.run();
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+    }
+
+    fn request_frames(&self) {
+        let windows = {
+            let mut windows = self.windows.borrow_mut();
+            windows.retain(|window| window.strong_count() > 0);
+            windows
+                .iter()
+                .filter_map(|window| window.upgrade().map(HeadlessWindow))
+                .collect::<Vec<_>>()
+        };
+
+        for window in windows {
+            window.request_frame();
+        }
+    }
+}
+
+impl Platform for HeadlessPlatform {
+    fn background_executor(&self) -> BackgroundExecutor {
+        self.background_executor.clone()
+    }
+
+    fn foreground_executor(&self) -> ForegroundExecutor {
+        self.foreground_executor.clone()
+    }
+
+    fn text_system(&self) -> Arc<dyn crate::PlatformTextSystem> {
+        self.text_system.clone()
+    }
+
+    fn run(&self, on_finish_launching: Box<dyn 'static + FnOnce()>) {
+        on_finish_launching();
+
+        let mut next_frame = Instant::now();
+        while !self.should_quit.get() {
+            self.drain_main_queue();
+
+            let now = Instant::now();
+            if now >= next_frame {
+                self.request_frames();
+                next_frame = now + FRAME_INTERVAL;
+            }
+
+            if self.should_quit.get() {
+                break;
+            }
+
+            // NOTE(mdeand): The dispatcher unparks us as soon as work is queued for the main
+            // NOTE(mdeand): thread, so this only sleeps while there's nothing to do.
+            std::thread::park_timeout(next_frame.saturating_duration_since(Instant::now()));
+        }
+
+        if let Some(mut on_quit) = self.callbacks.on_quit.take() {
+            on_quit();
+        }
+    }
+
+    fn quit(&self) {
+        self.should_quit.set(true);
+    }
+
+    fn restart(&self, _binary_path: Option<std::path::PathBuf>) {
+        log::warn!("restart is not supported on the headless platform");
+    }
+
+    fn activate(&self, _ignoring_other_apps: bool) {}
+
+    fn hide(&self) {}
+
+    fn hide_other_apps(&self) {}
+
+    fn unhide_other_apps(&self) {}
+
+    fn displays(&self) -> Vec<Rc<dyn PlatformDisplay>> {
+        vec![self.display.clone()]
+    }
+
+    fn primary_display(&self) -> Option<Rc<dyn PlatformDisplay>> {
+        Some(self.display.clone())
+    }
+
+    fn active_window(&self) -> Option<crate::AnyWindowHandle> {
+        None
+    }
+
+    fn open_window(
+        &self,
+        _handle: crate::AnyWindowHandle,
+        options: crate::WindowParams,
+    ) -> anyhow::Result<Box<dyn crate::PlatformWindow>> {
+        let window =
+            HeadlessWindow::new(&self.offscreen_backend()?, &options, self.display.clone());
+        self.windows.borrow_mut().push(window.downgrade());
+        Ok(Box::new(window))
+    }
+
+    fn window_appearance(&self) -> crate::WindowAppearance {
+        crate::WindowAppearance::default()
+    }
+
+    fn open_url(&self, _url: &str) {
+        log::warn!("open_url is not supported on the headless platform");
+    }
+
+    fn on_open_urls(&self, callback: Box<dyn FnMut(Vec<String>)>) {
+        self.callbacks.on_open_urls.set(Some(callback));
+    }
+
+    fn register_url_scheme(&self, _url: &str) -> crate::Task<anyhow::Result<()>> {
+        crate::Task::ready(Err(anyhow::anyhow!(
+            "register_url_scheme is not supported on the headless platform"
+        )))
+    }
+
+    fn prompt_for_paths(
+        &self,
+        _options: crate::PathPromptOptions,
+    ) -> futures::channel::oneshot::Receiver<anyhow::Result<Option<Vec<std::path::PathBuf>>>> {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let _ = sender.send(Ok(None));
+        receiver
+    }
+
+    fn prompt_for_new_path(
+        &self,
+        _directory: &std::path::Path,
+        _suggested_name: Option<&str>,
+    ) -> futures::channel::oneshot::Receiver<anyhow::Result<Option<std::path::PathBuf>>> {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let _ = sender.send(Ok(None));
+        receiver
+    }
+
+    fn can_select_mixed_files_and_dirs(&self) -> bool {
+        false
+    }
+
+    fn reveal_path(&self, _path: &std::path::Path) {}
+
+    fn open_with_system(&self, _path: &std::path::Path) {}
+
+    fn on_quit(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks.on_quit.set(Some(callback));
+    }
+
+    fn on_reopen(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks.on_reopen.set(Some(callback));
+    }
+
+    fn set_menus(&self, _menus: Vec<crate::Menu>, _keymap: &crate::Keymap) {}
+
+    fn set_dock_menu(&self, _menu: Vec<crate::MenuItem>, _keymap: &crate::Keymap) {}
+
+    fn on_app_menu_action(&self, callback: Box<dyn FnMut(&dyn crate::Action)>) {
+        self.callbacks.on_app_menu_action.set(Some(callback));
+    }
+
+    fn on_will_open_app_menu(&self, callback: Box<dyn FnMut()>) {
+        self.callbacks.on_will_open_app_menu.set(Some(callback));
+    }
+
+    fn on_validate_app_menu_command(&self, callback: Box<dyn FnMut(&dyn crate::Action) -> bool>) {
+        self.callbacks
+            .on_validate_app_menu_command
+            .set(Some(callback));
+    }
+
+    fn app_path(&self) -> anyhow::Result<std::path::PathBuf> {
+        Ok(std::env::current_exe()?)
+    }
+
+    fn path_for_auxiliary_executable(&self, _name: &str) -> anyhow::Result<std::path::PathBuf> {
+        Err(anyhow::anyhow!(
+            "path_for_auxiliary_executable is not supported on the headless platform"
+        ))
+    }
+
+    fn set_cursor_style(&self, _style: crate::CursorStyle) {}
+
+    fn should_auto_hide_scrollbars(&self) -> bool {
+        false
+    }
+
+    // NOTE(mdeand): There's no system clipboard to talk to, but keeping the last item around
+    // NOTE(mdeand): lets copy and paste work within the app.
+    fn write_to_clipboard(&self, item: ClipboardItem) {
+        self.clipboard.replace(Some(item));
+    }
+
+    fn read_from_clipboard(&self) -> Option<ClipboardItem> {
+        self.clipboard.borrow().clone()
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn write_to_primary(&self, item: ClipboardItem) {
+        self.primary.replace(Some(item));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.primary.borrow().clone()
+    }
+
+    fn write_credentials(
+        &self,
+        _url: &str,
+        _username: &str,
+        _password: &[u8],
+    ) -> crate::Task<anyhow::Result<()>> {
+        crate::Task::ready(Err(anyhow::anyhow!(
+            "write_credentials is not supported on the headless platform"
+        )))
+    }
+
+    fn read_credentials(
+        &self,
+        _url: &str,
+    ) -> crate::Task<anyhow::Result<Option<(String, Vec<u8>)>>> {
+        crate::Task::ready(Ok(None))
+    }
+
+    fn delete_credentials(&self, _url: &str) -> crate::Task<anyhow::Result<()>> {
+        crate::Task::ready(Ok(()))
+    }
+
+    fn keyboard_layout(&self) -> Box<dyn crate::PlatformKeyboardLayout> {
+        Box::new(CrossKeyboardLayout)
+    }
+
+    fn keyboard_mapper(&self) -> Rc<dyn crate::PlatformKeyboardMapper> {
+        Rc::new(DummyKeyboardMapper)
+    }
+
+    fn on_keyboard_layout_change(&self, _callback: Box<dyn FnMut()>) {}
+}
+
+/// The single, virtual display of the headless platform.
+#[derive(Debug)]
+pub(crate) struct HeadlessDisplay {
+    uuid: uuid::Uuid,
+    bounds: Bounds<Pixels>,
+}
+
+impl HeadlessDisplay {
+    fn new() -> Self {
+        Self {
+            uuid: uuid::Uuid::new_v4(),
+            bounds: Bounds::new(point(px(0.), px(0.)), size(px(1920.), px(1080.))),
+        }
+    }
+}
+
+impl PlatformDisplay for HeadlessDisplay {
+    fn id(&self) -> DisplayId {
+        DisplayId(0)
+    }
+
+    fn uuid(&self) -> Result<uuid::Uuid> {
+        Ok(self.uuid)
+    }
+
+    fn bounds(&self) -> Bounds<Pixels> {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_drains_the_main_thread_until_quit() {
+        let platform = Rc::new(HeadlessPlatform::new());
+        let events = Rc::new(RefCell::new(Vec::new()));
+        platform.on_quit(Box::new({
+            let events = events.clone();
+            move || events.borrow_mut().push("quit".to_string())
+        }));
+
+        platform.run(Box::new({
+            let platform = platform.clone();
+            let events = events.clone();
+            move || {
+                let background = platform.background_executor();
+                let foreground = platform.foreground_executor();
+                foreground
+                    .spawn({
+                        let foreground = foreground.clone();
+                        async move {
+                            // NOTE(mdeand): Work that hops between threads, and work spawned
+                            // NOTE(mdeand): by work on the main thread, runs before quitting.
+                            let answer = background.spawn(async { 42 }).await;
+                            events.borrow_mut().push(format!("background {answer}"));
+                            foreground
+                                .spawn(async move {
+                                    events.borrow_mut().push("foreground".to_string());
+                                    platform.quit();
+                                })
+                                .detach();
+                        }
+                    })
+                    .detach();
+            }
+        }));
+
+        assert_eq!(*events.borrow(), ["background 42", "foreground", "quit"]);
+    }
+}
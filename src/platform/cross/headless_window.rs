@@ -0,0 +1,394 @@
+use crate::{
+    Bounds, Capslock, DevicePixels, Modifiers, Pixels, PlatformAtlas, PlatformInputHandler,
+    PlatformWindow, Point, Size, WindowAppearance, WindowBackgroundAppearance, WindowBounds,
+    WindowParams,
+    platform::cross::{
+        headless_platform::HeadlessDisplay,
+        offscreen_renderer::{OffscreenBackend, OffscreenRenderer},
+        window::CrossWindowState,
+    },
+};
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+    sync::Arc,
+};
+
+/// A window that isn't backed by the windowing system. Every frame is rendered into an
+/// offscreen texture instead of being presented.
+#[derive(Clone)]
+pub struct HeadlessWindow(pub(crate) Rc<HeadlessWindowInner>);
+
+pub(crate) struct HeadlessWindowInner {
+    pub(crate) renderer: RefCell<OffscreenRenderer>,
+    pub(crate) sprite_atlas: Arc<dyn PlatformAtlas>,
+    pub(crate) bounds: Cell<Bounds<Pixels>>,
+    pub(crate) scale_factor: f32,
+    pub(crate) display: Rc<HeadlessDisplay>,
+    pub(crate) title: RefCell<String>,
+    pub(crate) active: Cell<bool>,
+    pub(crate) fullscreen: Cell<bool>,
+    pub(crate) state: CrossWindowState,
+}
+
+impl HeadlessWindow {
+    pub(crate) fn new(
+        backend: &OffscreenBackend,
+        params: &WindowParams,
+        display: Rc<HeadlessDisplay>,
+    ) -> Self {
+        // NOTE(mdeand): There's no display to inherit a scale factor from, so frames are
+        // NOTE(mdeand): rendered at one device pixel per logical pixel.
+        let scale_factor = 1.0;
+        let (renderer, sprite_atlas) =
+            OffscreenRenderer::new(backend, device_size(params.bounds.size, scale_factor));
+
+        Self(Rc::new(HeadlessWindowInner {
+            renderer: RefCell::new(renderer),
+            sprite_atlas,
+            bounds: Cell::new(params.bounds),
+            scale_factor,
+            display,
+            title: RefCell::new(
+                params
+                    .titlebar
+                    .as_ref()
+                    .and_then(|titlebar| titlebar.title.as_ref())
+                    .map(|title| title.to_string())
+                    .unwrap_or_default(),
+            ),
+            active: Cell::new(params.focus),
+            fullscreen: Cell::new(false),
+            state: CrossWindowState::default(),
+        }))
+    }
+
+    pub(crate) fn downgrade(&self) -> Weak<HeadlessWindowInner> {
+        Rc::downgrade(&self.0)
+    }
+
+    /// Asks the window to render a frame, as the windowing system would for on-screen windows.
+    pub(crate) fn request_frame(&self) {
+        self.0
+            .state
+            .callbacks
+            .invoke_mut(&self.0.state.callbacks.on_request_frame, |cb| {
+                cb(crate::RequestFrameOptions {
+                    require_presentation: false,
+                    force_render: false,
+                });
+            });
+    }
+}
+
+fn device_size(size: Size<Pixels>, scale_factor: f32) -> Size<DevicePixels> {
+    Size {
+        width: DevicePixels((size.width.0 * scale_factor).round().max(1.0) as i32),
+        height: DevicePixels((size.height.0 * scale_factor).round().max(1.0) as i32),
+    }
+}
+
+impl PlatformWindow for HeadlessWindow {
+    fn bounds(&self) -> Bounds<Pixels> {
+        self.0.bounds.get()
+    }
+
+    fn is_maximized(&self) -> bool {
+        false
+    }
+
+    fn window_bounds(&self) -> WindowBounds {
+        if self.0.fullscreen.get() {
+            WindowBounds::Fullscreen(self.bounds())
+        } else {
+            WindowBounds::Windowed(self.bounds())
+        }
+    }
+
+    fn content_size(&self) -> Size<Pixels> {
+        self.0.bounds.get().size
+    }
+
+    fn resize(&mut self, size: Size<Pixels>) {
+        let mut bounds = self.0.bounds.get();
+        bounds.size = size;
+        self.0.bounds.set(bounds);
+
+        self.0
+            .renderer
+            .borrow_mut()
+            .update_drawable_size(device_size(size, self.0.scale_factor));
+
+        let scale_factor = self.0.scale_factor;
+        self.0
+            .state
+            .callbacks
+            .invoke_mut(&self.0.state.callbacks.on_resize, |cb| {
+                cb(size, scale_factor);
+            });
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.0.scale_factor
+    }
+
+    fn appearance(&self) -> WindowAppearance {
+        WindowAppearance::default()
+    }
+
+    fn display(&self) -> Option<Rc<dyn crate::PlatformDisplay>> {
+        Some(self.0.display.clone())
+    }
+
+    fn mouse_position(&self) -> Point<Pixels> {
+        self.0.state.mouse_position.get()
+    }
+
+    fn modifiers(&self) -> Modifiers {
+        self.0.state.modifiers.get()
+    }
+
+    fn capslock(&self) -> Capslock {
+        self.0.state.capslock.get()
+    }
+
+    fn set_input_handler(&mut self, input_handler: PlatformInputHandler) {
+        self.0
+            .state
+            .input_handler
+            .borrow_mut()
+            .replace(input_handler);
+    }
+
+    fn take_input_handler(&mut self) -> Option<PlatformInputHandler> {
+        self.0.state.input_handler.borrow_mut().take()
+    }
+
+    fn prompt(
+        &self,
+        _level: crate::PromptLevel,
+        _msg: &str,
+        _detail: Option<&str>,
+        _answers: &[crate::PromptButton],
+    ) -> Option<futures::channel::oneshot::Receiver<usize>> {
+        None
+    }
+
+    fn activate(&self) {
+        if !self.0.active.replace(true) {
+            self.0
+                .state
+                .callbacks
+                .invoke_mut(&self.0.state.callbacks.on_active_status_change, |cb| {
+                    cb(true)
+                });
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.0.active.get()
+    }
+
+    fn is_hovered(&self) -> bool {
+        false
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.0.title.replace(title.to_string());
+    }
+
+    fn get_title(&self) -> String {
+        self.0.title.borrow().clone()
+    }
+
+    fn set_background_appearance(&self, _background_appearance: WindowBackgroundAppearance) {}
+
+    fn minimize(&self) {}
+
+    fn zoom(&self) {}
+
+    fn toggle_fullscreen(&self) {
+        self.0.fullscreen.set(!self.0.fullscreen.get());
+    }
+
+    fn is_fullscreen(&self) -> bool {
+        self.0.fullscreen.get()
+    }
+
+    fn on_request_frame(&self, callback: Box<dyn FnMut(crate::RequestFrameOptions)>) {
+        self.0.state.callbacks.on_request_frame.set(Some(callback));
+    }
+
+    fn on_input(
+        &self,
+        callback: Box<dyn FnMut(crate::PlatformInput) -> crate::DispatchEventResult>,
+    ) {
+        self.0.state.callbacks.on_input.set(Some(callback));
+    }
+
+    fn on_active_status_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.0
+            .state
+            .callbacks
+            .on_active_status_change
+            .set(Some(callback));
+    }
+
+    fn on_hover_status_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.0
+            .state
+            .callbacks
+            .on_hover_status_change
+            .set(Some(callback));
+    }
+
+    fn on_resize(&self, callback: Box<dyn FnMut(Size<Pixels>, f32)>) {
+        self.0.state.callbacks.on_resize.set(Some(callback));
+    }
+
+    fn on_moved(&self, callback: Box<dyn FnMut()>) {
+        self.0.state.callbacks.on_moved.set(Some(callback));
+    }
+
+    fn on_should_close(&self, callback: Box<dyn FnMut() -> bool>) {
+        self.0.state.callbacks.on_should_close.set(Some(callback));
+    }
+
+    fn on_hit_test_window_control(
+        &self,
+        callback: Box<dyn FnMut() -> Option<crate::WindowControlArea>>,
+    ) {
+        self.0
+            .state
+            .callbacks
+            .on_hit_test_window_control
+            .set(Some(callback));
+    }
+
+    fn on_close(&self, callback: Box<dyn FnOnce()>) {
+        self.0.state.callbacks.on_close.set(Some(callback));
+    }
+
+    fn on_appearance_changed(&self, callback: Box<dyn FnMut()>) {
+        self.0
+            .state
+            .callbacks
+            .on_appearance_changed
+            .set(Some(callback));
+    }
+
+    fn draw(&self, scene: &crate::Scene) {
+        self.0.renderer.borrow_mut().draw(scene);
+    }
+
+    fn capture_frame(
+        &self,
+        scene: &crate::Scene,
+    ) -> Option<futures::channel::oneshot::Receiver<anyhow::Result<image::RgbaImage>>> {
+        Some(self.0.renderer.borrow_mut().capture_frame(scene))
+    }
+
+    fn sprite_atlas(&self) -> Arc<dyn crate::PlatformAtlas> {
+        self.0.sprite_atlas.clone()
+    }
+
+    fn gpu_specs(&self) -> Option<crate::GpuSpecs> {
+        Some(self.0.renderer.borrow().gpu_specs())
+    }
+
+    fn last_frame_stats(&self) -> Option<crate::FrameStats> {
+        self.0.renderer.borrow().last_frame_stats()
+    }
+
+    fn update_ime_position(&self, _bounds: Bounds<Pixels>) {}
+}
+
+impl raw_window_handle::HasDisplayHandle for HeadlessWindow {
+    fn display_handle(
+        &self,
+    ) -> Result<raw_window_handle::DisplayHandle<'_>, raw_window_handle::HandleError> {
+        Err(raw_window_handle::HandleError::NotSupported)
+    }
+}
+
+impl raw_window_handle::HasWindowHandle for HeadlessWindow {
+    fn window_handle(
+        &self,
+    ) -> Result<raw_window_handle::WindowHandle<'_>, raw_window_handle::HandleError> {
+        Err(raw_window_handle::HandleError::NotSupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        AppContext as _, Application, Bounds, Context, IntoElement, ParentElement as _, Render,
+        Styled as _, Window, WindowBounds, WindowOptions, div, point, px, red, size,
+    };
+    use image::{Rgba, RgbaImage};
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Square;
+
+    impl Render for Square {
+        fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+            div()
+                .size_full()
+                .child(div().absolute().left(px(4.)).size(px(8.)).bg(red()))
+        }
+    }
+
+    #[test]
+    fn test_frames_round_trip_through_headless_windows() {
+        // NOTE(mdeand): Headless windows fall back to the software renderer when it's built.
+        #[cfg(not(feature = "software-renderer"))]
+        if crate::platform::cross::render_context::WgpuContext::for_test().is_none() {
+            return;
+        }
+
+        let frame = Rc::new(RefCell::new(None));
+        Application::headless().run({
+            let frame = frame.clone();
+            move |cx| {
+                let window = cx
+                    .open_window(
+                        WindowOptions {
+                            window_bounds: Some(WindowBounds::Windowed(Bounds::new(
+                                point(px(0.), px(0.)),
+                                size(px(16.), px(8.)),
+                            ))),
+                            ..Default::default()
+                        },
+                        |_, cx| cx.new(|_| Square),
+                    )
+                    .unwrap();
+                let display_id = window
+                    .update(cx, |_, window, cx| {
+                        window.display(cx).map(|display| display.id())
+                    })
+                    .unwrap();
+                assert!(display_id.is_some());
+                assert_eq!(display_id, cx.primary_display().map(|display| display.id()));
+                cx.spawn(async move |cx| {
+                    let capture = window
+                        .update(cx, |_, window, cx| window.capture_frame(cx))
+                        .unwrap();
+                    frame.replace(Some(capture.await));
+                    cx.update(|cx| cx.quit()).unwrap();
+                })
+                .detach();
+            }
+        });
+
+        let frame: RgbaImage = frame.take().unwrap().unwrap();
+        assert_eq!(frame.dimensions(), (16, 8));
+        for (x, _, pixel) in frame.enumerate_pixels() {
+            let red = *pixel == Rgba([255, 0, 0, 255]);
+            assert_eq!(
+                red,
+                (4..12).contains(&x),
+                "pixel in column {x} is {pixel:?}"
+            );
+        }
+    }
+}
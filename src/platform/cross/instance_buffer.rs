@@ -0,0 +1,212 @@
+/// The smallest size an instance buffer is ever allocated with.
+const MIN_INSTANCE_BUFFER_SIZE: u64 = 64 * 1024;
+
+/// How many consecutive frames a buffer has to be mostly unused before it is shrunk.
+const SHRINK_AFTER_FRAMES: u32 = 120;
+
+/// A storage buffer holding the per-instance data of a single primitive kind.
+///
+/// The buffer grows to fit the largest frame it has seen, and shrinks again once frames
+/// have used less than a quarter of it for a while, so a single dense frame doesn't pin
+/// its memory for the rest of the session.
+pub(crate) struct InstanceBuffer {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+    offset: u64,
+    /// How many bytes were written since the frame began, across resets.
+    written_bytes: u64,
+    underused_frames: u32,
+}
+
+impl InstanceBuffer {
+    pub(crate) fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        Self {
+            label,
+            buffer: create_buffer(device, label, MIN_INSTANCE_BUFFER_SIZE),
+            offset: 0,
+            written_bytes: 0,
+            underused_frames: 0,
+        }
+    }
+
+    pub(crate) fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub(crate) fn written_bytes(&self) -> u64 {
+        self.written_bytes
+    }
+
+    /// Prepares the buffer for a frame that needs `required` bytes, reallocating it if it is
+    /// too small or has been too large for a while. The size never exceeds `max_size`.
+    ///
+    /// Returns `true` if the underlying buffer was replaced, in which case any bind group
+    /// referencing it has to be recreated.
+    pub(crate) fn begin_frame(
+        &mut self,
+        device: &wgpu::Device,
+        required: u64,
+        max_size: u64,
+    ) -> bool {
+        self.offset = 0;
+        self.written_bytes = 0;
+
+        let current = self.buffer.size();
+        let (capacity, underused_frames) =
+            next_capacity(current, required, max_size, self.underused_frames);
+        self.underused_frames = underused_frames;
+
+        if capacity == current {
+            return false;
+        }
+
+        if capacity > current {
+            log::debug!(
+                "growing {} from {} to {} bytes",
+                self.label,
+                current,
+                capacity
+            );
+        } else {
+            log::debug!(
+                "shrinking {} from {} to {} bytes",
+                self.label,
+                current,
+                capacity
+            );
+        }
+
+        self.buffer = create_buffer(device, self.label, capacity);
+        true
+    }
+
+    /// Forgets everything written since the last reset, so the next submission can reuse the
+    /// whole buffer.
+    pub(crate) fn reset(&mut self) {
+        self.offset = 0;
+    }
+
+    /// Queues `data` to be written after everything else written since the last reset,
+    /// returning the index of its first element, or `None` if it doesn't fit.
+    ///
+    /// Each buffer only ever holds elements of a single type, so offsets stay aligned to
+    /// `T` and the returned index can be used directly as a first instance or vertex.
+    pub(crate) fn write<T>(&mut self, queue: &wgpu::Queue, data: &[T]) -> Option<u32> {
+        let element_size = std::mem::size_of::<T>() as u64;
+        let bytes = unsafe { as_bytes(data) };
+        let end = self.offset + bytes.len() as u64;
+
+        if end > self.buffer.size() {
+            log::error!(
+                "{} overflow: {} bytes needed, {} available",
+                self.label,
+                end,
+                self.buffer.size()
+            );
+            return None;
+        }
+
+        let first = (self.offset / element_size) as u32;
+        if !bytes.is_empty() {
+            queue.write_buffer(&self.buffer, self.offset, bytes);
+        }
+        self.offset = end;
+        self.written_bytes += bytes.len() as u64;
+        Some(first)
+    }
+}
+
+fn create_buffer(device: &wgpu::Device, label: &'static str, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: wgpu::BufferUsages::VERTEX
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    })
+}
+
+/// Computes the size an instance buffer of `current` bytes should have for a frame that
+/// needs `required` bytes, along with the updated count of consecutive underused frames.
+fn next_capacity(current: u64, required: u64, max_size: u64, underused_frames: u32) -> (u64, u32) {
+    let max_size = max_size.max(MIN_INSTANCE_BUFFER_SIZE);
+    let target = required
+        .checked_next_power_of_two()
+        .unwrap_or(u64::MAX)
+        .clamp(MIN_INSTANCE_BUFFER_SIZE, max_size);
+
+    if target > current {
+        return (target, 0);
+    }
+
+    if target <= current / 4 {
+        let underused_frames = underused_frames + 1;
+        if underused_frames >= SHRINK_AFTER_FRAMES {
+            // NOTE(mdeand): Keep some headroom so a frame that is slightly denser than the
+            // NOTE(mdeand): ones that triggered the shrink doesn't immediately grow it again.
+            return ((target * 2).min(current), 0);
+        }
+        return (current, underused_frames);
+    }
+
+    (current, 0)
+}
+
+pub(crate) unsafe fn as_bytes<T>(slice: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(
+            slice.as_ptr() as *const u8,
+            slice.len() * std::mem::size_of::<T>(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: u64 = 128 * 1024 * 1024;
+
+    #[test]
+    fn test_grows_to_next_power_of_two() {
+        assert_eq!(
+            next_capacity(MIN_INSTANCE_BUFFER_SIZE, 100_000, MAX, 0),
+            (131_072, 0)
+        );
+        assert_eq!(next_capacity(131_072, 131_072, MAX, 0), (131_072, 0));
+    }
+
+    #[test]
+    fn test_growth_is_clamped_to_max_size() {
+        assert_eq!(
+            next_capacity(MIN_INSTANCE_BUFFER_SIZE, MAX * 3, MAX, 0),
+            (MAX, 0)
+        );
+    }
+
+    #[test]
+    fn test_shrinks_only_after_sustained_underuse() {
+        let current = 4 * 1024 * 1024;
+        let mut underused_frames = 0;
+        for _ in 1..SHRINK_AFTER_FRAMES {
+            let (capacity, frames) = next_capacity(current, 1024, MAX, underused_frames);
+            assert_eq!(capacity, current);
+            underused_frames = frames;
+        }
+        assert_eq!(
+            next_capacity(current, 1024, MAX, underused_frames),
+            (2 * MIN_INSTANCE_BUFFER_SIZE, 0)
+        );
+    }
+
+    #[test]
+    fn test_busy_frame_resets_underuse() {
+        let current = 4 * 1024 * 1024;
+        assert_eq!(next_capacity(current, 1024, MAX, 10), (current, 11));
+        assert_eq!(
+            next_capacity(current, 2 * 1024 * 1024, MAX, 11),
+            (current, 0)
+        );
+    }
+}
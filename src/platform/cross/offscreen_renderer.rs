@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::channel::oneshot;
+use image::RgbaImage;
+
+#[cfg(feature = "software-renderer")]
+use crate::platform::cross::{software_atlas::SoftwareAtlas, software_renderer::SoftwareRenderer};
+use crate::{
+    DevicePixels, FrameStats, GpuSpecs, PlatformAtlas, Scene, Size,
+    platform::cross::{atlas::WgpuAtlas, render_context::WgpuContext, renderer::WgpuRenderer},
+};
+
+/// Set this environment variable to render offscreen windows on the CPU, even when an adapter
+/// is available. Requires the `software-renderer` feature.
+const SOFTWARE_RENDERER_ENV: &str = "GPUI_SOFTWARE_RENDERER";
+
+/// What offscreen windows render with. Shared between all the windows of a platform.
+#[derive(Clone)]
+pub(crate) enum OffscreenBackend {
+    Wgpu(Arc<WgpuContext>),
+    #[cfg(feature = "software-renderer")]
+    Software,
+}
+
+impl OffscreenBackend {
+    /// Picks wgpu when an adapter is available, and the software renderer otherwise or when
+    /// `GPUI_SOFTWARE_RENDERER` is set.
+    pub(crate) fn new() -> Result<Self> {
+        let force_software = std::env::var_os(SOFTWARE_RENDERER_ENV).is_some();
+
+        #[cfg(feature = "software-renderer")]
+        {
+            if force_software {
+                return Ok(OffscreenBackend::Software);
+            }
+            match WgpuContext::new() {
+                Ok(context) => Ok(OffscreenBackend::Wgpu(Arc::new(context))),
+                Err(error) => {
+                    log::warn!("no wgpu adapter, falling back to the software renderer: {error:?}");
+                    Ok(OffscreenBackend::Software)
+                }
+            }
+        }
+
+        #[cfg(not(feature = "software-renderer"))]
+        {
+            if force_software {
+                log::warn!(
+                    "{SOFTWARE_RENDERER_ENV} is set, but gpui was built without the \
+                     software-renderer feature"
+                );
+            }
+            Ok(OffscreenBackend::Wgpu(Arc::new(WgpuContext::new()?)))
+        }
+    }
+}
+
+/// Renders a window's frames into an image instead of a surface.
+pub(crate) enum OffscreenRenderer {
+    Wgpu(WgpuRenderer),
+    #[cfg(feature = "software-renderer")]
+    Software(SoftwareRenderer),
+}
+
+impl OffscreenRenderer {
+    /// Creates a renderer along with the atlas its sprites have to be rasterized into.
+    pub(crate) fn new(
+        backend: &OffscreenBackend,
+        size: Size<DevicePixels>,
+    ) -> (Self, Arc<dyn PlatformAtlas>) {
+        let width = size.width.0 as u32;
+        let height = size.height.0 as u32;
+        match backend {
+            OffscreenBackend::Wgpu(context) => {
+                let atlas = Arc::new(WgpuAtlas::new(context.clone()));
+                let renderer =
+                    WgpuRenderer::new_offscreen(context.clone(), atlas.clone(), width, height, 4);
+                (OffscreenRenderer::Wgpu(renderer), atlas)
+            }
+            #[cfg(feature = "software-renderer")]
+            OffscreenBackend::Software => {
+                let atlas = Arc::new(SoftwareAtlas::new());
+                let renderer = SoftwareRenderer::new(atlas.clone(), width, height);
+                (OffscreenRenderer::Software(renderer), atlas)
+            }
+        }
+    }
+
+    pub(crate) fn draw(&mut self, scene: &Scene) {
+        match self {
+            OffscreenRenderer::Wgpu(renderer) => {
+                renderer.draw(scene);
+            }
+            #[cfg(feature = "software-renderer")]
+            OffscreenRenderer::Software(renderer) => renderer.draw(scene),
+        }
+    }
+
+    pub(crate) fn capture_frame(&mut self, scene: &Scene) -> oneshot::Receiver<Result<RgbaImage>> {
+        match self {
+            OffscreenRenderer::Wgpu(renderer) => renderer.capture_frame(scene),
+            #[cfg(feature = "software-renderer")]
+            OffscreenRenderer::Software(renderer) => renderer.capture_frame(scene),
+        }
+    }
+
+    pub(crate) fn update_drawable_size(&mut self, size: Size<DevicePixels>) {
+        match self {
+            OffscreenRenderer::Wgpu(renderer) => renderer.update_drawable_size(size),
+            #[cfg(feature = "software-renderer")]
+            OffscreenRenderer::Software(renderer) => renderer.update_drawable_size(size),
+        }
+    }
+
+    pub(crate) fn gpu_specs(&self) -> GpuSpecs {
+        match self {
+            OffscreenRenderer::Wgpu(renderer) => renderer.gpu_specs(),
+            #[cfg(feature = "software-renderer")]
+            OffscreenRenderer::Software(renderer) => renderer.gpu_specs(),
+        }
+    }
+
+    pub(crate) fn last_frame_stats(&self) -> Option<FrameStats> {
+        match self {
+            OffscreenRenderer::Wgpu(renderer) => renderer.last_frame_stats(),
+            #[cfg(feature = "software-renderer")]
+            OffscreenRenderer::Software(_) => None,
+        }
+    }
+}
@@ -4,17 +4,25 @@ use crate::{
     MouseDownEvent, MouseExitEvent, MouseMoveEvent, MouseUpEvent, Pixels, Platform, PlatformInput,
     PlatformWindow as _, PriorityQueueReceiver, RunnableVariant, ScrollWheelEvent, Size,
     platform::cross::{
-        dispatcher::{CrossEvent, Dispatcher},
+        clipboard::{ClipboardKind, CrossClipboard},
+        cursor::CursorImages,
+        dispatcher::{CrossEvent, Dispatcher, MainThreadWaker},
+        display::{DisplayRegistry, primary_monitor},
         keyboard::CrossKeyboardLayout,
         render_context::WgpuContext,
         text_system::CosmicTextSystem,
-        window::CrossWindow,
+        window::{CrossWindow, window_attributes},
     },
     point,
 };
 use anyhow::Result;
 use collections::FxHashMap;
-use std::{cell::Cell, rc::Rc, sync::Arc, time::Instant};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use winit::event_loop::ActiveEventLoop;
 
 thread_local! {
@@ -34,25 +42,42 @@ pub(crate) struct CrossPlatform {
     background_executor: BackgroundExecutor,
     foreground_executor: ForegroundExecutor,
     text_system: Arc<CosmicTextSystem>,
-    wgpu_context: Arc<WgpuContext>,
+    /// Shared with the event loop, which replaces it when the device is lost.
+    wgpu_context: Rc<RefCell<Arc<WgpuContext>>>,
     main_rx: PriorityQueueReceiver<RunnableVariant>,
     event_loop: Cell<Option<winit::event_loop::EventLoop<CrossEvent>>>,
     event_loop_proxy: winit::event_loop::EventLoopProxy<CrossEvent>,
+    /// Shared with the event loop and the windows, which look up displays by their ids.
+    displays: Rc<DisplayRegistry>,
+    clipboard: CrossClipboard,
+    cursor_images: CursorImages,
     callbacks: PlatformCallbacks,
 }
 
 #[derive(Default)]
-struct PlatformCallbacks {
-    on_open_urls: Cell<Option<Box<dyn FnMut(Vec<String>)>>>,
-    on_quit: Cell<Option<Box<dyn FnMut()>>>,
-    on_reopen: Cell<Option<Box<dyn FnMut()>>>,
-    on_app_menu_action: Cell<Option<Box<dyn FnMut(&dyn crate::Action)>>>,
-    on_will_open_app_menu: Cell<Option<Box<dyn FnMut()>>>,
-    on_validate_app_menu_command: Cell<Option<Box<dyn FnMut(&dyn crate::Action) -> bool>>>,
+pub(crate) struct PlatformCallbacks {
+    pub(crate) on_open_urls: Cell<Option<Box<dyn FnMut(Vec<String>)>>>,
+    pub(crate) on_quit: Cell<Option<Box<dyn FnMut()>>>,
+    pub(crate) on_reopen: Cell<Option<Box<dyn FnMut()>>>,
+    pub(crate) on_app_menu_action: Cell<Option<Box<dyn FnMut(&dyn crate::Action)>>>,
+    pub(crate) on_will_open_app_menu: Cell<Option<Box<dyn FnMut()>>>,
+    pub(crate) on_validate_app_menu_command:
+        Cell<Option<Box<dyn FnMut(&dyn crate::Action) -> bool>>>,
 }
 
+/// How long to wait before trying to recreate a lost device again, after it failed.
+const DEVICE_RECOVERY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often to check whether displays were plugged in, unplugged or rearranged, since winit
+/// doesn't tell.
+const DISPLAY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 struct AppState {
     windows: FxHashMap<winit::window::WindowId, CrossWindow>,
+    wgpu_context: Rc<RefCell<Arc<WgpuContext>>>,
+    last_device_recovery: Option<Instant>,
+    displays: Rc<DisplayRegistry>,
+    last_display_poll: Option<Instant>,
     on_finish_launching: Cell<Option<Box<dyn 'static + FnOnce()>>>,
     main_rx: PriorityQueueReceiver<RunnableVariant>,
     current_modifiers: Modifiers,
@@ -75,7 +100,10 @@ impl CrossPlatform {
         event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
         let event_loop_proxy = event_loop.create_proxy();
 
-        let dispatcher = Arc::new(Dispatcher::new(main_tx, event_loop_proxy.clone()));
+        let dispatcher = Arc::new(Dispatcher::new(
+            main_tx,
+            MainThreadWaker::EventLoop(event_loop_proxy.clone()),
+        ));
         let background_executor = BackgroundExecutor::new(dispatcher.clone());
         let foreground_executor = ForegroundExecutor::new(dispatcher.clone());
 
@@ -83,10 +111,13 @@ impl CrossPlatform {
             background_executor,
             foreground_executor,
             text_system: Arc::new(CosmicTextSystem::new()),
-            wgpu_context: Arc::new(WgpuContext::new()?),
+            wgpu_context: Rc::new(RefCell::new(Arc::new(WgpuContext::new()?))),
             main_rx,
             event_loop: Cell::new(Some(event_loop)),
             event_loop_proxy,
+            displays: Rc::default(),
+            clipboard: CrossClipboard::default(),
+            cursor_images: CursorImages::default(),
             callbacks: PlatformCallbacks::default(),
         })
     }
@@ -110,6 +141,10 @@ impl Platform for CrossPlatform {
 
         let mut app_state = AppState {
             windows: Default::default(),
+            wgpu_context: self.wgpu_context.clone(),
+            last_device_recovery: None,
+            displays: self.displays.clone(),
+            last_display_poll: None,
             on_finish_launching: Cell::new(Some(on_finish_launching)),
             main_rx: self.main_rx.clone(),
             current_modifiers: Modifiers::default(),
@@ -155,13 +190,28 @@ impl Platform for CrossPlatform {
     }
 
     fn displays(&self) -> Vec<Rc<dyn crate::PlatformDisplay>> {
-        // TODO(mdeand): Add support for multiple displays.
-        vec![]
+        with_active_context(|event_loop, _| {
+            self.displays
+                .displays(event_loop.available_monitors())
+                .into_iter()
+                .map(|(_, display)| display)
+                .collect()
+        })
+        .unwrap_or_else(|| self.displays.last_displays())
+        .into_iter()
+        .map(|display| Rc::new(display) as Rc<dyn crate::PlatformDisplay>)
+        .collect()
     }
 
     fn primary_display(&self) -> Option<Rc<dyn crate::PlatformDisplay>> {
-        // TODO(mdeand): Add support for multiple displays and primary display.
-        None
+        let display = with_active_context(|event_loop, _| {
+            self.displays.display(
+                event_loop.available_monitors(),
+                &primary_monitor(event_loop)?,
+            )
+        })
+        .unwrap_or_else(|| self.displays.last_primary_display())?;
+        Some(Rc::new(display) as Rc<dyn crate::PlatformDisplay>)
     }
 
     fn active_window(&self) -> Option<crate::AnyWindowHandle> {
@@ -174,29 +224,27 @@ impl Platform for CrossPlatform {
         _handle: crate::AnyWindowHandle,
         options: crate::WindowParams,
     ) -> anyhow::Result<Box<dyn crate::PlatformWindow>> {
-        let window = CrossWindow::new(self.wgpu_context.clone(), self.event_loop_proxy.clone());
-
+        let window = CrossWindow::new(
+            self.wgpu_context.borrow().clone(),
+            self.event_loop_proxy.clone(),
+            self.displays.clone(),
+        );
+
+        let present_mode = options.present_mode;
+        let max_frame_latency = options.max_frame_latency;
+        window
+            .0
+            .background_appearance
+            .set(options.window_background);
         let success = with_active_context(|event_loop, app_state| {
-            let bounds = options.bounds;
-            let attributes = winit::window::Window::default_attributes()
-                .with_title(
-                    options
-                        .titlebar
-                        .and_then(|t| t.title)
-                        .map(|t| t.to_string())
-                        .unwrap_or_else(|| "GPUI".into()),
-                )
-                .with_inner_size(winit::dpi::LogicalSize::new(
-                    bounds.size.width.0 as f64,
-                    bounds.size.height.0 as f64,
-                ));
+            let attributes = window_attributes(&options, event_loop, &self.displays);
 
             let winit_window = event_loop
                 .create_window(attributes)
                 .expect("Failed to create window");
             let window_id = winit_window.id();
 
-            window.initialize(winit_window);
+            window.initialize(winit_window, present_mode, max_frame_latency);
             app_state.windows.insert(window_id, window.clone());
             window.window().request_redraw();
         })
@@ -294,19 +342,55 @@ impl Platform for CrossPlatform {
         ))
     }
 
-    fn set_cursor_style(&self, _style: crate::CursorStyle) {}
+    fn set_cursor_style(&self, style: crate::CursorStyle) {
+        with_active_context(|event_loop, app_state| {
+            let Some(window) = app_state
+                .windows
+                .values()
+                .find(|window| window.is_hovered())
+            else {
+                return;
+            };
+            let cursor = self.cursor_images.shown_cursor(style);
+            if window.0.state.cursor.replace(Some(cursor)) == Some(cursor) {
+                return;
+            }
+
+            match self.cursor_images.cursor(style, event_loop) {
+                Some(cursor) => {
+                    window.window().set_cursor(cursor);
+                    window.window().set_cursor_visible(true);
+                }
+                None => window.window().set_cursor_visible(false),
+            }
+        });
+    }
+
+    fn register_cursor_image(&self, id: crate::CursorImageId, image: crate::CursorImage) {
+        self.cursor_images.register(id, image);
+    }
 
     fn should_auto_hide_scrollbars(&self) -> bool {
         // TODO(mdeand): How do we want to implement this? For now, just return false.
         false
     }
 
-    fn write_to_clipboard(&self, _item: crate::ClipboardItem) {
-        log::warn!("write_to_clipboard is not yet implemented on this platform");
+    fn write_to_clipboard(&self, item: crate::ClipboardItem) {
+        self.clipboard.write(ClipboardKind::Clipboard, item);
     }
 
     fn read_from_clipboard(&self) -> Option<crate::ClipboardItem> {
-        None
+        self.clipboard.read(ClipboardKind::Clipboard)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn write_to_primary(&self, item: crate::ClipboardItem) {
+        self.clipboard.write(ClipboardKind::Primary, item);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn read_from_primary(&self) -> Option<crate::ClipboardItem> {
+        self.clipboard.read(ClipboardKind::Primary)
     }
 
     fn write_credentials(
@@ -346,6 +430,10 @@ impl Platform for CrossPlatform {
     fn on_keyboard_layout_change(&self, _callback: Box<dyn FnMut()>) {
         // TODO(mdeand): Is this possible to implement in a cross-platform way?
     }
+
+    fn on_displays_changed(&self, callback: Box<dyn FnMut()>) {
+        self.displays.on_displays_changed(callback);
+    }
 }
 
 impl AppState {
@@ -369,6 +457,48 @@ impl AppState {
             }
         }
     }
+
+    /// Replaces the context once its device was lost, and moves every window to the new one.
+    fn recover_lost_device(&mut self) {
+        if !self.wgpu_context.borrow().is_device_lost()
+            || self
+                .last_device_recovery
+                .is_some_and(|attempt| attempt.elapsed() < DEVICE_RECOVERY_INTERVAL)
+        {
+            return;
+        }
+        self.last_device_recovery = Some(Instant::now());
+
+        log::warn!("the GPU device was lost, recreating it");
+        let context = match self.wgpu_context.borrow().recreate() {
+            Ok(context) => Arc::new(context),
+            Err(error) => {
+                log::error!("failed to recreate the GPU device: {error:?}");
+                return;
+            }
+        };
+        *self.wgpu_context.borrow_mut() = context.clone();
+        self.last_device_recovery = None;
+
+        for window in self.windows.values() {
+            if let Err(error) = window.recover(context.clone()) {
+                log::error!("failed to recreate a window's renderer: {error:?}");
+            }
+        }
+    }
+
+    /// Tells the app when displays were plugged in, unplugged or rearranged since the last poll.
+    fn poll_displays(&mut self, event_loop: &ActiveEventLoop) {
+        if self
+            .last_display_poll
+            .is_some_and(|poll| poll.elapsed() < DISPLAY_POLL_INTERVAL)
+        {
+            return;
+        }
+        self.last_display_poll = Some(Instant::now());
+        self.displays
+            .poll(event_loop.available_monitors(), primary_monitor(event_loop));
+    }
 }
 
 impl winit::application::ApplicationHandler<CrossEvent> for AppState {
@@ -403,8 +533,13 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
         self.set_active_context(event_loop);
 
         self.drain_main_queue();
+        self.recover_lost_device();
+        self.poll_displays(event_loop);
+        self.wgpu_context.borrow().save_pipeline_cache();
 
+        let now = Instant::now();
         for window in self.windows.values() {
+            window.retry_skipped_draw(now);
             window.window().request_redraw();
         }
 
@@ -413,7 +548,9 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
 
     fn suspended(&mut self, _event_loop: &ActiveEventLoop) {}
 
-    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {}
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.wgpu_context.borrow().flush_pipeline_cache();
+    }
 
     fn memory_warning(&mut self, _event_loop: &ActiveEventLoop) {}
 
@@ -527,11 +664,12 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                     &window.0.state.callbacks.on_request_frame,
                     |cb| {
                         cb(crate::RequestFrameOptions {
-                            force_render: false,
+                            force_render: window.0.force_render.take(),
                             require_presentation: true,
                         });
                     },
                 );
+                window.update_ime_allowed();
             }
 
             winit::event::WindowEvent::KeyboardInput {
@@ -571,6 +709,10 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                 }
             }
 
+            winit::event::WindowEvent::Ime(ime) => {
+                window.handle_ime(ime);
+            }
+
             winit::event::WindowEvent::ModifiersChanged(new_modifiers) => {
                 let modifiers = winit_modifiers_to_gpui(new_modifiers.state());
                 self.current_modifiers = modifiers;
@@ -615,7 +757,27 @@ impl winit::application::ApplicationHandler<CrossEvent> for AppState {
                     });
             }
 
+            winit::event::WindowEvent::CursorEntered { .. } => {
+                window.0.state.hovered.set(true);
+                window
+                    .0
+                    .state
+                    .callbacks
+                    .invoke_mut(&window.0.state.callbacks.on_hover_status_change, |cb| {
+                        cb(true)
+                    });
+            }
+
             winit::event::WindowEvent::CursorLeft { .. } => {
+                window.0.state.hovered.set(false);
+                window
+                    .0
+                    .state
+                    .callbacks
+                    .invoke_mut(&window.0.state.callbacks.on_hover_status_change, |cb| {
+                        cb(false)
+                    });
+
                 let position = window.0.state.mouse_position.get();
                 let platform_event = PlatformInput::MouseExited(MouseExitEvent {
                     position,
@@ -1,6 +1,35 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use super::surface_registry::SurfaceRegistry;
+use anyhow::Result;
+
+use super::{
+    renderer::{PipelinesKey, WgpuPipelines},
+    surface_registry::SurfaceRegistry,
+};
+
+/// Set this environment variable to a directory to keep compiled pipelines there between
+/// runs, on backends that support it (currently Vulkan).
+const PIPELINE_CACHE_DIR_ENV: &str = "GPUI_PIPELINE_CACHE_DIR";
+
+/// Set this environment variable to measure how long frames take to draw on the GPU, on
+/// adapters that support timestamp queries.
+const GPU_TIMING_ENV: &str = "GPUI_GPU_TIMING";
+
+/// Set this environment variable to fail tests that need an adapter when there is none,
+/// instead of skipping them.
+#[cfg(test)]
+const REQUIRE_GPU_TESTS_ENV: &str = "REQUIRE_GPU_TESTS";
+
+/// The features timing the batches of a frame needs.
+const GPU_TIMING_FEATURES: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES);
 
 pub struct WgpuContext {
     pub(super) adapter: wgpu::Adapter,
@@ -9,37 +38,194 @@ pub struct WgpuContext {
     pub(super) instance: wgpu::Instance,
 
     pub(super) globals_buffer: wgpu::Buffer,
-    pub(super) quads_buffer: wgpu::Buffer,
-    pub(super) shadows_buffer: wgpu::Buffer,
-    pub(super) underlines_buffer: wgpu::Buffer,
-    pub(super) mono_sprites_buffer: wgpu::Buffer,
-    pub(super) poly_sprites_buffer: wgpu::Buffer,
     pub(super) color_adjustments_buffer: wgpu::Buffer,
 
+    pub(super) atlas_sampler: wgpu::Sampler,
+    pub(super) surface_sampler: wgpu::Sampler,
+    pub(super) path_sampler: wgpu::Sampler,
+
+    /// Pipelines shared by every renderer drawing to targets of the same kind.
+    pub(super) pipelines: Mutex<HashMap<PipelinesKey, Arc<WgpuPipelines>>>,
+    pub(super) pipeline_cache: Option<wgpu::PipelineCache>,
+    pipeline_cache_path: Option<PathBuf>,
+    /// Set when pipelines were compiled since the pipeline cache was last saved.
+    pipeline_cache_dirty: AtomicBool,
+
     pub(crate) surface_registry: Arc<SurfaceRegistry>,
+
+    /// Set once the device is lost, by the driver or by [`WgpuContext::simulate_device_loss`].
+    device_lost: Arc<AtomicBool>,
 }
 
 impl WgpuContext {
-    pub fn new() -> anyhow::Result<Self> {
+    pub fn new() -> Result<Self> {
+        let (adapter, device, queue, instance) = Self::request_device()?;
+        let surface_registry = Arc::new(SurfaceRegistry::new(device.clone(), queue.clone()));
+        Ok(Self::with_device(
+            adapter,
+            device,
+            queue,
+            instance,
+            surface_registry,
+        ))
+    }
+
+    /// Creates a context for a test that needs an adapter. Without one, the test is skipped
+    /// with a message saying so, or fails when `REQUIRE_GPU_TESTS` is set.
+    #[cfg(test)]
+    pub(crate) fn for_test() -> Option<Self> {
+        match Self::new() {
+            Ok(context) => Some(context),
+            Err(error) if std::env::var_os(REQUIRE_GPU_TESTS_ENV).is_some() => {
+                panic!("{REQUIRE_GPU_TESTS_ENV} is set, but there is no wgpu adapter: {error:?}")
+            }
+            Err(error) => {
+                let thread = std::thread::current();
+                let test = thread.name().unwrap_or_default();
+                eprintln!("skipping {test}, there is no wgpu adapter: {error:?}");
+                None
+            }
+        }
+    }
+
+    /// Creates a context on a new device to replace this one once its device was lost.
+    ///
+    /// The surface registry is carried over, with every registered surface recreated on the
+    /// new device, so the handles given out for them keep working. Anything else created on
+    /// the old device has to be recreated by its owner.
+    pub fn recreate(&self) -> Result<Self> {
+        let (adapter, device, queue, instance) = Self::request_device()?;
+        self.surface_registry
+            .attach_device(device.clone(), queue.clone());
+        Ok(Self::with_device(
+            adapter,
+            device,
+            queue,
+            instance,
+            self.surface_registry.clone(),
+        ))
+    }
+
+    /// Whether the device was lost, after which nothing drawn with this context is presented
+    /// and it has to be [recreated](Self::recreate).
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
+    /// Marks the device as lost without it actually being lost, to exercise the recovery.
+    pub fn simulate_device_loss(&self) {
+        self.device_lost.store(true, Ordering::Release);
+    }
+
+    /// Whether renderers should time the batches they draw.
+    pub(super) fn gpu_timing(&self) -> bool {
+        self.device.features().contains(GPU_TIMING_FEATURES)
+    }
+
+    /// Marks the pipeline cache as holding pipelines that weren't saved yet. Compiling happens
+    /// while drawing, so the cache is only written once the app is idle, by
+    /// [`save_pipeline_cache`](Self::save_pipeline_cache).
+    pub(super) fn mark_pipeline_cache_dirty(&self) {
+        if self.pipeline_cache.is_some() {
+            self.pipeline_cache_dirty.store(true, Ordering::Release);
+        }
+    }
+
+    /// Writes the pipelines compiled since the last save to the pipeline cache directory, if
+    /// there is one, on a background thread.
+    pub(super) fn save_pipeline_cache(&self) {
+        let Some((path, data)) = self.unsaved_pipeline_cache() else {
+            return;
+        };
+        // NOTE(mdeand): Caches run into megabytes, which the main thread shouldn't wait on.
+        std::thread::spawn(move || write_pipeline_cache(&path, &data));
+    }
+
+    /// Writes the pipelines compiled since the last save to the pipeline cache directory, if
+    /// there is one, before returning. Used on exit, where a background thread wouldn't finish.
+    pub(super) fn flush_pipeline_cache(&self) {
+        if let Some((path, data)) = self.unsaved_pipeline_cache() {
+            write_pipeline_cache(&path, &data);
+        }
+    }
+
+    fn unsaved_pipeline_cache(&self) -> Option<(PathBuf, Vec<u8>)> {
+        if !self.pipeline_cache_dirty.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        let (Some(cache), Some(path)) = (&self.pipeline_cache, &self.pipeline_cache_path) else {
+            return None;
+        };
+        Some((path.clone(), cache.get_data()?))
+    }
+
+    fn request_device() -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue, wgpu::Instance)> {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             ..Default::default()
         });
 
-        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            compatible_surface: None,
-            force_fallback_adapter: false,
-        }))?;
+        let request_adapter = |force_fallback_adapter| {
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter,
+            }))
+        };
+
+        // NOTE(mdeand): Machines without a GPU (e.g. CI servers running headless) can still
+        // NOTE(mdeand): render through a software adapter, just slowly.
+        let adapter = match request_adapter(false) {
+            Ok(adapter) => adapter,
+            Err(error) => {
+                log::warn!("no hardware adapter available ({error}), using a fallback adapter");
+                request_adapter(true)?
+            }
+        };
+
+        let mut required_features = wgpu::Features::empty();
+        if std::env::var_os(PIPELINE_CACHE_DIR_ENV).is_some() {
+            required_features |= adapter.features() & wgpu::Features::PIPELINE_CACHE;
+        }
+        if std::env::var_os(GPU_TIMING_ENV).is_some() {
+            if adapter.features().contains(GPU_TIMING_FEATURES) {
+                required_features |= GPU_TIMING_FEATURES;
+            } else {
+                log::info!("timestamp queries aren't supported by this adapter");
+            }
+        }
 
         let (device, queue) =
             pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
+                required_features,
                 required_limits: wgpu::Limits::default(),
                 ..Default::default()
             }))?;
 
+        Ok((adapter, device, queue, instance))
+    }
+
+    fn with_device(
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        instance: wgpu::Instance,
+        surface_registry: Arc<SurfaceRegistry>,
+    ) -> Self {
+        let device_lost = Arc::new(AtomicBool::new(false));
+        device.set_device_lost_callback({
+            let device_lost = device_lost.clone();
+            move |reason, message| {
+                // NOTE(mdeand): Destroying the device on purpose reports it as destroyed, which
+                // NOTE(mdeand): isn't a loss anyone has to recover from.
+                if reason != wgpu::DeviceLostReason::Destroyed {
+                    log::error!("the GPU device was lost ({reason:?}): {message}");
+                    device_lost.store(true, Ordering::Release);
+                }
+            }
+        });
+
         let globals_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Globals Buffer"),
             // FIXME(mdeand): Hack
@@ -48,77 +234,110 @@ impl WgpuContext {
             mapped_at_creation: false,
         });
 
-        let quads_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Quads Buffer"),
-            // TODO(mdeand): Determine appropriate size
-            size: 1024 * 1024, // 1 MB buffer for quads, for now. (:
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
-
-        let mono_sprites_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Monosprites Buffer"),
-            // TODO(mdeand): Determine appropriate size, or make resizable.
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
+        let color_adjustments_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Color Adjustments Buffer"),
+            size: 1024 * 16, // TODO(mdeand): 16 KB buffer for color adjustments, for now. (:
+            usage: wgpu::BufferUsages::STORAGE
                 | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
+                | wgpu::BufferUsages::UNIFORM,
             mapped_at_creation: false,
         });
 
-        let shadows_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Shadows Buffer"),
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
+        let atlas_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("atlas_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
         });
 
-        let underlines_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Underlines Buffer"),
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
+        let surface_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("surface_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
         });
 
-        let poly_sprites_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Poly Sprites Buffer"),
-            size: 1024 * 1024,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
+        // NOTE(mdeand): The intermediate path texture is sampled 1:1 with the viewport, so
+        // NOTE(mdeand): filtering would only blur the edges produced by the MSAA resolve.
+        let path_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("path_sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
         });
 
-        let color_adjustments_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Color Adjustments Buffer"),
-            size: 1024 * 16, // TODO(mdeand): 16 KB buffer for color adjustments, for now. (:
-            usage: wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::UNIFORM,
-            mapped_at_creation: false,
-        });
+        let (pipeline_cache, pipeline_cache_path) = Self::load_pipeline_cache(&adapter, &device);
 
-        Ok(Self {
+        Self {
             adapter,
             device,
             queue,
             instance,
 
             globals_buffer,
-            quads_buffer,
-            shadows_buffer,
-            underlines_buffer,
-            mono_sprites_buffer,
-            poly_sprites_buffer,
             color_adjustments_buffer,
 
-            surface_registry: Arc::new(SurfaceRegistry::new()),
-        })
+            atlas_sampler,
+            surface_sampler,
+            path_sampler,
+
+            pipelines: Mutex::new(HashMap::new()),
+            pipeline_cache,
+            pipeline_cache_path,
+            pipeline_cache_dirty: AtomicBool::new(false),
+
+            surface_registry,
+
+            device_lost,
+        }
+    }
+
+    /// Creates the pipeline cache from what an earlier run left in the pipeline cache
+    /// directory, when one is set and the device supports it.
+    fn load_pipeline_cache(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+    ) -> (Option<wgpu::PipelineCache>, Option<PathBuf>) {
+        let Some(directory) = std::env::var_os(PIPELINE_CACHE_DIR_ENV).map(PathBuf::from) else {
+            return (None, None);
+        };
+        let Some(file_name) = wgpu::util::pipeline_cache_key(&adapter.get_info())
+            .filter(|_| device.features().contains(wgpu::Features::PIPELINE_CACHE))
+        else {
+            log::info!("pipeline caches aren't supported by this adapter");
+            return (None, None);
+        };
+        if let Err(error) = std::fs::create_dir_all(&directory) {
+            log::warn!("failed to create the pipeline cache directory {directory:?}: {error}");
+            return (None, None);
+        }
+
+        let path = directory.join(file_name);
+        let data = std::fs::read(&path).ok();
+        // SAFETY: The data was written by `write_pipeline_cache`, and is only used by wgpu if it
+        // was created for the same adapter and driver. Otherwise the cache starts out empty.
+        let cache = unsafe {
+            device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("pipeline_cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+        (Some(cache), Some(path))
+    }
+}
+
+impl Drop for WgpuContext {
+    fn drop(&mut self) {
+        // NOTE(mdeand): Offscreen contexts have no event loop to save the cache when idle.
+        self.flush_pipeline_cache();
+    }
+}
+
+fn write_pipeline_cache(path: &Path, data: &[u8]) {
+    let temp_path = path.with_extension("temp");
+    let result = std::fs::write(&temp_path, data).and_then(|()| std::fs::rename(&temp_path, path));
+    if let Err(error) = result {
+        log::warn!("failed to write the pipeline cache to {path:?}: {error}");
     }
 }
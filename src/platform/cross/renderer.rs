@@ -1,10 +1,21 @@
 use std::sync::Arc;
 
+use anyhow::Result;
+use futures::channel::oneshot;
+use image::RgbaImage;
+
 use crate::{
-    AtlasTextureId, AtlasTile, DevicePixels, GpuSpecs, Hsla, LinearColorStop, MonochromeSprite,
-    PlatformAtlas, PrimitiveBatch, Quad, ScaledPixels, Scene, TransformationMatrix, color,
-    geometry,
-    platform::cross::{atlas::WgpuAtlas, render_context::WgpuContext},
+    AtlasTextureId, AtlasTile, BlendMode, Blur, ContentMask, DevicePixels, FrameStats, GpuSpecs,
+    Hsla, Layer, LinearColorStop, MAX_SHADER_QUAD_UNIFORMS, MonochromeSprite, Path, PlatformAtlas,
+    PolychromeSprite, PresentMode, PrimitiveBatch, Quad, ScaledPixels, Scene, SceneBackground,
+    ShaderQuadSource, Shadow, TransformationMatrix, Underline, color, geometry,
+    platform::cross::{
+        atlas::WgpuAtlas,
+        capture,
+        gpu_timer::{GpuTimer, TimestampEvent},
+        instance_buffer::InstanceBuffer,
+        render_context::WgpuContext,
+    },
 };
 
 const fn map_attributes<const N: usize>(
@@ -57,62 +68,47 @@ impl color::Hsla {
     ];
 }
 
-impl color::LinearColorStop {
-    const VERTEX_ATTRIBUTES: &'static [wgpu::VertexAttribute; 2] = &[
+impl SceneBackground {
+    const VERTEX_ATTRIBUTES: &'static [wgpu::VertexAttribute; 7] = &[
         wgpu::VertexAttribute {
-            offset: std::mem::offset_of!(LinearColorStop, color) as wgpu::BufferAddress,
+            offset: std::mem::offset_of!(SceneBackground, tag) as wgpu::BufferAddress,
             shader_location: 0,
-            format: wgpu::VertexFormat::Float32x4,
+            format: wgpu::VertexFormat::Uint32,
         },
         wgpu::VertexAttribute {
-            offset: std::mem::offset_of!(LinearColorStop, percentage) as wgpu::BufferAddress,
+            offset: std::mem::offset_of!(SceneBackground, color_space) as wgpu::BufferAddress,
             shader_location: 1,
+            format: wgpu::VertexFormat::Uint32,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(SceneBackground, solid) as wgpu::BufferAddress,
+            shader_location: 2,
+            format: wgpu::VertexFormat::Uint32,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(SceneBackground, gradient_angle_or_pattern_height)
+                as wgpu::BufferAddress,
+            shader_location: 3,
             format: wgpu::VertexFormat::Float32,
         },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(SceneBackground, stops_offset) as wgpu::BufferAddress,
+            shader_location: 4,
+            format: wgpu::VertexFormat::Uint32,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(SceneBackground, gradient_center) as wgpu::BufferAddress,
+            shader_location: 5,
+            format: wgpu::VertexFormat::Float32x2,
+        },
+        wgpu::VertexAttribute {
+            offset: std::mem::offset_of!(SceneBackground, stop_count) as wgpu::BufferAddress,
+            shader_location: 6,
+            format: wgpu::VertexFormat::Uint32,
+        },
     ];
 }
 
-impl color::Background {
-    const VERTEX_ATTRIBUTES: &'static [wgpu::VertexAttribute; 7] = &{
-        let linear_color_stop_vertex_attributes = map_attributes(
-            LinearColorStop::VERTEX_ATTRIBUTES,
-            4,
-            std::mem::offset_of!(color::Background, colors) as wgpu::BufferAddress,
-        );
-
-        [
-            wgpu::VertexAttribute {
-                offset: std::mem::offset_of!(color::Background, tag) as wgpu::BufferAddress,
-                shader_location: 0,
-                format: wgpu::VertexFormat::Uint32,
-            },
-            wgpu::VertexAttribute {
-                offset: std::mem::offset_of!(color::Background, color_space) as wgpu::BufferAddress,
-                shader_location: 1,
-                format: wgpu::VertexFormat::Uint32,
-            },
-            wgpu::VertexAttribute {
-                offset: std::mem::offset_of!(color::Background, solid) as wgpu::BufferAddress,
-                shader_location: 2,
-                format: wgpu::VertexFormat::Uint32,
-            },
-            wgpu::VertexAttribute {
-                offset: std::mem::offset_of!(color::Background, gradient_angle_or_pattern_height)
-                    as wgpu::BufferAddress,
-                shader_location: 3,
-                format: wgpu::VertexFormat::Float32,
-            },
-            linear_color_stop_vertex_attributes[0],
-            linear_color_stop_vertex_attributes[1],
-            wgpu::VertexAttribute {
-                offset: std::mem::offset_of!(color::Background, pad) as wgpu::BufferAddress,
-                shader_location: 6,
-                format: wgpu::VertexFormat::Uint32,
-            },
-        ]
-    };
-}
-
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 struct GlobalParams {
@@ -225,6 +221,7 @@ impl Bounds {
 struct SurfaceParams {
     bounds: Bounds,
     content_mask: Bounds,
+    content_mask_corner_radii: [f32; 4],
 }
 
 impl Quad {
@@ -242,7 +239,7 @@ impl Quad {
         );
 
         let background_vertex_attributes = map_attributes(
-            color::Background::VERTEX_ATTRIBUTES,
+            SceneBackground::VERTEX_ATTRIBUTES,
             6,
             std::mem::offset_of!(Quad, background) as wgpu::BufferAddress,
         );
@@ -347,23 +344,73 @@ struct SurfacesData {
     s_texture: wgpu::Sampler,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
 struct PathSprite {
-    bounds: geometry::Bounds<f32>,
+    bounds: geometry::Bounds<ScaledPixels>,
 }
 
+/// A blur along with how the renderer samples it, laid out like `BlurInstance` in
+/// `shaders/blur.wgsl`.
 #[repr(C)]
-#[derive(Clone, Copy)]
+struct BlurInstance {
+    blur: Blur,
+    region: geometry::Bounds<f32>,
+    downsample: f32,
+    sigma: f32,
+}
+
+/// An isolated layer composited onto the frame, laid out like `LayerInstance` in
+/// `shaders/layers.wgsl`.
+#[repr(C)]
+struct LayerInstance {
+    bounds: geometry::Bounds<ScaledPixels>,
+    opacity: f32,
+    blend_mode: BlendMode,
+}
+
+/// A shader quad along with the time it's drawn at, laid out like `ShaderQuad` in
+/// `shaders/shader_quad.wgsl`.
+#[repr(C)]
+struct ShaderQuadInstance {
+    bounds: geometry::Bounds<ScaledPixels>,
+    content_mask: ContentMask<ScaledPixels>,
+    time: f32,
+    pad: [f32; 3],
+    uniforms: [f32; MAX_SHADER_QUAD_UNIFORMS],
+}
+
+/// The largest standard deviation, in texels, the Gaussian passes blur with. Larger radii are
+/// blurred at a lower resolution instead, to bound the number of texels each pass reads.
+const MAX_BLUR_SIGMA: f32 = 4.0;
+
+/// The most device pixels averaged into a single texel when downsampling for a blur.
+const MAX_BLUR_DOWNSAMPLE: f32 = 16.0;
+
+/// Returns how many device pixels to average into each blurred texel for a blur of the given
+/// radius, in device pixels.
+fn blur_downsample(radius: f32) -> f32 {
+    let mut downsample = 1.0;
+    while radius / downsample > MAX_BLUR_SIGMA && downsample < MAX_BLUR_DOWNSAMPLE {
+        downsample *= 2.0;
+    }
+    downsample
+}
+
+#[repr(C)]
+#[derive(Clone)]
 struct PathRasterizationVertex {
     xy_position: geometry::Point<ScaledPixels>,
     st_position: geometry::Point<f32>,
-    color: color::Background,
-    bounds: geometry::Bounds<f32>,
+    color: SceneBackground,
+    bounds: geometry::Bounds<ScaledPixels>,
+    content_mask: ContentMask<ScaledPixels>,
 }
 
 impl PathRasterizationVertex {
     const VERTEX_ATTRIBUTES: &'static [wgpu::VertexAttribute; 10] = &{
         let color_vertex_attributes = map_attributes(
-            color::Background::VERTEX_ATTRIBUTES,
+            SceneBackground::VERTEX_ATTRIBUTES,
             2,
             std::mem::offset_of!(PathRasterizationVertex, color) as wgpu::BufferAddress,
         );
@@ -568,7 +615,16 @@ struct ColorAdjustments {
     _padding: [f32; 3],
 }
 
-struct WgpuPipelines {
+/// What the pipelines drawing into a target depend on. Renderers of targets that agree on all
+/// of them share their pipelines.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct PipelinesKey {
+    format: wgpu::TextureFormat,
+    premultiplied_alpha: bool,
+    path_sample_count: u32,
+}
+
+pub(super) struct WgpuPipelines {
     color_targets: Vec<Option<wgpu::ColorTargetState>>,
 
     quads_bind_group_layout: wgpu::BindGroupLayout,
@@ -578,6 +634,12 @@ struct WgpuPipelines {
     mono_sprites_bind_group_layout: wgpu::BindGroupLayout,
     poly_sprites_bind_group_layout: wgpu::BindGroupLayout,
     surfaces_bind_group_layout: wgpu::BindGroupLayout,
+    path_rasterization_bind_group_layout: wgpu::BindGroupLayout,
+    paths_bind_group_layout: wgpu::BindGroupLayout,
+    blurs_bind_group_layout: wgpu::BindGroupLayout,
+    layers_bind_group_layout: wgpu::BindGroupLayout,
+    shader_quads_bind_group_layout: wgpu::BindGroupLayout,
+    shader_quads_pipeline_layout: wgpu::PipelineLayout,
 
     globals_bind_group: wgpu::BindGroup,
     color_adjustments_bind_group: wgpu::BindGroup,
@@ -588,13 +650,53 @@ struct WgpuPipelines {
     mono_sprites_pipeline: wgpu::RenderPipeline,
     poly_sprites_pipeline: wgpu::RenderPipeline,
     surfaces_pipeline: wgpu::RenderPipeline,
+    path_rasterization_pipeline: wgpu::RenderPipeline,
+    paths_pipeline: wgpu::RenderPipeline,
+    layer_mask_pipeline: wgpu::RenderPipeline,
+    blur_downsample_pipeline: wgpu::RenderPipeline,
+    blur_horizontal_pipeline: wgpu::RenderPipeline,
+    blur_vertical_pipeline: wgpu::RenderPipeline,
+    blurs_pipeline: wgpu::RenderPipeline,
+    /// Writes blurred texels over the layer texture they were read from, instead of blending.
+    layer_blur_pipeline: wgpu::RenderPipeline,
+    layers_pipeline: wgpu::RenderPipeline,
+    clear_pipeline: wgpu::RenderPipeline,
+    layer_clear_pipeline: wgpu::RenderPipeline,
+    /// Pipelines of shader quads by the hash of their source, `None` for sources that failed
+    /// to compile.
+    shader_quad_pipelines: Mutex<HashMap<u64, Option<wgpu::RenderPipeline>>>,
 }
 
 impl WgpuPipelines {
-    pub fn new(
+    /// Returns the pipelines drawing into targets configured like `surface_configuration`,
+    /// compiling them the first time a renderer of such a target is created on `context`.
+    fn shared(
+        context: &WgpuContext,
+        surface_configuration: &wgpu::SurfaceConfiguration,
+        path_sample_count: u32,
+    ) -> Arc<Self> {
+        let key = PipelinesKey {
+            format: surface_configuration.format,
+            premultiplied_alpha: surface_configuration.alpha_mode
+                == wgpu::CompositeAlphaMode::PreMultiplied,
+            path_sample_count,
+        };
+        let mut pipelines = context.pipelines.lock().unwrap();
+        if let Some(pipelines) = pipelines.get(&key) {
+            return pipelines.clone();
+        }
+
+        let shared = Arc::new(Self::new(context, surface_configuration, path_sample_count));
+        pipelines.insert(key, shared.clone());
+        drop(pipelines);
+        context.mark_pipeline_cache_dirty();
+        shared
+    }
+
+    fn new(
         context: &WgpuContext,
         surface_configuration: &wgpu::SurfaceConfiguration,
-        _path_sample_count: u32,
+        path_sample_count: u32,
     ) -> Self {
         let quads_shader = context
             .device
@@ -637,6 +739,44 @@ impl WgpuPipelines {
                     ),
                 });
 
+        let path_rasterization_shader =
+            context
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("path_rasterization shader"),
+                    source: wgpu::ShaderSource::Wgsl(
+                        include_str!("shaders/path_rasterization.wgsl").into(),
+                    ),
+                });
+
+        let paths_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("paths_shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/paths.wgsl").into()),
+            });
+
+        let blur_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("blur_shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blur.wgsl").into()),
+            });
+
+        let layers_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("layers_shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/layers.wgsl").into()),
+            });
+
+        let clear_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("clear_shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("shaders/clear.wgsl").into()),
+            });
+
         let blend_mode = match surface_configuration.alpha_mode {
             wgpu::CompositeAlphaMode::PreMultiplied => {
                 wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
@@ -714,16 +854,28 @@ impl WgpuPipelines {
                 .device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     label: Some("quads_bind_group_layout"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
                         },
-                        count: None,
-                    }],
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
                 });
 
         let quads_pipeline_layout =
@@ -902,74 +1054,276 @@ impl WgpuPipelines {
                     immediate_size: 0,
                 });
 
-        let globals_bind_group = context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("globals_bind_group"),
-                layout: &globals_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &context.globals_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                }],
-            });
+        let path_rasterization_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("path_rasterization_bind_group_layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
 
-        let color_adjustments_bind_group =
+        let path_rasterization_pipeline_layout =
             context
                 .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("color_adjustments_bind_group"),
-                    layout: &color_adjustments_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("path_rasterization_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &path_rasterization_bind_group_layout,
+                    ],
+                    immediate_size: 0,
+                });
+
+        let paths_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("paths_bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
                         binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &context.color_adjustments_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     }],
                 });
 
-        Self {
-            color_targets: color_targets.to_vec(),
+        let paths_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("paths_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &sprites_bind_group_layout,
+                        &paths_bind_group_layout,
+                    ],
+                    immediate_size: 0,
+                });
 
-            quads_bind_group_layout,
-            shadows_bind_group_layout,
-            underlines_bind_group_layout,
-            mono_sprites_bind_group_layout,
-            sprites_bind_group_layout,
-            poly_sprites_bind_group_layout,
+        let blurs_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("blurs_bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
 
-            globals_bind_group,
-            color_adjustments_bind_group,
+        let blurs_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("blurs_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &sprites_bind_group_layout,
+                        &blurs_bind_group_layout,
+                    ],
+                    immediate_size: 0,
+                });
 
-            quads_pipeline: context.device.create_render_pipeline(
-                &wgpu::RenderPipelineDescriptor {
-                    label: Some("quads"),
-                    layout: Some(&quads_pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &quads_shader,
-                        entry_point: Some("vs_quad"),
-                        compilation_options: wgpu::PipelineCompilationOptions::default(),
-                        buffers: &[],
-                    },
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleStrip,
-                        ..Default::default()
-                    },
-                    depth_stencil: None,
-                    multisample: wgpu::MultisampleState::default(),
-                    fragment: Some(wgpu::FragmentState {
-                        module: &quads_shader,
+        let layers_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("layers_bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
+        let layers_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("layers_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &sprites_bind_group_layout,
+                        &sprites_bind_group_layout,
+                        &layers_bind_group_layout,
+                    ],
+                    immediate_size: 0,
+                });
+
+        let shader_quads_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("shader_quads_bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
+        let shader_quads_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("shader_quads_pipeline_layout"),
+                    bind_group_layouts: &[
+                        &globals_bind_group_layout,
+                        &shader_quads_bind_group_layout,
+                    ],
+                    immediate_size: 0,
+                });
+
+        // NOTE(mdeand): The downsample and Gaussian passes replace the texels of the blur
+        // NOTE(mdeand): textures rather than blending into them.
+        let blur_pass_pipeline = |label: &str, fragment_entry_point: &str| {
+            context
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&blurs_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &blur_shader,
+                        entry_point: Some("vs_blur_pass"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &blur_shader,
+                        entry_point: Some(fragment_entry_point),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_configuration.format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                })
+        };
+
+        let globals_bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("globals_bind_group"),
+                layout: &globals_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &context.globals_buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                }],
+            });
+
+        let color_adjustments_bind_group =
+            context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("color_adjustments_bind_group"),
+                    layout: &color_adjustments_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &context.color_adjustments_buffer,
+                            offset: 0,
+                            size: None,
+                        }),
+                    }],
+                });
+
+        Self {
+            color_targets: color_targets.to_vec(),
+
+            quads_bind_group_layout,
+            shadows_bind_group_layout,
+            underlines_bind_group_layout,
+            mono_sprites_bind_group_layout,
+            sprites_bind_group_layout,
+            poly_sprites_bind_group_layout,
+            path_rasterization_bind_group_layout,
+            paths_bind_group_layout,
+            blurs_bind_group_layout,
+            layers_bind_group_layout,
+            shader_quads_bind_group_layout,
+            shader_quads_pipeline_layout,
+
+            globals_bind_group,
+            color_adjustments_bind_group,
+
+            quads_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("quads"),
+                    layout: Some(&quads_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &quads_shader,
+                        entry_point: Some("vs_quad"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &quads_shader,
                         entry_point: Some("fs_quad"),
                         compilation_options: wgpu::PipelineCompilationOptions::default(),
                         targets: color_targets,
                     }),
                     multiview_mask: None,
-                    cache: None,
+                    cache: context.pipeline_cache.as_ref(),
                 },
             ),
 
@@ -996,7 +1350,7 @@ impl WgpuPipelines {
                         targets: color_targets,
                     }),
                     multiview_mask: None,
-                    cache: None,
+                    cache: context.pipeline_cache.as_ref(),
                 },
             ),
 
@@ -1023,7 +1377,7 @@ impl WgpuPipelines {
                         targets: color_targets,
                     }),
                     multiview_mask: None,
-                    cache: None,
+                    cache: context.pipeline_cache.as_ref(),
                 },
             ),
 
@@ -1050,7 +1404,7 @@ impl WgpuPipelines {
                     }),
                     multisample: wgpu::MultisampleState::default(),
                     multiview_mask: None,
-                    cache: None,
+                    cache: context.pipeline_cache.as_ref(),
                 },
             ),
 
@@ -1077,7 +1431,7 @@ impl WgpuPipelines {
                     }),
                     multisample: wgpu::MultisampleState::default(),
                     multiview_mask: None,
-                    cache: None,
+                    cache: context.pipeline_cache.as_ref(),
                 },
             ),
 
@@ -1106,64 +1460,1172 @@ impl WgpuPipelines {
                     }),
                     multisample: wgpu::MultisampleState::default(),
                     multiview_mask: None,
-                    cache: None,
+                    cache: context.pipeline_cache.as_ref(),
                 },
             ),
-        }
-    }
-}
 
-struct RenderingParameters {
-    path_sample_count: u32,
-    gamma_ratios: [f32; 4],
-    grayscale_enhanced_contrast: f32,
-}
+            // NOTE(mdeand): Paths are rasterized into an intermediate texture that always holds
+            // NOTE(mdeand): premultiplied colors, regardless of the surface's alpha mode.
+            path_rasterization_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("path_rasterization"),
+                    layout: Some(&path_rasterization_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &path_rasterization_shader,
+                        entry_point: Some("vs_path_rasterization"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &path_rasterization_shader,
+                        entry_point: Some("fs_path_rasterization"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_configuration.format,
+                            blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: path_sample_count,
+                        ..Default::default()
+                    },
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
 
-impl RenderingParameters {
-    fn from_env() -> Self {
-        use std::env;
+            paths_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("paths"),
+                    layout: Some(&paths_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &paths_shader,
+                        entry_point: Some("vs_path"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &paths_shader,
+                        entry_point: Some("fs_path"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: color_targets,
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
 
-        let path_sample_count = env::var("ZED_PATH_SAMPLE_COUNT")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(4);
-        let gamma = env::var("ZED_FONTS_GAMMA")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(1.8_f32)
-            .clamp(1.0, 2.2);
-        let gamma_ratios = crate::platform::get_gamma_correction_ratios(gamma);
-        let grayscale_enhanced_contrast = env::var("ZED_FONTS_GRAYSCALE_ENHANCED_CONTRAST")
-            .ok()
-            .and_then(|v| v.parse().ok())
-            .unwrap_or(1.0_f32)
-            .max(0.0);
+            // NOTE(mdeand): Clip paths are rasterized like other paths, then their coverage scales
+            // NOTE(mdeand): every channel of the layer texture, which holds premultiplied colors.
+            layer_mask_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("layer_mask"),
+                    layout: Some(&paths_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &paths_shader,
+                        entry_point: Some("vs_path"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &paths_shader,
+                        entry_point: Some("fs_path"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_configuration.format,
+                            blend: Some(wgpu::BlendState {
+                                color: wgpu::BlendComponent {
+                                    src_factor: wgpu::BlendFactor::Zero,
+                                    dst_factor: wgpu::BlendFactor::SrcAlpha,
+                                    operation: wgpu::BlendOperation::Add,
+                                },
+                                alpha: wgpu::BlendComponent {
+                                    src_factor: wgpu::BlendFactor::Zero,
+                                    dst_factor: wgpu::BlendFactor::SrcAlpha,
+                                    operation: wgpu::BlendOperation::Add,
+                                },
+                            }),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
 
-        Self {
-            path_sample_count,
-            gamma_ratios,
-            grayscale_enhanced_contrast,
-        }
-    }
-}
+            blur_downsample_pipeline: blur_pass_pipeline("blur_downsample", "fs_blur_downsample"),
+            blur_horizontal_pipeline: blur_pass_pipeline("blur_horizontal", "fs_blur_horizontal"),
+            blur_vertical_pipeline: blur_pass_pipeline("blur_vertical", "fs_blur_vertical"),
 
-use std::collections::HashMap;
-use std::sync::Mutex;
+            blurs_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("blurs"),
+                    layout: Some(&blurs_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &blur_shader,
+                        entry_point: Some("vs_blur"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &blur_shader,
+                        entry_point: Some("fs_blur"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: color_targets,
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
 
-pub struct WgpuRenderer {
-    context: Arc<WgpuContext>,
-    surface: wgpu::Surface<'static>,
-    surface_configuration: wgpu::SurfaceConfiguration,
-    atlas_sampler: wgpu::Sampler,
-    surface_sampler: wgpu::Sampler,
-    surface_params_buffer: wgpu::Buffer,
-    atlas: Arc<WgpuAtlas>,
-    pipelines: WgpuPipelines,
-    rendering_parameters: RenderingParameters,
+            layer_blur_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("layer_blur"),
+                    layout: Some(&blurs_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &blur_shader,
+                        entry_point: Some("vs_blur"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &blur_shader,
+                        entry_point: Some("fs_blur"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_configuration.format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
 
-    // cache bind groups for each double-buffered surface (index 0/1)
-    surface_bind_groups:
-        Mutex<HashMap<crate::platform::cross::surface_registry::SurfaceId, [wgpu::BindGroup; 2]>>,
+            layers_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("layers"),
+                    layout: Some(&layers_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &layers_shader,
+                        entry_point: Some("vs_layer"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &layers_shader,
+                        entry_point: Some("fs_layer"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: color_targets,
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
+
+            // NOTE(mdeand): Unlike a cleared render pass, drawing respects the scissor rect.
+            clear_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("clear"),
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        module: &clear_shader,
+                        entry_point: Some("vs_clear"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &clear_shader,
+                        entry_point: Some("fs_clear"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_configuration.format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
+
+            layer_clear_pipeline: context.device.create_render_pipeline(
+                &wgpu::RenderPipelineDescriptor {
+                    label: Some("layer_clear"),
+                    layout: None,
+                    vertex: wgpu::VertexState {
+                        module: &clear_shader,
+                        entry_point: Some("vs_clear"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &clear_shader,
+                        entry_point: Some("fs_clear_layer"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: surface_configuration.format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                },
+            ),
+
+            shader_quad_pipelines: Mutex::new(HashMap::default()),
+        }
+    }
+
+    /// Returns the pipeline drawing shader quads with the given source, compiling it the first
+    /// time the source is seen.
+    fn shader_quad_pipeline(
+        &self,
+        context: &WgpuContext,
+        source: &ShaderQuadSource,
+    ) -> Option<wgpu::RenderPipeline> {
+        let device = &context.device;
+        let color_targets = &self.color_targets;
+        let layout = &self.shader_quads_pipeline_layout;
+        let mut compiled = false;
+        let pipeline = self
+            .shader_quad_pipelines
+            .lock()
+            .unwrap()
+            .entry(source.hash)
+            .or_insert_with(|| {
+                compiled = true;
+                // NOTE(mdeand): Errors in user shaders must not take the device down, so they're
+                // NOTE(mdeand): caught in an error scope and the source is skipped from then on.
+                let error_scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+                let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("shader_quad_shader"),
+                    source: wgpu::ShaderSource::Wgsl(
+                        format!(
+                            "{}\n{}",
+                            include_str!("shaders/shader_quad.wgsl"),
+                            source.wgsl
+                        )
+                        .into(),
+                    ),
+                });
+                let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("shader_quad"),
+                    layout: Some(layout),
+                    vertex: wgpu::VertexState {
+                        module: &module,
+                        entry_point: Some("vs_shader_quad"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    fragment: Some(wgpu::FragmentState {
+                        module: &module,
+                        entry_point: Some("fs_shader_quad"),
+                        compilation_options: wgpu::PipelineCompilationOptions::default(),
+                        targets: color_targets,
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview_mask: None,
+                    cache: context.pipeline_cache.as_ref(),
+                });
+                match pollster::block_on(error_scope.pop()) {
+                    Some(error) => {
+                        log::warn!("failed to compile shader quad: {error}");
+                        None
+                    }
+                    None => Some(pipeline),
+                }
+            })
+            .clone();
+        if compiled && pipeline.is_some() {
+            context.mark_pipeline_cache_dirty();
+        }
+        pipeline
+    }
+}
+
+pub(crate) struct RenderingParameters {
+    pub(crate) path_sample_count: u32,
+    pub(crate) gamma_ratios: [f32; 4],
+    pub(crate) grayscale_enhanced_contrast: f32,
+}
+
+impl RenderingParameters {
+    pub(crate) fn from_env(default_path_sample_count: u32) -> Self {
+        use std::env;
+
+        let path_sample_count = env::var("ZED_PATH_SAMPLE_COUNT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default_path_sample_count);
+        let gamma = env::var("ZED_FONTS_GAMMA")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1.8_f32)
+            .clamp(1.0, 2.2);
+        let gamma_ratios = crate::platform::get_gamma_correction_ratios(gamma);
+        let grayscale_enhanced_contrast = env::var("ZED_FONTS_GRAYSCALE_ENHANCED_CONTRAST")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1.0_f32)
+            .max(0.0);
+
+        Self {
+            path_sample_count,
+            gamma_ratios,
+            grayscale_enhanced_contrast,
+        }
+    }
+}
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// The per-window instance buffers, one for each kind of primitive.
+struct InstanceBuffers {
+    quads: InstanceBuffer,
+    shadows: InstanceBuffer,
+    underlines: InstanceBuffer,
+    mono_sprites: InstanceBuffer,
+    poly_sprites: InstanceBuffer,
+    path_vertices: InstanceBuffer,
+    path_sprites: InstanceBuffer,
+    blurs: InstanceBuffer,
+    layers: InstanceBuffer,
+    shader_quads: InstanceBuffer,
+    /// The gradient stops of the frame's quads and paths, which are written once per frame
+    /// and aren't reset between submissions.
+    gradient_stops: InstanceBuffer,
+}
+
+impl InstanceBuffers {
+    fn new(device: &wgpu::Device) -> Self {
+        Self {
+            quads: InstanceBuffer::new(device, "Quads Buffer"),
+            shadows: InstanceBuffer::new(device, "Shadows Buffer"),
+            underlines: InstanceBuffer::new(device, "Underlines Buffer"),
+            mono_sprites: InstanceBuffer::new(device, "Monosprites Buffer"),
+            poly_sprites: InstanceBuffer::new(device, "Poly Sprites Buffer"),
+            path_vertices: InstanceBuffer::new(device, "Path Vertices Buffer"),
+            path_sprites: InstanceBuffer::new(device, "Path Sprites Buffer"),
+            blurs: InstanceBuffer::new(device, "Blurs Buffer"),
+            layers: InstanceBuffer::new(device, "Layers Buffer"),
+            shader_quads: InstanceBuffer::new(device, "Shader Quads Buffer"),
+            gradient_stops: InstanceBuffer::new(device, "Gradient Stops Buffer"),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.quads.reset();
+        self.shadows.reset();
+        self.underlines.reset();
+        self.mono_sprites.reset();
+        self.poly_sprites.reset();
+        self.path_vertices.reset();
+        self.path_sprites.reset();
+        self.blurs.reset();
+        self.layers.reset();
+        self.shader_quads.reset();
+    }
+
+    /// How many bytes of instance data were written since the frame began.
+    fn written_bytes(&self) -> u64 {
+        [
+            &self.quads,
+            &self.shadows,
+            &self.underlines,
+            &self.mono_sprites,
+            &self.poly_sprites,
+            &self.path_vertices,
+            &self.path_sprites,
+            &self.blurs,
+            &self.layers,
+            &self.shader_quads,
+            &self.gradient_stops,
+        ]
+        .iter()
+        .map(|buffer| buffer.written_bytes())
+        .sum()
+    }
+}
+
+/// Bind groups referencing the instance buffers, recreated every frame since the buffers may
+/// have been reallocated.
+struct InstanceBindGroups {
+    quads: wgpu::BindGroup,
+    shadows: wgpu::BindGroup,
+    underlines: wgpu::BindGroup,
+    mono_sprites: wgpu::BindGroup,
+    poly_sprites: wgpu::BindGroup,
+    path_rasterization: wgpu::BindGroup,
+    paths: wgpu::BindGroup,
+    path_texture: wgpu::BindGroup,
+    blurs: wgpu::BindGroup,
+    layers: wgpu::BindGroup,
+    shader_quads: wgpu::BindGroup,
+}
+
+#[derive(Clone, Copy)]
+enum InstanceKind {
+    Quads,
+    Shadows,
+    Underlines,
+    MonoSprites,
+    PolySprites,
+    PathVertices,
+    PathSprites,
+    Blurs,
+    Layers,
+    ShaderQuads,
+}
+
+const INSTANCE_KIND_COUNT: usize = 10;
+
+/// Splits the scene's batches into segments whose instances each fit in buffers of
+/// `max_size` bytes, so scenes too large for a single upload can be drawn over several
+/// submissions. Batches are only split when they don't fit in a segment on their own.
+fn split_into_segments<'a>(
+    batches: impl Iterator<Item = PrimitiveBatch<'a>>,
+    max_size: u64,
+) -> Vec<Vec<PrimitiveBatch<'a>>> {
+    fn capacity<T>(max_size: u64) -> usize {
+        (max_size / std::mem::size_of::<T>() as u64).max(1) as usize
+    }
+
+    let mut segments = Vec::new();
+    let mut segment = Vec::new();
+    let mut usage = [0u64; INSTANCE_KIND_COUNT];
+
+    // Appends `batch` to the current segment, starting a new one first if the instances it
+    // needs don't fit in what is left of the current segment's budget.
+    let mut push = |batch: PrimitiveBatch<'a>, needs: &[(InstanceKind, u64)]| {
+        let mut needed = usage;
+        for &(kind, bytes) in needs {
+            needed[kind as usize] += bytes;
+        }
+        let fits = needed.iter().all(|&bytes| bytes <= max_size);
+        if !fits && !segment.is_empty() {
+            segments.push(std::mem::take(&mut segment));
+            usage = [0; INSTANCE_KIND_COUNT];
+        }
+        for &(kind, bytes) in needs {
+            usage[kind as usize] += bytes;
+        }
+        segment.push(batch);
+    };
+
+    fn bytes<T>(len: usize) -> u64 {
+        (len * std::mem::size_of::<T>()) as u64
+    }
+
+    for batch in batches {
+        match batch {
+            PrimitiveBatch::Quads(quads) => {
+                for chunk in quads.chunks(capacity::<Quad>(max_size)) {
+                    push(
+                        PrimitiveBatch::Quads(chunk),
+                        &[(InstanceKind::Quads, bytes::<Quad>(chunk.len()))],
+                    );
+                }
+            }
+            PrimitiveBatch::Shadows(shadows) => {
+                for chunk in shadows.chunks(capacity::<Shadow>(max_size)) {
+                    push(
+                        PrimitiveBatch::Shadows(chunk),
+                        &[(InstanceKind::Shadows, bytes::<Shadow>(chunk.len()))],
+                    );
+                }
+            }
+            PrimitiveBatch::Underlines(underlines) => {
+                for chunk in underlines.chunks(capacity::<Underline>(max_size)) {
+                    push(
+                        PrimitiveBatch::Underlines(chunk),
+                        &[(InstanceKind::Underlines, bytes::<Underline>(chunk.len()))],
+                    );
+                }
+            }
+            PrimitiveBatch::MonochromeSprites {
+                texture_id,
+                sprites,
+            } => {
+                for chunk in sprites.chunks(capacity::<MonochromeSprite>(max_size)) {
+                    push(
+                        PrimitiveBatch::MonochromeSprites {
+                            texture_id,
+                            sprites: chunk,
+                        },
+                        &[(
+                            InstanceKind::MonoSprites,
+                            bytes::<MonochromeSprite>(chunk.len()),
+                        )],
+                    );
+                }
+            }
+            PrimitiveBatch::PolychromeSprites {
+                texture_id,
+                sprites,
+            } => {
+                for chunk in sprites.chunks(capacity::<PolychromeSprite>(max_size)) {
+                    push(
+                        PrimitiveBatch::PolychromeSprites {
+                            texture_id,
+                            sprites: chunk,
+                        },
+                        &[(
+                            InstanceKind::PolySprites,
+                            bytes::<PolychromeSprite>(chunk.len()),
+                        )],
+                    );
+                }
+            }
+            PrimitiveBatch::Paths(paths) => {
+                // NOTE(mdeand): Paths can only be split between paths, not within one, so
+                // NOTE(mdeand): a single path with more vertices than fit in the largest
+                // NOTE(mdeand): buffer the device allows can't be drawn at all.
+                let mut start = 0;
+                let mut vertex_bytes = 0;
+                for (ix, path) in paths.iter().enumerate() {
+                    let path_bytes = bytes::<PathRasterizationVertex>(path.vertices.len());
+                    if path_bytes > max_size {
+                        log::error!(
+                            "path with {} vertices exceeds the maximum instance buffer size of {} bytes, skipping it",
+                            path.vertices.len(),
+                            max_size
+                        );
+                        if start < ix {
+                            push(
+                                PrimitiveBatch::Paths(&paths[start..ix]),
+                                &path_needs(vertex_bytes, ix - start),
+                            );
+                        }
+                        start = ix + 1;
+                        vertex_bytes = 0;
+                        continue;
+                    }
+
+                    if vertex_bytes + path_bytes > max_size {
+                        push(
+                            PrimitiveBatch::Paths(&paths[start..ix]),
+                            &path_needs(vertex_bytes, ix - start),
+                        );
+                        start = ix;
+                        vertex_bytes = 0;
+                    }
+                    vertex_bytes += path_bytes;
+                }
+                if start < paths.len() {
+                    push(
+                        PrimitiveBatch::Paths(&paths[start..]),
+                        &path_needs(vertex_bytes, paths.len() - start),
+                    );
+                }
+            }
+            PrimitiveBatch::Blurs(blurs) => {
+                for chunk in blurs.chunks(capacity::<BlurInstance>(max_size)) {
+                    push(
+                        PrimitiveBatch::Blurs(chunk),
+                        &[(InstanceKind::Blurs, bytes::<BlurInstance>(chunk.len()))],
+                    );
+                }
+            }
+            PrimitiveBatch::Surfaces(surfaces) => push(PrimitiveBatch::Surfaces(surfaces), &[]),
+            PrimitiveBatch::ShaderQuads(quads) => {
+                for chunk in quads.chunks(capacity::<ShaderQuadInstance>(max_size)) {
+                    push(
+                        PrimitiveBatch::ShaderQuads(chunk),
+                        &[(
+                            InstanceKind::ShaderQuads,
+                            bytes::<ShaderQuadInstance>(chunk.len()),
+                        )],
+                    );
+                }
+            }
+            PrimitiveBatch::Layers(layers) => {
+                // NOTE(mdeand): A layer's scene is drawn in the segment the layer is composited
+                // NOTE(mdeand): in, so layers are never split.
+                let mut needs = Vec::new();
+                for layer in layers {
+                    layer_needs(layer, &mut needs);
+                }
+                push(PrimitiveBatch::Layers(layers), &needs);
+            }
+        }
+    }
+
+    if !segment.is_empty() || segments.is_empty() {
+        segments.push(segment);
+    }
+    segments
+}
+
+fn path_needs(vertex_bytes: u64, path_count: usize) -> [(InstanceKind, u64); 2] {
+    [
+        (InstanceKind::PathVertices, vertex_bytes),
+        (
+            InstanceKind::PathSprites,
+            (path_count * std::mem::size_of::<PathSprite>()) as u64,
+        ),
+    ]
+}
+
+/// Appends the instances needed to draw the layer's scene, including nested layers, and to
+/// composite it.
+fn layer_needs(layer: &Layer, needs: &mut Vec<(InstanceKind, u64)>) {
+    fn bytes<T>(len: usize) -> u64 {
+        (len * std::mem::size_of::<T>()) as u64
+    }
+
+    let scene = &layer.scene;
+    let path_vertex_count = scene.paths.iter().map(|path| path.vertices.len()).sum();
+    needs.extend([
+        (InstanceKind::Layers, bytes::<LayerInstance>(1)),
+        (InstanceKind::Quads, bytes::<Quad>(scene.quads.len())),
+        (InstanceKind::Shadows, bytes::<Shadow>(scene.shadows.len())),
+        (
+            InstanceKind::Underlines,
+            bytes::<Underline>(scene.underlines.len()),
+        ),
+        (
+            InstanceKind::MonoSprites,
+            bytes::<MonochromeSprite>(scene.monochrome_sprites.len()),
+        ),
+        (
+            InstanceKind::PolySprites,
+            bytes::<PolychromeSprite>(scene.polychrome_sprites.len()),
+        ),
+        (
+            InstanceKind::PathVertices,
+            bytes::<PathRasterizationVertex>(path_vertex_count),
+        ),
+        (
+            InstanceKind::PathSprites,
+            bytes::<PathSprite>(scene.paths.len()),
+        ),
+        (
+            InstanceKind::Blurs,
+            bytes::<BlurInstance>(scene.blurs.len()),
+        ),
+        (
+            InstanceKind::ShaderQuads,
+            bytes::<ShaderQuadInstance>(scene.shader_quads.len()),
+        ),
+    ]);
+    if layer.blur_radius > ScaledPixels(0.) {
+        needs.push((InstanceKind::Blurs, bytes::<BlurInstance>(1)));
+    }
+    if let Some(mask) = &layer.mask {
+        needs.extend([
+            (
+                InstanceKind::PathVertices,
+                bytes::<PathRasterizationVertex>(mask.vertices.len()),
+            ),
+            (InstanceKind::PathSprites, bytes::<PathSprite>(1)),
+        ]);
+    }
+    for layer in &scene.layers {
+        layer_needs(layer, needs);
+    }
+}
+
+/// Returns the scene along with the scenes of every layer nested in it.
+fn scene_with_layers(scene: &Scene) -> Vec<&Scene> {
+    let mut scenes = vec![scene];
+    let mut ix = 0;
+    while ix < scenes.len() {
+        let scene = scenes[ix];
+        scenes.extend(scene.layers.iter().map(|layer| &layer.scene));
+        ix += 1;
+    }
+    scenes
+}
+
+/// Returns the largest sample count no greater than `requested` that the adapter can use to
+/// multisample render targets of the given format.
+fn supported_path_sample_count(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+    requested: u32,
+) -> u32 {
+    let flags = adapter.get_texture_format_features(format).flags;
+    [16, 8, 4, 2]
+        .into_iter()
+        .find(|&count| count <= requested && flags.sample_count_supported(count))
+        .unwrap_or(1)
+}
+
+struct PathIntermediateTextures {
+    view: wgpu::TextureView,
+    msaa_view: Option<wgpu::TextureView>,
+}
+
+impl PathIntermediateTextures {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("path_intermediate_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let msaa_view = (sample_count > 1).then(|| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("path_intermediate_msaa_texture"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            msaa_view,
+        }
+    }
+}
+
+/// The textures blurs are computed in, created the first time a frame contains a blur.
+///
+/// What has been drawn behind a blur is copied into `source`, downsampled into `ping`, blurred
+/// horizontally into `pong` and then vertically back into `ping`.
+struct BlurTextures {
+    source: wgpu::Texture,
+    source_bind_group: wgpu::BindGroup,
+    ping_view: wgpu::TextureView,
+    ping_bind_group: wgpu::BindGroup,
+    pong_view: wgpu::TextureView,
+    pong_bind_group: wgpu::BindGroup,
+}
+
+impl BlurTextures {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let create_texture = |label: &str, usage: wgpu::TextureUsages| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: usage | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let create_bind_group = |label: &str, view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            })
+        };
+
+        let source = create_texture("blur_source_texture", wgpu::TextureUsages::COPY_DST);
+        let source_view = source.create_view(&wgpu::TextureViewDescriptor::default());
+        let ping_view = create_texture("blur_ping_texture", wgpu::TextureUsages::RENDER_ATTACHMENT)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let pong_view = create_texture("blur_pong_texture", wgpu::TextureUsages::RENDER_ATTACHMENT)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            source_bind_group: create_bind_group("blur_source_bind_group", &source_view),
+            source,
+            ping_bind_group: create_bind_group("blur_ping_bind_group", &ping_view),
+            ping_view,
+            pong_bind_group: create_bind_group("blur_pong_bind_group", &pong_view),
+            pong_view,
+        }
+    }
+}
+
+/// A texture isolated layers are drawn into before they're composited onto the frame, with
+/// the size of the viewport. Blended layers also copy what's beneath them into one.
+struct LayerTexture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl LayerTexture {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        // NOTE(mdeand): Blurs within the layer copy out of it, like they do out of the frame.
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("layer_texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("layer_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+        Self {
+            texture,
+            view,
+            bind_group,
+        }
+    }
+}
+
+/// Returns the whole device pixels a blur has to sample, or `None` if none of them are
+/// visible.
+fn blur_region(blur: &Blur, viewport_width: u32, viewport_height: u32) -> Option<[u32; 4]> {
+    pixel_region(
+        blur.bounds.intersect(&blur.content_mask.bounds),
+        viewport_width,
+        viewport_height,
+    )
+}
+
+/// Returns the pixels two `[x, y, width, height]` regions have in common, or `None` if they
+/// don't overlap.
+fn intersect_regions(a: [u32; 4], b: [u32; 4]) -> Option<[u32; 4]> {
+    let left = a[0].max(b[0]);
+    let top = a[1].max(b[1]);
+    let right = (a[0] + a[2]).min(b[0] + b[2]);
+    let bottom = (a[1] + a[3]).min(b[1] + b[3]);
+    (right > left && bottom > top).then(|| [left, top, right - left, bottom - top])
+}
+
+/// Returns the smallest `[x, y, width, height]` region covering both regions.
+fn union_regions(a: [u32; 4], b: [u32; 4]) -> [u32; 4] {
+    let left = a[0].min(b[0]);
+    let top = a[1].min(b[1]);
+    let right = (a[0] + a[2]).max(b[0] + b[2]);
+    let bottom = (a[1] + a[3]).max(b[1] + b[3]);
+    [left, top, right - left, bottom - top]
+}
+
+/// Returns the pixels of a frame to copy into the surface texture it's presented in, given
+/// the pixels drawn this frame and those drawn by the frames presented before it, newest last,
+/// where `None` stands for every pixel. The texture may still show any of the last
+/// `stale_frames` frames, so the pixels any of them drew are copied too, and every pixel is
+/// copied until that many frames were presented.
+fn present_region(
+    presented: &VecDeque<Option<[u32; 4]>>,
+    stale_frames: usize,
+    drawn: Option<[u32; 4]>,
+) -> Option<[u32; 4]> {
+    if presented.len() < stale_frames {
+        return None;
+    }
+    presented
+        .iter()
+        .rev()
+        .take(stale_frames)
+        .try_fold(drawn?, |region, presented| {
+            Some(union_regions(region, (*presented)?))
+        })
+}
+
+/// Returns the whole device pixels covered by `bounds` within the viewport, as
+/// `[x, y, width, height]`, or `None` if there are none.
+fn pixel_region(
+    bounds: geometry::Bounds<ScaledPixels>,
+    viewport_width: u32,
+    viewport_height: u32,
+) -> Option<[u32; 4]> {
+    let left = bounds.origin.x.0.floor().max(0.0);
+    let top = bounds.origin.y.0.floor().max(0.0);
+    let right = (bounds.origin.x.0 + bounds.size.width.0)
+        .ceil()
+        .min(viewport_width as f32);
+    let bottom = (bounds.origin.y.0 + bounds.size.height.0)
+        .ceil()
+        .min(viewport_height as f32);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some([
+        left as u32,
+        top as u32,
+        (right - left) as u32,
+        (bottom - top) as u32,
+    ])
+}
+
+/// Which pixels of a frame have to be drawn.
+enum Redraw {
+    Everything,
+    /// Only the pixels within `[x, y, width, height]`, the rest is left as it was drawn last.
+    Region([u32; 4]),
+    Nothing,
+}
+
+/// What became of a frame given to [`WgpuRenderer::draw`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawResult {
+    /// The frame was drawn and presented, or was already on screen.
+    Drawn,
+    /// The surface had to be reconfigured before the frame could be drawn into it, and the
+    /// frame should be drawn again right away.
+    Retry,
+    /// The frame was dropped because the device was lost or the surface had no texture to draw
+    /// into, and should only be drawn again once the next frame is due.
+    Skipped,
+}
+
+/// Where a [`WgpuRenderer`] draws its frames.
+enum RenderTarget {
+    /// The surface of an on-screen window.
+    Surface(wgpu::Surface<'static>),
+    /// An offscreen texture, used by headless windows.
+    Texture(wgpu::Texture),
+}
+
+/// How many frames may be queued ahead of the display, unless the window asks otherwise.
+const DEFAULT_MAX_FRAME_LATENCY: u32 = 2;
+
+/// The fewest frames presented before the current one a surface texture is assumed to possibly
+/// still show. Swap chains hold `desired_maximum_frame_latency + 1` textures, unless the
+/// platform needs more, which is assumed to be at most four.
+const MIN_STALE_SURFACE_FRAMES: usize = 3;
+
+/// The present mode requested through the environment, for windows that don't pick one.
+///
+/// `GPUI_PRESENT_MODE=fifo|fifo_relaxed|mailbox|immediate` picks a mode, and
+/// `GPUI_DISABLE_VSYNC=1` is a shorthand for `immediate`.
+fn present_mode_from_env() -> PresentMode {
+    std::env::var("GPUI_PRESENT_MODE")
+        .ok()
+        .and_then(|s| match s.to_lowercase().as_str() {
+            "fifo" => Some(PresentMode::Fifo),
+            "fifo_relaxed" => Some(PresentMode::FifoRelaxed),
+            "mailbox" => Some(PresentMode::Mailbox),
+            "immediate" => Some(PresentMode::Immediate),
+            _ => None,
+        })
+        .unwrap_or_else(|| {
+            if std::env::var("GPUI_DISABLE_VSYNC").is_ok() {
+                PresentMode::Immediate
+            } else {
+                PresentMode::Fifo
+            }
+        })
+}
+
+/// Picks the supported mode closest to the requested one. `Fifo` is supported everywhere.
+fn resolve_present_mode(
+    present_mode: PresentMode,
+    supported: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    let candidates: &[wgpu::PresentMode] = match present_mode {
+        PresentMode::Fifo => &[wgpu::PresentMode::Fifo],
+        PresentMode::FifoRelaxed => &[wgpu::PresentMode::FifoRelaxed],
+        // NOTE(mdeand): Both are asking for low latency, so prefer the other one over
+        // NOTE(mdeand): throttling to the refresh rate.
+        PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate],
+        PresentMode::Immediate => &[wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox],
+    };
+
+    candidates
+        .iter()
+        .find(|mode| supported.contains(mode))
+        .copied()
+        .unwrap_or_else(|| {
+            log::warn!("present mode {present_mode:?} is not supported, falling back to Fifo");
+            wgpu::PresentMode::Fifo
+        })
+}
+
+/// The format of offscreen render targets. Like the surface format, it must not be sRGB since
+/// the shaders already output sRGB-encoded colors.
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+fn create_offscreen_texture(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offscreen_target"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+pub struct WgpuRenderer {
+    context: Arc<WgpuContext>,
+    target: RenderTarget,
+    surface_configuration: wgpu::SurfaceConfiguration,
+    surface_params_buffer: wgpu::Buffer,
+    atlas: Arc<WgpuAtlas>,
+    pipelines: Arc<WgpuPipelines>,
+    rendering_parameters: RenderingParameters,
+    path_intermediate_textures: PathIntermediateTextures,
+    blur_textures: Option<BlurTextures>,
+    /// Layer textures that aren't being drawn into, reused by the next layers. Nested layers
+    /// each take one of their own.
+    layer_textures: Vec<LayerTexture>,
+    /// What's beneath a blended layer, copied out of the texture it's composited onto.
+    backdrop_texture: Option<LayerTexture>,
+    instance_buffers: InstanceBuffers,
+    max_instance_buffer_size: u64,
+    /// When the renderer was created, which the time given to shader quads is relative to.
+    start_time: std::time::Instant,
+    /// The frame last drawn into the surface, which is kept so the next frame only has to
+    /// redraw what changed. Offscreen targets keep their frames themselves.
+    frame_texture: Option<wgpu::Texture>,
+    /// The id of the scene drawn last and the atlas's removed tile count at the time, as long
+    /// as the frame it was drawn into is still there to draw over.
+    last_frame: Option<(u64, u64)>,
+    /// The pixels drawing is limited to while a frame is only partially redrawn, as
+    /// `[x, y, width, height]`.
+    scissor: Option<[u32; 4]>,
+    /// The pixels copied into the surface by the last frames presented, newest last, where
+    /// `None` stands for every pixel. Cleared whenever the surface is configured, since its
+    /// textures are created anew.
+    presented_regions: VecDeque<Option<[u32; 4]>>,
+    /// Whether the surface failed to hand out a texture since one was last drawn into, so
+    /// repeated failures are only logged once.
+    acquire_failed: bool,
+    /// Times the batches of each frame on the GPU, when the device can.
+    gpu_timer: Option<GpuTimer>,
+    /// The stats of the frame being drawn.
+    frame_stats: FrameStats,
+    /// The stats of the last frame that was measured, which GPU times are added to once
+    /// they're read back.
+    last_frame_stats: Arc<Mutex<Option<FrameStats>>>,
+
+    // cache bind groups for each double-buffered surface (index 0/1)
+    surface_bind_groups:
+        Mutex<HashMap<crate::platform::cross::surface_registry::SurfaceId, [wgpu::BindGroup; 2]>>,
 }
 
 impl WgpuRenderer {
@@ -1174,6 +2636,8 @@ impl WgpuRenderer {
         width: u32,
         height: u32,
         path_sample_count: u32,
+        present_mode: Option<PresentMode>,
+        max_frame_latency: Option<u32>,
     ) -> anyhow::Result<Self>
     where
         WindowHandle: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
@@ -1208,92 +2672,1153 @@ impl WgpuRenderer {
             surface_capabilities.alpha_modes[0]
         };
 
-        // allow overriding vsync behaviour.  The default is `Fifo` (vsync
-        // enabled) which is what `wgpu` considers the safest presentation mode.
-        // Setting `GPUI_DISABLE_VSYNC=1` in the environment will switch to
-        // `Immediate`, which drops frames at the display's full rate.  A more
-        // fine‑grained control (`GPUI_PRESENT_MODE=mailbox|fifo|immediate`) is
-        // also supported for experimentation.
-        let present_mode = std::env::var("GPUI_PRESENT_MODE")
-            .ok()
-            .and_then(|s| match s.to_lowercase().as_str() {
-                "mailbox" => Some(wgpu::PresentMode::Mailbox),
-                "immediate" => Some(wgpu::PresentMode::Immediate),
-                "fifo" => Some(wgpu::PresentMode::Fifo),
-                _ => None,
+        let present_mode = resolve_present_mode(
+            present_mode.unwrap_or_else(present_mode_from_env),
+            &surface_capabilities.present_modes,
+        );
+
+        // NOTE(mdeand): Blurs copy what has been drawn behind them out of the frame, which
+        // NOTE(mdeand): needs the surface to be a copy source.
+        let mut usage = if surface_capabilities
+            .usages
+            .contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC
+        } else {
+            log::warn!("the surface can't be copied from, blurs won't be drawn");
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        };
+        // NOTE(mdeand): Frames are drawn into a texture of their own and copied into the
+        // NOTE(mdeand): surface, so only what changed since the last frame has to be drawn.
+        if surface_capabilities
+            .usages
+            .contains(wgpu::TextureUsages::COPY_DST)
+        {
+            usage |= wgpu::TextureUsages::COPY_DST;
+        }
+
+        let surface_configuration = wgpu::SurfaceConfiguration {
+            usage,
+            format,
+            width,
+            height,
+            present_mode,
+            alpha_mode,
+            view_formats: vec![],
+            desired_maximum_frame_latency: max_frame_latency
+                .unwrap_or(DEFAULT_MAX_FRAME_LATENCY)
+                .max(1),
+        };
+
+        Ok(Self::with_target(
+            context,
+            RenderTarget::Surface(surface),
+            surface_configuration,
+            atlas,
+            path_sample_count,
+        ))
+    }
+
+    /// Creates a renderer that draws into an offscreen texture rather than a window surface.
+    pub fn new_offscreen(
+        context: Arc<WgpuContext>,
+        atlas: Arc<WgpuAtlas>,
+        width: u32,
+        height: u32,
+        path_sample_count: u32,
+    ) -> Self {
+        let texture = create_offscreen_texture(&context.device, OFFSCREEN_FORMAT, width, height);
+
+        // NOTE(mdeand): Offscreen frames are read back as straight-alpha images, so render
+        // NOTE(mdeand): them the same way we would for an opaque surface.
+        let surface_configuration = wgpu::SurfaceConfiguration {
+            usage: texture.usage(),
+            format: OFFSCREEN_FORMAT,
+            width: texture.width(),
+            height: texture.height(),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: DEFAULT_MAX_FRAME_LATENCY,
+        };
+
+        Self::with_target(
+            context,
+            RenderTarget::Texture(texture),
+            surface_configuration,
+            atlas,
+            path_sample_count,
+        )
+    }
+
+    fn with_target(
+        context: Arc<WgpuContext>,
+        target: RenderTarget,
+        surface_configuration: wgpu::SurfaceConfiguration,
+        atlas: Arc<WgpuAtlas>,
+        path_sample_count: u32,
+    ) -> Self {
+        let format = surface_configuration.format;
+        let width = surface_configuration.width;
+        let height = surface_configuration.height;
+
+        let surface_params_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Surface Params Buffer"),
+            size: std::mem::size_of::<SurfaceParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut rendering_parameters = RenderingParameters::from_env(path_sample_count);
+        rendering_parameters.path_sample_count = supported_path_sample_count(
+            &context.adapter,
+            format,
+            rendering_parameters.path_sample_count,
+        );
+
+        let pipelines = WgpuPipelines::shared(
+            context.as_ref(),
+            &surface_configuration,
+            rendering_parameters.path_sample_count,
+        );
+
+        let path_intermediate_textures = PathIntermediateTextures::new(
+            &context.device,
+            format,
+            width,
+            height,
+            rendering_parameters.path_sample_count,
+        );
+
+        let instance_buffers = InstanceBuffers::new(&context.device);
+        let limits = context.device.limits();
+        let max_instance_buffer_size =
+            (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+
+        Self {
+            context: context.clone(),
+            target,
+            surface_configuration,
+            atlas,
+            surface_params_buffer,
+            pipelines,
+            rendering_parameters,
+            path_intermediate_textures,
+            blur_textures: None,
+            layer_textures: Vec::new(),
+            backdrop_texture: None,
+            instance_buffers,
+            max_instance_buffer_size,
+            start_time: std::time::Instant::now(),
+            frame_texture: None,
+            last_frame: None,
+            scissor: None,
+            presented_regions: VecDeque::new(),
+            acquire_failed: false,
+            gpu_timer: context
+                .gpu_timing()
+                .then(|| GpuTimer::new(&context.device, &context.queue)),
+            frame_stats: FrameStats::default(),
+            last_frame_stats: Arc::new(Mutex::new(None)),
+            surface_bind_groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn begin_main_pass<'encoder>(
+        command_encoder: &'encoder mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        scissor: Option<[u32; 4]>,
+    ) -> wgpu::RenderPass<'encoder> {
+        let mut pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("main"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+                resolve_target: None,
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        if let Some([x, y, width, height]) = scissor {
+            pass.set_scissor_rect(x, y, width, height);
+        }
+        pass
+    }
+
+    /// Rasterizes a batch of paths into the intermediate path texture, returning whether the
+    /// paths' vertices could be uploaded.
+    fn rasterize_paths(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        paths: &[Path<ScaledPixels>],
+        path_rasterization_bind_group: &wgpu::BindGroup,
+    ) -> bool {
+        let mut vertices = Vec::new();
+        for path in paths {
+            let clipped_bounds = path.clipped_bounds();
+            vertices.extend(path.vertices.iter().map(|vertex| PathRasterizationVertex {
+                xy_position: vertex.xy_position,
+                st_position: vertex.st_position,
+                color: path.color,
+                bounds: clipped_bounds,
+                content_mask: path.content_mask.clone(),
+            }));
+        }
+
+        let Some(first_vertex) = self
+            .instance_buffers
+            .path_vertices
+            .write(&self.context.queue, &vertices)
+        else {
+            return false;
+        };
+
+        let textures = &self.path_intermediate_textures;
+        let (view, resolve_target) = match &textures.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(&textures.view)),
+            None => (&textures.view, None),
+        };
+
+        let mut pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("path_rasterization"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: if resolve_target.is_some() {
+                        wgpu::StoreOp::Discard
+                    } else {
+                        wgpu::StoreOp::Store
+                    },
+                },
+                resolve_target,
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+
+        let count = vertices.len() as u32;
+        pass.set_pipeline(&self.pipelines.path_rasterization_pipeline);
+        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+        pass.set_bind_group(1, path_rasterization_bind_group, &[]);
+        pass.draw(first_vertex..first_vertex + count, 0..1);
+
+        true
+    }
+
+    /// Blurs what has been drawn behind `blur` so far, and draws the result back over the
+    /// frame within the blur's rounded bounds. With `replace`, the blurred texels are written
+    /// over the texture instead, which blurs a transparent layer texture in place.
+    fn draw_blur(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        surface_texture: &wgpu::Texture,
+        surface_view: &wgpu::TextureView,
+        blur: &Blur,
+        blurs_bind_group: &wgpu::BindGroup,
+        replace: bool,
+    ) {
+        if !surface_texture
+            .usage()
+            .contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            return;
+        }
+
+        let width = self.surface_configuration.width;
+        let height = self.surface_configuration.height;
+        let Some([x, y, region_width, region_height]) = blur_region(blur, width, height) else {
+            return;
+        };
+
+        let downsample = blur_downsample(blur.radius.0);
+        let instance = BlurInstance {
+            blur: blur.clone(),
+            region: geometry::Bounds {
+                origin: geometry::Point {
+                    x: x as f32,
+                    y: y as f32,
+                },
+                size: geometry::Size {
+                    width: region_width as f32,
+                    height: region_height as f32,
+                },
+            },
+            downsample,
+            sigma: blur.radius.0 / downsample,
+        };
+        let Some(instance_ix) = self
+            .instance_buffers
+            .blurs
+            .write(&self.context.queue, std::slice::from_ref(&instance))
+        else {
+            return;
+        };
+        let instances = instance_ix..instance_ix + 1;
+
+        let textures = self.blur_textures.get_or_insert_with(|| {
+            BlurTextures::new(
+                &self.context.device,
+                &self.pipelines.sprites_bind_group_layout,
+                &self.context.atlas_sampler,
+                self.surface_configuration.format,
+                width,
+                height,
+            )
+        });
+
+        // NOTE(mdeand): The region keeps its position in the source texture, so the shaders
+        // NOTE(mdeand): can address it with the same device pixels as the frame.
+        command_encoder.copy_texture_to_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: surface_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyTextureInfo {
+                texture: &textures.source,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: region_width,
+                height: region_height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let blurred_width = region_width.div_ceil(downsample as u32) as f32;
+        let blurred_height = region_height.div_ceil(downsample as u32) as f32;
+        let passes = [
+            (
+                &self.pipelines.blur_downsample_pipeline,
+                &textures.source_bind_group,
+                &textures.ping_view,
+            ),
+            (
+                &self.pipelines.blur_horizontal_pipeline,
+                &textures.ping_bind_group,
+                &textures.pong_view,
+            ),
+            (
+                &self.pipelines.blur_vertical_pipeline,
+                &textures.pong_bind_group,
+                &textures.ping_view,
+            ),
+        ];
+        for (pipeline, input, output) in passes {
+            let mut pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("blur"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    resolve_target: None,
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_viewport(0.0, 0.0, blurred_width, blurred_height, 0.0, 1.0);
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+            pass.set_bind_group(1, input, &[]);
+            pass.set_bind_group(2, blurs_bind_group, &[]);
+            pass.draw(0..3, instances.clone());
+        }
+
+        let mut pass = Self::begin_main_pass(
+            command_encoder,
+            surface_view,
+            wgpu::LoadOp::Load,
+            self.scissor,
+        );
+        pass.set_pipeline(if replace {
+            &self.pipelines.layer_blur_pipeline
+        } else {
+            &self.pipelines.blurs_pipeline
+        });
+        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+        pass.set_bind_group(1, &textures.ping_bind_group, &[]);
+        pass.set_bind_group(2, blurs_bind_group, &[]);
+        pass.draw(0..4, instances);
+    }
+
+    /// Draws the layer's scene into a transparent layer texture, then composites it onto
+    /// `surface_view` with the layer's opacity and blend mode.
+    fn draw_layer(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        surface_texture: &wgpu::Texture,
+        surface_view: &wgpu::TextureView,
+        layer: &Layer,
+        bind_groups: &InstanceBindGroups,
+        seen_surfaces: &mut Vec<crate::platform::cross::surface_registry::SurfaceId>,
+    ) {
+        let width = self.surface_configuration.width;
+        let height = self.surface_configuration.height;
+        // NOTE(mdeand): Only the pixels of the layer that are redrawn are cleared, drawn into and
+        // NOTE(mdeand): composited, rather than the whole layer texture.
+        let Some(layer_region) = pixel_region(layer.bounds, width, height) else {
+            return;
+        };
+        let Some([x, y, region_width, region_height]) =
+            self.scissor.map_or(Some(layer_region), |scissor| {
+                intersect_regions(scissor, layer_region)
             })
-            .unwrap_or_else(|| {
-                if std::env::var("GPUI_DISABLE_VSYNC").is_ok() {
-                    wgpu::PresentMode::Immediate
-                } else {
-                    wgpu::PresentMode::Fifo
+        else {
+            return;
+        };
+
+        // NOTE(mdeand): Blending needs what's beneath the layer, which is copied out of the
+        // NOTE(mdeand): target like it is for blurs. Without that, the layer is drawn normally.
+        let blended = layer.blend_mode != BlendMode::Normal
+            && surface_texture
+                .usage()
+                .contains(wgpu::TextureUsages::COPY_SRC);
+        let instance = LayerInstance {
+            bounds: layer.bounds,
+            opacity: layer.opacity,
+            blend_mode: if blended {
+                layer.blend_mode
+            } else {
+                BlendMode::Normal
+            },
+        };
+        let Some(instance_ix) = self
+            .instance_buffers
+            .layers
+            .write(&self.context.queue, std::slice::from_ref(&instance))
+        else {
+            return;
+        };
+
+        let target = self.layer_textures.pop().unwrap_or_else(|| {
+            LayerTexture::new(
+                &self.context.device,
+                &self.pipelines.sprites_bind_group_layout,
+                &self.context.atlas_sampler,
+                self.surface_configuration.format,
+                self.surface_configuration.width,
+                self.surface_configuration.height,
+            )
+        });
+
+        let scissor = self.scissor.replace([x, y, region_width, region_height]);
+        let mut pass = Self::begin_main_pass(
+            command_encoder,
+            &target.view,
+            wgpu::LoadOp::Load,
+            self.scissor,
+        );
+        pass.set_pipeline(&self.pipelines.layer_clear_pipeline);
+        pass.draw(0..3, 0..1);
+        drop(pass);
+
+        let batches = layer.scene.batches().collect::<Vec<_>>();
+        self.draw_segment(
+            command_encoder,
+            &target.texture,
+            &target.view,
+            wgpu::LoadOp::Load,
+            &batches,
+            bind_groups,
+            seen_surfaces,
+        );
+        if layer.blur_radius > ScaledPixels(0.) {
+            // NOTE(mdeand): The blur reads the whole pixels covering the layer, so it may write
+            // NOTE(mdeand): them back with nothing left to blend with.
+            let bounds = geometry::Bounds {
+                origin: geometry::Point {
+                    x: ScaledPixels(x as f32),
+                    y: ScaledPixels(y as f32),
+                },
+                size: geometry::Size {
+                    width: ScaledPixels(region_width as f32),
+                    height: ScaledPixels(region_height as f32),
+                },
+            };
+            let blur = Blur {
+                order: layer.order,
+                radius: layer.blur_radius,
+                bounds,
+                corner_radii: geometry::Corners::default(),
+                content_mask: ContentMask {
+                    bounds,
+                    ..Default::default()
+                },
+            };
+            self.draw_blur(
+                command_encoder,
+                &target.texture,
+                &target.view,
+                &blur,
+                &bind_groups.blurs,
+                true,
+            );
+        }
+        if let Some(mask) = &layer.mask {
+            self.apply_layer_mask(
+                command_encoder,
+                &target.view,
+                layer.bounds,
+                mask,
+                bind_groups,
+            );
+        }
+        self.scissor = scissor;
+
+        let backdrop = if blended {
+            let backdrop = self.backdrop_texture.get_or_insert_with(|| {
+                LayerTexture::new(
+                    &self.context.device,
+                    &self.pipelines.sprites_bind_group_layout,
+                    &self.context.atlas_sampler,
+                    self.surface_configuration.format,
+                    width,
+                    height,
+                )
+            });
+            command_encoder.copy_texture_to_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: surface_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyTextureInfo {
+                    texture: &backdrop.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::Extent3d {
+                    width: region_width,
+                    height: region_height,
+                    depth_or_array_layers: 1,
+                },
+            );
+            &backdrop.bind_group
+        } else {
+            // NOTE(mdeand): The shader only reads the backdrop of blended layers.
+            &target.bind_group
+        };
+
+        let mut pass = Self::begin_main_pass(
+            command_encoder,
+            surface_view,
+            wgpu::LoadOp::Load,
+            self.scissor,
+        );
+        pass.set_pipeline(&self.pipelines.layers_pipeline);
+        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+        pass.set_bind_group(1, &target.bind_group, &[]);
+        pass.set_bind_group(2, backdrop, &[]);
+        pass.set_bind_group(3, &bind_groups.layers, &[]);
+        pass.draw(0..4, instance_ix..instance_ix + 1);
+        drop(pass);
+
+        self.layer_textures.push(target);
+    }
+
+    /// Multiplies the layer texture within `bounds` by the coverage of the layer's clip path.
+    fn apply_layer_mask(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        layer_view: &wgpu::TextureView,
+        bounds: geometry::Bounds<ScaledPixels>,
+        mask: &Path<ScaledPixels>,
+        bind_groups: &InstanceBindGroups,
+    ) {
+        if !self.rasterize_paths(
+            command_encoder,
+            std::slice::from_ref(mask),
+            &bind_groups.path_rasterization,
+        ) {
+            return;
+        }
+        let Some(instance_ix) = self
+            .instance_buffers
+            .path_sprites
+            .write(&self.context.queue, &[PathSprite { bounds }])
+        else {
+            return;
+        };
+
+        let mut pass = Self::begin_main_pass(
+            command_encoder,
+            layer_view,
+            wgpu::LoadOp::Load,
+            self.scissor,
+        );
+        pass.set_pipeline(&self.pipelines.layer_mask_pipeline);
+        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+        pass.set_bind_group(1, &bind_groups.path_texture, &[]);
+        pass.set_bind_group(2, &bind_groups.paths, &[]);
+        pass.draw(0..4, instance_ix..instance_ix + 1);
+    }
+
+    /// Sizes every instance buffer for the scene, returning bind groups for this frame.
+    fn prepare_instance_buffers(&mut self, scene: &Scene) -> InstanceBindGroups {
+        let device = &self.context.device;
+        let max_size = self.max_instance_buffer_size;
+        let buffers = &mut self.instance_buffers;
+
+        fn required_bytes<T>(len: usize) -> u64 {
+            (len * std::mem::size_of::<T>()) as u64
+        }
+
+        // NOTE(mdeand): Layers are drawn with the same buffers as the frame they're in.
+        let scenes = scene_with_layers(scene);
+        let count =
+            |len: fn(&Scene) -> usize| -> usize { scenes.iter().map(|scene| len(scene)).sum() };
+        let path_vertex_count = count(|scene| {
+            let mask_vertex_count = scene
+                .layers
+                .iter()
+                .filter_map(|layer| layer.mask.as_ref())
+                .map(|mask| mask.vertices.len());
+            scene
+                .paths
+                .iter()
+                .map(|path| path.vertices.len())
+                .chain(mask_vertex_count)
+                .sum()
+        });
+        let path_sprite_count = count(|scene| {
+            let mask_count = scene.layers.iter().filter(|layer| layer.mask.is_some());
+            scene.paths.len() + mask_count.count()
+        });
+
+        buffers.quads.begin_frame(
+            device,
+            required_bytes::<Quad>(count(|scene| scene.quads.len())),
+            max_size,
+        );
+        buffers.shadows.begin_frame(
+            device,
+            required_bytes::<Shadow>(count(|scene| scene.shadows.len())),
+            max_size,
+        );
+        buffers.underlines.begin_frame(
+            device,
+            required_bytes::<Underline>(count(|scene| scene.underlines.len())),
+            max_size,
+        );
+        buffers.mono_sprites.begin_frame(
+            device,
+            required_bytes::<MonochromeSprite>(count(|scene| scene.monochrome_sprites.len())),
+            max_size,
+        );
+        buffers.poly_sprites.begin_frame(
+            device,
+            required_bytes::<PolychromeSprite>(count(|scene| scene.polychrome_sprites.len())),
+            max_size,
+        );
+        buffers.path_vertices.begin_frame(
+            device,
+            required_bytes::<PathRasterizationVertex>(path_vertex_count),
+            max_size,
+        );
+        buffers.path_sprites.begin_frame(
+            device,
+            required_bytes::<PathSprite>(path_sprite_count),
+            max_size,
+        );
+        buffers.blurs.begin_frame(
+            device,
+            required_bytes::<BlurInstance>(count(|scene| scene.blurs.len())),
+            max_size,
+        );
+        buffers.layers.begin_frame(
+            device,
+            required_bytes::<LayerInstance>(count(|scene| scene.layers.len())),
+            max_size,
+        );
+        buffers.shader_quads.begin_frame(
+            device,
+            required_bytes::<ShaderQuadInstance>(count(|scene| scene.shader_quads.len())),
+            max_size,
+        );
+        // NOTE(mdeand): Layers use the gradient stops of the root scene.
+        buffers.gradient_stops.begin_frame(
+            device,
+            required_bytes::<LinearColorStop>(scene.gradient_stops.len()),
+            max_size,
+        );
+        buffers
+            .gradient_stops
+            .write(&self.context.queue, &scene.gradient_stops);
+
+        let storage_bind_group = |label, layout, buffer: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer,
+                        offset: 0,
+                        size: None,
+                    }),
+                }],
+            })
+        };
+
+        let gradient_storage_bind_group = |label, layout, buffer: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer,
+                            offset: 0,
+                            size: None,
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: buffers.gradient_stops.buffer(),
+                            offset: 0,
+                            size: None,
+                        }),
+                    },
+                ],
+            })
+        };
+
+        InstanceBindGroups {
+            quads: gradient_storage_bind_group(
+                "quads_bind_group",
+                &self.pipelines.quads_bind_group_layout,
+                buffers.quads.buffer(),
+            ),
+            shadows: storage_bind_group(
+                "shadows_bind_group",
+                &self.pipelines.shadows_bind_group_layout,
+                buffers.shadows.buffer(),
+            ),
+            underlines: storage_bind_group(
+                "underlines_bind_group",
+                &self.pipelines.underlines_bind_group_layout,
+                buffers.underlines.buffer(),
+            ),
+            mono_sprites: storage_bind_group(
+                "mono_sprites_bind_group",
+                &self.pipelines.mono_sprites_bind_group_layout,
+                buffers.mono_sprites.buffer(),
+            ),
+            poly_sprites: storage_bind_group(
+                "poly_sprites_bind_group",
+                &self.pipelines.poly_sprites_bind_group_layout,
+                buffers.poly_sprites.buffer(),
+            ),
+            path_rasterization: gradient_storage_bind_group(
+                "path_rasterization_bind_group",
+                &self.pipelines.path_rasterization_bind_group_layout,
+                buffers.path_vertices.buffer(),
+            ),
+            paths: storage_bind_group(
+                "paths_bind_group",
+                &self.pipelines.paths_bind_group_layout,
+                buffers.path_sprites.buffer(),
+            ),
+            path_texture: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("path_texture_bind_group"),
+                layout: &self.pipelines.sprites_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(
+                            &self.path_intermediate_textures.view,
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.context.path_sampler),
+                    },
+                ],
+            }),
+            blurs: storage_bind_group(
+                "blurs_bind_group",
+                &self.pipelines.blurs_bind_group_layout,
+                buffers.blurs.buffer(),
+            ),
+            layers: storage_bind_group(
+                "layers_bind_group",
+                &self.pipelines.layers_bind_group_layout,
+                buffers.layers.buffer(),
+            ),
+            shader_quads: storage_bind_group(
+                "shader_quads_bind_group",
+                &self.pipelines.shader_quads_bind_group_layout,
+                buffers.shader_quads.buffer(),
+            ),
+        }
+    }
+
+    /// Draws `scene` and presents it. Frames are skipped while the device is lost or the
+    /// surface has no texture to draw into, and have to be retried when the surface has to be
+    /// reconfigured before it can be drawn into.
+    pub fn draw(&mut self, scene: &Scene) -> DrawResult {
+        if self.context.is_device_lost() {
+            return DrawResult::Skipped;
+        }
+
+        // NOTE(mdeand): Frames are only partially redrawn into frames kept from before, which
+        // NOTE(mdeand): surfaces keep in a texture of their own.
+        let frame_texture = self.frame_texture();
+        let redraw = if frame_texture.is_some() || matches!(self.target, RenderTarget::Texture(_)) {
+            self.redraw(scene)
+        } else {
+            Redraw::Everything
+        };
+        if let Redraw::Nothing = redraw {
+            // NOTE(mdeand): The frame on screen is still up to date, so nothing is presented.
+            self.last_frame = (scene.id != 0).then(|| (scene.id, self.atlas.removed_tile_count()));
+            return DrawResult::Drawn;
+        }
+
+        let (surface_texture, texture) = match &self.target {
+            RenderTarget::Surface(surface) => {
+                let surface_texture = match surface.get_current_texture() {
+                    Ok(surface_texture) => surface_texture,
+                    // NOTE(mdeand): The swap chain no longer matches the window, or was taken
+                    // NOTE(mdeand): away from us (e.g. when a fullscreen app went exclusive).
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        surface.configure(&self.context.device, &self.surface_configuration);
+                        self.presented_regions.clear();
+                        return DrawResult::Retry;
+                    }
+                    Err(error) => {
+                        if !std::mem::replace(&mut self.acquire_failed, true) {
+                            match error {
+                                wgpu::SurfaceError::Timeout => log::warn!(
+                                    "timed out acquiring the next surface texture, skipping frames until one is acquired"
+                                ),
+                                error => log::error!(
+                                    "failed to acquire the next surface texture, skipping frames until one is acquired: {error}"
+                                ),
+                            }
+                        }
+                        return DrawResult::Skipped;
+                    }
+                };
+                self.acquire_failed = false;
+                let texture = surface_texture.texture.clone();
+                (Some(surface_texture), texture)
+            }
+            RenderTarget::Texture(texture) => (None, texture.clone()),
+        };
+
+        self.frame_stats = FrameStats::default();
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            // NOTE(mdeand): Timestamps of earlier frames are only read back once the device is
+            // NOTE(mdeand): polled after the GPU finished drawing them.
+            if let Err(error) = self.context.device.poll(wgpu::PollType::Poll) {
+                log::warn!("failed to poll the device for GPU timestamps: {error}");
+            }
+            gpu_timer.begin_frame();
+        }
+
+        let target_texture = frame_texture.as_ref().unwrap_or(&texture);
+        let target_view = target_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let drawn = match redraw {
+            Redraw::Region(region) => Some(region),
+            Redraw::Everything | Redraw::Nothing => None,
+        };
+        self.scissor = drawn;
+        self.render(scene, target_texture, &target_view);
+        self.scissor = None;
+        self.last_frame = (scene.id != 0).then(|| (scene.id, self.atlas.removed_tile_count()));
+
+        let frame_stats = std::mem::take(&mut self.frame_stats);
+        match &mut self.gpu_timer {
+            Some(gpu_timer) => gpu_timer.end_frame(
+                &self.context.device,
+                &self.context.queue,
+                frame_stats,
+                &self.last_frame_stats,
+            ),
+            None => *self.last_frame_stats.lock().unwrap() = Some(frame_stats),
+        }
+
+        if let Some(frame_texture) = &frame_texture {
+            // NOTE(mdeand): Only FIFO presentation hands surface textures out again in the order
+            // NOTE(mdeand): they were presented in, otherwise one may show any earlier frame.
+            let stale_frames = match self.surface_configuration.present_mode {
+                wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed => Some(
+                    (self.surface_configuration.desired_maximum_frame_latency as usize)
+                        .max(MIN_STALE_SURFACE_FRAMES),
+                ),
+                _ => None,
+            };
+            let region = stale_frames.and_then(|stale_frames| {
+                let region = present_region(&self.presented_regions, stale_frames, drawn);
+                self.presented_regions.push_back(drawn);
+                if self.presented_regions.len() > stale_frames {
+                    self.presented_regions.pop_front();
                 }
+                region
             });
+            let [x, y, width, height] = region.unwrap_or([
+                0,
+                0,
+                self.surface_configuration.width,
+                self.surface_configuration.height,
+            ]);
+
+            let mut command_encoder =
+                self.context
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("present"),
+                    });
+            command_encoder.copy_texture_to_texture(
+                wgpu::TexelCopyTextureInfo {
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    ..frame_texture.as_image_copy()
+                },
+                wgpu::TexelCopyTextureInfo {
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    ..texture.as_image_copy()
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+            self.context.queue.submit(Some(command_encoder.finish()));
+        }
 
-        let surface_configuration = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width,
-            height,
-            present_mode,
-            alpha_mode,
-            view_formats: vec![],
-            // TODO(mdeand): Make this configurable?
-            desired_maximum_frame_latency: 2,
-        };
+        if let Some(surface_texture) = surface_texture {
+            let suboptimal = surface_texture.suboptimal;
+            surface_texture.present();
+            if suboptimal && let RenderTarget::Surface(surface) = &self.target {
+                surface.configure(&self.context.device, &self.surface_configuration);
+                self.presented_regions.clear();
+            }
+        }
+        DrawResult::Drawn
+    }
 
-        let atlas_sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("atlas_sampler"),
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
+    /// Returns the texture frames for the surface are drawn into, if the surface can be copied
+    /// into. Offscreen targets are drawn into directly.
+    fn frame_texture(&mut self) -> Option<wgpu::Texture> {
+        let RenderTarget::Surface(_) = &self.target else {
+            return None;
+        };
+        if !self
+            .surface_configuration
+            .usage
+            .contains(wgpu::TextureUsages::COPY_DST)
+        {
+            return None;
+        }
+        let frame_texture = self.frame_texture.get_or_insert_with(|| {
+            self.context
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("frame_texture"),
+                    size: wgpu::Extent3d {
+                        width: self.surface_configuration.width.max(1),
+                        height: self.surface_configuration.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.surface_configuration.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                    view_formats: &[],
+                })
         });
+        Some(frame_texture.clone())
+    }
 
-        let surface_sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("surface_sampler"),
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+    /// Decides which pixels of the frame drawn last have to be drawn again for `scene`, from
+    /// the damage recorded when the scene was finished.
+    fn redraw(&self, scene: &Scene) -> Redraw {
+        let Some((last_scene_id, removed_tile_count)) = self.last_frame else {
+            return Redraw::Everything;
+        };
+        if removed_tile_count != self.atlas.removed_tile_count() {
+            return Redraw::Everything;
+        }
+        let mut damage = if scene.id == last_scene_id {
+            Vec::new()
+        } else {
+            match &scene.damage {
+                Some(damage) if damage.since == last_scene_id => damage.bounds.clone(),
+                _ => return Redraw::Everything,
+            }
+        };
 
-        let surface_params_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Surface Params Buffer"),
-            size: std::mem::size_of::<SurfaceParams>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        // NOTE(mdeand): Surfaces and shader quads may look different every frame without their
+        // NOTE(mdeand): primitives changing, so they're always drawn.
+        let scenes = scene_with_layers(scene);
+        for scene in &scenes {
+            damage.extend(
+                scene
+                    .surfaces
+                    .iter()
+                    .map(|surface| surface.bounds.intersect(&surface.content_mask.bounds)),
+            );
+            damage.extend(
+                scene
+                    .shader_quads
+                    .iter()
+                    .map(|quad| quad.bounds.intersect(&quad.content_mask.bounds)),
+            );
+        }
+        let Some(mut region) = damage
+            .into_iter()
+            .filter(|bounds| !bounds.is_empty())
+            .reduce(|region, bounds| region.union(&bounds))
+        else {
+            return Redraw::Nothing;
+        };
 
-        let pipelines =
-            WgpuPipelines::new(context.as_ref(), &surface_configuration, path_sample_count);
+        // NOTE(mdeand): A blur spreads whatever changed beneath it over its whole bounds, and
+        // NOTE(mdeand): copies out what's beneath it, which has to be drawn this frame too.
+        // NOTE(mdeand): Blurred layers likewise spread whatever changed in them.
+        let blur_bounds = scenes
+            .iter()
+            .flat_map(|scene| &scene.blurs)
+            .map(|blur| blur.bounds.intersect(&blur.content_mask.bounds))
+            .chain(
+                scenes
+                    .iter()
+                    .flat_map(|scene| &scene.layers)
+                    .filter(|layer| layer.blur_radius > ScaledPixels(0.))
+                    .map(|layer| layer.bounds),
+            )
+            .collect::<Vec<_>>();
+        loop {
+            let mut grown = false;
+            for blur_bounds in &blur_bounds {
+                if blur_bounds.is_empty() || !region.intersects(blur_bounds) {
+                    continue;
+                }
+                let union = region.union(blur_bounds);
+                if union != region {
+                    region = union;
+                    grown = true;
+                }
+            }
+            if !grown {
+                break;
+            }
+        }
 
-        Ok(Self {
-            context: context.clone(),
-            surface,
-            surface_configuration,
-            atlas,
-            atlas_sampler,
-            surface_sampler,
-            surface_params_buffer,
-            pipelines,
-            rendering_parameters: RenderingParameters::from_env(),
-            surface_bind_groups: Mutex::new(HashMap::new()),
-        })
+        // NOTE(mdeand): Antialiased edges may reach just past the bounds of their primitives.
+        match pixel_region(
+            region.dilate(ScaledPixels(1.0)),
+            self.surface_configuration.width,
+            self.surface_configuration.height,
+        ) {
+            Some(region) => Redraw::Region(region),
+            None => Redraw::Nothing,
+        }
     }
 
-    pub fn draw(&self, scene: &Scene) {
-        let mut command_encoder =
-            self.context
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("main"),
-                });
+    /// Rebuilds the renderer on `context`, once the device it was created on was lost. The
+    /// window's surface is created again since it belongs to the old instance, keeping its
+    /// size, present mode and frame latency.
+    ///
+    /// The atlas is shared with the window, so it has to be recreated separately.
+    pub fn recreate<WindowHandle>(
+        &mut self,
+        context: Arc<WgpuContext>,
+        window: WindowHandle,
+    ) -> anyhow::Result<()>
+    where
+        WindowHandle: raw_window_handle::HasWindowHandle + raw_window_handle::HasDisplayHandle,
+    {
+        let present_mode = match self.surface_configuration.present_mode {
+            wgpu::PresentMode::FifoRelaxed => PresentMode::FifoRelaxed,
+            wgpu::PresentMode::Mailbox => PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate => PresentMode::Immediate,
+            _ => PresentMode::Fifo,
+        };
+        let viewport_size = self.viewport_size();
+        *self = Self::new(
+            context,
+            window,
+            self.atlas.clone(),
+            self.surface_configuration.width,
+            self.surface_configuration.height,
+            self.rendering_parameters.path_sample_count,
+            Some(present_mode),
+            Some(self.surface_configuration.desired_maximum_frame_latency),
+        )?;
+        self.update_drawable_size(viewport_size);
+        Ok(())
+    }
+
+    /// Rebuilds an offscreen renderer on `context`, once the device it was created on was
+    /// lost. See [`WgpuRenderer::recreate`].
+    pub fn recreate_offscreen(&mut self, context: Arc<WgpuContext>) {
+        *self = Self::new_offscreen(
+            context,
+            self.atlas.clone(),
+            self.surface_configuration.width,
+            self.surface_configuration.height,
+            self.rendering_parameters.path_sample_count,
+        );
+    }
 
-        self.atlas.before_frame(&mut command_encoder);
+    /// Renders `scene` again into an offscreen texture and reads it back into CPU memory.
+    ///
+    /// The texture has the format of the surface, so frames of surfaces with float formats
+    /// can't be captured.
+    pub fn capture_frame(&mut self, scene: &Scene) -> oneshot::Receiver<Result<RgbaImage>> {
+        let texture = create_offscreen_texture(
+            &self.context.device,
+            self.surface_configuration.format,
+            self.surface_configuration.width,
+            self.surface_configuration.height,
+        );
+        self.render(
+            scene,
+            &texture,
+            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        );
 
-        // keep track of which surface ids we rendered this frame
-        let mut seen_surfaces: Vec<crate::platform::cross::surface_registry::SurfaceId> =
-            Vec::new();
+        capture::read_texture(
+            &self.context.device,
+            &self.context.queue,
+            &texture,
+            self.surface_configuration.alpha_mode == wgpu::CompositeAlphaMode::PreMultiplied,
+        )
+    }
 
+    fn render(
+        &mut self,
+        scene: &Scene,
+        surface_texture: &wgpu::Texture,
+        surface_view: &wgpu::TextureView,
+    ) {
         let color_adjustments = ColorAdjustments {
             gamma_ratios: self.rendering_parameters.gamma_ratios,
             grayscale_enhanced_contrast: self.rendering_parameters.grayscale_enhanced_contrast,
@@ -1323,413 +3848,547 @@ impl WgpuRenderer {
             bytemuck::bytes_of(&globals),
         );
 
-        unsafe fn as_bytes<T>(slice: &[T]) -> &[u8] {
-            unsafe {
-                std::slice::from_raw_parts(
-                    slice.as_ptr() as *const u8,
-                    slice.len() * std::mem::size_of::<T>(),
-                )
-            }
-        }
-
-        if !scene.quads.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.quads_buffer, 0, unsafe {
-                    as_bytes(&scene.quads)
-                });
-        }
-        if !scene.shadows.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.shadows_buffer, 0, unsafe {
-                    as_bytes(&scene.shadows)
-                });
+        let segments = split_into_segments(scene.batches(), self.max_instance_buffer_size);
+        if segments.len() > 1 {
+            log::warn!(
+                "scene exceeds the maximum instance buffer size of {} bytes, drawing it in {} submissions",
+                self.max_instance_buffer_size,
+                segments.len()
+            );
         }
-        if !scene.underlines.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.underlines_buffer, 0, unsafe {
-                    as_bytes(&scene.underlines)
-                });
-        }
-        if !scene.monochrome_sprites.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.mono_sprites_buffer, 0, unsafe {
-                    as_bytes(&scene.monochrome_sprites)
-                });
-        }
-        if !scene.polychrome_sprites.is_empty() {
-            self.context
-                .queue
-                .write_buffer(&self.context.poly_sprites_buffer, 0, unsafe {
-                    as_bytes(&scene.polychrome_sprites)
-                });
-        }
-
-        let surface_texture = self
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
-
-        let quads_bind_group = self
-            .context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("quads_bind_group"),
-                layout: &self.pipelines.quads_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &self.context.quads_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                }],
-            });
 
-        let shadows_bind_group =
-            self.context
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("shadows_bind_group"),
-                    layout: &self.pipelines.shadows_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.shadows_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
-                });
+        let bind_groups = self.prepare_instance_buffers(scene);
 
-        let underlines_bind_group =
-            self.context
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("underlines_bind_group"),
-                    layout: &self.pipelines.underlines_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.underlines_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
-                });
+        // keep track of which surface ids we rendered this frame
+        let mut seen_surfaces: Vec<crate::platform::cross::surface_registry::SurfaceId> =
+            Vec::new();
 
-        let mono_sprites_bind_group =
-            self.context
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("mono_sprites_bind_group"),
-                    layout: &self.pipelines.mono_sprites_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.mono_sprites_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
-                });
+        for (segment_ix, segment) in segments.iter().enumerate() {
+            let mut command_encoder =
+                self.context
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("main"),
+                    });
+
+            let load = if segment_ix == 0 {
+                let (atlas_uploads, atlas_upload_bytes) =
+                    self.atlas.before_frame(scene, &mut command_encoder);
+                self.frame_stats.atlas_uploads += atlas_uploads;
+                self.frame_stats.atlas_upload_bytes += atlas_upload_bytes;
+                if self.scissor.is_some() {
+                    let mut pass = Self::begin_main_pass(
+                        &mut command_encoder,
+                        surface_view,
+                        wgpu::LoadOp::Load,
+                        self.scissor,
+                    );
+                    pass.set_pipeline(&self.pipelines.clear_pipeline);
+                    pass.draw(0..3, 0..1);
+                    wgpu::LoadOp::Load
+                } else {
+                    wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                }
+            } else {
+                wgpu::LoadOp::Load
+            };
+
+            // NOTE(mdeand): Writes queued since the previous submission are applied before
+            // NOTE(mdeand): this one, so every segment can start at the front of the buffers.
+            self.instance_buffers.reset();
+            self.draw_segment(
+                &mut command_encoder,
+                surface_texture,
+                surface_view,
+                load,
+                segment,
+                &bind_groups,
+                &mut seen_surfaces,
+            );
+
+            self.context.queue.submit(Some(command_encoder.finish()));
+        }
 
-        let poly_sprites_bind_group =
-            self.context
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("poly_sprites_bind_group"),
-                    layout: &self.pipelines.poly_sprites_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &self.context.poly_sprites_buffer,
-                            offset: 0,
-                            size: None,
-                        }),
-                    }],
-                });
+        self.frame_stats.instance_bytes += self.instance_buffers.written_bytes();
 
+        // remove cached bind groups for surfaces that disappeared this frame
         {
-            let mut pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("main"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_texture
-                        .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default()),
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                    resolve_target: None,
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-                multiview_mask: None,
-            });
+            let mut cache = self.surface_bind_groups.lock().unwrap();
+            cache.retain(|id, _| seen_surfaces.contains(id));
+        }
+    }
 
-            let mut quads_first_instance: u32 = 0;
-            let mut shadows_first_instance: u32 = 0;
-            let mut underlines_first_instance: u32 = 0;
-            let mut mono_sprites_first_instance: u32 = 0;
-            let mut poly_sprites_first_instance: u32 = 0;
-
-            for batch in scene.batches() {
-                match batch {
-                    PrimitiveBatch::Quads(quads) => {
-                        let count = quads.len() as u32;
-                        pass.set_pipeline(&self.pipelines.quads_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &quads_bind_group, &[]);
-                        pass.draw(0..4, quads_first_instance..quads_first_instance + count);
-                        quads_first_instance += count;
-                    }
+    fn draw_segment(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        surface_texture: &wgpu::Texture,
+        surface_view: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        batches: &[PrimitiveBatch<'_>],
+        bind_groups: &InstanceBindGroups,
+        seen_surfaces: &mut Vec<crate::platform::cross::surface_registry::SurfaceId>,
+    ) {
+        let mut pass = Self::begin_main_pass(command_encoder, surface_view, load, self.scissor);
+        self.write_timestamp(&mut pass, TimestampEvent::PassStart);
+
+        for batch in batches {
+            let batch_stats = self.frame_stats.batch_mut(batch.kind());
+            batch_stats.batches += 1;
+            batch_stats.instances += batch.len();
+            self.write_timestamp(&mut pass, TimestampEvent::Batch(batch.kind()));
+
+            match batch {
+                PrimitiveBatch::Quads(quads) => {
+                    let Some(first_instance) = self
+                        .instance_buffers
+                        .quads
+                        .write(&self.context.queue, quads)
+                    else {
+                        continue;
+                    };
+                    let count = quads.len() as u32;
+                    pass.set_pipeline(&self.pipelines.quads_pipeline);
+                    pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                    pass.set_bind_group(1, &bind_groups.quads, &[]);
+                    pass.draw(0..4, first_instance..first_instance + count);
+                }
 
-                    PrimitiveBatch::MonochromeSprites {
-                        texture_id,
-                        sprites,
-                    } => {
-                        let count = sprites.len() as u32;
-                        let tex_info = self.atlas.get_texture_info(texture_id);
-
-                        let sprites_texture_bind_group =
-                            self.context
-                                .device
-                                .create_bind_group(&wgpu::BindGroupDescriptor {
-                                    label: Some("sprites_bind_group"),
-                                    layout: &self.pipelines.sprites_bind_group_layout,
-                                    entries: &[
-                                        wgpu::BindGroupEntry {
-                                            binding: 0,
-                                            resource: wgpu::BindingResource::TextureView(
-                                                &tex_info.raw_view,
-                                            ),
-                                        },
-                                        wgpu::BindGroupEntry {
-                                            binding: 1,
-                                            resource: wgpu::BindingResource::Sampler(
-                                                &self.atlas_sampler,
-                                            ),
-                                        },
-                                    ],
-                                });
-
-                        pass.set_pipeline(&self.pipelines.mono_sprites_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &self.pipelines.color_adjustments_bind_group, &[]);
-                        pass.set_bind_group(2, &sprites_texture_bind_group, &[]);
-                        pass.set_bind_group(3, &mono_sprites_bind_group, &[]);
-                        pass.draw(
-                            0..4,
-                            mono_sprites_first_instance..mono_sprites_first_instance + count,
-                        );
-                        mono_sprites_first_instance += count;
-                    }
-                    PrimitiveBatch::PolychromeSprites {
-                        texture_id,
-                        sprites,
-                    } => {
-                        let count = sprites.len() as u32;
-                        let tex_info = self.atlas.get_texture_info(texture_id);
-
-                        let sprites_texture_bind_group =
-                            self.context
-                                .device
-                                .create_bind_group(&wgpu::BindGroupDescriptor {
-                                    label: Some("poly_sprites_texture_bind_group"),
-                                    layout: &self.pipelines.sprites_bind_group_layout,
-                                    entries: &[
-                                        wgpu::BindGroupEntry {
-                                            binding: 0,
-                                            resource: wgpu::BindingResource::TextureView(
-                                                &tex_info.raw_view,
-                                            ),
-                                        },
-                                        wgpu::BindGroupEntry {
-                                            binding: 1,
-                                            resource: wgpu::BindingResource::Sampler(
-                                                &self.atlas_sampler,
-                                            ),
-                                        },
-                                    ],
-                                });
-
-                        pass.set_pipeline(&self.pipelines.poly_sprites_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &sprites_texture_bind_group, &[]);
-                        pass.set_bind_group(2, &poly_sprites_bind_group, &[]);
-                        pass.draw(
-                            0..4,
-                            poly_sprites_first_instance..poly_sprites_first_instance + count,
+                PrimitiveBatch::MonochromeSprites {
+                    texture_id,
+                    sprites,
+                } => {
+                    let Some(first_instance) = self
+                        .instance_buffers
+                        .mono_sprites
+                        .write(&self.context.queue, sprites)
+                    else {
+                        continue;
+                    };
+                    let count = sprites.len() as u32;
+                    let tex_info = self.atlas.get_texture_info(*texture_id);
+
+                    let sprites_texture_bind_group =
+                        self.context
+                            .device
+                            .create_bind_group(&wgpu::BindGroupDescriptor {
+                                label: Some("sprites_bind_group"),
+                                layout: &self.pipelines.sprites_bind_group_layout,
+                                entries: &[
+                                    wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: wgpu::BindingResource::TextureView(
+                                            &tex_info.raw_view,
+                                        ),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: wgpu::BindingResource::Sampler(
+                                            &self.context.atlas_sampler,
+                                        ),
+                                    },
+                                ],
+                            });
+
+                    pass.set_pipeline(&self.pipelines.mono_sprites_pipeline);
+                    pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                    pass.set_bind_group(1, &self.pipelines.color_adjustments_bind_group, &[]);
+                    pass.set_bind_group(2, &sprites_texture_bind_group, &[]);
+                    pass.set_bind_group(3, &bind_groups.mono_sprites, &[]);
+                    pass.draw(0..4, first_instance..first_instance + count);
+                }
+                PrimitiveBatch::PolychromeSprites {
+                    texture_id,
+                    sprites,
+                } => {
+                    let Some(first_instance) = self
+                        .instance_buffers
+                        .poly_sprites
+                        .write(&self.context.queue, sprites)
+                    else {
+                        continue;
+                    };
+                    let count = sprites.len() as u32;
+                    let tex_info = self.atlas.get_texture_info(*texture_id);
+
+                    let sprites_texture_bind_group =
+                        self.context
+                            .device
+                            .create_bind_group(&wgpu::BindGroupDescriptor {
+                                label: Some("poly_sprites_texture_bind_group"),
+                                layout: &self.pipelines.sprites_bind_group_layout,
+                                entries: &[
+                                    wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: wgpu::BindingResource::TextureView(
+                                            &tex_info.raw_view,
+                                        ),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: wgpu::BindingResource::Sampler(
+                                            &self.context.atlas_sampler,
+                                        ),
+                                    },
+                                ],
+                            });
+
+                    pass.set_pipeline(&self.pipelines.poly_sprites_pipeline);
+                    pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                    pass.set_bind_group(1, &sprites_texture_bind_group, &[]);
+                    pass.set_bind_group(2, &bind_groups.poly_sprites, &[]);
+                    pass.draw(0..4, first_instance..first_instance + count);
+                }
+                PrimitiveBatch::Shadows(shadows) => {
+                    let Some(first_instance) = self
+                        .instance_buffers
+                        .shadows
+                        .write(&self.context.queue, shadows)
+                    else {
+                        continue;
+                    };
+                    let count = shadows.len() as u32;
+                    pass.set_pipeline(&self.pipelines.shadows_pipeline);
+                    pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                    pass.set_bind_group(1, &bind_groups.shadows, &[]);
+                    pass.draw(0..4, first_instance..first_instance + count);
+                }
+                PrimitiveBatch::Blurs(blurs) => {
+                    // NOTE(mdeand): Blurs sample what has been drawn so far, so the main pass
+                    // NOTE(mdeand): has to be interrupted for them like it is for paths. Each
+                    // NOTE(mdeand): blur is composited before the next one samples the frame.
+                    drop(pass);
+
+                    for blur in blurs.iter() {
+                        self.draw_blur(
+                            command_encoder,
+                            surface_texture,
+                            surface_view,
+                            blur,
+                            &bind_groups.blurs,
+                            false,
                         );
-                        poly_sprites_first_instance += count;
                     }
-                    PrimitiveBatch::Shadows(shadows) => {
-                        let count = shadows.len() as u32;
-                        pass.set_pipeline(&self.pipelines.shadows_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &shadows_bind_group, &[]);
-                        pass.draw(0..4, shadows_first_instance..shadows_first_instance + count);
-                        shadows_first_instance += count;
+
+                    pass = Self::begin_main_pass(
+                        command_encoder,
+                        surface_view,
+                        wgpu::LoadOp::Load,
+                        self.scissor,
+                    );
+                }
+                PrimitiveBatch::ShaderQuads(quads) => {
+                    let time = self.start_time.elapsed().as_secs_f32();
+                    let instances = quads
+                        .iter()
+                        .map(|quad| ShaderQuadInstance {
+                            bounds: quad.bounds,
+                            content_mask: quad.content_mask.clone(),
+                            time,
+                            pad: [0.0; 3],
+                            uniforms: quad.uniforms,
+                        })
+                        .collect::<Vec<_>>();
+                    let Some(first_instance) = self
+                        .instance_buffers
+                        .shader_quads
+                        .write(&self.context.queue, &instances)
+                    else {
+                        continue;
+                    };
+
+                    // NOTE(mdeand): Quads of the same draw order are sorted by their source, so
+                    // NOTE(mdeand): each run of quads sharing a pipeline is drawn at once.
+                    let mut start = first_instance;
+                    for run in quads.chunk_by(|a, b| a.source.hash == b.source.hash) {
+                        let end = start + run.len() as u32;
+                        if let Some(pipeline) = self
+                            .pipelines
+                            .shader_quad_pipeline(&self.context, &run[0].source)
+                        {
+                            pass.set_pipeline(&pipeline);
+                            pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                            pass.set_bind_group(1, &bind_groups.shader_quads, &[]);
+                            pass.draw(0..4, start..end);
+                        }
+                        start = end;
                     }
-                    PrimitiveBatch::Underlines(underlines) => {
-                        let count = underlines.len() as u32;
-                        pass.set_pipeline(&self.pipelines.underlines_pipeline);
-                        pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
-                        pass.set_bind_group(1, &underlines_bind_group, &[]);
-                        pass.draw(
-                            0..4,
-                            underlines_first_instance..underlines_first_instance + count,
+                }
+                PrimitiveBatch::Layers(layers) => {
+                    // NOTE(mdeand): Each layer is drawn into a texture of its own before it's
+                    // NOTE(mdeand): composited, which needs passes of its own.
+                    drop(pass);
+
+                    for layer in layers.iter() {
+                        self.draw_layer(
+                            command_encoder,
+                            surface_texture,
+                            surface_view,
+                            layer,
+                            bind_groups,
+                            seen_surfaces,
                         );
-                        underlines_first_instance += count;
                     }
-                    PrimitiveBatch::Surfaces(surfaces) => {
-                        for surface in surfaces {
-                            if let crate::SurfaceContent::Wgpu(surface_id) = &surface.content {
-                                if let Some(idx) =
-                                    self.context.surface_registry.front_index(*surface_id)
+
+                    pass = Self::begin_main_pass(
+                        command_encoder,
+                        surface_view,
+                        wgpu::LoadOp::Load,
+                        self.scissor,
+                    );
+                }
+                PrimitiveBatch::Underlines(underlines) => {
+                    let Some(first_instance) = self
+                        .instance_buffers
+                        .underlines
+                        .write(&self.context.queue, underlines)
+                    else {
+                        continue;
+                    };
+                    let count = underlines.len() as u32;
+                    pass.set_pipeline(&self.pipelines.underlines_pipeline);
+                    pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                    pass.set_bind_group(1, &bind_groups.underlines, &[]);
+                    pass.draw(0..4, first_instance..first_instance + count);
+                }
+                PrimitiveBatch::Surfaces(surfaces) => {
+                    for surface in surfaces {
+                        if let crate::SurfaceContent::Wgpu(surface_id) = &surface.content {
+                            if let Some(idx) =
+                                self.context.surface_registry.front_index(*surface_id)
+                            {
+                                if self
+                                    .context
+                                    .surface_registry
+                                    .view_at(*surface_id, idx)
+                                    .is_some()
                                 {
-                                    if self
-                                        .context
+                                    // consuming the front view means the frame has been
+                                    // queued for compositing, so clear the pending flag
+                                    self.context
                                         .surface_registry
-                                        .view_at(*surface_id, idx)
-                                        .is_some()
-                                    {
-                                        // consuming the front view means the frame has been
-                                        // queued for compositing, so clear the pending flag
-                                        self.context
-                                            .surface_registry
-                                            .clear_present_pending(*surface_id);
-
-                                        let params = SurfaceParams {
-                                            bounds: Bounds {
-                                                origin: [
-                                                    surface.bounds.origin.x.0,
-                                                    surface.bounds.origin.y.0,
-                                                ],
-                                                size: [
-                                                    surface.bounds.size.width.0,
-                                                    surface.bounds.size.height.0,
-                                                ],
-                                            },
-                                            content_mask: Bounds {
-                                                origin: [
-                                                    surface.content_mask.bounds.origin.x.0,
-                                                    surface.content_mask.bounds.origin.y.0,
-                                                ],
-                                                size: [
-                                                    surface.content_mask.bounds.size.width.0,
-                                                    surface.content_mask.bounds.size.height.0,
-                                                ],
-                                            },
-                                        };
-
-                                        self.context.queue.write_buffer(
-                                            &self.surface_params_buffer,
-                                            0,
-                                            bytemuck::bytes_of(&params),
-                                        );
-
-                                        // fetch or create cached bind groups for this surface
-                                        let surface_bind_group = {
-                                            let mut cache =
-                                                self.surface_bind_groups.lock().unwrap();
-                                            let entry = cache
-                                                .entry(*surface_id)
-                                                .or_insert_with(|| {
-                                                    // create both groups for front index 0 and 1
-                                                    let v0 = self
-                                                        .context
-                                                        .surface_registry
-                                                        .view_at(*surface_id, 0)
-                                                        .unwrap();
-                                                    let v1 = self
-                                                        .context
-                                                        .surface_registry
-                                                        .view_at(*surface_id, 1)
-                                                        .unwrap();
-                                                    let create_bg = |view: &wgpu::TextureView| {
-                                                        self.context
-                                                            .device
-                                                            .create_bind_group(&wgpu::BindGroupDescriptor {
-                                                                label: Some("surface_bind_group"),
-                                                                layout: &self.pipelines.surfaces_bind_group_layout,
-                                                                entries: &[
-                                                                    wgpu::BindGroupEntry {
-                                                                        binding: 0,
-                                                                        resource:
-                                                                            wgpu::BindingResource::Buffer(
-                                                                                wgpu::BufferBinding {
-                                                                                    buffer: &self
-                                                                                        .surface_params_buffer,
-                                                                                    offset: 0,
-                                                                                    size: None,
-                                                                                },
-                                                                            ),
-                                                                    },
-                                                                    wgpu::BindGroupEntry {
-                                                                        binding: 1,
-                                                                        resource:
-                                                                            wgpu::BindingResource::TextureView(
-                                                                                view,
-                                                                            ),
-                                                                    },
-                                                                    wgpu::BindGroupEntry {
-                                                                        binding: 2,
-                                                                        resource:
-                                                                            wgpu::BindingResource::Sampler(
-                                                                                &self.surface_sampler,
-                                                                            ),
-                                                                    },
-                                                                ],
-                                                            })
-                                                    };
-                                                    [create_bg(&v0), create_bg(&v1)]
-                                                });
-                                            entry[idx].clone()
-                                        };
-
-                                        pass.set_pipeline(&self.pipelines.surfaces_pipeline);
-                                        pass.set_bind_group(
-                                            0,
-                                            &self.pipelines.globals_bind_group,
-                                            &[],
-                                        );
-                                        pass.set_bind_group(1, &surface_bind_group, &[]);
-                                        pass.draw(0..4, 0..1);
-
-                                        seen_surfaces.push(*surface_id);
-                                    }
+                                        .clear_present_pending(*surface_id);
+
+                                    let params = SurfaceParams {
+                                        bounds: Bounds {
+                                            origin: [
+                                                surface.bounds.origin.x.0,
+                                                surface.bounds.origin.y.0,
+                                            ],
+                                            size: [
+                                                surface.bounds.size.width.0,
+                                                surface.bounds.size.height.0,
+                                            ],
+                                        },
+                                        content_mask: Bounds {
+                                            origin: [
+                                                surface.content_mask.bounds.origin.x.0,
+                                                surface.content_mask.bounds.origin.y.0,
+                                            ],
+                                            size: [
+                                                surface.content_mask.bounds.size.width.0,
+                                                surface.content_mask.bounds.size.height.0,
+                                            ],
+                                        },
+                                        content_mask_corner_radii: [
+                                            surface.content_mask.corner_radii.top_left.0,
+                                            surface.content_mask.corner_radii.top_right.0,
+                                            surface.content_mask.corner_radii.bottom_right.0,
+                                            surface.content_mask.corner_radii.bottom_left.0,
+                                        ],
+                                    };
+
+                                    self.context.queue.write_buffer(
+                                        &self.surface_params_buffer,
+                                        0,
+                                        bytemuck::bytes_of(&params),
+                                    );
+
+                                    // fetch or create cached bind groups for this surface
+                                    let surface_bind_group = {
+                                        let mut cache = self.surface_bind_groups.lock().unwrap();
+                                        let entry = cache.entry(*surface_id).or_insert_with(|| {
+                                            // create both groups for front index 0 and 1
+                                            let v0 = self
+                                                .context
+                                                .surface_registry
+                                                .view_at(*surface_id, 0)
+                                                .unwrap();
+                                            let v1 = self
+                                                .context
+                                                .surface_registry
+                                                .view_at(*surface_id, 1)
+                                                .unwrap();
+                                            let create_bg = |view: &wgpu::TextureView| {
+                                                self.context
+                                                    .device
+                                                    .create_bind_group(&wgpu::BindGroupDescriptor {
+                                                    label: Some("surface_bind_group"),
+                                                    layout: &self
+                                                        .pipelines
+                                                        .surfaces_bind_group_layout,
+                                                    entries: &[
+                                                        wgpu::BindGroupEntry {
+                                                            binding: 0,
+                                                            resource: wgpu::BindingResource::Buffer(
+                                                                wgpu::BufferBinding {
+                                                                    buffer: &self
+                                                                        .surface_params_buffer,
+                                                                    offset: 0,
+                                                                    size: None,
+                                                                },
+                                                            ),
+                                                        },
+                                                        wgpu::BindGroupEntry {
+                                                            binding: 1,
+                                                            resource:
+                                                                wgpu::BindingResource::TextureView(
+                                                                    view,
+                                                                ),
+                                                        },
+                                                        wgpu::BindGroupEntry {
+                                                            binding: 2,
+                                                            resource:
+                                                                wgpu::BindingResource::Sampler(
+                                                                    &self.context.surface_sampler,
+                                                                ),
+                                                        },
+                                                    ],
+                                                })
+                                            };
+                                            [create_bg(&v0), create_bg(&v1)]
+                                        });
+                                        entry[idx].clone()
+                                    };
+
+                                    pass.set_pipeline(&self.pipelines.surfaces_pipeline);
+                                    pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                                    pass.set_bind_group(1, &surface_bind_group, &[]);
+                                    pass.draw(0..4, 0..1);
+
+                                    seen_surfaces.push(*surface_id);
                                 }
                             }
                         }
                     }
-                    // TODO(mdeand): Implement paths rendering.
-                    PrimitiveBatch::Paths(_) => {}
+                }
+                PrimitiveBatch::Paths(paths) => {
+                    // NOTE(mdeand): Paths are rasterized into a separate (multisampled)
+                    // NOTE(mdeand): texture, so the main pass has to be interrupted and
+                    // NOTE(mdeand): resumed afterwards without clearing what was drawn.
+                    drop(pass);
+
+                    let rasterized = self.rasterize_paths(
+                        command_encoder,
+                        paths,
+                        &bind_groups.path_rasterization,
+                    );
+
+                    pass = Self::begin_main_pass(
+                        command_encoder,
+                        surface_view,
+                        wgpu::LoadOp::Load,
+                        self.scissor,
+                    );
+
+                    if !rasterized {
+                        continue;
+                    }
+
+                    // When copying paths from the intermediate texture to the drawable,
+                    // each pixel must only be copied once, in case of transparent paths.
+                    //
+                    // If all paths have the same draw order, then their bounds are all
+                    // disjoint, so we can copy each path's bounds individually. If this
+                    // batch combines different draw orders, we perform a single copy
+                    // for a minimal spanning rect.
+                    let sprites = if paths.first().map(|path| path.order)
+                        == paths.last().map(|path| path.order)
+                    {
+                        paths
+                            .iter()
+                            .map(|path| PathSprite {
+                                bounds: path.clipped_bounds(),
+                            })
+                            .collect::<Vec<_>>()
+                    } else {
+                        let mut bounds = paths[0].clipped_bounds();
+                        for path in &paths[1..] {
+                            bounds = bounds.union(&path.clipped_bounds());
+                        }
+                        vec![PathSprite { bounds }]
+                    };
+
+                    let Some(first_instance) = self
+                        .instance_buffers
+                        .path_sprites
+                        .write(&self.context.queue, &sprites)
+                    else {
+                        continue;
+                    };
+                    let count = sprites.len() as u32;
+                    pass.set_pipeline(&self.pipelines.paths_pipeline);
+                    pass.set_bind_group(0, &self.pipelines.globals_bind_group, &[]);
+                    pass.set_bind_group(1, &bind_groups.path_texture, &[]);
+                    pass.set_bind_group(2, &bind_groups.paths, &[]);
+                    pass.draw(0..4, first_instance..first_instance + count);
                 }
             }
         }
+        self.write_timestamp(&mut pass, TimestampEvent::PassEnd);
+    }
 
-        // remove cached bind groups for surfaces that disappeared this frame
-        {
-            let mut cache = self.surface_bind_groups.lock().unwrap();
-            cache.retain(|id, _| seen_surfaces.contains(id));
+    /// Marks `event` with a GPU timestamp written into `pass`, when frames are timed.
+    fn write_timestamp(&mut self, pass: &mut wgpu::RenderPass<'_>, event: TimestampEvent) {
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.write(pass, event);
         }
-        self.context.queue.submit(Some(command_encoder.finish()));
-
-        surface_texture.present();
     }
 
     pub fn update_drawable_size(&mut self, size: geometry::Size<DevicePixels>) {
         self.surface_configuration.width = size.width.0 as u32;
         self.surface_configuration.height = size.height.0 as u32;
-        self.surface
-            .configure(&self.context.device, &self.surface_configuration);
+        match &mut self.target {
+            RenderTarget::Surface(surface) => {
+                surface.configure(&self.context.device, &self.surface_configuration);
+                self.presented_regions.clear();
+            }
+            RenderTarget::Texture(texture) => {
+                *texture = create_offscreen_texture(
+                    &self.context.device,
+                    self.surface_configuration.format,
+                    self.surface_configuration.width,
+                    self.surface_configuration.height,
+                );
+            }
+        }
+        self.path_intermediate_textures = PathIntermediateTextures::new(
+            &self.context.device,
+            self.surface_configuration.format,
+            self.surface_configuration.width,
+            self.surface_configuration.height,
+            self.rendering_parameters.path_sample_count,
+        );
+        self.blur_textures = None;
+        self.layer_textures.clear();
+        self.backdrop_texture = None;
+        self.frame_texture = None;
+        self.last_frame = None;
+    }
+
+    /// Reconfigures the surface to present frames with the given mode. Offscreen renderers
+    /// never present, so this does nothing for them.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        let RenderTarget::Surface(surface) = &self.target else {
+            return;
+        };
+        let capabilities = surface.get_capabilities(&self.context.adapter);
+        let present_mode = resolve_present_mode(present_mode, &capabilities.present_modes);
+        if present_mode == self.surface_configuration.present_mode {
+            return;
+        }
+        self.surface_configuration.present_mode = present_mode;
+        surface.configure(&self.context.device, &self.surface_configuration);
+        self.presented_regions.clear();
     }
 
     pub fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas> {
@@ -1746,16 +4405,42 @@ impl WgpuRenderer {
         }
     }
 
+    /// Returns the stats of the last frame whose GPU times were read back, or of the last
+    /// frame drawn when frames aren't timed.
+    pub fn last_frame_stats(&self) -> Option<FrameStats> {
+        self.last_frame_stats.lock().unwrap().clone()
+    }
+
     pub fn update_transparency(&mut self, transparent: bool) {
-        self.surface_configuration.alpha_mode = if transparent {
+        let RenderTarget::Surface(surface) = &self.target else {
+            return;
+        };
+        let preferred_alpha_mode = if transparent {
             wgpu::CompositeAlphaMode::PreMultiplied
         } else {
-            // TODO(mdeand): Support for non-X11?
-            // wgpu::CompositeAlphaMode::Opaque
-            wgpu::CompositeAlphaMode::Inherit
+            wgpu::CompositeAlphaMode::Opaque
+        };
+        // NOTE(mdeand): Surfaces don't support every mode, in which case the first one they do
+        // NOTE(mdeand): support is used like when the surface was created.
+        let alpha_modes = surface.get_capabilities(&self.context.adapter).alpha_modes;
+        let alpha_mode = if alpha_modes.contains(&preferred_alpha_mode) {
+            preferred_alpha_mode
+        } else {
+            alpha_modes[0]
         };
-        self.surface
-            .configure(&self.context.device, &self.surface_configuration);
+        if alpha_mode == self.surface_configuration.alpha_mode {
+            return;
+        }
+        self.surface_configuration.alpha_mode = alpha_mode;
+        surface.configure(&self.context.device, &self.surface_configuration);
+        self.presented_regions.clear();
+        // NOTE(mdeand): Blending depends on whether the surface expects premultiplied colors.
+        self.pipelines = WgpuPipelines::shared(
+            &self.context,
+            &self.surface_configuration,
+            self.rendering_parameters.path_sample_count,
+        );
+        self.last_frame = None;
     }
 
     pub fn viewport_size(&self) -> geometry::Size<DevicePixels> {
@@ -1765,3 +4450,220 @@ impl WgpuRenderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blur_downsample_bounds_sigma() {
+        assert_eq!(blur_downsample(2.0), 1.0);
+        assert_eq!(blur_downsample(4.0), 1.0);
+        assert_eq!(blur_downsample(10.0), 4.0);
+        assert_eq!(blur_downsample(1000.0), MAX_BLUR_DOWNSAMPLE);
+    }
+
+    #[test]
+    fn test_unsupported_present_modes_fall_back() {
+        let supported = [wgpu::PresentMode::Fifo, wgpu::PresentMode::Immediate];
+        assert_eq!(
+            resolve_present_mode(PresentMode::Mailbox, &supported),
+            wgpu::PresentMode::Immediate
+        );
+        assert_eq!(
+            resolve_present_mode(PresentMode::Immediate, &supported),
+            wgpu::PresentMode::Immediate
+        );
+        assert_eq!(
+            resolve_present_mode(PresentMode::FifoRelaxed, &supported),
+            wgpu::PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn test_present_regions_cover_the_frames_surface_textures_may_still_show() {
+        let mut presented = VecDeque::new();
+        assert_eq!(present_region(&presented, 2, Some([4, 4, 4, 4])), None);
+        presented.push_back(None);
+        presented.push_back(Some([0, 0, 2, 2]));
+        assert_eq!(present_region(&presented, 2, Some([4, 4, 4, 4])), None);
+        assert_eq!(
+            present_region(&presented, 1, Some([4, 4, 4, 4])),
+            Some([0, 0, 8, 8])
+        );
+        assert_eq!(present_region(&presented, 1, None), None);
+
+        presented.push_back(Some([8, 2, 2, 2]));
+        assert_eq!(
+            present_region(&presented, 2, Some([4, 4, 2, 2])),
+            Some([0, 0, 10, 6])
+        );
+    }
+
+    #[test]
+    fn test_renderers_share_pipelines() {
+        let Some(context) = WgpuContext::for_test() else {
+            return;
+        };
+        let context = Arc::new(context);
+        let atlas = Arc::new(WgpuAtlas::new(context.clone()));
+        let first = WgpuRenderer::new_offscreen(context.clone(), atlas.clone(), 16, 16, 1);
+        let second = WgpuRenderer::new_offscreen(context.clone(), atlas.clone(), 32, 8, 1);
+        assert!(Arc::ptr_eq(&first.pipelines, &second.pipelines));
+        assert_eq!(context.pipelines.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_shader_quad_instances_match_the_wgsl_struct() {
+        // NOTE(mdeand): `ShaderQuad` in shader_quad.wgsl is 128 bytes, with the uniforms packed
+        // NOTE(mdeand): into vec4s.
+        assert_eq!(std::mem::size_of::<ShaderQuadInstance>(), 128);
+        assert_eq!(MAX_SHADER_QUAD_UNIFORMS % 4, 0);
+        assert!(include_str!("shaders/shader_quad.wgsl").contains(&format!(
+            "uniforms: array<vec4<f32>, {}>",
+            MAX_SHADER_QUAD_UNIFORMS / 4
+        )));
+    }
+
+    #[test]
+    fn test_shader_quad_pipelines_are_compiled_once_per_source() {
+        let Some(context) = WgpuContext::for_test() else {
+            return;
+        };
+        let context = Arc::new(context);
+        let atlas = Arc::new(WgpuAtlas::new(context.clone()));
+        let renderer = WgpuRenderer::new_offscreen(context.clone(), atlas, 16, 16, 1);
+        let pipelines = &renderer.pipelines;
+
+        let valid = ShaderQuadSource::new(
+            "fn shade(input: ShaderQuadInput) -> vec4<f32> { return vec4(input.uv, 0., 1.); }"
+                .into(),
+        );
+        assert!(pipelines.shader_quad_pipeline(&context, &valid).is_some());
+        assert!(pipelines.shader_quad_pipeline(&context, &valid).is_some());
+
+        let invalid = ShaderQuadSource::new("fn shade(input: ShaderQuadInput) {".into());
+        assert!(pipelines.shader_quad_pipeline(&context, &invalid).is_none());
+        assert!(pipelines.shader_quad_pipeline(&context, &invalid).is_none());
+
+        assert_eq!(pipelines.shader_quad_pipelines.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_partial_redraws_match_full_redraws() {
+        let Some(context) = WgpuContext::for_test() else {
+            return;
+        };
+        let context = Arc::new(context);
+        let atlas = Arc::new(WgpuAtlas::new(context.clone()));
+        let content_mask = ContentMask {
+            bounds: geometry::Bounds {
+                origin: geometry::point(ScaledPixels(0.0), ScaledPixels(0.0)),
+                size: geometry::size(ScaledPixels(32.0), ScaledPixels(16.0)),
+            },
+            ..Default::default()
+        };
+        let quad = |x: f32, color: Hsla| Quad {
+            bounds: geometry::Bounds {
+                origin: geometry::point(ScaledPixels(x), ScaledPixels(4.0)),
+                size: geometry::size(ScaledPixels(8.0), ScaledPixels(8.0)),
+            },
+            content_mask: content_mask.clone(),
+            background: color.into(),
+            ..Default::default()
+        };
+
+        let mut first = Scene::default();
+        first.insert_primitive(quad(2.0, color::red()));
+        first.insert_primitive(quad(16.0, color::blue()));
+        first.finish();
+        first.track_damage(&Scene::default());
+        let mut second = Scene::default();
+        second.insert_primitive(quad(2.0, color::red()));
+        second.insert_primitive(quad(20.0, color::green()));
+        second.finish();
+        second.track_damage(&first);
+
+        let mut renderer = WgpuRenderer::new_offscreen(context.clone(), atlas, 32, 16, 1);
+        assert_eq!(renderer.draw(&first), DrawResult::Drawn);
+        assert!(matches!(
+            renderer.redraw(&second),
+            Redraw::Region([15, 3, 14, 10])
+        ));
+        assert_eq!(renderer.draw(&second), DrawResult::Drawn);
+        assert!(matches!(renderer.redraw(&second), Redraw::Nothing));
+
+        let partial_and_full = |renderer: &mut WgpuRenderer, scene: &Scene| {
+            let RenderTarget::Texture(texture) = &renderer.target else {
+                unreachable!()
+            };
+            let partial = capture::read_texture(&context.device, &context.queue, texture, false);
+            let partial = pollster::block_on(partial).unwrap().unwrap();
+            let full = pollster::block_on(renderer.capture_frame(scene))
+                .unwrap()
+                .unwrap();
+            (partial, full)
+        };
+        let (partial, full) = partial_and_full(&mut renderer, &second);
+        assert_eq!(partial, full);
+
+        // NOTE(mdeand): The blur of a shadow reaches three standard deviations past its bounds.
+        let mut third = Scene::default();
+        third.insert_primitive(quad(2.0, color::red()));
+        third.insert_primitive(quad(20.0, color::green()));
+        third.insert_primitive(Shadow {
+            order: 0,
+            blur_radius: ScaledPixels(1.0),
+            bounds: geometry::Bounds {
+                origin: geometry::point(ScaledPixels(8.0), ScaledPixels(4.0)),
+                size: geometry::size(ScaledPixels(4.0), ScaledPixels(4.0)),
+            },
+            corner_radii: Default::default(),
+            content_mask: content_mask.clone(),
+            color: color::black(),
+            transformation: TransformationMatrix::unit(),
+        });
+        third.finish();
+        third.track_damage(&second);
+        assert!(matches!(
+            renderer.redraw(&third),
+            Redraw::Region([4, 0, 12, 12])
+        ));
+        assert_eq!(renderer.draw(&third), DrawResult::Drawn);
+        let (partial, full) = partial_and_full(&mut renderer, &third);
+        assert_eq!(partial, full);
+    }
+
+    #[test]
+    fn test_recovers_from_simulated_device_loss() {
+        let Some(context) = WgpuContext::for_test() else {
+            return;
+        };
+        let context = Arc::new(context);
+        let atlas = Arc::new(WgpuAtlas::new(context.clone()));
+        let mut renderer = WgpuRenderer::new_offscreen(context.clone(), atlas.clone(), 16, 16, 1);
+        let surface_id = context
+            .surface_registry
+            .create(8, 8, wgpu::TextureFormat::Rgba8Unorm);
+        let scene = Scene::default();
+        assert_eq!(renderer.draw(&scene), DrawResult::Drawn);
+
+        context.simulate_device_loss();
+        assert_eq!(renderer.draw(&scene), DrawResult::Skipped);
+
+        let recovered = Arc::new(context.recreate().unwrap());
+        atlas.recreate(recovered.clone());
+        renderer.recreate_offscreen(recovered.clone());
+        assert_eq!(renderer.draw(&scene), DrawResult::Drawn);
+        assert_eq!(
+            renderer.viewport_size(),
+            geometry::size(DevicePixels(16), DevicePixels(16))
+        );
+        assert!(Arc::ptr_eq(
+            &recovered.surface_registry,
+            &context.surface_registry
+        ));
+        assert_eq!(recovered.surface_registry.generation(), 1);
+        assert_eq!(recovered.surface_registry.size(surface_id), Some((8, 8)));
+    }
+}
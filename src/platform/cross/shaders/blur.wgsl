@@ -0,0 +1,224 @@
+struct Globals {
+    viewport_size: vec2<f32>,
+    premultiplied_alpha: u32,
+    pad: u32,
+}
+
+struct Bounds {
+    origin: vec2<f32>,
+    size: vec2<f32>,
+}
+
+struct Corners {
+    top_left: f32,
+    top_right: f32,
+    bottom_right: f32,
+    bottom_left: f32,
+}
+
+struct ContentMask {
+    bounds: Bounds,
+    corner_radii: Corners,
+}
+
+struct Blur {
+    order: u32,
+    radius: f32,
+    bounds: Bounds,
+    corner_radii: Corners,
+    content_mask: ContentMask,
+}
+
+struct BlurInstance {
+    blur: Blur,
+    // The device pixels copied out of the frame, in whole pixels.
+    region: Bounds,
+    // How many device pixels are averaged into each texel of the blurred textures.
+    downsample: f32,
+    // The standard deviation of the blur, in texels of the blurred textures.
+    sigma: f32,
+}
+
+struct BlurPassVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) blur_id: u32,
+}
+
+struct BlurVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) blur_id: u32,
+    @location(3) clip_distances: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var t_blur: texture_2d<f32>;
+@group(1) @binding(1) var s_blur: sampler;
+@group(2) @binding(0) var<storage, read> b_blurs: array<BlurInstance>;
+
+fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
+    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    return vec4<f32>(device_position, 0.0, 1.0);
+}
+
+fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
+    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
+    return to_device_position_impl(position);
+}
+
+fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec4<f32> {
+    let tl = position - clip_bounds.origin;
+    let br = clip_bounds.origin + clip_bounds.size - position;
+    return vec4<f32>(tl.x, br.x, tl.y, br.y);
+}
+
+// The coverage of a rounded content mask at a position in device pixels. Fragments outside of
+// the mask's bounds are already discarded using their clip distances, so only its corners
+// matter here.
+fn content_mask_coverage(position: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center_to_point = position - bounds.origin - half_size;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = length(max(corner_center_to_point, vec2<f32>(0.0))) - corner_radius;
+    return select(saturate(0.5 - distance), 1.0, corner_radius == 0.0);
+}
+
+fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
+    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
+    return distance_from_clip_rect_impl(position, clip_bounds);
+}
+
+fn pick_corner_radius(center_to_point: vec2<f32>, radii: Corners) -> f32 {
+    if (center_to_point.x < 0.0) {
+        if (center_to_point.y < 0.0) {
+            return radii.top_left;
+        } else {
+            return radii.bottom_left;
+        }
+    } else {
+        if (center_to_point.y < 0.0) {
+            return radii.top_right;
+        } else {
+            return radii.bottom_right;
+        }
+    }
+}
+
+fn quad_sdf(point: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center = bounds.origin + half_size;
+    let center_to_point = point - center;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    return length(max(vec2<f32>(0.0), corner_center_to_point)) +
+        min(0.0, max(corner_center_to_point.x, corner_center_to_point.y)) -
+        corner_radius;
+}
+
+// The size of the blurred region in texels of the blurred textures.
+fn blurred_size(blur: BlurInstance) -> vec2<f32> {
+    return ceil(blur.region.size / blur.downsample);
+}
+
+// A triangle covering the whole viewport, which the renderer restricts to the texels the
+// blurred region downsamples to.
+@vertex
+fn vs_blur_pass(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> BlurPassVarying {
+    let unit_vertex = vec2<f32>(f32((vertex_id << 1u) & 2u), f32(vertex_id & 2u));
+
+    var out = BlurPassVarying();
+    out.position = vec4<f32>(unit_vertex * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.blur_id = instance_id;
+    return out;
+}
+
+// Averages the block of source pixels each texel covers, without reading past the region.
+@fragment
+fn fs_blur_downsample(input: BlurPassVarying) -> @location(0) vec4<f32> {
+    let blur = b_blurs[input.blur_id];
+    let texture_size = vec2<f32>(textureDimensions(t_blur));
+    let block_origin = blur.region.origin + floor(input.position.xy) * blur.downsample;
+    let min_position = blur.region.origin + vec2<f32>(0.5);
+    let max_position = blur.region.origin + blur.region.size - vec2<f32>(0.5);
+
+    // Every bilinear tap averages 2x2 pixels, so half as many taps as pixels are needed.
+    let taps = max(u32(blur.downsample) / 2u, 1u);
+    let step = blur.downsample / f32(taps);
+    var color = vec4<f32>(0.0);
+    for (var y = 0u; y < taps; y += 1u) {
+        for (var x = 0u; x < taps; x += 1u) {
+            let offset = (vec2<f32>(f32(x), f32(y)) + vec2<f32>(0.5)) * step;
+            let position = clamp(block_origin + offset, min_position, max_position);
+            color += textureSampleLevel(t_blur, s_blur, position / texture_size, 0.0);
+        }
+    }
+    return color / f32(taps * taps);
+}
+
+fn gaussian_blur(position: vec2<f32>, blur: BlurInstance, direction: vec2<i32>) -> vec4<f32> {
+    let max_texel = vec2<i32>(blurred_size(blur)) - vec2<i32>(1);
+    let texel = vec2<i32>(floor(position));
+    let sigma = max(blur.sigma, 0.01);
+    let half_width = i32(ceil(3.0 * sigma));
+
+    var color = vec4<f32>(0.0);
+    var total_weight = 0.0;
+    for (var i = -half_width; i <= half_width; i += 1) {
+        let weight = exp(-f32(i * i) / (2.0 * sigma * sigma));
+        let coordinates = clamp(texel + direction * i, vec2<i32>(0), max_texel);
+        color += textureLoad(t_blur, coordinates, 0) * weight;
+        total_weight += weight;
+    }
+    return color / total_weight;
+}
+
+@fragment
+fn fs_blur_horizontal(input: BlurPassVarying) -> @location(0) vec4<f32> {
+    return gaussian_blur(input.position.xy, b_blurs[input.blur_id], vec2<i32>(1, 0));
+}
+
+@fragment
+fn fs_blur_vertical(input: BlurPassVarying) -> @location(0) vec4<f32> {
+    return gaussian_blur(input.position.xy, b_blurs[input.blur_id], vec2<i32>(0, 1));
+}
+
+@vertex
+fn vs_blur(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> BlurVarying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+    let blur = b_blurs[instance_id].blur;
+
+    var out = BlurVarying();
+    out.position = to_device_position(unit_vertex, blur.bounds);
+    out.blur_id = instance_id;
+    out.clip_distances = distance_from_clip_rect(unit_vertex, blur.bounds, blur.content_mask.bounds);
+    return out;
+}
+
+// Draws the blurred texels back over the frame, within the blur's rounded bounds.
+@fragment
+fn fs_blur(input: BlurVarying) -> @location(0) vec4<f32> {
+    if (any(input.clip_distances < vec4<f32>(0.0))) {
+        return vec4<f32>(0.0);
+    }
+
+    let blur = b_blurs[input.blur_id];
+    let mask_coverage = content_mask_coverage(input.position.xy, blur.blur.content_mask.bounds,
+        blur.blur.content_mask.corner_radii);
+    let alpha = saturate(0.5 - quad_sdf(input.position.xy, blur.blur.bounds, blur.blur.corner_radii))
+        * mask_coverage;
+
+    let texture_size = vec2<f32>(textureDimensions(t_blur));
+    let position = clamp(
+        (input.position.xy - blur.region.origin) / blur.downsample,
+        vec2<f32>(0.5),
+        blurred_size(blur) - vec2<f32>(0.5),
+    );
+    let color = textureSampleLevel(t_blur, s_blur, position / texture_size, 0.0);
+
+    // The blurred texels were copied out of the frame, so they're premultiplied exactly
+    // when the frame is.
+    if (globals.premultiplied_alpha != 0u) {
+        return color * alpha;
+    }
+    return vec4<f32>(color.rgb, color.a * alpha);
+}
@@ -0,0 +1,18 @@
+// Resets the pixels of a frame that are about to be redrawn, within the scissor rect of the
+// pass, to what a cleared frame holds, or the pixels of a layer texture to transparent.
+
+@vertex
+fn vs_clear(@builtin(vertex_index) vertex_id: u32) -> @builtin(position) vec4<f32> {
+    let unit_vertex = vec2<f32>(f32((vertex_id << 1u) & 2u), f32(vertex_id & 2u));
+    return vec4<f32>(unit_vertex * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+@fragment
+fn fs_clear() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_clear_layer() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0);
+}
@@ -0,0 +1,168 @@
+struct Globals {
+    viewport_size: vec2<f32>,
+    premultiplied_alpha: u32,
+    pad: u32,
+}
+
+struct Bounds {
+    origin: vec2<f32>,
+    size: vec2<f32>,
+}
+
+struct LayerInstance {
+    bounds: Bounds,
+    opacity: f32,
+    blend_mode: u32,
+}
+
+struct LayerVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) layer_id: u32,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var t_layer: texture_2d<f32>;
+@group(1) @binding(1) var s_layer: sampler;
+@group(2) @binding(0) var t_backdrop: texture_2d<f32>;
+@group(2) @binding(1) var s_backdrop: sampler;
+@group(3) @binding(0) var<storage, read> b_layers: array<LayerInstance>;
+
+const BLEND_MODE_NORMAL: u32 = 0u;
+const BLEND_MODE_MULTIPLY: u32 = 1u;
+const BLEND_MODE_SCREEN: u32 = 2u;
+const BLEND_MODE_OVERLAY: u32 = 3u;
+const BLEND_MODE_DARKEN: u32 = 4u;
+const BLEND_MODE_LIGHTEN: u32 = 5u;
+const BLEND_MODE_COLOR_DODGE: u32 = 6u;
+const BLEND_MODE_COLOR_BURN: u32 = 7u;
+const BLEND_MODE_HARD_LIGHT: u32 = 8u;
+const BLEND_MODE_SOFT_LIGHT: u32 = 9u;
+const BLEND_MODE_DIFFERENCE: u32 = 10u;
+const BLEND_MODE_EXCLUSION: u32 = 11u;
+
+fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
+    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    return vec4<f32>(device_position, 0.0, 1.0);
+}
+
+fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
+    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
+    return to_device_position_impl(position);
+}
+
+fn hard_light(backdrop: f32, source: f32) -> f32 {
+    if (source <= 0.5) {
+        return backdrop * 2.0 * source;
+    }
+    let screened = 2.0 * source - 1.0;
+    return backdrop + screened - backdrop * screened;
+}
+
+fn soft_light(backdrop: f32, source: f32) -> f32 {
+    if (source <= 0.5) {
+        return backdrop - (1.0 - 2.0 * source) * backdrop * (1.0 - backdrop);
+    }
+    var d = sqrt(backdrop);
+    if (backdrop <= 0.25) {
+        d = ((16.0 * backdrop - 12.0) * backdrop + 4.0) * backdrop;
+    }
+    return backdrop + (2.0 * source - 1.0) * (d - backdrop);
+}
+
+// The separable blend functions of the W3C compositing spec, taking straight colors.
+fn blend_channel(blend_mode: u32, backdrop: f32, source: f32) -> f32 {
+    switch (blend_mode) {
+        case BLEND_MODE_MULTIPLY: {
+            return backdrop * source;
+        }
+        case BLEND_MODE_SCREEN: {
+            return backdrop + source - backdrop * source;
+        }
+        case BLEND_MODE_OVERLAY: {
+            return hard_light(source, backdrop);
+        }
+        case BLEND_MODE_DARKEN: {
+            return min(backdrop, source);
+        }
+        case BLEND_MODE_LIGHTEN: {
+            return max(backdrop, source);
+        }
+        case BLEND_MODE_COLOR_DODGE: {
+            if (backdrop == 0.0) {
+                return 0.0;
+            }
+            if (source >= 1.0) {
+                return 1.0;
+            }
+            return min(1.0, backdrop / (1.0 - source));
+        }
+        case BLEND_MODE_COLOR_BURN: {
+            if (backdrop >= 1.0) {
+                return 1.0;
+            }
+            if (source == 0.0) {
+                return 0.0;
+            }
+            return 1.0 - min(1.0, (1.0 - backdrop) / source);
+        }
+        case BLEND_MODE_HARD_LIGHT: {
+            return hard_light(backdrop, source);
+        }
+        case BLEND_MODE_SOFT_LIGHT: {
+            return soft_light(backdrop, source);
+        }
+        case BLEND_MODE_DIFFERENCE: {
+            return abs(backdrop - source);
+        }
+        case BLEND_MODE_EXCLUSION: {
+            return backdrop + source - 2.0 * backdrop * source;
+        }
+        default: {
+            return source;
+        }
+    }
+}
+
+@vertex
+fn vs_layer(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> LayerVarying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+    let layer = b_layers[instance_id];
+
+    var out = LayerVarying();
+    out.position = to_device_position(unit_vertex, layer.bounds);
+    out.layer_id = instance_id;
+    return out;
+}
+
+@fragment
+fn fs_layer(input: LayerVarying) -> @location(0) vec4<f32> {
+    let layer = b_layers[input.layer_id];
+    // The layer texture has the size of the viewport, so it's read 1:1 with the frame.
+    let texel = textureLoad(t_layer, vec2<i32>(input.position.xy), 0);
+    if (texel.a == 0.0) {
+        return vec4<f32>(0.0);
+    }
+    // Layers are drawn into a transparent texture, which leaves premultiplied colors behind
+    // even when the primitives blend straight alpha colors.
+    var color = texel.rgb / texel.a;
+
+    if (layer.blend_mode != BLEND_MODE_NORMAL) {
+        // The backdrop holds a copy of what was drawn beneath the layer, in the same pixels.
+        let backdrop = textureLoad(t_backdrop, vec2<i32>(input.position.xy), 0);
+        if (backdrop.a > 0.0) {
+            let backdrop_color = backdrop.rgb / backdrop.a;
+            let blended = vec3<f32>(
+                blend_channel(layer.blend_mode, backdrop_color.r, color.r),
+                blend_channel(layer.blend_mode, backdrop_color.g, color.g),
+                blend_channel(layer.blend_mode, backdrop_color.b, color.b),
+            );
+            color = mix(color, blended, backdrop.a);
+        }
+    }
+
+    let alpha = texel.a * layer.opacity;
+    if (globals.premultiplied_alpha != 0u) {
+        return vec4<f32>(color * alpha, alpha);
+    }
+    return vec4<f32>(color, alpha);
+}
@@ -0,0 +1,378 @@
+const M_PI_F: f32 = 3.1415926;
+
+struct Globals {
+    viewport_size: vec2<f32>,
+    premultiplied_alpha: u32,
+    pad: u32,
+}
+
+struct Hsla {
+  h: f32,
+  s: f32,
+  l: f32,
+  a: f32,
+}
+
+struct Bounds {
+  origin: vec2<f32>,
+  size: vec2<f32>,
+}
+
+struct Corners {
+    top_left: f32,
+    top_right: f32,
+    bottom_right: f32,
+    bottom_left: f32,
+}
+
+struct ContentMask {
+    bounds: Bounds,
+    corner_radii: Corners,
+}
+
+struct LinearColorStop {
+  color: Hsla,
+  percentage: f32,
+}
+
+struct Background {
+  tag: u32,
+  color_space: u32,
+  solid: Hsla,
+  gradient_angle_or_pattern_height: f32,
+  stops_offset: u32,
+  gradient_center: vec2<f32>,
+  stop_count: u32,
+  pad: u32,
+}
+
+struct PathRasterizationVertex {
+    xy_position: vec2<f32>,
+    st_position: vec2<f32>,
+    color: Background,
+    bounds: Bounds,
+    content_mask: ContentMask,
+}
+
+struct PathRasterizationVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) st_position: vec2<f32>,
+    @location(1) @interpolate(flat) vertex_id: u32,
+    // TODO: use `clip_distance` once Naga supports it
+    @location(2) clip_distances: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var<storage, read> b_path_vertices: array<PathRasterizationVertex>;
+@group(1) @binding(1) var<storage, read> b_gradient_stops: array<LinearColorStop>;
+
+/// Convert an Oklab color to linear sRGB space.
+fn oklab_to_linear_srgb(color: vec4<f32>) -> vec4<f32> {
+	let l_ = color.r + 0.3963377774 * color.g + 0.2158037573 * color.b;
+	let m_ = color.r - 0.1055613458 * color.g - 0.0638541728 * color.b;
+	let s_ = color.r - 0.0894841775 * color.g - 1.2914855480 * color.b;
+
+	let l = l_ * l_ * l_;
+	let m = m_ * m_ * m_;
+	let s = s_ * s_ * s_;
+
+	return vec4<f32>(
+		4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+		-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+		-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+		color.a
+	);
+}
+
+// https://gamedev.stackexchange.com/questions/92015/optimized-linear-to-srgb-glsl
+fn srgb_to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = srgb < vec3<f32>(0.04045);
+    let higher = pow((srgb + vec3<f32>(0.055)) / vec3<f32>(1.055), vec3<f32>(2.4));
+    let lower = srgb / vec3<f32>(12.92);
+    return select(higher, lower, cutoff);
+}
+
+/// Convert a sRGBA color to linear space.
+fn srgba_to_linear(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(srgb_to_linear(color.rgb), color.a);
+}
+
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let cutoff = linear < vec3<f32>(0.0031308);
+    let higher = vec3<f32>(1.055) * pow(linear, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
+    let lower = linear * vec3<f32>(12.92);
+    return select(higher, lower, cutoff);
+}
+
+/// Convert a linear color to sRGBA space.
+fn linear_to_srgba(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(linear_to_srgb(color.rgb), color.a);
+}
+
+/// Convert a linear sRGB to Oklab space.
+/// Reference: https://bottosson.github.io/posts/oklab/#converting-from-linear-srgb-to-oklab
+fn linear_srgb_to_oklab(color: vec4<f32>) -> vec4<f32> {
+	let l = 0.4122214708 * color.r + 0.5363325363 * color.g + 0.0514459929 * color.b;
+	let m = 0.2119034982 * color.r + 0.6806995451 * color.g + 0.1073969566 * color.b;
+	let s = 0.0883024619 * color.r + 0.2817188376 * color.g + 0.6299787005 * color.b;
+
+	let l_ = pow(l, 1.0 / 3.0);
+	let m_ = pow(m, 1.0 / 3.0);
+	let s_ = pow(s, 1.0 / 3.0);
+
+	return vec4<f32>(
+		0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_,
+		1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_,
+		0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_,
+		color.a
+	);
+}
+
+fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
+    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    return vec4<f32>(device_position, 0.0, 1.0);
+}
+
+/// Hsla to linear RGBA conversion.
+fn hsla_to_rgba(hsla: Hsla) -> vec4<f32> {
+    let h = hsla.h * 6.0; // Now, it's an angle but scaled in [0, 6) range
+    let s = hsla.s;
+    let l = hsla.l;
+    let a = hsla.a;
+
+    let c = (1.0 - abs(2.0 * l - 1.0)) * s;
+    let x = c * (1.0 - abs(h % 2.0 - 1.0));
+    let m = l - c / 2.0;
+    var color = vec3<f32>(m);
+
+    if (h >= 0.0 && h < 1.0) {
+        color.r += c;
+        color.g += x;
+    } else if (h >= 1.0 && h < 2.0) {
+        color.r += x;
+        color.g += c;
+    } else if (h >= 2.0 && h < 3.0) {
+        color.g += c;
+        color.b += x;
+    } else if (h >= 3.0 && h < 4.0) {
+        color.g += x;
+        color.b += c;
+    } else if (h >= 4.0 && h < 5.0) {
+        color.r += x;
+        color.b += c;
+    } else {
+        color.r += c;
+        color.b += x;
+    }
+
+    return vec4<f32>(color, a);
+}
+
+/// Convert a linear sRGB color into the color space a gradient is interpolated in.
+fn to_gradient_color_space(color: vec4<f32>, color_space: u32) -> vec4<f32> {
+    switch (color_space) {
+        default: {
+            return linear_to_srgba(color);
+        }
+        case 1u: {
+            return linear_srgb_to_oklab(color);
+        }
+    }
+}
+
+/// Convert a color from the color space a gradient is interpolated in back to linear sRGB.
+fn from_gradient_color_space(color: vec4<f32>, color_space: u32) -> vec4<f32> {
+    switch (color_space) {
+        default: {
+            return srgba_to_linear(color);
+        }
+        case 1u: {
+            return oklab_to_linear_srgb(color);
+        }
+    }
+}
+
+// Interpolates between the two color stops surrounding `t`, clamping to the
+// first and last stops outside of their range.
+fn gradient_stops_color(background: Background, t: f32) -> vec4<f32> {
+    let stop_count = background.stop_count;
+    if (stop_count == 0u) {
+        return vec4<f32>(0.0);
+    }
+
+    var start = b_gradient_stops[background.stops_offset];
+    var end = start;
+    for (var i = 1u; i < stop_count; i++) {
+        start = end;
+        end = b_gradient_stops[background.stops_offset + i];
+        if (t <= end.percentage) {
+            break;
+        }
+    }
+
+    let span = end.percentage - start.percentage;
+    var stop_t = select(0.0, 1.0, t >= end.percentage);
+    if (span > 0.0) {
+        stop_t = clamp((t - start.percentage) / span, 0.0, 1.0);
+    }
+
+    // The hsla_to_rgba is returns a linear sRGB color
+    let start_color = to_gradient_color_space(hsla_to_rgba(start.color), background.color_space);
+    let end_color = to_gradient_color_space(hsla_to_rgba(end.color), background.color_space);
+    return from_gradient_color_space(mix(start_color, end_color, stop_t), background.color_space);
+}
+
+fn gradient_color(background: Background, position: vec2<f32>, bounds: Bounds,
+    solid_color: vec4<f32>) -> vec4<f32> {
+    var background_color = vec4<f32>(0.0);
+
+    switch (background.tag) {
+        default: {
+            return solid_color;
+        }
+        case 1u: {
+            // Linear gradient background.
+            // -90 degrees to match the CSS gradient angle.
+            let angle = background.gradient_angle_or_pattern_height;
+            let radians = (angle % 360.0 - 90.0) * M_PI_F / 180.0;
+            var direction = vec2<f32>(cos(radians), sin(radians));
+
+            // Expand the short side to be the same as the long side
+            if (bounds.size.x > bounds.size.y) {
+                direction.y *= bounds.size.y / bounds.size.x;
+            } else {
+                direction.x *= bounds.size.x / bounds.size.y;
+            }
+
+            // Get the t value for the linear gradient with the color stop percentages.
+            let half_size = bounds.size / 2.0;
+            let center = bounds.origin + half_size;
+            let center_to_point = position - center;
+            var t = dot(center_to_point, direction) / length(direction);
+            // Check the direct to determine the use x or y
+            if (abs(direction.x) > abs(direction.y)) {
+                t = (t + half_size.x) / bounds.size.x;
+            } else {
+                t = (t + half_size.y) / bounds.size.y;
+            }
+
+            background_color = gradient_stops_color(background, t);
+        }
+        case 2u: {
+            let gradient_angle_or_pattern_height = background.gradient_angle_or_pattern_height;
+            let pattern_width = (gradient_angle_or_pattern_height / 65535.0f) / 255.0f;
+            let pattern_interval = (gradient_angle_or_pattern_height % 65535.0f) / 255.0f;
+            let pattern_height = pattern_width + pattern_interval;
+            let stripe_angle = M_PI_F / 4.0;
+            let pattern_period = pattern_height * sin(stripe_angle);
+            let rotation = mat2x2<f32>(
+                cos(stripe_angle), -sin(stripe_angle),
+                sin(stripe_angle), cos(stripe_angle)
+            );
+            let relative_position = position - bounds.origin;
+            let rotated_point = rotation * relative_position;
+            let pattern = rotated_point.x % pattern_period;
+            let distance = min(pattern, pattern_period - pattern) - pattern_period * (pattern_width / pattern_height) /  2.0f;
+            background_color = solid_color;
+            background_color.a *= saturate(0.5 - distance);
+        }
+        case 3u: {
+            // Radial gradient background, an ellipse that follows the aspect ratio
+            // of the bounds and reaches the last stop at the farthest corner.
+            let center = background.gradient_center;
+            let relative_position = (position - bounds.origin) / bounds.size;
+            let farthest_corner = max(center, vec2<f32>(1.0) - center);
+            let t = length(relative_position - center) / length(farthest_corner);
+            background_color = gradient_stops_color(background, t);
+        }
+        case 4u: {
+            // Conic gradient background, going clockwise from the top.
+            let center = bounds.origin + background.gradient_center * bounds.size;
+            let center_to_point = position - center;
+            let angle = atan2(center_to_point.x, -center_to_point.y) * 180.0 / M_PI_F;
+            let t = fract((angle - background.gradient_angle_or_pattern_height) / 360.0);
+            background_color = gradient_stops_color(background, t);
+        }
+    }
+
+    return background_color;
+}
+
+fn pick_corner_radius(center_to_point: vec2<f32>, radii: Corners) -> f32 {
+    if (center_to_point.x < 0.0) {
+        if (center_to_point.y < 0.0) {
+            return radii.top_left;
+        } else {
+            return radii.bottom_left;
+        }
+    } else {
+        if (center_to_point.y < 0.0) {
+            return radii.top_right;
+        } else {
+            return radii.bottom_right;
+        }
+    }
+}
+
+fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec4<f32> {
+    let tl = position - clip_bounds.origin;
+    let br = clip_bounds.origin + clip_bounds.size - position;
+    return vec4<f32>(tl.x, br.x, tl.y, br.y);
+}
+
+// The coverage of a rounded content mask at a position in device pixels. Fragments outside of
+// the mask's bounds are already discarded using their clip distances, so only its corners
+// matter here.
+fn content_mask_coverage(position: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center_to_point = position - bounds.origin - half_size;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = length(max(corner_center_to_point, vec2<f32>(0.0))) - corner_radius;
+    return select(saturate(0.5 - distance), 1.0, corner_radius == 0.0);
+}
+
+@vertex
+fn vs_path_rasterization(@builtin(vertex_index) vertex_id: u32) -> PathRasterizationVarying {
+    let v = b_path_vertices[vertex_id];
+
+    var out = PathRasterizationVarying();
+    out.position = to_device_position_impl(v.xy_position);
+    out.st_position = v.st_position;
+    out.vertex_id = vertex_id;
+    out.clip_distances = distance_from_clip_rect_impl(v.xy_position, v.bounds);
+    return out;
+}
+
+@fragment
+fn fs_path_rasterization(input: PathRasterizationVarying) -> @location(0) vec4<f32> {
+    // Derivatives must be computed in uniform control flow, before the clip test.
+    let dx = dpdx(input.st_position);
+    let dy = dpdy(input.st_position);
+    if (any(input.clip_distances < vec4<f32>(0.0))) {
+        return vec4<f32>(0.0);
+    }
+
+    let v = b_path_vertices[input.vertex_id];
+    let background = v.color;
+    let bounds = v.bounds;
+    let mask_coverage = content_mask_coverage(input.position.xy, v.content_mask.bounds,
+        v.content_mask.corner_radii);
+
+    var alpha = mask_coverage;
+    // Straight edges have a constant `s`, so there is no curve to anti-alias.
+    if (length(vec2<f32>(dx.x, dy.x)) >= 0.001) {
+        // Loop-Blinn quadratic curve coverage: `s^2 - t` is negative inside the curve.
+        let gradient = 2.0 * input.st_position.xx * vec2<f32>(dx.x, dy.x) - vec2<f32>(dx.y, dy.y);
+        let f = input.st_position.x * input.st_position.x - input.st_position.y;
+        let distance = f / length(gradient);
+        alpha *= saturate(0.5 - distance);
+    }
+
+    let color = gradient_color(background, input.position.xy, bounds,
+        hsla_to_rgba(background.solid));
+
+    // The intermediate texture always holds premultiplied colors, so overlapping
+    // triangles of the same path accumulate correctly.
+    return vec4<f32>(color.rgb * color.a * alpha, color.a * alpha);
+}
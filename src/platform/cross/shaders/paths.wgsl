@@ -0,0 +1,60 @@
+struct Globals {
+    viewport_size: vec2<f32>,
+    premultiplied_alpha: u32,
+    pad: u32,
+}
+
+struct Bounds {
+    origin: vec2<f32>,
+    size: vec2<f32>,
+}
+
+struct PathSprite {
+    bounds: Bounds,
+}
+
+struct PathVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) texture_coords: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var t_sprite: texture_2d<f32>;
+@group(1) @binding(1) var s_sprite: sampler;
+@group(2) @binding(0) var<storage, read> b_path_sprites: array<PathSprite>;
+
+fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
+    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    return vec4<f32>(device_position, 0.0, 1.0);
+}
+
+fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
+    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
+    return to_device_position_impl(position);
+}
+
+@vertex
+fn vs_path(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> PathVarying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+    let sprite = b_path_sprites[instance_id];
+
+    // The content mask was already applied while rasterizing, so the sprite
+    // only has to map its screen-space bounds onto the intermediate texture.
+    let screen_position = sprite.bounds.origin + unit_vertex * sprite.bounds.size;
+
+    var out = PathVarying();
+    out.position = to_device_position(unit_vertex, sprite.bounds);
+    out.texture_coords = screen_position / globals.viewport_size;
+    return out;
+}
+
+@fragment
+fn fs_path(input: PathVarying) -> @location(0) vec4<f32> {
+    let sample = textureSample(t_sprite, s_sprite, input.texture_coords);
+    if (globals.premultiplied_alpha != 0u || sample.a == 0.0) {
+        return sample;
+    }
+    // The intermediate texture is premultiplied, but straight-alpha targets
+    // expect unpremultiplied colors like every other pipeline produces.
+    return vec4<f32>(sample.rgb / sample.a, sample.a);
+}
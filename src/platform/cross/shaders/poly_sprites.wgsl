@@ -16,6 +16,11 @@ struct Corners {
     bottom_left: f32,
 }
 
+struct ContentMask {
+    bounds: Bounds,
+    corner_radii: Corners,
+}
+
 struct AtlasTextureId {
     index: u32,
     kind: u32,
@@ -33,15 +38,21 @@ struct AtlasTile {
     bounds: AtlasBounds,
 }
 
+struct TransformationMatrix {
+    rotation_scale: mat2x2<f32>,
+    translation: vec2<f32>,
+}
+
 struct PolychromeSprite {
     order: u32,
     pad: u32,
     grayscale: u32,
     opacity: f32,
     bounds: Bounds,
-    content_mask: Bounds,
+    content_mask: ContentMask,
     corner_radii: Corners,
     tile: AtlasTile,
+    transformation: TransformationMatrix,
 }
 
 const GRAYSCALE_FACTORS: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
@@ -51,6 +62,7 @@ struct PolySpriteVarying {
     @location(0) tile_position: vec2<f32>,
     @location(1) @interpolate(flat) sprite_id: u32,
     @location(3) clip_distances: vec4<f32>,
+    @location(4) local_position: vec2<f32>,
 }
 
 @group(0) @binding(0) var<uniform> globals: Globals;
@@ -63,9 +75,9 @@ fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
     return vec4<f32>(device_position, 0.0, 1.0);
 }
 
-fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return to_device_position_impl(position);
+// The Rust side stores the rotation and scale row-major, so it's transposed here.
+fn transform_position(position: vec2<f32>, transform: TransformationMatrix) -> vec2<f32> {
+    return transpose(transform.rotation_scale) * position + transform.translation;
 }
 
 fn to_tile_position(unit_vertex: vec2<f32>, tile: AtlasTile) -> vec2<f32> {
@@ -79,9 +91,16 @@ fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec
     return vec4<f32>(tl.x, br.x, tl.y, br.y);
 }
 
-fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return distance_from_clip_rect_impl(position, clip_bounds);
+// The coverage of a rounded content mask at a position in device pixels. Fragments outside of
+// the mask's bounds are already discarded using their clip distances, so only its corners
+// matter here.
+fn content_mask_coverage(position: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center_to_point = position - bounds.origin - half_size;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = length(max(corner_center_to_point, vec2<f32>(0.0))) - corner_radius;
+    return select(saturate(0.5 - distance), 1.0, corner_radius == 0.0);
 }
 
 fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
@@ -133,10 +152,13 @@ fn vs_poly_sprite(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index
     let sprite = b_poly_sprites[instance_id];
 
     var out = PolySpriteVarying();
-    out.position = to_device_position(unit_vertex, sprite.bounds);
+    let local_position = unit_vertex * sprite.bounds.size + sprite.bounds.origin;
+    let position = transform_position(local_position, sprite.transformation);
+    out.position = to_device_position_impl(position);
+    out.local_position = local_position;
     out.tile_position = to_tile_position(unit_vertex, sprite.tile);
     out.sprite_id = instance_id;
-    out.clip_distances = distance_from_clip_rect(unit_vertex, sprite.bounds, sprite.content_mask);
+    out.clip_distances = distance_from_clip_rect_impl(position, sprite.content_mask.bounds);
     return out;
 }
 
@@ -148,12 +170,14 @@ fn fs_poly_sprite(input: PolySpriteVarying) -> @location(0) vec4<f32> {
     }
 
     let sprite = b_poly_sprites[input.sprite_id];
-    let distance = quad_sdf(input.position.xy, sprite.bounds, sprite.corner_radii);
+    let distance = quad_sdf(input.local_position, sprite.bounds, sprite.corner_radii);
+    let mask_coverage = content_mask_coverage(input.position.xy, sprite.content_mask.bounds,
+        sprite.content_mask.corner_radii);
 
     var color = sample;
     if ((sprite.grayscale & 0xFFu) != 0u) {
         let grayscale = dot(color.rgb, GRAYSCALE_FACTORS);
         color = vec4<f32>(vec3<f32>(grayscale), sample.a);
     }
-    return blend_color(color, sprite.opacity * saturate(0.5 - distance));
+    return blend_color(color, sprite.opacity * saturate(0.5 - distance) * mask_coverage);
 }
@@ -6,12 +6,6 @@ struct Globals {
     pad: u32,
 }
 
-struct GradientColor {
-  solid: vec4<f32>,
-  color0: vec4<f32>,
-  color1: vec4<f32>,
-}
-
 struct Hsla {
   h: f32,
   s: f32,
@@ -34,8 +28,9 @@ struct Background {
   color_space: u32,
   solid: Hsla,
   gradient_angle_or_pattern_height: f32,
-  color0: LinearColorStop,
-  color1: LinearColorStop,
+  stops_offset: u32,
+  gradient_center: vec2<f32>,
+  stop_count: u32,
   pad: u32,
 }
 
@@ -53,15 +48,26 @@ struct Corners {
   bottom_left: f32,
 }
 
+struct ContentMask {
+    bounds: Bounds,
+    corner_radii: Corners,
+}
+
+struct TransformationMatrix {
+    rotation_scale: mat2x2<f32>,
+    translation: vec2<f32>,
+}
+
 struct Quad {
     order: u32,
     border_style: u32,
     bounds: Bounds,
-    content_mask: Bounds,
+    content_mask: ContentMask,
     background: Background,
     border_color: Hsla,
     corner_radii: Corners,
     border_widths: Edges,
+    transformation: TransformationMatrix,
 }
 
 struct QuadVarying {
@@ -71,12 +77,12 @@ struct QuadVarying {
     // TODO: use `clip_distance` once Naga supports it
     @location(2) clip_distances: vec4<f32>,
     @location(3) @interpolate(flat) background_solid: vec4<f32>,
-    @location(4) @interpolate(flat) background_color0: vec4<f32>,
-    @location(5) @interpolate(flat) background_color1: vec4<f32>,
+    @location(4) local_position: vec2<f32>,
 }
 
 @group(0) @binding(0) var<uniform> globals: Globals;
 @group(1) @binding(0) var<storage, read> b_quads: array<Quad>;
+@group(1) @binding(1) var<storage, read> b_gradient_stops: array<LinearColorStop>;
 
 /// Convert an Oklab color to linear sRGB space.
 fn oklab_to_linear_srgb(color: vec4<f32>) -> vec4<f32> {
@@ -162,9 +168,9 @@ fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
     return vec4<f32>(device_position, 0.0, 1.0);
 }
 
-fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return to_device_position_impl(position);
+// The Rust side stores the rotation and scale row-major, so it's transposed here.
+fn transform_position(position: vec2<f32>, transform: TransformationMatrix) -> vec2<f32> {
+    return transpose(transform.rotation_scale) * position + transform.translation;
 }
 
 /// Hsla to linear RGBA conversion.
@@ -202,35 +208,62 @@ fn hsla_to_rgba(hsla: Hsla) -> vec4<f32> {
     return vec4<f32>(color, a);
 }
 
-fn prepare_gradient_color(tag: u32, color_space: u32,
-    solid: Hsla, color0: LinearColorStop, color1: LinearColorStop) -> GradientColor {
-    var result = GradientColor();
-
-    if (tag == 0u || tag == 2u) {
-        result.solid = hsla_to_rgba(solid);
-    } else if (tag == 1u) {
-        // The hsla_to_rgba is returns a linear sRGB color
-        result.color0 = hsla_to_rgba(color0.color);
-        result.color1 = hsla_to_rgba(color1.color);
-
-        // Prepare color space in vertex for avoid conversion
-        // in fragment shader for performance reasons
-        if (color_space == 0u) {
-            // sRGB
-            result.color0 = linear_to_srgba(result.color0);
-            result.color1 = linear_to_srgba(result.color1);
-        } else if (color_space == 1u) {
-            // Oklab
-            result.color0 = linear_srgb_to_oklab(result.color0);
-            result.color1 = linear_srgb_to_oklab(result.color1);
+/// Convert a linear sRGB color into the color space a gradient is interpolated in.
+fn to_gradient_color_space(color: vec4<f32>, color_space: u32) -> vec4<f32> {
+    switch (color_space) {
+        default: {
+            return linear_to_srgba(color);
+        }
+        case 1u: {
+            return linear_srgb_to_oklab(color);
         }
     }
+}
 
-    return result;
+/// Convert a color from the color space a gradient is interpolated in back to linear sRGB.
+fn from_gradient_color_space(color: vec4<f32>, color_space: u32) -> vec4<f32> {
+    switch (color_space) {
+        default: {
+            return srgba_to_linear(color);
+        }
+        case 1u: {
+            return oklab_to_linear_srgb(color);
+        }
+    }
+}
+
+// Interpolates between the two color stops surrounding `t`, clamping to the
+// first and last stops outside of their range.
+fn gradient_stops_color(background: Background, t: f32) -> vec4<f32> {
+    let stop_count = background.stop_count;
+    if (stop_count == 0u) {
+        return vec4<f32>(0.0);
+    }
+
+    var start = b_gradient_stops[background.stops_offset];
+    var end = start;
+    for (var i = 1u; i < stop_count; i++) {
+        start = end;
+        end = b_gradient_stops[background.stops_offset + i];
+        if (t <= end.percentage) {
+            break;
+        }
+    }
+
+    let span = end.percentage - start.percentage;
+    var stop_t = select(0.0, 1.0, t >= end.percentage);
+    if (span > 0.0) {
+        stop_t = clamp((t - start.percentage) / span, 0.0, 1.0);
+    }
+
+    // The hsla_to_rgba is returns a linear sRGB color
+    let start_color = to_gradient_color_space(hsla_to_rgba(start.color), background.color_space);
+    let end_color = to_gradient_color_space(hsla_to_rgba(end.color), background.color_space);
+    return from_gradient_color_space(mix(start_color, end_color, stop_t), background.color_space);
 }
 
 fn gradient_color(background: Background, position: vec2<f32>, bounds: Bounds,
-    solid_color: vec4<f32>, color0: vec4<f32>, color1: vec4<f32>) -> vec4<f32> {
+    solid_color: vec4<f32>) -> vec4<f32> {
     var background_color = vec4<f32>(0.0);
 
     switch (background.tag) {
@@ -243,8 +276,6 @@ fn gradient_color(background: Background, position: vec2<f32>, bounds: Bounds,
             let angle = background.gradient_angle_or_pattern_height;
             let radians = (angle % 360.0 - 90.0) * M_PI_F / 180.0;
             var direction = vec2<f32>(cos(radians), sin(radians));
-            let stop0_percentage = background.color0.percentage;
-            let stop1_percentage = background.color1.percentage;
 
             // Expand the short side to be the same as the long side
             if (bounds.size.x > bounds.size.y) {
@@ -265,19 +296,7 @@ fn gradient_color(background: Background, position: vec2<f32>, bounds: Bounds,
                 t = (t + half_size.y) / bounds.size.y;
             }
 
-            // Adjust t based on the stop percentages
-            t = (t - stop0_percentage) / (stop1_percentage - stop0_percentage);
-            t = clamp(t, 0.0, 1.0);
-
-            switch (background.color_space) {
-                default: {
-                    background_color = srgba_to_linear(mix(color0, color1, t));
-                }
-                case 1u: {
-                    let oklab_color = mix(color0, color1, t);
-                    background_color = oklab_to_linear_srgb(oklab_color);
-                }
-            }
+            background_color = gradient_stops_color(background, t);
         }
         case 2u: {
             let gradient_angle_or_pattern_height = background.gradient_angle_or_pattern_height;
@@ -297,6 +316,23 @@ fn gradient_color(background: Background, position: vec2<f32>, bounds: Bounds,
             background_color = solid_color;
             background_color.a *= saturate(0.5 - distance);
         }
+        case 3u: {
+            // Radial gradient background, an ellipse that follows the aspect ratio
+            // of the bounds and reaches the last stop at the farthest corner.
+            let center = background.gradient_center;
+            let relative_position = (position - bounds.origin) / bounds.size;
+            let farthest_corner = max(center, vec2<f32>(1.0) - center);
+            let t = length(relative_position - center) / length(farthest_corner);
+            background_color = gradient_stops_color(background, t);
+        }
+        case 4u: {
+            // Conic gradient background, going clockwise from the top.
+            let center = bounds.origin + background.gradient_center * bounds.size;
+            let center_to_point = position - center;
+            let angle = atan2(center_to_point.x, -center_to_point.y) * 180.0 / M_PI_F;
+            let t = fract((angle - background.gradient_angle_or_pattern_height) / 360.0);
+            background_color = gradient_stops_color(background, t);
+        }
     }
 
     return background_color;
@@ -316,9 +352,16 @@ fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec
     return vec4<f32>(tl.x, br.x, tl.y, br.y);
 }
 
-fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return distance_from_clip_rect_impl(position, clip_bounds);
+// The coverage of a rounded content mask at a position in device pixels. Fragments outside of
+// the mask's bounds are already discarded using their clip distances, so only its corners
+// matter here.
+fn content_mask_coverage(position: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center_to_point = position - bounds.origin - half_size;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = length(max(corner_center_to_point, vec2<f32>(0.0))) - corner_radius;
+    return select(saturate(0.5 - distance), 1.0, corner_radius == 0.0);
 }
 
 // Signed distance of the point to the quad's border - positive outside the
@@ -418,22 +461,15 @@ fn vs_quad(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) insta
     let quad = b_quads[instance_id];
 
     var out = QuadVarying();
-    out.position = to_device_position(unit_vertex, quad.bounds);
-
-    let gradient = prepare_gradient_color(
-        quad.background.tag,
-        quad.background.color_space,
-        quad.background.solid,
-        quad.background.color0,
-        quad.background.color1
-    );
-    
-    out.background_solid = gradient.solid;
-    out.background_color0 = gradient.color0;
-    out.background_color1 = gradient.color1;
+    let local_position = unit_vertex * quad.bounds.size + quad.bounds.origin;
+    let position = transform_position(local_position, quad.transformation);
+    out.position = to_device_position_impl(position);
+    out.local_position = local_position;
+
+    out.background_solid = hsla_to_rgba(quad.background.solid);
     out.border_color = hsla_to_rgba(quad.border_color);
     out.quad_id = instance_id;
-    out.clip_distances = distance_from_clip_rect(unit_vertex, quad.bounds, quad.content_mask);
+    out.clip_distances = distance_from_clip_rect_impl(position, quad.content_mask.bounds);
     return out;
 }
 
@@ -445,9 +481,11 @@ fn fs_quad(input: QuadVarying) -> @location(0) vec4<f32> {
     }
 
     let quad = b_quads[input.quad_id];
+    let mask_coverage = content_mask_coverage(input.position.xy, quad.content_mask.bounds,
+        quad.content_mask.corner_radii);
 
-    let background_color = gradient_color(quad.background, input.position.xy, quad.bounds,
-        input.background_solid, input.background_color0, input.background_color1);
+    let background_color = gradient_color(quad.background, input.local_position, quad.bounds,
+        input.background_solid);
 
     let unrounded = quad.corner_radii.top_left == 0.0 &&
         quad.corner_radii.bottom_left == 0.0 &&
@@ -460,12 +498,12 @@ fn fs_quad(input: QuadVarying) -> @location(0) vec4<f32> {
             quad.border_widths.right == 0.0 &&
             quad.border_widths.bottom == 0.0 &&
             unrounded) {
-        return blend_color(background_color, 1.0);
+        return blend_color(background_color, mask_coverage);
     }
 
     let size = quad.bounds.size;
     let half_size = size / 2.0;
-    let point = input.position.xy - quad.bounds.origin;
+    let point = input.local_position - quad.bounds.origin;
     let center_to_point = point - half_size;
 
     // Signed distance field threshold for inclusion of pixels. 0.5 is the
@@ -526,7 +564,7 @@ fn fs_quad(input: QuadVarying) -> @location(0) vec4<f32> {
     // However, that might negatively impact performance in the case of
     // reasonable sizes for rounded corners.
     if (is_within_inner_straight_border && !is_near_rounded_corner) {
-        return blend_color(background_color, 1.0);
+        return blend_color(background_color, mask_coverage);
     }
 
     // Signed distance of the point to the outside edge of the quad's border. It
@@ -765,5 +803,5 @@ fn fs_quad(input: QuadVarying) -> @location(0) vec4<f32> {
                     saturate(antialias_threshold - inner_sdf));
     }
 
-    return blend_color(color, saturate(antialias_threshold - outer_sdf));
+    return blend_color(color, saturate(antialias_threshold - outer_sdf) * mask_coverage);
 }
\ No newline at end of file
@@ -0,0 +1,128 @@
+// The prelude of every shader quad pipeline. The user's source is appended to it, and has to
+// define `fn shade(input: ShaderQuadInput) -> vec4<f32>`, returning a straight alpha color.
+
+struct Globals {
+    viewport_size: vec2<f32>,
+    premultiplied_alpha: u32,
+    pad: u32,
+}
+
+struct Bounds {
+    origin: vec2<f32>,
+    size: vec2<f32>,
+}
+
+struct Corners {
+    top_left: f32,
+    top_right: f32,
+    bottom_right: f32,
+    bottom_left: f32,
+}
+
+struct ContentMask {
+    bounds: Bounds,
+    corner_radii: Corners,
+}
+
+struct ShaderQuad {
+    bounds: Bounds,
+    content_mask: ContentMask,
+    time: f32,
+    pad: vec3<f32>,
+    uniforms: array<vec4<f32>, 4>,
+}
+
+// What `shade` is given for each pixel of the quad.
+struct ShaderQuadInput {
+    // The position of the pixel in device pixels, from the quad's top left corner.
+    position: vec2<f32>,
+    // The position of the pixel relative to the quad's size, from (0, 0) to (1, 1).
+    uv: vec2<f32>,
+    // The size of the quad in device pixels.
+    size: vec2<f32>,
+    // The seconds elapsed since the window's renderer was created.
+    time: f32,
+    // The uniforms the quad was painted with, four to a vector.
+    uniforms: array<vec4<f32>, 4>,
+}
+
+struct ShaderQuadVarying {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) quad_id: u32,
+    @location(1) clip_distances: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var<storage, read> b_shader_quads: array<ShaderQuad>;
+
+fn to_device_position(position: vec2<f32>) -> vec4<f32> {
+    let device_position = position / globals.viewport_size * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    return vec4<f32>(device_position, 0.0, 1.0);
+}
+
+fn pick_corner_radius(center_to_point: vec2<f32>, radii: Corners) -> f32 {
+    if (center_to_point.x < 0.0) {
+        if (center_to_point.y < 0.0) {
+            return radii.top_left;
+        } else {
+            return radii.bottom_left;
+        }
+    } else {
+        if (center_to_point.y < 0.0) {
+            return radii.top_right;
+        } else {
+            return radii.bottom_right;
+        }
+    }
+}
+
+// The coverage of a rounded content mask at a position in device pixels. Fragments outside of
+// the mask's bounds are already discarded using their clip distances, so only its corners
+// matter here.
+fn content_mask_coverage(position: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center_to_point = position - bounds.origin - half_size;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = length(max(corner_center_to_point, vec2<f32>(0.0))) - corner_radius;
+    return select(saturate(0.5 - distance), 1.0, corner_radius == 0.0);
+}
+
+@vertex
+fn vs_shader_quad(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) instance_id: u32) -> ShaderQuadVarying {
+    let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
+    let quad = b_shader_quads[instance_id];
+    let position = unit_vertex * quad.bounds.size + quad.bounds.origin;
+
+    let clip_origin = quad.content_mask.bounds.origin;
+    let clip_size = quad.content_mask.bounds.size;
+    let tl = position - clip_origin;
+    let br = clip_origin + clip_size - position;
+
+    var out = ShaderQuadVarying();
+    out.position = to_device_position(position);
+    out.quad_id = instance_id;
+    out.clip_distances = vec4<f32>(tl.x, br.x, tl.y, br.y);
+    return out;
+}
+
+@fragment
+fn fs_shader_quad(input: ShaderQuadVarying) -> @location(0) vec4<f32> {
+    if (any(input.clip_distances < vec4<f32>(0.0))) {
+        return vec4<f32>(0.0);
+    }
+
+    let quad = b_shader_quads[input.quad_id];
+    var shader_input = ShaderQuadInput();
+    shader_input.position = input.position.xy - quad.bounds.origin;
+    shader_input.uv = shader_input.position / quad.bounds.size;
+    shader_input.size = quad.bounds.size;
+    shader_input.time = quad.time;
+    shader_input.uniforms = quad.uniforms;
+
+    let color = shade(shader_input);
+    let alpha = color.a * content_mask_coverage(input.position.xy, quad.content_mask.bounds,
+        quad.content_mask.corner_radii);
+    let multiplier = select(1.0, alpha, globals.premultiplied_alpha != 0u);
+    return vec4<f32>(color.rgb * multiplier, alpha);
+}
@@ -25,13 +25,24 @@ struct Corners {
     bottom_left: f32,
 }
 
+struct ContentMask {
+    bounds: Bounds,
+    corner_radii: Corners,
+}
+
+struct TransformationMatrix {
+    rotation_scale: mat2x2<f32>,
+    translation: vec2<f32>,
+}
+
 struct Shadow {
     order: u32,
     blur_radius: f32,
     bounds: Bounds,
     corner_radii: Corners,
-    content_mask: Bounds,
+    content_mask: ContentMask,
     color: Hsla,
+    transformation: TransformationMatrix,
 }
 
 struct ShadowVarying {
@@ -39,6 +50,7 @@ struct ShadowVarying {
     @location(0) @interpolate(flat) color: vec4<f32>,
     @location(1) @interpolate(flat) shadow_id: u32,
     @location(3) clip_distances: vec4<f32>,
+    @location(4) local_position: vec2<f32>,
 }
 
 @group(0) @binding(0) var<uniform> globals: Globals;
@@ -49,9 +61,9 @@ fn to_device_position_impl(position: vec2<f32>) -> vec4<f32> {
     return vec4<f32>(device_position, 0.0, 1.0);
 }
 
-fn to_device_position(unit_vertex: vec2<f32>, bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return to_device_position_impl(position);
+// The Rust side stores the rotation and scale row-major, so it's transposed here.
+fn transform_position(position: vec2<f32>, transform: TransformationMatrix) -> vec2<f32> {
+    return transpose(transform.rotation_scale) * position + transform.translation;
 }
 
 fn hsla_to_rgba(hsla: Hsla) -> vec4<f32> {
@@ -94,9 +106,16 @@ fn distance_from_clip_rect_impl(position: vec2<f32>, clip_bounds: Bounds) -> vec
     return vec4<f32>(tl.x, br.x, tl.y, br.y);
 }
 
-fn distance_from_clip_rect(unit_vertex: vec2<f32>, bounds: Bounds, clip_bounds: Bounds) -> vec4<f32> {
-    let position = unit_vertex * vec2<f32>(bounds.size) + bounds.origin;
-    return distance_from_clip_rect_impl(position, clip_bounds);
+// The coverage of a rounded content mask at a position in device pixels. Fragments outside of
+// the mask's bounds are already discarded using their clip distances, so only its corners
+// matter here.
+fn content_mask_coverage(position: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center_to_point = position - bounds.origin - half_size;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = length(max(corner_center_to_point, vec2<f32>(0.0))) - corner_radius;
+    return select(saturate(0.5 - distance), 1.0, corner_radius == 0.0);
 }
 
 fn blend_color(color: vec4<f32>, alpha_factor: f32) -> vec4<f32> {
@@ -150,10 +169,13 @@ fn vs_shadow(@builtin(vertex_index) vertex_id: u32, @builtin(instance_index) ins
     shadow.bounds.size += 2.0 * vec2<f32>(margin);
 
     var out = ShadowVarying();
-    out.position = to_device_position(unit_vertex, shadow.bounds);
+    let local_position = unit_vertex * shadow.bounds.size + shadow.bounds.origin;
+    let position = transform_position(local_position, shadow.transformation);
+    out.position = to_device_position_impl(position);
+    out.local_position = local_position;
     out.color = hsla_to_rgba(shadow.color);
     out.shadow_id = instance_id;
-    out.clip_distances = distance_from_clip_rect(unit_vertex, shadow.bounds, shadow.content_mask);
+    out.clip_distances = distance_from_clip_rect_impl(position, shadow.content_mask.bounds);
     return out;
 }
 
@@ -166,7 +188,7 @@ fn fs_shadow(input: ShadowVarying) -> @location(0) vec4<f32> {
     let shadow = b_shadows[input.shadow_id];
     let half_size = shadow.bounds.size / 2.0;
     let center = shadow.bounds.origin + half_size;
-    let center_to_point = input.position.xy - center;
+    let center_to_point = input.local_position - center;
 
     let corner_radius = pick_corner_radius(center_to_point, shadow.corner_radii);
 
@@ -185,5 +207,7 @@ fn fs_shadow(input: ShadowVarying) -> @location(0) vec4<f32> {
         y += step;
     }
 
+    alpha *= content_mask_coverage(input.position.xy, shadow.content_mask.bounds,
+        shadow.content_mask.corner_radii);
     return blend_color(input.color, alpha);
 }
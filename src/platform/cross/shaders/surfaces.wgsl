@@ -9,9 +9,17 @@ struct Bounds {
     size: vec2<f32>,
 }
 
+struct Corners {
+    top_left: f32,
+    top_right: f32,
+    bottom_right: f32,
+    bottom_left: f32,
+}
+
 struct SurfaceParams {
     bounds: Bounds,
     content_mask: Bounds,
+    content_mask_corner_radii: Corners,
 }
 
 struct SurfaceVarying {
@@ -30,6 +38,34 @@ fn to_device_position(position: vec2<f32>) -> vec4<f32> {
     return vec4<f32>(device_position, 0.0, 1.0);
 }
 
+fn pick_corner_radius(center_to_point: vec2<f32>, radii: Corners) -> f32 {
+    if (center_to_point.x < 0.0) {
+        if (center_to_point.y < 0.0) {
+            return radii.top_left;
+        } else {
+            return radii.bottom_left;
+        }
+    } else {
+        if (center_to_point.y < 0.0) {
+            return radii.top_right;
+        } else {
+            return radii.bottom_right;
+        }
+    }
+}
+
+// The coverage of a rounded content mask at a position in device pixels. Fragments outside of
+// the mask's bounds are already discarded using their clip distances, so only its corners
+// matter here.
+fn content_mask_coverage(position: vec2<f32>, bounds: Bounds, corner_radii: Corners) -> f32 {
+    let half_size = bounds.size / 2.0;
+    let center_to_point = position - bounds.origin - half_size;
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = abs(center_to_point) - half_size + corner_radius;
+    let distance = length(max(corner_center_to_point, vec2<f32>(0.0))) - corner_radius;
+    return select(saturate(0.5 - distance), 1.0, corner_radius == 0.0);
+}
+
 @vertex
 fn vs_surface(@builtin(vertex_index) vertex_id: u32) -> SurfaceVarying {
     let unit_vertex = vec2<f32>(f32(vertex_id & 1u), 0.5 * f32(vertex_id & 2u));
@@ -54,7 +90,8 @@ fn fs_surface(input: SurfaceVarying) -> @location(0) vec4<f32> {
     }
 
     let color = textureSample(t_surface, s_surface, input.tex_coord);
-    let alpha = color.a;
+    let alpha = color.a * content_mask_coverage(input.position.xy, params.content_mask,
+        params.content_mask_corner_radii);
     let multiplier = select(1.0, alpha, globals.premultiplied_alpha != 0u);
     return vec4<f32>(color.rgb * multiplier, alpha);
 }
@@ -0,0 +1,164 @@
+use std::{borrow::Cow, sync::Arc};
+
+use collections::FxHashMap;
+use parking_lot::Mutex;
+
+use crate::{
+    AtlasKey, AtlasTextureId, AtlasTextureKind, AtlasTile, Bounds, DevicePixels, PlatformAtlas,
+    Point, Size, TileId,
+};
+
+/// An atlas that keeps its tiles in CPU memory, for the software renderer to sample from.
+///
+/// Every tile gets a texture of its own, so there's no packing to do and removing a tile
+/// frees its memory right away.
+pub(crate) struct SoftwareAtlas(Mutex<SoftwareAtlasState>);
+
+struct SoftwareAtlasState {
+    next_texture_index: u32,
+    tiles_by_key: FxHashMap<AtlasKey, AtlasTile>,
+    textures: FxHashMap<AtlasTextureId, Arc<SoftwareTexture>>,
+}
+
+/// The texels of a single atlas texture, one byte per texel for monochrome textures and four
+/// for polychrome ones.
+pub(crate) struct SoftwareTexture {
+    pub(crate) size: Size<DevicePixels>,
+    pub(crate) bytes_per_texel: usize,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl SoftwareTexture {
+    /// Returns the texel at the given coordinates, clamped to the texture's edges, with every
+    /// channel normalized to `0..=1`. Monochrome texels are returned in the red channel.
+    pub(crate) fn texel(&self, x: i32, y: i32) -> [f32; 4] {
+        let x = x.clamp(0, self.size.width.0 - 1) as usize;
+        let y = y.clamp(0, self.size.height.0 - 1) as usize;
+        let offset = (y * self.size.width.0 as usize + x) * self.bytes_per_texel;
+        let mut texel = [0.0, 0.0, 0.0, 1.0];
+        for (channel, byte) in texel
+            .iter_mut()
+            .zip(&self.bytes[offset..offset + self.bytes_per_texel])
+        {
+            *channel = *byte as f32 / 255.0;
+        }
+        texel
+    }
+}
+
+impl SoftwareAtlas {
+    pub(crate) fn new() -> Self {
+        SoftwareAtlas(Mutex::new(SoftwareAtlasState {
+            next_texture_index: 0,
+            tiles_by_key: FxHashMap::default(),
+            textures: FxHashMap::default(),
+        }))
+    }
+
+    pub(crate) fn texture(&self, texture_id: AtlasTextureId) -> Option<Arc<SoftwareTexture>> {
+        self.0.lock().textures.get(&texture_id).cloned()
+    }
+}
+
+impl PlatformAtlas for SoftwareAtlas {
+    fn get_or_insert_with<'a>(
+        &self,
+        key: &AtlasKey,
+        build: &mut dyn FnMut() -> anyhow::Result<Option<(Size<DevicePixels>, Cow<'a, [u8]>)>>,
+    ) -> anyhow::Result<Option<AtlasTile>> {
+        if let Some(tile) = self.0.lock().tiles_by_key.get(key) {
+            return Ok(Some(tile.clone()));
+        }
+
+        let Some((size, bytes)) = build()? else {
+            return Ok(None);
+        };
+
+        let kind = key.texture_kind();
+        let bytes_per_texel = match kind {
+            AtlasTextureKind::Monochrome => 1,
+            AtlasTextureKind::Polychrome => 4,
+        };
+        let expected_len = size.width.0.max(0) as usize * size.height.0.max(0) as usize;
+        if size.width.0 <= 0 || size.height.0 <= 0 || bytes.len() < expected_len * bytes_per_texel {
+            anyhow::bail!(
+                "invalid atlas tile of size {size:?} with {} bytes",
+                bytes.len()
+            );
+        }
+
+        // NOTE(mdeand): The lock isn't held while building, so another thread may have inserted
+        // NOTE(mdeand): the tile in the meantime. Keeping theirs leaves nothing orphaned.
+        let mut atlas = self.0.lock();
+        if let Some(tile) = atlas.tiles_by_key.get(key) {
+            return Ok(Some(tile.clone()));
+        }
+        let texture_id = AtlasTextureId {
+            index: atlas.next_texture_index,
+            kind,
+        };
+        atlas.next_texture_index += 1;
+
+        let tile = AtlasTile {
+            texture_id,
+            tile_id: TileId(texture_id.index),
+            padding: 0,
+            bounds: Bounds {
+                origin: Point::default(),
+                size,
+            },
+        };
+        atlas.textures.insert(
+            texture_id,
+            Arc::new(SoftwareTexture {
+                size,
+                bytes_per_texel,
+                bytes: bytes.into_owned(),
+            }),
+        );
+        atlas.tiles_by_key.insert(key.clone(), tile.clone());
+
+        Ok(Some(tile))
+    }
+
+    fn remove(&self, key: &AtlasKey) {
+        let mut atlas = self.0.lock();
+        if let Some(tile) = atlas.tiles_by_key.remove(key) {
+            atlas.textures.remove(&tile.texture_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImageId, RenderImageParams, size};
+
+    #[test]
+    fn test_tiles_inserted_while_building_are_kept() {
+        let atlas = SoftwareAtlas::new();
+        let key = AtlasKey::Image(RenderImageParams {
+            image_id: ImageId(0),
+            frame_index: 0,
+        });
+        let bytes = vec![0; 4];
+        let mut build = || {
+            Ok(Some((
+                size(DevicePixels(1), DevicePixels(1)),
+                Cow::Borrowed(&bytes[..]),
+            )))
+        };
+
+        // NOTE(mdeand): Inserting the same tile while building it stands in for another
+        // NOTE(mdeand): thread winning the race.
+        let mut inner_tile = None;
+        let tile = atlas
+            .get_or_insert_with(&key, &mut || {
+                inner_tile = atlas.get_or_insert_with(&key, &mut build)?;
+                build()
+            })
+            .unwrap();
+        assert_eq!(tile, inner_tile);
+        assert_eq!(atlas.0.lock().textures.len(), 1);
+    }
+}
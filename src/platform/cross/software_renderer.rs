@@ -0,0 +1,2057 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::channel::oneshot;
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    BlendMode, Blur, BorderStyle, Bounds, ContentMask, Corners, DevicePixels, GpuSpecs, Hsla,
+    Layer, LinearColorStop, MonochromeSprite, Path, PolychromeSprite, PrimitiveBatch, Quad,
+    ScaledPixels, Scene, SceneBackground, Shadow, Size, TransformationMatrix, Underline,
+    color::{BackgroundTag, ColorSpace},
+    platform::cross::{
+        renderer::RenderingParameters,
+        software_atlas::{SoftwareAtlas, SoftwareTexture},
+    },
+};
+
+const M_PI_F: f32 = std::f32::consts::PI;
+
+/// The sample count used for paths when `ZED_PATH_SAMPLE_COUNT` isn't set, matching the
+/// windows' wgpu renderers.
+const DEFAULT_PATH_SAMPLE_COUNT: u32 = 4;
+
+type Color = [f32; 4];
+type Vec2 = [f32; 2];
+
+/// Renders scenes on the CPU into an RGBA image.
+///
+/// Every primitive is shaded with the same math as its WGSL shader in `shaders/`, so frames
+/// match what the wgpu renderer draws into an `Rgba8Unorm` texture, up to rounding. That makes
+/// this both a fallback for machines without any adapter, and a reference to test the wgpu
+/// pipelines against.
+pub(crate) struct SoftwareRenderer {
+    atlas: Arc<SoftwareAtlas>,
+    frame: RgbaImage,
+    // NOTE(mdeand): Holds premultiplied colors, like the intermediate texture paths are
+    // NOTE(mdeand): rasterized into on the GPU.
+    path_intermediate: Vec<Color>,
+    /// The gradient stops of the scene being drawn, which its layers use as well.
+    gradient_stops: Vec<LinearColorStop>,
+    rendering_parameters: RenderingParameters,
+}
+
+impl SoftwareRenderer {
+    pub fn new(atlas: Arc<SoftwareAtlas>, width: u32, height: u32) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        Self {
+            atlas,
+            frame: RgbaImage::new(width, height),
+            path_intermediate: vec![[0.0; 4]; width as usize * height as usize],
+            gradient_stops: Vec::new(),
+            rendering_parameters: RenderingParameters::from_env(DEFAULT_PATH_SAMPLE_COUNT),
+        }
+    }
+
+    pub fn update_drawable_size(&mut self, size: Size<DevicePixels>) {
+        let width = size.width.0.max(1) as u32;
+        let height = size.height.0.max(1) as u32;
+        if self.frame.dimensions() != (width, height) {
+            self.frame = RgbaImage::new(width, height);
+            self.path_intermediate = vec![[0.0; 4]; width as usize * height as usize];
+        }
+    }
+
+    /// The most recently drawn frame, with straight alpha.
+    pub fn frame(&self) -> &RgbaImage {
+        &self.frame
+    }
+
+    pub fn draw(&mut self, scene: &Scene) {
+        for pixel in self.frame.pixels_mut() {
+            *pixel = Rgba([0, 0, 0, 255]);
+        }
+        self.gradient_stops.clone_from(&scene.gradient_stops);
+        self.draw_batches(scene);
+    }
+
+    fn draw_batches(&mut self, scene: &Scene) {
+        for batch in scene.batches() {
+            match batch {
+                PrimitiveBatch::Shadows(shadows) => {
+                    for shadow in shadows {
+                        self.draw_shadow(shadow);
+                    }
+                }
+                PrimitiveBatch::Blurs(blurs) => {
+                    for blur in blurs {
+                        self.draw_blur(blur);
+                    }
+                }
+                PrimitiveBatch::Quads(quads) => {
+                    for quad in quads {
+                        self.draw_quad(quad);
+                    }
+                }
+                PrimitiveBatch::Paths(paths) => self.draw_paths(paths),
+                PrimitiveBatch::Underlines(underlines) => {
+                    for underline in underlines {
+                        self.draw_underline(underline);
+                    }
+                }
+                PrimitiveBatch::MonochromeSprites {
+                    texture_id,
+                    sprites,
+                } => {
+                    let Some(texture) = self.atlas.texture(texture_id) else {
+                        log::error!("missing atlas texture {texture_id:?}");
+                        continue;
+                    };
+                    for sprite in sprites {
+                        self.draw_mono_sprite(sprite, &texture);
+                    }
+                }
+                PrimitiveBatch::PolychromeSprites {
+                    texture_id,
+                    sprites,
+                } => {
+                    let Some(texture) = self.atlas.texture(texture_id) else {
+                        log::error!("missing atlas texture {texture_id:?}");
+                        continue;
+                    };
+                    for sprite in sprites {
+                        self.draw_poly_sprite(sprite, &texture);
+                    }
+                }
+                // NOTE(mdeand): Surfaces are textures owned by other GPU APIs, there's nothing
+                // NOTE(mdeand): to read them back from here.
+                PrimitiveBatch::Surfaces(_) => {}
+                // NOTE(mdeand): Shader quads run user WGSL, which only a GPU can execute.
+                PrimitiveBatch::ShaderQuads(_) => {}
+                PrimitiveBatch::Layers(layers) => {
+                    for layer in layers {
+                        self.draw_layer(layer);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the scene and resolves the returned receiver with the resulting frame, mirroring
+    /// [`WgpuRenderer::capture_frame`](super::renderer::WgpuRenderer::capture_frame).
+    pub fn capture_frame(&mut self, scene: &Scene) -> oneshot::Receiver<Result<RgbaImage>> {
+        self.draw(scene);
+        let (sender, receiver) = oneshot::channel();
+        let _ = sender.send(Ok(self.frame.clone()));
+        receiver
+    }
+
+    pub fn gpu_specs(&self) -> GpuSpecs {
+        GpuSpecs {
+            is_software_emulated: true,
+            device_name: "gpui software renderer".to_string(),
+            driver_name: String::new(),
+            driver_info: String::new(),
+        }
+    }
+
+    /// Runs `shade` for the center of every pixel covered by `rect`, blending the colors it
+    /// returns into the frame.
+    fn fill_rect(&mut self, rect: Rect, mut shade: impl FnMut(Vec2) -> Color) {
+        let (width, height) = self.frame.dimensions();
+        let (x_range, y_range) = rect.pixel_ranges(width, height);
+        for y in y_range {
+            for x in x_range.clone() {
+                let color = shade([x as f32 + 0.5, y as f32 + 0.5]);
+                blend_over(self.frame.get_pixel_mut(x, y), color);
+            }
+        }
+    }
+
+    /// Runs `shade` for the center of every pixel covered by `rect` once `transformation` maps
+    /// it onto the frame, with the center mapped back into the space of `rect`. Pixels outside
+    /// of `mask` are skipped, and pixels on its rounded corners fade out.
+    fn fill_transformed_rect(
+        &mut self,
+        rect: Rect,
+        transformation: &TransformationMatrix,
+        mask: Mask,
+        mut shade: impl FnMut(Vec2) -> Color,
+    ) {
+        if *transformation == TransformationMatrix::unit() {
+            self.fill_rect(rect, |position| mask.apply(position, || shade(position)));
+            return;
+        }
+
+        // NOTE(mdeand): The shaders transform the rectangle's corners, this maps every pixel in
+        // NOTE(mdeand): the transformed rectangle's bounding box back onto it instead.
+        let Some(inverse) = transformation.inverse() else {
+            return;
+        };
+        let corners = [
+            rect.origin,
+            [rect.origin[0] + rect.size[0], rect.origin[1]],
+            [rect.origin[0], rect.origin[1] + rect.size[1]],
+            [rect.origin[0] + rect.size[0], rect.origin[1] + rect.size[1]],
+        ]
+        .map(|corner| transform_point(transformation, corner));
+        let min = corners
+            .iter()
+            .fold([f32::MAX; 2], |min, p| [min[0].min(p[0]), min[1].min(p[1])]);
+        let max = corners
+            .iter()
+            .fold([f32::MIN; 2], |max, p| [max[0].max(p[0]), max[1].max(p[1])]);
+        let transformed_rect = Rect {
+            origin: min,
+            size: [max[0] - min[0], max[1] - min[1]],
+        };
+
+        self.fill_rect(transformed_rect, |position| {
+            let local_position = transform_point(&inverse, position);
+            if !rect.contains(local_position) {
+                return [0.0; 4];
+            }
+            mask.apply(position, || shade(local_position))
+        });
+    }
+
+    /// Draws the layer's scene into a transparent frame of its own, then composites it onto
+    /// the frame, like `layers.wgsl`.
+    fn draw_layer(&mut self, layer: &Layer) {
+        let (width, height) = self.frame.dimensions();
+        let below = std::mem::replace(&mut self.frame, RgbaImage::new(width, height));
+        self.draw_batches(&layer.scene);
+        let mut layer_frame = std::mem::replace(&mut self.frame, below);
+        if layer.blur_radius.0 > 0.0 {
+            Self::blur_layer_frame(&mut layer_frame, layer);
+        }
+        let backdrop = (layer.blend_mode != BlendMode::Normal).then(|| self.frame.clone());
+        // NOTE(mdeand): The clip path is rasterized like any other path, its coverage is the alpha
+        // NOTE(mdeand): of the intermediate buffer, which has 8 bits per channel.
+        let mask_coverage = layer.mask.as_ref().map(|mask| {
+            self.rasterize_paths(std::slice::from_ref(mask));
+            self.path_intermediate
+                .iter()
+                .map(|sample| (saturate(sample[3]) * 255.0).round() / 255.0)
+                .collect::<Vec<_>>()
+        });
+
+        // NOTE(mdeand): Blending into a transparent frame leaves premultiplied colors behind.
+        self.fill_rect(Rect::from(&layer.bounds), |position| {
+            let [x, y] = position.map(|coordinate| coordinate as u32);
+            let texel = load_texel(&layer_frame, x, y);
+            if texel[3] == 0.0 {
+                return [0.0; 4];
+            }
+            let mut color = [
+                texel[0] / texel[3],
+                texel[1] / texel[3],
+                texel[2] / texel[3],
+            ];
+            if let Some(backdrop) = &backdrop {
+                let backdrop = load_texel(backdrop, x, y);
+                if backdrop[3] > 0.0 {
+                    color = [0, 1, 2].map(|ix| {
+                        let blended =
+                            blend_channel(layer.blend_mode, backdrop[ix] / backdrop[3], color[ix]);
+                        color[ix] + (blended - color[ix]) * backdrop[3]
+                    });
+                }
+            }
+            let coverage = mask_coverage
+                .as_ref()
+                .map_or(1.0, |coverage| coverage[(y * width + x) as usize]);
+            [
+                color[0],
+                color[1],
+                color[2],
+                texel[3] * layer.opacity * coverage,
+            ]
+        });
+    }
+
+    /// Blurs the whole pixels covering the layer in place, like the wgpu renderer.
+    fn blur_layer_frame(layer_frame: &mut RgbaImage, layer: &Layer) {
+        let (width, height) = layer_frame.dimensions();
+        let bounds = Rect::from(&layer.bounds);
+        let left = bounds.origin[0].floor().max(0.0);
+        let top = bounds.origin[1].floor().max(0.0);
+        let right = (bounds.origin[0] + bounds.size[0]).ceil().min(width as f32);
+        let bottom = (bounds.origin[1] + bounds.size[1])
+            .ceil()
+            .min(height as f32);
+        if right <= left || bottom <= top {
+            return;
+        }
+        let (left, top) = (left as u32, top as u32);
+        let region_width = right as u32 - left;
+        let region_height = bottom as u32 - top;
+
+        let blurred = gaussian_blur(
+            layer_frame,
+            [left, top, region_width, region_height],
+            layer.blur_radius.0,
+        );
+        for (ix, color) in blurred.into_iter().enumerate() {
+            let x = left + ix as u32 % region_width;
+            let y = top + ix as u32 / region_width;
+            layer_frame.get_pixel_mut(x, y).0 =
+                color.map(|channel| (saturate(channel) * 255.0).round() as u8);
+        }
+    }
+
+    fn draw_quad(&mut self, quad: &Quad) {
+        let bounds = Rect::from(&quad.bounds);
+        let mask = Mask::from(&quad.content_mask);
+        let gradient = prepare_gradient_color(&quad.background, &self.gradient_stops);
+        let border_color = hsla_to_rgba(quad.border_color);
+        self.fill_transformed_rect(bounds, &quad.transformation, mask, |position| {
+            fs_quad(quad, bounds, &gradient, border_color, position)
+        });
+    }
+
+    fn draw_shadow(&mut self, shadow: &Shadow) {
+        let bounds = Rect::from(&shadow.bounds);
+        let mask = Mask::from(&shadow.content_mask);
+        let blur_radius = shadow.blur_radius.0;
+        let margin = 3.0 * blur_radius;
+        let color = hsla_to_rgba(shadow.color);
+        let corner_radii = corners(&shadow.corner_radii);
+        let transformation = &shadow.transformation;
+        self.fill_transformed_rect(bounds.dilate(margin), transformation, mask, |position| {
+            let half_size = [bounds.size[0] / 2.0, bounds.size[1] / 2.0];
+            let center = [
+                bounds.origin[0] + half_size[0],
+                bounds.origin[1] + half_size[1],
+            ];
+            let center_to_point = [position[0] - center[0], position[1] - center[1]];
+            let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+
+            let low = center_to_point[1] - half_size[1];
+            let high = center_to_point[1] + half_size[1];
+            let start = (-3.0 * blur_radius).clamp(low, high);
+            let end = (3.0 * blur_radius).clamp(low, high);
+
+            let step = (end - start) / 4.0;
+            let mut y = start + step * 0.5;
+            let mut alpha = 0.0;
+            for _ in 0..4 {
+                let blur = blur_along_x(
+                    center_to_point[0],
+                    center_to_point[1] - y,
+                    blur_radius,
+                    corner_radius,
+                    half_size,
+                );
+                alpha += blur * gaussian(y, blur_radius) * step;
+                y += step;
+            }
+
+            blend_color(color, alpha)
+        });
+    }
+
+    /// Blurs the pixels behind the blur's bounds with a separable Gaussian and draws them back
+    /// within its rounded corners.
+    ///
+    /// The GPU blurs large radii at a lower resolution, so frames only match it closely for
+    /// radii of a few pixels.
+    fn draw_blur(&mut self, blur: &Blur) {
+        let bounds = Rect::from(&blur.bounds);
+        let mask = Mask::from(&blur.content_mask);
+        let corner_radii = corners(&blur.corner_radii);
+        let sigma = blur.radius.0;
+        let (width, height) = self.frame.dimensions();
+
+        // NOTE(mdeand): Like the wgpu renderer, only the whole pixels covered by both the
+        // NOTE(mdeand): bounds and the content mask are sampled, clamping at their edges.
+        let left = bounds.origin[0].max(mask.rect.origin[0]).floor().max(0.0);
+        let top = bounds.origin[1].max(mask.rect.origin[1]).floor().max(0.0);
+        let right = (bounds.origin[0] + bounds.size[0])
+            .min(mask.rect.origin[0] + mask.rect.size[0])
+            .ceil()
+            .min(width as f32);
+        let bottom = (bounds.origin[1] + bounds.size[1])
+            .min(mask.rect.origin[1] + mask.rect.size[1])
+            .ceil()
+            .min(height as f32);
+        if sigma <= 0.0 || right <= left || bottom <= top {
+            return;
+        }
+        let (left, top) = (left as u32, top as u32);
+        let region_width = right as usize - left as usize;
+        let region_height = bottom as usize - top as usize;
+        let blurred = gaussian_blur(
+            &self.frame,
+            [left, top, region_width as u32, region_height as u32],
+            sigma,
+        );
+
+        self.fill_rect(bounds, |position| {
+            let coverage = mask.coverage(position);
+            if coverage == 0.0 {
+                return [0.0; 4];
+            }
+            let alpha = saturate(0.5 - quad_sdf(position, bounds, corner_radii)) * coverage;
+            let x = (position[0] as usize)
+                .saturating_sub(left as usize)
+                .min(region_width - 1);
+            let y = (position[1] as usize)
+                .saturating_sub(top as usize)
+                .min(region_height - 1);
+            let color = blurred[y * region_width + x];
+            [color[0], color[1], color[2], color[3] * alpha]
+        });
+    }
+
+    fn draw_underline(&mut self, underline: &Underline) {
+        const WAVE_FREQUENCY: f32 = 2.0;
+        const WAVE_HEIGHT_RATIO: f32 = 0.8;
+
+        let bounds = Rect::from(&underline.bounds);
+        let mask = Mask::from(&underline.content_mask);
+        let color = hsla_to_rgba(underline.color);
+        let thickness = underline.thickness.0;
+        let transformation = &underline.transformation;
+        self.fill_transformed_rect(bounds, transformation, mask, |position| {
+            if (underline.wavy & 0xFF) == 0 {
+                return blend_color(color, color[3]);
+            }
+
+            let half_thickness = thickness * 0.5;
+            let height = bounds.size[1];
+            let st = [
+                (position[0] - bounds.origin[0]) / height,
+                (position[1] - bounds.origin[1]) / height - 0.5,
+            ];
+            let frequency = M_PI_F * WAVE_FREQUENCY * thickness / height;
+            let amplitude = (thickness * WAVE_HEIGHT_RATIO) / height;
+
+            let sine = (st[0] * frequency).sin() * amplitude;
+            let d_sine = (st[0] * frequency).cos() * amplitude * frequency;
+            let distance = (st[1] - sine) / (1.0 + d_sine * d_sine).sqrt();
+            let distance_in_pixels = distance * height;
+            let distance_from_top_border = distance_in_pixels - half_thickness;
+            let distance_from_bottom_border = distance_in_pixels + half_thickness;
+            let alpha =
+                saturate(0.5 - (-distance_from_bottom_border).max(distance_from_top_border));
+            blend_color(color, alpha * color[3])
+        });
+    }
+
+    fn draw_mono_sprite(&mut self, sprite: &MonochromeSprite, texture: &SoftwareTexture) {
+        let bounds = Rect::from(&sprite.bounds);
+        let mask = Mask::from(&sprite.content_mask);
+        let color = hsla_to_rgba(sprite.color);
+        let tile = &sprite.tile.bounds;
+        let tile_origin = [tile.origin.x.0 as f32, tile.origin.y.0 as f32];
+        let tile_size = [tile.size.width.0 as f32, tile.size.height.0 as f32];
+        let gamma_ratios = self.rendering_parameters.gamma_ratios;
+        let enhanced_contrast = self.rendering_parameters.grayscale_enhanced_contrast;
+
+        let transformation = &sprite.transformation;
+        self.fill_transformed_rect(bounds, transformation, mask, |position| {
+            let unit = [
+                (position[0] - bounds.origin[0]) / bounds.size[0],
+                (position[1] - bounds.origin[1]) / bounds.size[1],
+            ];
+            let tile_position = [
+                tile_origin[0] + unit[0] * tile_size[0],
+                tile_origin[1] + unit[1] * tile_size[1],
+            ];
+            let sample = sample_bilinear(texture, tile_position)[0];
+            let alpha_corrected =
+                apply_contrast_and_gamma_correction(sample, color, enhanced_contrast, gamma_ratios);
+            blend_color(color, alpha_corrected)
+        });
+    }
+
+    fn draw_poly_sprite(&mut self, sprite: &PolychromeSprite, texture: &SoftwareTexture) {
+        const GRAYSCALE_FACTORS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+        let bounds = Rect::from(&sprite.bounds);
+        let mask = Mask::from(&sprite.content_mask);
+        let corner_radii = corners(&sprite.corner_radii);
+        let tile = &sprite.tile.bounds;
+        let tile_origin = [tile.origin.x.0 as f32, tile.origin.y.0 as f32];
+        let tile_size = [tile.size.width.0 as f32, tile.size.height.0 as f32];
+
+        let transformation = &sprite.transformation;
+        self.fill_transformed_rect(bounds, transformation, mask, |position| {
+            let unit = [
+                (position[0] - bounds.origin[0]) / bounds.size[0],
+                (position[1] - bounds.origin[1]) / bounds.size[1],
+            ];
+            let tile_position = [
+                tile_origin[0] + unit[0] * tile_size[0],
+                tile_origin[1] + unit[1] * tile_size[1],
+            ];
+            // NOTE(mdeand): Texels are read exactly as the wgpu atlas uploads them, so both
+            // NOTE(mdeand): renderers agree on the channel order of sprite data.
+            let sample = sample_bilinear(texture, tile_position);
+            let distance = quad_sdf(position, bounds, corner_radii);
+
+            let mut color = sample;
+            if sprite.grayscale {
+                let grayscale = color[0] * GRAYSCALE_FACTORS[0]
+                    + color[1] * GRAYSCALE_FACTORS[1]
+                    + color[2] * GRAYSCALE_FACTORS[2];
+                color = [grayscale, grayscale, grayscale, sample[3]];
+            }
+            blend_color(color, sprite.opacity * saturate(0.5 - distance))
+        });
+    }
+
+    /// Rasterizes a batch of paths into the intermediate buffer, then composites them onto the
+    /// frame, as the wgpu renderer does with its intermediate texture.
+    fn draw_paths(&mut self, paths: &[Path<ScaledPixels>]) {
+        let (width, height) = self.frame.dimensions();
+        self.rasterize_paths(paths);
+
+        // NOTE(mdeand): Same as the wgpu renderer, each pixel must only be composited once, so
+        // NOTE(mdeand): paths of different draw orders are copied through a single rect.
+        let sprites = if paths.first().map(|path| path.order) == paths.last().map(|path| path.order)
+        {
+            paths
+                .iter()
+                .map(|path| Rect::from(&path.clipped_bounds()))
+                .collect::<Vec<_>>()
+        } else {
+            let mut bounds = paths[0].clipped_bounds();
+            for path in &paths[1..] {
+                bounds = bounds.union(&path.clipped_bounds());
+            }
+            vec![Rect::from(&bounds)]
+        };
+
+        for sprite in sprites {
+            let (x_range, y_range) = sprite.pixel_ranges(width, height);
+            for y in y_range {
+                for x in x_range.clone() {
+                    // NOTE(mdeand): The intermediate texture has 8 bits per channel.
+                    let sample = self.path_intermediate[(y * width + x) as usize]
+                        .map(|channel| (saturate(channel) * 255.0).round() / 255.0);
+                    let color = if sample[3] == 0.0 {
+                        sample
+                    } else {
+                        [
+                            sample[0] / sample[3],
+                            sample[1] / sample[3],
+                            sample[2] / sample[3],
+                            sample[3],
+                        ]
+                    };
+                    blend_over(self.frame.get_pixel_mut(x, y), color);
+                }
+            }
+        }
+    }
+
+    /// Rasterizes the paths into the intermediate buffer, which holds premultiplied colors.
+    fn rasterize_paths(&mut self, paths: &[Path<ScaledPixels>]) {
+        let (width, height) = self.frame.dimensions();
+        self.path_intermediate.fill([0.0; 4]);
+        let sample_offsets = sample_offsets(self.rendering_parameters.path_sample_count);
+
+        for path in paths {
+            let clipped_bounds = Rect::from(&path.clipped_bounds());
+            let mask = Mask::from(&path.content_mask);
+            let gradient = prepare_gradient_color(&path.color, &self.gradient_stops);
+            for triangle in path.vertices.chunks_exact(3) {
+                let xy = [0, 1, 2].map(|ix| {
+                    let position = &triangle[ix].xy_position;
+                    [position.x.0, position.y.0]
+                });
+                let st = [0, 1, 2].map(|ix| {
+                    let position = &triangle[ix].st_position;
+                    [position.x, position.y]
+                });
+                rasterize_triangle(
+                    &mut self.path_intermediate,
+                    width,
+                    height,
+                    xy,
+                    st,
+                    sample_offsets,
+                    |position, st_position, dx, dy| {
+                        if clipped_bounds.clip_test(position) {
+                            return [0.0; 4];
+                        }
+                        // NOTE(mdeand): The intermediate buffer holds premultiplied colors, so
+                        // NOTE(mdeand): every channel is scaled by the mask's coverage.
+                        let coverage = mask.coverage(position);
+                        fs_path_rasterization(
+                            &path.color,
+                            &gradient,
+                            clipped_bounds,
+                            position,
+                            st_position,
+                            dx,
+                            dy,
+                        )
+                        .map(|channel| channel * coverage)
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// An axis aligned rectangle in device pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rect {
+    origin: Vec2,
+    size: Vec2,
+}
+
+/// A content mask, whose rectangle may have rounded corners.
+#[derive(Clone, Copy)]
+struct Mask {
+    rect: Rect,
+    corner_radii: [f32; 4],
+}
+
+impl From<&ContentMask<ScaledPixels>> for Mask {
+    fn from(content_mask: &ContentMask<ScaledPixels>) -> Self {
+        Mask {
+            rect: Rect::from(&content_mask.bounds),
+            corner_radii: corners(&content_mask.corner_radii),
+        }
+    }
+}
+
+impl Mask {
+    /// The coverage of the mask at the point, like `content_mask_coverage` in the shaders.
+    fn coverage(&self, position: Vec2) -> f32 {
+        if self.rect.clip_test(position) {
+            return 0.0;
+        }
+        let half_size = [self.rect.size[0] / 2.0, self.rect.size[1] / 2.0];
+        let center_to_point = [
+            position[0] - self.rect.origin[0] - half_size[0],
+            position[1] - self.rect.origin[1] - half_size[1],
+        ];
+        let corner_radius = pick_corner_radius(center_to_point, self.corner_radii);
+        if corner_radius == 0.0 {
+            return 1.0;
+        }
+        let corner_center_to_point = [
+            center_to_point[0].abs() - half_size[0] + corner_radius,
+            center_to_point[1].abs() - half_size[1] + corner_radius,
+        ];
+        let distance = length([
+            corner_center_to_point[0].max(0.0),
+            corner_center_to_point[1].max(0.0),
+        ]) - corner_radius;
+        saturate(0.5 - distance)
+    }
+
+    /// Runs `shade` for a point covered by the mask, fading its color by the coverage.
+    fn apply(&self, position: Vec2, shade: impl FnOnce() -> Color) -> Color {
+        let coverage = self.coverage(position);
+        if coverage == 0.0 {
+            return [0.0; 4];
+        }
+        let color = shade();
+        [color[0], color[1], color[2], color[3] * coverage]
+    }
+}
+
+impl From<&Bounds<ScaledPixels>> for Rect {
+    fn from(bounds: &Bounds<ScaledPixels>) -> Self {
+        Rect {
+            origin: [bounds.origin.x.0, bounds.origin.y.0],
+            size: [bounds.size.width.0, bounds.size.height.0],
+        }
+    }
+}
+
+impl Rect {
+    fn dilate(self, amount: f32) -> Self {
+        Rect {
+            origin: [self.origin[0] - amount, self.origin[1] - amount],
+            size: [self.size[0] + 2.0 * amount, self.size[1] + 2.0 * amount],
+        }
+    }
+
+    /// The pixels whose centers are covered by the rectangle, following the same top-left
+    /// rule as GPU rasterizers.
+    fn pixel_ranges(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> (std::ops::Range<u32>, std::ops::Range<u32>) {
+        let range = |start: f32, end: f32, limit: u32| {
+            let start = (start - 0.5).ceil().clamp(0.0, limit as f32) as u32;
+            let end = (end - 0.5).ceil().clamp(0.0, limit as f32) as u32;
+            start..end.max(start)
+        };
+        (
+            range(self.origin[0], self.origin[0] + self.size[0], width),
+            range(self.origin[1], self.origin[1] + self.size[1], height),
+        )
+    }
+
+    /// Whether the point is within this rectangle, including its top and left edges.
+    fn contains(&self, position: Vec2) -> bool {
+        (self.origin[0]..self.origin[0] + self.size[0]).contains(&position[0])
+            && (self.origin[1]..self.origin[1] + self.size[1]).contains(&position[1])
+    }
+
+    /// Whether the point is outside of this rectangle, when used as a content mask.
+    fn clip_test(&self, position: Vec2) -> bool {
+        position[0] - self.origin[0] < 0.0
+            || self.origin[0] + self.size[0] - position[0] < 0.0
+            || position[1] - self.origin[1] < 0.0
+            || self.origin[1] + self.size[1] - position[1] < 0.0
+    }
+}
+
+/// Applies the transformation to a point, as the shaders do.
+fn transform_point(transformation: &TransformationMatrix, point: Vec2) -> Vec2 {
+    let [[a, b], [c, d]] = transformation.rotation_scale;
+    let [x, y] = transformation.translation;
+    [
+        a * point[0] + b * point[1] + x,
+        c * point[0] + d * point[1] + y,
+    ]
+}
+
+/// Corner radii in the order top left, top right, bottom right, bottom left.
+fn corners(corners: &Corners<ScaledPixels>) -> [f32; 4] {
+    [
+        corners.top_left.0,
+        corners.top_right.0,
+        corners.bottom_right.0,
+        corners.bottom_left.0,
+    ]
+}
+
+/// Blends a straight alpha color over a pixel, like `wgpu::BlendState::ALPHA_BLENDING`.
+fn blend_over(pixel: &mut Rgba<u8>, color: Color) {
+    if color.iter().any(|channel| !channel.is_finite()) {
+        return;
+    }
+    let color = color.map(saturate);
+    let alpha = color[3];
+    let destination = pixel.0.map(|channel| channel as f32 / 255.0);
+    let blended = [
+        color[0] * alpha + destination[0] * (1.0 - alpha),
+        color[1] * alpha + destination[1] * (1.0 - alpha),
+        color[2] * alpha + destination[2] * (1.0 - alpha),
+        alpha + destination[3] * (1.0 - alpha),
+    ];
+    pixel.0 = blended.map(|channel| (saturate(channel) * 255.0).round() as u8);
+}
+
+fn load_texel(image: &RgbaImage, x: u32, y: u32) -> Color {
+    image
+        .get_pixel(x, y)
+        .0
+        .map(|channel| channel as f32 / 255.0)
+}
+
+/// The separable blend functions of the W3C compositing spec, like `blend_channel` in
+/// `layers.wgsl`.
+/// Blurs the pixels of `frame` within `[left, top, width, height]` with a Gaussian of the given
+/// standard deviation, clamping at the edges of the region, and returns the region row by row.
+fn gaussian_blur(
+    frame: &RgbaImage,
+    [left, top, region_width, region_height]: [u32; 4],
+    sigma: f32,
+) -> Vec<Color> {
+    let (region_width, region_height) = (region_width as usize, region_height as usize);
+
+    let mut region = Vec::with_capacity(region_width * region_height);
+    for y in 0..region_height {
+        for x in 0..region_width {
+            let pixel = frame.get_pixel(left + x as u32, top + y as u32);
+            region.push(pixel.0.map(|channel| channel as f32 / 255.0));
+        }
+    }
+
+    let half_width = (3.0 * sigma).ceil() as isize;
+    let weights = (-half_width..=half_width)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let total_weight: f32 = weights.iter().sum();
+    let blur_pass = |source: &[Color], step: [isize; 2]| {
+        let mut blurred = vec![[0.0; 4]; source.len()];
+        for y in 0..region_height as isize {
+            for x in 0..region_width as isize {
+                let mut color = [0.0; 4];
+                for (i, weight) in (-half_width..=half_width).zip(&weights) {
+                    let sample_x = (x + step[0] * i).clamp(0, region_width as isize - 1);
+                    let sample_y = (y + step[1] * i).clamp(0, region_height as isize - 1);
+                    let sample = source[sample_y as usize * region_width + sample_x as usize];
+                    for (channel, sample) in color.iter_mut().zip(sample) {
+                        *channel += sample * weight;
+                    }
+                }
+                blurred[y as usize * region_width + x as usize] =
+                    color.map(|channel| channel / total_weight);
+            }
+        }
+        blurred
+    };
+    blur_pass(&blur_pass(&region, [1, 0]), [0, 1])
+}
+
+fn blend_channel(blend_mode: BlendMode, backdrop: f32, source: f32) -> f32 {
+    fn hard_light(backdrop: f32, source: f32) -> f32 {
+        if source <= 0.5 {
+            return backdrop * 2.0 * source;
+        }
+        let screened = 2.0 * source - 1.0;
+        backdrop + screened - backdrop * screened
+    }
+
+    match blend_mode {
+        BlendMode::Normal => source,
+        BlendMode::Multiply => backdrop * source,
+        BlendMode::Screen => backdrop + source - backdrop * source,
+        BlendMode::Overlay => hard_light(source, backdrop),
+        BlendMode::Darken => backdrop.min(source),
+        BlendMode::Lighten => backdrop.max(source),
+        BlendMode::ColorDodge => {
+            if backdrop == 0.0 {
+                0.0
+            } else if source >= 1.0 {
+                1.0
+            } else {
+                (backdrop / (1.0 - source)).min(1.0)
+            }
+        }
+        BlendMode::ColorBurn => {
+            if backdrop >= 1.0 {
+                1.0
+            } else if source == 0.0 {
+                0.0
+            } else {
+                1.0 - ((1.0 - backdrop) / source).min(1.0)
+            }
+        }
+        BlendMode::HardLight => hard_light(backdrop, source),
+        BlendMode::SoftLight => {
+            if source <= 0.5 {
+                return backdrop - (1.0 - 2.0 * source) * backdrop * (1.0 - backdrop);
+            }
+            let d = if backdrop <= 0.25 {
+                ((16.0 * backdrop - 12.0) * backdrop + 4.0) * backdrop
+            } else {
+                backdrop.sqrt()
+            };
+            backdrop + (2.0 * source - 1.0) * (d - backdrop)
+        }
+        BlendMode::Difference => (backdrop - source).abs(),
+        BlendMode::Exclusion => backdrop + source - 2.0 * backdrop * source,
+    }
+}
+
+fn saturate(value: f32) -> f32 {
+    value.clamp(0.0, 1.0)
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    [0, 1, 2, 3].map(|ix| a[ix] * (1.0 - t) + b[ix] * t)
+}
+
+fn length(v: Vec2) -> f32 {
+    (v[0] * v[0] + v[1] * v[1]).sqrt()
+}
+
+/// WGSL's `%`, which takes the sign of the dividend, just like Rust's.
+fn fmod(a: f32, b: f32) -> f32 {
+    a - b * (a / b).trunc()
+}
+
+fn hsla_to_rgba(hsla: Hsla) -> Color {
+    let h = hsla.h * 6.0;
+    let s = hsla.s;
+    let l = hsla.l;
+    let a = hsla.a;
+
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h % 2.0) - 1.0).abs());
+    let m = l - c / 2.0;
+    let mut color = [m, m, m];
+
+    if (0.0..1.0).contains(&h) {
+        color[0] += c;
+        color[1] += x;
+    } else if (1.0..2.0).contains(&h) {
+        color[0] += x;
+        color[1] += c;
+    } else if (2.0..3.0).contains(&h) {
+        color[1] += c;
+        color[2] += x;
+    } else if (3.0..4.0).contains(&h) {
+        color[1] += x;
+        color[2] += c;
+    } else if (4.0..5.0).contains(&h) {
+        color[0] += x;
+        color[2] += c;
+    } else {
+        color[0] += c;
+        color[2] += x;
+    }
+
+    [color[0], color[1], color[2], a]
+}
+
+fn blend_color(color: Color, alpha_factor: f32) -> Color {
+    // NOTE(mdeand): Frames are rendered with straight alpha, so the shaders'
+    // NOTE(mdeand): `premultiplied_alpha` flag is always off here.
+    [color[0], color[1], color[2], color[3] * alpha_factor]
+}
+
+fn srgb_to_linear(srgb: f32) -> f32 {
+    if srgb < 0.04045 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(linear: f32) -> f32 {
+    if linear < 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn srgba_to_linear(color: Color) -> Color {
+    [
+        srgb_to_linear(color[0]),
+        srgb_to_linear(color[1]),
+        srgb_to_linear(color[2]),
+        color[3],
+    ]
+}
+
+fn linear_to_srgba(color: Color) -> Color {
+    [
+        linear_to_srgb(color[0]),
+        linear_to_srgb(color[1]),
+        linear_to_srgb(color[2]),
+        color[3],
+    ]
+}
+
+fn oklab_to_linear_srgb(color: Color) -> Color {
+    let l_ = color[0] + 0.3963377774 * color[1] + 0.2158037573 * color[2];
+    let m_ = color[0] - 0.1055613458 * color[1] - 0.0638541728 * color[2];
+    let s_ = color[0] - 0.0894841775 * color[1] - 1.2914855480 * color[2];
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+        color[3],
+    ]
+}
+
+fn linear_srgb_to_oklab(color: Color) -> Color {
+    let l = 0.4122214708 * color[0] + 0.5363325363 * color[1] + 0.0514459929 * color[2];
+    let m = 0.2119034982 * color[0] + 0.6806995451 * color[1] + 0.1073969566 * color[2];
+    let s = 0.0883024619 * color[0] + 0.2817188376 * color[1] + 0.6299787005 * color[2];
+
+    let l_ = l.powf(1.0 / 3.0);
+    let m_ = m.powf(1.0 / 3.0);
+    let s_ = s.powf(1.0 / 3.0);
+
+    [
+        0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_,
+        1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_,
+        0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_,
+        color[3],
+    ]
+}
+
+/// The colors derived from a background once per primitive, before interpolating them per
+/// fragment. Gradient stops are converted into the color space they're interpolated in.
+struct GradientColor {
+    solid: Color,
+    stops: Vec<(Color, f32)>,
+}
+
+fn prepare_gradient_color(
+    background: &SceneBackground,
+    gradient_stops: &[LinearColorStop],
+) -> GradientColor {
+    let stops = background
+        .stops(gradient_stops)
+        .iter()
+        .map(|stop| {
+            let color = hsla_to_rgba(stop.color);
+            let color = match background.color_space {
+                ColorSpace::Srgb => linear_to_srgba(color),
+                ColorSpace::Oklab => linear_srgb_to_oklab(color),
+            };
+            (color, stop.percentage)
+        })
+        .collect();
+
+    GradientColor {
+        solid: hsla_to_rgba(background.solid),
+        stops,
+    }
+}
+
+/// Interpolates between the two color stops surrounding `t`, clamping to the first and last
+/// stops outside of their range.
+fn gradient_stops_color(background: &SceneBackground, gradient: &GradientColor, t: f32) -> Color {
+    let Some(&first) = gradient.stops.first() else {
+        return [0.0; 4];
+    };
+
+    let mut start = first;
+    let mut end = first;
+    for &stop in &gradient.stops[1..] {
+        start = end;
+        end = stop;
+        if t <= end.1 {
+            break;
+        }
+    }
+
+    let span = end.1 - start.1;
+    let stop_t = if span > 0.0 {
+        ((t - start.1) / span).clamp(0.0, 1.0)
+    } else if t >= end.1 {
+        1.0
+    } else {
+        0.0
+    };
+
+    let color = mix(start.0, end.0, stop_t);
+    match background.color_space {
+        ColorSpace::Srgb => srgba_to_linear(color),
+        ColorSpace::Oklab => oklab_to_linear_srgb(color),
+    }
+}
+
+fn gradient_color(
+    background: &SceneBackground,
+    gradient: &GradientColor,
+    position: Vec2,
+    bounds: Rect,
+) -> Color {
+    match background.tag {
+        BackgroundTag::Solid => gradient.solid,
+        BackgroundTag::LinearGradient => {
+            // -90 degrees to match the CSS gradient angle.
+            let angle = background.gradient_angle_or_pattern_height;
+            let radians = (fmod(angle, 360.0) - 90.0) * M_PI_F / 180.0;
+            let mut direction = [radians.cos(), radians.sin()];
+
+            // Expand the short side to be the same as the long side
+            if bounds.size[0] > bounds.size[1] {
+                direction[1] *= bounds.size[1] / bounds.size[0];
+            } else {
+                direction[0] *= bounds.size[0] / bounds.size[1];
+            }
+
+            let half_size = [bounds.size[0] / 2.0, bounds.size[1] / 2.0];
+            let center_to_point = [
+                position[0] - bounds.origin[0] - half_size[0],
+                position[1] - bounds.origin[1] - half_size[1],
+            ];
+            let mut t = (center_to_point[0] * direction[0] + center_to_point[1] * direction[1])
+                / length(direction);
+            if direction[0].abs() > direction[1].abs() {
+                t = (t + half_size[0]) / bounds.size[0];
+            } else {
+                t = (t + half_size[1]) / bounds.size[1];
+            }
+
+            gradient_stops_color(background, gradient, t)
+        }
+        BackgroundTag::RadialGradient => {
+            // An ellipse that follows the aspect ratio of the bounds and reaches the last stop
+            // at the farthest corner.
+            let center = background.gradient_center;
+            let relative_position = [
+                (position[0] - bounds.origin[0]) / bounds.size[0],
+                (position[1] - bounds.origin[1]) / bounds.size[1],
+            ];
+            let farthest_corner = [center.x.max(1.0 - center.x), center.y.max(1.0 - center.y)];
+            let t = length([
+                relative_position[0] - center.x,
+                relative_position[1] - center.y,
+            ]) / length(farthest_corner);
+            gradient_stops_color(background, gradient, t)
+        }
+        BackgroundTag::ConicGradient => {
+            // Clockwise from the top.
+            let center_to_point = [
+                position[0] - bounds.origin[0] - background.gradient_center.x * bounds.size[0],
+                position[1] - bounds.origin[1] - background.gradient_center.y * bounds.size[1],
+            ];
+            let angle = center_to_point[0].atan2(-center_to_point[1]) * 180.0 / M_PI_F;
+            let t = ((angle - background.gradient_angle_or_pattern_height) / 360.0).rem_euclid(1.0);
+            gradient_stops_color(background, gradient, t)
+        }
+        BackgroundTag::PatternSlash => {
+            let gradient_angle_or_pattern_height = background.gradient_angle_or_pattern_height;
+            let pattern_width = (gradient_angle_or_pattern_height / 65535.0) / 255.0;
+            let pattern_interval = fmod(gradient_angle_or_pattern_height, 65535.0) / 255.0;
+            let pattern_height = pattern_width + pattern_interval;
+            let stripe_angle = M_PI_F / 4.0;
+            let pattern_period = pattern_height * stripe_angle.sin();
+            let relative_position = [
+                position[0] - bounds.origin[0],
+                position[1] - bounds.origin[1],
+            ];
+            let rotated_x = stripe_angle.cos() * relative_position[0]
+                + stripe_angle.sin() * relative_position[1];
+            let pattern = fmod(rotated_x, pattern_period);
+            let distance = pattern.min(pattern_period - pattern)
+                - pattern_period * (pattern_width / pattern_height) / 2.0;
+            let mut color = gradient.solid;
+            color[3] *= saturate(0.5 - distance);
+            color
+        }
+    }
+}
+
+fn pick_corner_radius(center_to_point: Vec2, radii: [f32; 4]) -> f32 {
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    if center_to_point[0] < 0.0 {
+        if center_to_point[1] < 0.0 {
+            top_left
+        } else {
+            bottom_left
+        }
+    } else if center_to_point[1] < 0.0 {
+        top_right
+    } else {
+        bottom_right
+    }
+}
+
+fn quad_sdf(point: Vec2, bounds: Rect, corner_radii: [f32; 4]) -> f32 {
+    let half_size = [bounds.size[0] / 2.0, bounds.size[1] / 2.0];
+    let center_to_point = [
+        point[0] - bounds.origin[0] - half_size[0],
+        point[1] - bounds.origin[1] - half_size[1],
+    ];
+    let corner_radius = pick_corner_radius(center_to_point, corner_radii);
+    let corner_center_to_point = [
+        center_to_point[0].abs() - half_size[0] + corner_radius,
+        center_to_point[1].abs() - half_size[1] + corner_radius,
+    ];
+    quad_sdf_impl(corner_center_to_point, corner_radius)
+}
+
+fn quad_sdf_impl(corner_center_to_point: Vec2, corner_radius: f32) -> f32 {
+    if corner_radius == 0.0 {
+        corner_center_to_point[0].max(corner_center_to_point[1])
+    } else {
+        let signed_distance_to_inset_quad = length([
+            corner_center_to_point[0].max(0.0),
+            corner_center_to_point[1].max(0.0),
+        ]) + corner_center_to_point[0]
+            .max(corner_center_to_point[1])
+            .min(0.0);
+        signed_distance_to_inset_quad - corner_radius
+    }
+}
+
+fn quarter_ellipse_sdf(point: Vec2, radii: Vec2) -> f32 {
+    let circle_vec = [point[0] / radii[0], point[1] / radii[1]];
+    let unit_circle_sdf = length(circle_vec) - 1.0;
+    unit_circle_sdf * (radii[0] + radii[1]) * -0.5
+}
+
+fn corner_dash_velocity(dv1: f32, dv2: f32) -> f32 {
+    if dv1 == 0.0 {
+        dv2
+    } else if dv2 == 0.0 {
+        dv1
+    } else {
+        dv1.min(dv2)
+    }
+}
+
+fn dash_alpha(t: f32, period: f32, length: f32, dash_velocity: f32, threshold: f32) -> f32 {
+    let half_period = period / 2.0;
+    let half_length = length / 2.0;
+    let centered = fmod(t + half_period - half_length, period) - half_period;
+    let signed_distance = centered.abs() - half_length;
+    saturate(threshold - signed_distance / dash_velocity)
+}
+
+fn over(below: Color, above: Color) -> Color {
+    let alpha = above[3] + below[3] * (1.0 - above[3]);
+    let channel =
+        |ix: usize| (above[ix] * above[3] + below[ix] * below[3] * (1.0 - above[3])) / alpha;
+    [channel(0), channel(1), channel(2), alpha]
+}
+
+/// The body of `fs_quad`, for a fragment that passed the content mask test.
+fn fs_quad(
+    quad: &Quad,
+    bounds: Rect,
+    gradient: &GradientColor,
+    input_border_color: Color,
+    position: Vec2,
+) -> Color {
+    let background_color = gradient_color(&quad.background, gradient, position, bounds);
+
+    let radii = corners(&quad.corner_radii);
+    let unrounded = radii.iter().all(|radius| *radius == 0.0);
+    let widths = &quad.border_widths;
+    let (w_t, w_r, w_b, w_l) = (widths.top.0, widths.right.0, widths.bottom.0, widths.left.0);
+
+    // Fast path when the quad is not rounded and doesn't have any border
+    if w_t == 0.0 && w_l == 0.0 && w_r == 0.0 && w_b == 0.0 && unrounded {
+        return blend_color(background_color, 1.0);
+    }
+
+    let size = bounds.size;
+    let half_size = [size[0] / 2.0, size[1] / 2.0];
+    let point = [
+        position[0] - bounds.origin[0],
+        position[1] - bounds.origin[1],
+    ];
+    let center_to_point = [point[0] - half_size[0], point[1] - half_size[1]];
+
+    let antialias_threshold = 0.5;
+
+    let corner_radius = pick_corner_radius(center_to_point, radii);
+
+    let border = [
+        if center_to_point[0] < 0.0 { w_l } else { w_r },
+        if center_to_point[1] < 0.0 { w_t } else { w_b },
+    ];
+
+    let reduced_border = border.map(|width| {
+        if width == 0.0 {
+            -antialias_threshold
+        } else {
+            width
+        }
+    });
+
+    let corner_to_point = [
+        center_to_point[0].abs() - half_size[0],
+        center_to_point[1].abs() - half_size[1],
+    ];
+    let corner_center_to_point = [
+        corner_to_point[0] + corner_radius,
+        corner_to_point[1] + corner_radius,
+    ];
+
+    let is_near_rounded_corner =
+        corner_center_to_point[0] >= 0.0 && corner_center_to_point[1] >= 0.0;
+
+    let straight_border_inner_corner_to_point = [
+        corner_to_point[0] + reduced_border[0],
+        corner_to_point[1] + reduced_border[1],
+    ];
+
+    let is_beyond_inner_straight_border = straight_border_inner_corner_to_point[0] > 0.0
+        || straight_border_inner_corner_to_point[1] > 0.0;
+
+    let is_within_inner_straight_border = straight_border_inner_corner_to_point[0]
+        < -antialias_threshold
+        && straight_border_inner_corner_to_point[1] < -antialias_threshold;
+
+    if is_within_inner_straight_border && !is_near_rounded_corner {
+        return blend_color(background_color, 1.0);
+    }
+
+    let outer_sdf = quad_sdf_impl(corner_center_to_point, corner_radius);
+
+    let inner_sdf = if corner_center_to_point[0] <= 0.0 || corner_center_to_point[1] <= 0.0 {
+        -straight_border_inner_corner_to_point[0].max(straight_border_inner_corner_to_point[1])
+    } else if is_beyond_inner_straight_border {
+        -1.0
+    } else if reduced_border[0] == reduced_border[1] {
+        -(outer_sdf + reduced_border[0])
+    } else {
+        let ellipse_radii = [
+            (corner_radius - reduced_border[0]).max(0.0),
+            (corner_radius - reduced_border[1]).max(0.0),
+        ];
+        quarter_ellipse_sdf(corner_center_to_point, ellipse_radii)
+    };
+
+    let border_sdf = inner_sdf.max(outer_sdf);
+
+    let mut color = background_color;
+    if border_sdf < antialias_threshold {
+        let mut border_color = input_border_color;
+
+        if quad.border_style == BorderStyle::Dashed {
+            let t;
+            let mut max_t;
+            let dash_velocity;
+
+            let dash_length_per_width = 2.0;
+            let dash_gap_per_width = 1.0;
+            let dash_period_per_width = dash_length_per_width + dash_gap_per_width;
+            let dv_numerator = 1.0 / dash_period_per_width;
+
+            if unrounded {
+                let is_horizontal = corner_center_to_point[0] < corner_center_to_point[1];
+                let dashed_border = [w_b.max(w_t), w_r.max(w_l)];
+                let border_width = if is_horizontal {
+                    dashed_border[0]
+                } else {
+                    dashed_border[1]
+                };
+                dash_velocity = dv_numerator / border_width;
+                t = if is_horizontal { point[0] } else { point[1] } * dash_velocity;
+                max_t = if is_horizontal { size[0] } else { size[1] } * dash_velocity;
+            } else {
+                let [r_tl, r_tr, r_br, r_bl] = radii;
+
+                let side_velocity = |width: f32| {
+                    if width <= 0.0 {
+                        0.0
+                    } else {
+                        dv_numerator / width
+                    }
+                };
+                let dv_t = side_velocity(w_t);
+                let dv_r = side_velocity(w_r);
+                let dv_b = side_velocity(w_b);
+                let dv_l = side_velocity(w_l);
+
+                let s_t = (size[0] - r_tl - r_tr) * dv_t;
+                let s_r = (size[1] - r_tr - r_br) * dv_r;
+                let s_b = (size[0] - r_br - r_bl) * dv_b;
+                let s_l = (size[1] - r_bl - r_tl) * dv_l;
+
+                let corner_dash_velocity_tr = corner_dash_velocity(dv_t, dv_r);
+                let corner_dash_velocity_br = corner_dash_velocity(dv_b, dv_r);
+                let corner_dash_velocity_bl = corner_dash_velocity(dv_b, dv_l);
+                let corner_dash_velocity_tl = corner_dash_velocity(dv_t, dv_l);
+
+                let c_tr = r_tr * (M_PI_F / 2.0) * corner_dash_velocity_tr;
+                let c_br = r_br * (M_PI_F / 2.0) * corner_dash_velocity_br;
+                let c_bl = r_bl * (M_PI_F / 2.0) * corner_dash_velocity_bl;
+                let c_tl = r_tl * (M_PI_F / 2.0) * corner_dash_velocity_tl;
+
+                let upto_tr = s_t;
+                let upto_r = upto_tr + c_tr;
+                let upto_br = upto_r + s_r;
+                let upto_b = upto_br + c_br;
+                let upto_bl = upto_b + s_b;
+                let upto_l = upto_bl + c_bl;
+                let upto_tl = upto_l + s_l;
+                max_t = upto_tl + c_tl;
+
+                if is_near_rounded_corner {
+                    let radians = corner_center_to_point[1].atan2(corner_center_to_point[0]);
+                    let corner_t = radians * corner_radius;
+
+                    if center_to_point[0] >= 0.0 {
+                        if center_to_point[1] < 0.0 {
+                            dash_velocity = corner_dash_velocity_tr;
+                            t = upto_r - corner_t * dash_velocity;
+                        } else {
+                            dash_velocity = corner_dash_velocity_br;
+                            t = upto_br + corner_t * dash_velocity;
+                        }
+                    } else if center_to_point[1] >= 0.0 {
+                        dash_velocity = corner_dash_velocity_bl;
+                        t = upto_l - corner_t * dash_velocity;
+                    } else {
+                        dash_velocity = corner_dash_velocity_tl;
+                        t = upto_tl + corner_t * dash_velocity;
+                    }
+                } else {
+                    let is_horizontal = corner_center_to_point[0] < corner_center_to_point[1];
+                    if is_horizontal {
+                        if center_to_point[1] < 0.0 {
+                            dash_velocity = dv_t;
+                            t = (point[0] - r_tl) * dash_velocity;
+                        } else {
+                            dash_velocity = dv_b;
+                            t = upto_bl - (point[0] - r_bl) * dash_velocity;
+                        }
+                    } else if center_to_point[0] < 0.0 {
+                        dash_velocity = dv_l;
+                        t = upto_tl - (point[1] - r_tl) * dash_velocity;
+                    } else {
+                        dash_velocity = dv_r;
+                        t = upto_r + (point[1] - r_tr) * dash_velocity;
+                    }
+                }
+            }
+
+            let dash_length = dash_length_per_width / dash_period_per_width;
+
+            // Straight borders should start and end with a dash.
+            if unrounded {
+                max_t -= dash_length;
+            }
+            if max_t >= 1.0 {
+                let dash_count = max_t.floor();
+                let dash_period = max_t / dash_count;
+                border_color[3] *= dash_alpha(
+                    t,
+                    dash_period,
+                    dash_length,
+                    dash_velocity,
+                    antialias_threshold,
+                );
+            } else if unrounded {
+                let dash_gap = max_t - dash_length;
+                if dash_gap > 0.0 {
+                    let dash_period = dash_length + dash_gap;
+                    border_color[3] *= dash_alpha(
+                        t,
+                        dash_period,
+                        dash_length,
+                        dash_velocity,
+                        antialias_threshold,
+                    );
+                }
+            }
+        }
+
+        let blended_border = over(background_color, border_color);
+        color = mix(
+            background_color,
+            blended_border,
+            saturate(antialias_threshold - inner_sdf),
+        );
+    }
+
+    blend_color(color, saturate(antialias_threshold - outer_sdf))
+}
+
+fn gaussian(x: f32, sigma: f32) -> f32 {
+    (-(x * x) / (2.0 * sigma * sigma)).exp() / ((2.0 * M_PI_F).sqrt() * sigma)
+}
+
+fn erf(v: Vec2) -> Vec2 {
+    v.map(|v| {
+        let s = v.signum() * (v != 0.0) as u8 as f32;
+        let a = v.abs();
+        let r1 = 1.0 + (0.278393 + (0.230389 + (0.000972 + 0.078108 * a) * a) * a) * a;
+        let r2 = r1 * r1;
+        s - s / (r2 * r2)
+    })
+}
+
+fn blur_along_x(x: f32, y: f32, sigma: f32, corner: f32, half_size: Vec2) -> f32 {
+    let delta = (half_size[1] - corner - y.abs()).min(0.0);
+    let curved = half_size[0] - corner + (corner * corner - delta * delta).max(0.0).sqrt();
+    let integral = erf([
+        (x - curved) * (0.5f32.sqrt() / sigma),
+        (x + curved) * (0.5f32.sqrt() / sigma),
+    ])
+    .map(|v| 0.5 + 0.5 * v);
+    integral[1] - integral[0]
+}
+
+fn color_brightness(color: Color) -> f32 {
+    color[0] * 0.30 + color[1] * 0.59 + color[2] * 0.11
+}
+
+fn apply_contrast_and_gamma_correction(
+    sample: f32,
+    color: Color,
+    enhanced_contrast_factor: f32,
+    gamma_ratios: [f32; 4],
+) -> f32 {
+    let brightness = color_brightness(color);
+    let enhanced_contrast = enhanced_contrast_factor * saturate(4.0 * (0.75 - brightness));
+    let contrasted = sample * (enhanced_contrast + 1.0) / (sample * enhanced_contrast + 1.0);
+
+    let brightness_adjustment = gamma_ratios[0] * brightness + gamma_ratios[1];
+    let correction =
+        brightness_adjustment * contrasted + (gamma_ratios[2] * brightness + gamma_ratios[3]);
+    contrasted + contrasted * (1.0 - contrasted) * correction
+}
+
+/// Samples a texture with bilinear filtering at a position in texels, like a linear sampler
+/// with clamped addressing.
+fn sample_bilinear(texture: &SoftwareTexture, position: Vec2) -> Color {
+    let x = position[0] - 0.5;
+    let y = position[1] - 0.5;
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+    let (x0, y0) = (x0 as i32, y0 as i32);
+
+    let top = mix(texture.texel(x0, y0), texture.texel(x0 + 1, y0), fx);
+    let bottom = mix(texture.texel(x0, y0 + 1), texture.texel(x0 + 1, y0 + 1), fx);
+    mix(top, bottom, fy)
+}
+
+/// The standard multisample positions, as offsets from the pixel center.
+fn sample_offsets(sample_count: u32) -> &'static [Vec2] {
+    const ONE: [Vec2; 1] = [[0.0, 0.0]];
+    const TWO: [Vec2; 2] = [[0.25, 0.25], [-0.25, -0.25]];
+    const FOUR: [Vec2; 4] = [
+        [-0.125, -0.375],
+        [0.375, -0.125],
+        [-0.375, 0.125],
+        [0.125, 0.375],
+    ];
+    const EIGHT: [Vec2; 8] = [
+        [0.0625, -0.1875],
+        [-0.0625, 0.1875],
+        [0.3125, 0.0625],
+        [-0.1875, -0.3125],
+        [-0.3125, 0.3125],
+        [-0.4375, -0.0625],
+        [0.1875, 0.4375],
+        [0.4375, -0.4375],
+    ];
+
+    match sample_count {
+        0 | 1 => &ONE,
+        2 | 3 => &TWO,
+        4..=7 => &FOUR,
+        _ => &EIGHT,
+    }
+}
+
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Rasterizes a triangle into a premultiplied buffer, shading each covered pixel once at its
+/// center and weighting the result by the fraction of samples the triangle covers, like a
+/// multisampled render target that's resolved afterwards.
+fn rasterize_triangle(
+    target: &mut [Color],
+    width: u32,
+    height: u32,
+    xy: [Vec2; 3],
+    st: [Vec2; 3],
+    sample_offsets: &[Vec2],
+    mut shade: impl FnMut(Vec2, Vec2, Vec2, Vec2) -> Color,
+) {
+    let area = edge(xy[0], xy[1], xy[2]);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+
+    // NOTE(mdeand): Normalize the winding, so points inside have positive edge functions.
+    let sign = area.signum();
+    let edges = [(1, 2), (2, 0), (0, 1)];
+    // NOTE(mdeand): Samples exactly on a shared edge belong to just one of the triangles,
+    // NOTE(mdeand): so they're not counted twice.
+    let owns_edge = edges.map(|(a, b)| {
+        let (a, b) = (xy[a], xy[b]);
+        let dy = (b[1] - a[1]) * sign;
+        let dx = (b[0] - a[0]) * sign;
+        dy < 0.0 || (dy == 0.0 && dx > 0.0)
+    });
+    let inside = |p: Vec2| {
+        edges.iter().zip(owns_edge).all(|(&(a, b), owns_edge)| {
+            let w = edge(xy[a], xy[b], p) * sign;
+            w > 0.0 || (w == 0.0 && owns_edge)
+        })
+    };
+
+    // Barycentric weights are affine in the position, so their derivatives are constant.
+    let weight_dx = edges.map(|(a, b)| (xy[a][1] - xy[b][1]) / area);
+    let weight_dy = edges.map(|(a, b)| (xy[b][0] - xy[a][0]) / area);
+    let interpolate = |weights: [f32; 3]| {
+        [0, 1].map(|axis| {
+            weights[0] * st[0][axis] + weights[1] * st[1][axis] + weights[2] * st[2][axis]
+        })
+    };
+    let dx = interpolate(weight_dx);
+    let dy = interpolate(weight_dy);
+
+    let min = [
+        xy[0][0].min(xy[1][0]).min(xy[2][0]),
+        xy[0][1].min(xy[1][1]).min(xy[2][1]),
+    ];
+    let max = [
+        xy[0][0].max(xy[1][0]).max(xy[2][0]),
+        xy[0][1].max(xy[1][1]).max(xy[2][1]),
+    ];
+    let x_range = min[0].floor().max(0.0) as u32..(max[0].ceil().max(0.0) as u32).min(width);
+    let y_range = min[1].floor().max(0.0) as u32..(max[1].ceil().max(0.0) as u32).min(height);
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let center = [x as f32 + 0.5, y as f32 + 0.5];
+            let covered = sample_offsets
+                .iter()
+                .filter(|offset| inside([center[0] + offset[0], center[1] + offset[1]]))
+                .count();
+            if covered == 0 {
+                continue;
+            }
+            let coverage = covered as f32 / sample_offsets.len() as f32;
+
+            let weights = edges.map(|(a, b)| edge(xy[a], xy[b], center) / area);
+            let color = shade(center, interpolate(weights), dx, dy);
+            if color.iter().any(|channel| !channel.is_finite()) {
+                continue;
+            }
+
+            let destination = &mut target[(y * width + x) as usize];
+            let source = color.map(|channel| saturate(channel) * coverage);
+            *destination = [0, 1, 2, 3].map(|ix| source[ix] + destination[ix] * (1.0 - source[3]));
+        }
+    }
+}
+
+/// The body of `fs_path_rasterization`, returning a premultiplied color.
+fn fs_path_rasterization(
+    background: &SceneBackground,
+    gradient: &GradientColor,
+    bounds: Rect,
+    position: Vec2,
+    st_position: Vec2,
+    dx: Vec2,
+    dy: Vec2,
+) -> Color {
+    let alpha = if length([dx[0], dy[0]]) < 0.001 {
+        // Straight edges have a constant `s`, so there is no curve to anti-alias.
+        1.0
+    } else {
+        // Loop-Blinn quadratic curve coverage: `s^2 - t` is negative inside the curve.
+        let gradient = [
+            2.0 * st_position[0] * dx[0] - dx[1],
+            2.0 * st_position[0] * dy[0] - dy[1],
+        ];
+        let f = st_position[0] * st_position[0] - st_position[1];
+        let distance = f / length(gradient);
+        saturate(0.5 - distance)
+    };
+
+    let color = gradient_color(background, gradient, position, bounds);
+    [
+        color[0] * color[3] * alpha,
+        color[1] * color[3] * alpha,
+        color[2] * color[3] * alpha,
+        color[3] * alpha,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ContentMask, DrawOrder, Edges, LayerIsolation, Point, Radians, conic_gradient,
+        linear_color_stop, linear_gradient_with_stops, point, size,
+    };
+
+    fn scaled_bounds(x: f32, y: f32, width: f32, height: f32) -> Bounds<ScaledPixels> {
+        Bounds {
+            origin: point(ScaledPixels(x), ScaledPixels(y)),
+            size: size(ScaledPixels(width), ScaledPixels(height)),
+        }
+    }
+
+    fn renderer(width: u32, height: u32) -> SoftwareRenderer {
+        SoftwareRenderer::new(Arc::new(SoftwareAtlas::new()), width, height)
+    }
+
+    fn solid_quad(bounds: Bounds<ScaledPixels>, color: Hsla) -> Quad {
+        Quad {
+            order: DrawOrder::default(),
+            border_style: BorderStyle::Solid,
+            bounds,
+            content_mask: ContentMask {
+                bounds: scaled_bounds(0.0, 0.0, 1000.0, 1000.0),
+                ..Default::default()
+            },
+            background: color.into(),
+            border_color: Hsla::default(),
+            corner_radii: Corners::default(),
+            border_widths: Edges::default(),
+            transformation: TransformationMatrix::unit(),
+        }
+    }
+
+    #[test]
+    fn test_quads_cover_pixel_centers() {
+        let mut renderer = renderer(8, 8);
+        let red = Hsla {
+            h: 0.0,
+            s: 1.0,
+            l: 0.5,
+            a: 1.0,
+        };
+        renderer.draw_quad(&solid_quad(scaled_bounds(2.0, 2.0, 3.4, 3.6), red));
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(2, 2), &Rgba([255, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(4, 4), &Rgba([255, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(5, 4), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(4, 5), &Rgba([255, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(4, 6), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_content_masks_clip_quads() {
+        let mut renderer = renderer(8, 8);
+        let mut quad = solid_quad(scaled_bounds(0.0, 0.0, 8.0, 8.0), Hsla::white());
+        quad.content_mask.bounds = scaled_bounds(0.0, 0.0, 4.0, 8.0);
+        renderer.draw_quad(&quad);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(3, 3), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(4, 3), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_rounded_content_masks_clip_corners() {
+        let mut renderer = renderer(8, 8);
+        let mut quad = solid_quad(scaled_bounds(0.0, 0.0, 8.0, 8.0), Hsla::white());
+        quad.content_mask.corner_radii = Corners::all(ScaledPixels(4.0));
+        renderer.draw_quad(&quad);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(7, 7), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(3, 3), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(4, 0), &Rgba([246, 246, 246, 255]));
+    }
+
+    #[test]
+    fn test_isolated_layers_fade_overlapping_children_as_a_group() {
+        let red = Hsla {
+            h: 0.0,
+            s: 1.0,
+            l: 0.5,
+            a: 1.0,
+        };
+        let blue = Hsla {
+            h: 2.0 / 3.0,
+            s: 1.0,
+            l: 0.5,
+            a: 1.0,
+        };
+        let mut scene = Scene::default();
+        scene.push_layer(
+            scaled_bounds(0.0, 0.0, 8.0, 8.0),
+            Some(LayerIsolation {
+                opacity: 0.5,
+                blend_mode: BlendMode::Normal,
+                mask: None,
+                blur_radius: ScaledPixels(0.0),
+            }),
+        );
+        scene.insert_primitive(solid_quad(scaled_bounds(0.0, 0.0, 6.0, 8.0), red));
+        scene.insert_primitive(solid_quad(scaled_bounds(2.0, 0.0, 6.0, 8.0), blue));
+        scene.pop_layer();
+        scene.finish();
+
+        let mut renderer = renderer(8, 8);
+        renderer.draw(&scene);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([128, 0, 0, 255]));
+        // The red quad doesn't show through the blue one where they overlap.
+        assert_eq!(frame.get_pixel(4, 0), &Rgba([0, 0, 128, 255]));
+        assert_eq!(frame.get_pixel(7, 0), &Rgba([0, 0, 128, 255]));
+    }
+
+    #[test]
+    fn test_blended_layers_mix_with_the_colors_beneath() {
+        let gray = Hsla {
+            h: 0.0,
+            s: 0.0,
+            l: 0.5,
+            a: 1.0,
+        };
+        let mut scene = Scene::default();
+        scene.insert_primitive(solid_quad(scaled_bounds(0.0, 0.0, 8.0, 8.0), Hsla::white()));
+        scene.push_layer(
+            scaled_bounds(0.0, 0.0, 8.0, 8.0),
+            Some(LayerIsolation {
+                opacity: 1.0,
+                blend_mode: BlendMode::Difference,
+                mask: None,
+                blur_radius: ScaledPixels(0.0),
+            }),
+        );
+        scene.insert_primitive(solid_quad(scaled_bounds(0.0, 0.0, 4.0, 8.0), Hsla::white()));
+        scene.insert_primitive(solid_quad(scaled_bounds(4.0, 0.0, 4.0, 8.0), gray));
+        scene.pop_layer();
+        scene.finish();
+
+        let mut renderer = renderer(8, 8);
+        renderer.draw(&scene);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(7, 0), &Rgba([127, 127, 127, 255]));
+    }
+
+    #[test]
+    fn test_blurred_layers_bleed_past_their_primitives() {
+        let mut scene = Scene::default();
+        scene.push_layer(
+            scaled_bounds(0.0, 0.0, 16.0, 16.0),
+            Some(LayerIsolation {
+                opacity: 1.0,
+                blend_mode: BlendMode::Normal,
+                mask: None,
+                blur_radius: ScaledPixels(1.0),
+            }),
+        );
+        scene.insert_primitive(solid_quad(scaled_bounds(6.0, 6.0, 4.0, 4.0), Hsla::white()));
+        scene.pop_layer();
+        scene.finish();
+        assert_eq!(scene.layers[0].bounds, scaled_bounds(3.0, 3.0, 10.0, 10.0));
+
+        let mut renderer = renderer(16, 16);
+        renderer.draw(&scene);
+
+        let frame = renderer.frame();
+        assert!(frame.get_pixel(5, 7)[0] > 0);
+        assert!(frame.get_pixel(8, 8)[0] < 255);
+        assert_eq!(frame.get_pixel(1, 1), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_transformed_quads_cover_the_transformed_pixels() {
+        let mut renderer = renderer(8, 8);
+        let mut quad = solid_quad(scaled_bounds(0.0, 0.0, 4.0, 2.0), Hsla::white());
+        quad.transformation = TransformationMatrix::unit()
+            .translate(point(ScaledPixels(2.0), ScaledPixels(0.0)))
+            .rotate(Radians(std::f32::consts::FRAC_PI_2));
+        renderer.draw_quad(&quad);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(1, 3), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(3, 1), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(0, 4), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_rounded_corners_are_antialiased() {
+        let mut renderer = renderer(16, 16);
+        let mut quad = solid_quad(scaled_bounds(0.0, 0.0, 16.0, 16.0), Hsla::white());
+        quad.corner_radii = Corners::all(ScaledPixels(8.0));
+        renderer.draw_quad(&quad);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(8, 8), &Rgba([255, 255, 255, 255]));
+        let edge = frame.get_pixel(1, 3).0[0];
+        assert!(
+            edge > 0 && edge < 255,
+            "expected partial coverage, got {edge}"
+        );
+    }
+
+    #[test]
+    fn test_blurs_soften_edges_within_their_bounds() {
+        let mut renderer = renderer(32, 16);
+        renderer.draw_quad(&solid_quad(
+            scaled_bounds(0.0, 0.0, 16.0, 16.0),
+            Hsla::white(),
+        ));
+        renderer.draw_blur(&Blur {
+            order: DrawOrder::default(),
+            radius: ScaledPixels(2.0),
+            bounds: scaled_bounds(8.0, 0.0, 16.0, 16.0),
+            corner_radii: Corners::default(),
+            content_mask: ContentMask {
+                bounds: scaled_bounds(0.0, 0.0, 1000.0, 1000.0),
+                ..Default::default()
+            },
+        });
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(4, 8), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(28, 8), &Rgba([0, 0, 0, 255]));
+        let edge = frame.get_pixel(16, 8).0[0];
+        assert!(
+            edge > 64 && edge < 192,
+            "expected a softened edge, got {edge}"
+        );
+        assert!(frame.get_pixel(10, 8).0[0] > frame.get_pixel(15, 8).0[0]);
+        assert!(frame.get_pixel(16, 8).0[0] > frame.get_pixel(20, 8).0[0]);
+    }
+
+    #[test]
+    fn test_gradients_interpolate_between_stops() {
+        let red = Hsla {
+            h: 0.0,
+            s: 1.0,
+            l: 0.5,
+            a: 1.0,
+        };
+        let blue = Hsla {
+            h: 2.0 / 3.0,
+            s: 1.0,
+            l: 0.5,
+            a: 1.0,
+        };
+        let green = Hsla {
+            h: 1.0 / 3.0,
+            s: 1.0,
+            l: 0.5,
+            a: 1.0,
+        };
+
+        let mut renderer = renderer(16, 1);
+        let mut quad = solid_quad(scaled_bounds(0.0, 0.0, 16.0, 1.0), red);
+        quad.background = SceneBackground::new(
+            &linear_gradient_with_stops(
+                90.0,
+                [
+                    linear_color_stop(red, 0.0),
+                    linear_color_stop(green, 0.5),
+                    linear_color_stop(blue, 1.0),
+                ],
+            ),
+            &mut renderer.gradient_stops,
+        );
+        renderer.draw_quad(&quad);
+        let frame = renderer.frame();
+        let [r, g, b, _] = frame.get_pixel(8, 0).0;
+        assert!(
+            g > r && g > b,
+            "expected green in the middle, got {r} {g} {b}"
+        );
+        assert!(frame.get_pixel(0, 0).0[0] > 200);
+        assert!(frame.get_pixel(15, 0).0[2] > 200);
+
+        // A hard stop halfway around splits the conic gradient into a red right half and a blue
+        // left half.
+        let mut renderer = renderer(16, 16);
+        let mut quad = solid_quad(scaled_bounds(0.0, 0.0, 16.0, 16.0), red);
+        quad.background = SceneBackground::new(
+            &conic_gradient(
+                0.0,
+                point(0.5, 0.5),
+                [
+                    linear_color_stop(red, 0.0),
+                    linear_color_stop(red, 0.5),
+                    linear_color_stop(blue, 0.5),
+                    linear_color_stop(blue, 1.0),
+                ],
+            )
+            .color_space(ColorSpace::Oklab),
+            &mut renderer.gradient_stops,
+        );
+        renderer.draw_quad(&quad);
+        let frame = renderer.frame();
+        let [r, _, b, _] = frame.get_pixel(12, 8).0;
+        assert!(r > 250 && b < 5, "expected red on the right, got {r} {b}");
+        let [r, _, b, _] = frame.get_pixel(3, 8).0;
+        assert!(r < 5 && b > 250, "expected blue on the left, got {r} {b}");
+    }
+
+    #[test]
+    fn test_path_triangles_fill_their_interior() {
+        let mut renderer = renderer(8, 8);
+        let mut path = Path::new(Point::default()).scale(1.0);
+        path.bounds = scaled_bounds(0.0, 0.0, 8.0, 8.0);
+        path.content_mask.bounds = scaled_bounds(0.0, 0.0, 8.0, 8.0);
+        path.color = Hsla::white().into();
+        for (x, y) in [(0.0, 0.0), (8.0, 0.0), (0.0, 8.0)] {
+            path.vertices.push(crate::PathVertex {
+                xy_position: point(ScaledPixels(x), ScaledPixels(y)),
+                st_position: point(0.0, 1.0),
+                content_mask: ContentMask::default(),
+            });
+        }
+        renderer.draw_paths(&[path]);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(1, 1), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(7, 7), &Rgba([0, 0, 0, 255]));
+        let diagonal = frame.get_pixel(3, 4).0[0];
+        assert!(
+            diagonal > 0 && diagonal < 255,
+            "expected partial coverage, got {diagonal}"
+        );
+    }
+
+    #[test]
+    fn test_clip_paths_mask_layers() {
+        let mut mask = Path::new(Point::default()).scale(1.0);
+        mask.bounds = scaled_bounds(0.0, 0.0, 8.0, 8.0);
+        mask.content_mask.bounds = scaled_bounds(0.0, 0.0, 8.0, 8.0);
+        mask.color = Hsla::white().into();
+        for (x, y) in [(0.0, 0.0), (8.0, 0.0), (0.0, 8.0)] {
+            mask.vertices.push(crate::PathVertex {
+                xy_position: point(ScaledPixels(x), ScaledPixels(y)),
+                st_position: point(0.0, 1.0),
+                content_mask: ContentMask::default(),
+            });
+        }
+        let mut scene = Scene::default();
+        scene.push_layer(
+            scaled_bounds(0.0, 0.0, 8.0, 8.0),
+            Some(LayerIsolation {
+                opacity: 1.0,
+                blend_mode: BlendMode::Normal,
+                mask: Some(mask),
+                blur_radius: ScaledPixels(0.0),
+            }),
+        );
+        scene.insert_primitive(solid_quad(scaled_bounds(0.0, 0.0, 8.0, 8.0), Hsla::white()));
+        scene.pop_layer();
+        scene.finish();
+
+        let mut renderer = renderer(8, 8);
+        renderer.draw(&scene);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(1, 1), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(7, 7), &Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_mono_sprites_are_transformed() {
+        let atlas = Arc::new(SoftwareAtlas::new());
+        let mut renderer = SoftwareRenderer::new(atlas.clone(), 8, 8);
+        let texture = SoftwareTexture {
+            size: size(DevicePixels(2), DevicePixels(2)),
+            bytes_per_texel: 1,
+            bytes: vec![255; 4],
+        };
+        let sprite = MonochromeSprite {
+            order: DrawOrder::default(),
+            pad: 0,
+            bounds: scaled_bounds(0.0, 0.0, 2.0, 2.0),
+            content_mask: ContentMask {
+                bounds: scaled_bounds(0.0, 0.0, 8.0, 8.0),
+                ..Default::default()
+            },
+            color: Hsla::white(),
+            tile: crate::AtlasTile {
+                texture_id: crate::AtlasTextureId {
+                    index: 0,
+                    kind: crate::AtlasTextureKind::Monochrome,
+                },
+                tile_id: crate::TileId(0),
+                padding: 0,
+                bounds: Bounds {
+                    origin: Point::default(),
+                    size: texture.size,
+                },
+            },
+            transformation: TransformationMatrix::unit()
+                .translate(point(ScaledPixels(4.0), ScaledPixels(4.0))),
+        };
+        renderer.draw_mono_sprite(&sprite, &texture);
+
+        let frame = renderer.frame();
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(4, 4), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(5, 5), &Rgba([255, 255, 255, 255]));
+        assert_eq!(frame.get_pixel(6, 6), &Rgba([0, 0, 0, 255]));
+    }
+}
@@ -25,26 +25,53 @@ struct DoubleBuffer {
 pub struct SurfaceRegistry {
     surfaces: Mutex<HashMap<SurfaceId, DoubleBuffer>>,
     next_id: AtomicU64,
+    /// The device and queue the surfaces are created on, replaced when the device is lost.
+    device: Mutex<(wgpu::Device, wgpu::Queue)>,
+    /// Incremented every time the surfaces move to a new device.
+    generation: AtomicU64,
 }
 
 impl SurfaceRegistry {
-    pub fn new() -> Self {
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue) -> Self {
         Self {
             surfaces: Mutex::new(HashMap::new()),
             next_id: AtomicU64::new(1),
+            device: Mutex::new((device, queue)),
+            generation: AtomicU64::new(0),
         }
     }
 
+    /// The device surfaces are currently created on.
+    pub fn device(&self) -> wgpu::Device {
+        self.device.lock().unwrap().0.clone()
+    }
+
+    /// The queue of the device surfaces are currently created on.
+    pub fn queue(&self) -> wgpu::Queue {
+        self.device.lock().unwrap().1.clone()
+    }
+
+    /// How many times the surfaces moved to a new device. Anything created with an earlier
+    /// device has to be recreated when this changes.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Move every surface to a new device, after the previous one was lost. The buffers are
+    /// recreated at the same size and format, and their contents are lost.
+    pub fn attach_device(&self, device: wgpu::Device, queue: wgpu::Queue) {
+        let mut surfaces = self.surfaces.lock().unwrap();
+        for db in surfaces.values_mut() {
+            *db = Self::create_double_buffer(&device, db.width, db.height, db.format);
+        }
+        *self.device.lock().unwrap() = (device, queue);
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
     /// Create a new double-buffered surface. Returns its `SurfaceId`.
-    pub fn create(
-        &self,
-        device: &wgpu::Device,
-        width: u32,
-        height: u32,
-        format: wgpu::TextureFormat,
-    ) -> SurfaceId {
+    pub fn create(&self, width: u32, height: u32, format: wgpu::TextureFormat) -> SurfaceId {
         let id = SurfaceId(self.next_id.fetch_add(1, Ordering::Relaxed));
-        let db = Self::create_double_buffer(device, width, height, format);
+        let db = Self::create_double_buffer(&self.device(), width, height, format);
         self.surfaces.lock().unwrap().insert(id, db);
         id
     }
@@ -57,19 +84,14 @@ impl SurfaceRegistry {
     }
 
     /// Resize both buffers, creating new textures.
-    pub fn resize(
-        &self,
-        device: &wgpu::Device,
-        id: SurfaceId,
-        width: u32,
-        height: u32,
-    ) {
+    pub fn resize(&self, id: SurfaceId, width: u32, height: u32) {
+        let device = self.device();
         let mut surfaces = self.surfaces.lock().unwrap();
         if let Some(db) = surfaces.get_mut(&id) {
             if db.width == width && db.height == height {
                 return;
             }
-            let new_db = Self::create_double_buffer(device, width, height, db.format);
+            let new_db = Self::create_double_buffer(&device, width, height, db.format);
             *db = new_db;
         }
     }
@@ -1,18 +1,31 @@
 use crate::{
-    Bounds, Capslock, Modifiers, Pixels, PlatformInputHandler, PlatformWindow, Point, Size,
-    WgpuSurfaceHandle, WindowAppearance, WindowBackgroundAppearance, WindowBounds,
+    Bounds, Capslock, Modifiers, Pixels, PlatformInputHandler, PlatformWindow, Point, PresentMode,
+    Size, WgpuSurfaceHandle, WindowAppearance, WindowBackgroundAppearance, WindowBounds,
+    WindowKind, WindowParams,
     platform::cross::{
         atlas::WgpuAtlas,
+        cursor::ShownCursor,
         dispatcher::CrossEvent,
+        display::{DisplayRegistry, monitor_bounds},
         render_context::WgpuContext,
-        renderer::WgpuRenderer,
+        renderer::{DrawResult, WgpuRenderer},
     },
 };
 use std::{
     cell::{Cell, OnceCell, RefCell},
+    ops::Range,
+    rc::Rc,
     sync::Arc,
+    time::{Duration, Instant},
 };
-use winit::event_loop::EventLoopProxy;
+use winit::{
+    event_loop::{ActiveEventLoop, EventLoopProxy},
+    window::{WindowAttributes, WindowButtons, WindowLevel},
+};
+
+/// How long to wait before drawing a skipped frame again, so a surface that keeps failing to
+/// hand out textures isn't asked for one on every turn of the event loop.
+const SKIPPED_FRAME_RETRY_DELAY: Duration = Duration::from_millis(100);
 
 #[derive(Clone)]
 pub struct CrossWindow(pub(crate) Arc<CrossWindowInner>);
@@ -20,10 +33,18 @@ pub struct CrossWindow(pub(crate) Arc<CrossWindowInner>);
 pub(crate) struct CrossWindowInner {
     pub(crate) winit_window: OnceCell<Arc<winit::window::Window>>,
     pub(crate) renderer: OnceCell<RefCell<WgpuRenderer>>,
-    pub(crate) wgpu_context: Arc<WgpuContext>,
+    pub(crate) wgpu_context: RefCell<Arc<WgpuContext>>,
     pub(crate) sprite_atlas: Arc<WgpuAtlas>,
     pub(crate) event_loop_proxy: EventLoopProxy<CrossEvent>,
+    pub(crate) displays: Rc<DisplayRegistry>,
     pub(crate) state: CrossWindowState,
+    /// Set when everything has to be painted again on the next frame, rather than reusing
+    /// what was painted before.
+    pub(crate) force_render: Cell<bool>,
+    /// When the last skipped frame should be drawn again.
+    pub(crate) retry_draw_at: Cell<Option<Instant>>,
+    /// Whether the renderer has to present frames with transparency.
+    pub(crate) background_appearance: Cell<WindowBackgroundAppearance>,
 }
 
 #[derive(Default)]
@@ -33,6 +54,11 @@ pub(crate) struct CrossWindowState {
     pub(crate) mouse_position: Cell<Point<Pixels>>,
     pub(crate) modifiers: Cell<Modifiers>,
     pub(crate) capslock: Cell<Capslock>,
+    pub(crate) hovered: Cell<bool>,
+    /// Whether winit lets the IME compose text into the window.
+    pub(crate) ime_allowed: Cell<bool>,
+    /// The cursor last shown over the window, to not hand the same one to winit again.
+    pub(crate) cursor: Cell<Option<ShownCursor>>,
 }
 
 #[derive(Default)]
@@ -68,18 +94,28 @@ impl CrossWindow {
     pub(crate) fn new(
         wgpu_context: Arc<WgpuContext>,
         event_loop_proxy: EventLoopProxy<CrossEvent>,
+        displays: Rc<DisplayRegistry>,
     ) -> Self {
         Self(Arc::new(CrossWindowInner {
             winit_window: OnceCell::new(),
-            wgpu_context: wgpu_context.clone(),
+            wgpu_context: RefCell::new(wgpu_context.clone()),
             renderer: OnceCell::new(),
             sprite_atlas: Arc::new(WgpuAtlas::new(wgpu_context.clone())),
             event_loop_proxy,
+            displays,
             state: CrossWindowState::default(),
+            force_render: Cell::new(false),
+            retry_draw_at: Cell::new(None),
+            background_appearance: Cell::new(WindowBackgroundAppearance::Opaque),
         }))
     }
 
-    pub(crate) fn initialize(&self, winit_window: winit::window::Window) {
+    pub(crate) fn initialize(
+        &self,
+        winit_window: winit::window::Window,
+        present_mode: Option<PresentMode>,
+        max_frame_latency: Option<u32>,
+    ) {
         let initial_size = winit_window.inner_size();
 
         self.0
@@ -88,21 +124,99 @@ impl CrossWindow {
             .expect("winit_window already initialized");
 
         if initial_size.width > 0 && initial_size.height > 0 {
-            let renderer = WgpuRenderer::new(
-                self.0.wgpu_context.clone(),
+            let mut renderer = WgpuRenderer::new(
+                self.0.wgpu_context.borrow().clone(),
                 self.window(),
                 self.0.sprite_atlas.clone(),
                 initial_size.width,
                 initial_size.height,
                 4,
+                present_mode,
+                max_frame_latency,
             )
             .expect("Failed to create renderer");
+            renderer.update_transparency(
+                self.0.background_appearance.get() != WindowBackgroundAppearance::Opaque,
+            );
 
             let _ = self.0.renderer.set(RefCell::new(renderer));
             self.window().request_redraw();
         }
     }
 
+    /// Moves the window to `context` after the device it was drawing with was lost. Sprites are
+    /// rasterized again, and the next frame paints everything from scratch.
+    pub(crate) fn recover(&self, context: Arc<WgpuContext>) -> anyhow::Result<()> {
+        *self.0.wgpu_context.borrow_mut() = context.clone();
+        self.0.sprite_atlas.recreate(context.clone());
+        if let Some(renderer) = self.0.renderer.get() {
+            renderer.borrow_mut().recreate(context, self.window())?;
+        }
+        self.0.force_render.set(true);
+        self.window().request_redraw();
+        Ok(())
+    }
+
+    /// Lets the IME compose text into the window while an element with an input handler is
+    /// focused, which sets the handler whenever the window draws.
+    pub(crate) fn update_ime_allowed(&self) {
+        let allowed = self.0.state.input_handler.borrow().is_some();
+        if self.0.state.ime_allowed.replace(allowed) != allowed {
+            self.window().set_ime_allowed(allowed);
+        }
+    }
+
+    /// Hands the text the IME composes to the input handler, and moves the IME's candidate
+    /// window next to the text being composed.
+    pub(crate) fn handle_ime(&self, ime: winit::event::Ime) {
+        let Some(mut input_handler) = self.0.state.input_handler.take() else {
+            return;
+        };
+
+        let range = match ime {
+            winit::event::Ime::Enabled => input_handler
+                .selected_text_range(true)
+                .map(|selection| selection.range.end..selection.range.end),
+            winit::event::Ime::Preedit(text, cursor) => {
+                // NOTE(mdeand): The composition replaces the selection. Where it starts is the
+                // NOTE(mdeand): only part of it that has been laid out yet.
+                let marked_range = input_handler.marked_text_range();
+                let composing = marked_range.is_some();
+                let start = marked_range
+                    .or_else(|| {
+                        input_handler
+                            .selected_text_range(true)
+                            .map(|selection| selection.range)
+                    })
+                    .map(|range| range.start);
+
+                if preedit_marks_text(&text, composing) {
+                    let selected_range = preedit_selected_range(&text, cursor);
+                    input_handler.replace_and_mark_text_in_range(None, &text, selected_range);
+                }
+                start.map(|start| start..start)
+            }
+            winit::event::Ime::Commit(text) => {
+                input_handler.replace_text_in_range(None, &text);
+                None
+            }
+            winit::event::Ime::Disabled => {
+                input_handler.unmark_text();
+                None
+            }
+        };
+        if let Some(bounds) = range.and_then(|range| input_handler.bounds_for_range(range)) {
+            self.update_ime_position(bounds);
+        }
+
+        // NOTE(mdeand): Handling the text may have drawn a frame, which sets the handler again.
+        self.0
+            .state
+            .input_handler
+            .borrow_mut()
+            .get_or_insert(input_handler);
+    }
+
     pub(crate) fn window(&self) -> &winit::window::Window {
         &*self
             .0
@@ -110,6 +224,17 @@ impl CrossWindow {
             .get()
             .expect("winit_window should be initialized")
     }
+
+    /// Paints everything again on the next frame if the last frame was skipped and is due to
+    /// be drawn again.
+    pub(crate) fn retry_skipped_draw(&self, now: Instant) {
+        if let Some(retry_draw_at) = self.0.retry_draw_at.get()
+            && retry_draw_at <= now
+        {
+            self.0.retry_draw_at.set(None);
+            self.0.force_render.set(true);
+        }
+    }
 }
 
 impl PlatformWindow for CrossWindow {
@@ -184,9 +309,14 @@ impl PlatformWindow for CrossWindow {
         }
     }
 
-    fn display(&self) -> Option<std::rc::Rc<dyn crate::PlatformDisplay>> {
-        // TODO(mdeand): Add support for querying the display.
-        None
+    fn display(&self) -> Option<Rc<dyn crate::PlatformDisplay>> {
+        let window = self.window();
+        let monitor = window.current_monitor()?;
+        let display = self
+            .0
+            .displays
+            .display(window.available_monitors(), &monitor)?;
+        Some(Rc::new(display))
     }
 
     fn mouse_position(&self) -> Point<Pixels> {
@@ -232,16 +362,22 @@ impl PlatformWindow for CrossWindow {
     }
 
     fn is_hovered(&self) -> bool {
-        // TODO(mdeand): Add support for tracking hover status.
-        false
+        self.0.state.hovered.get()
     }
 
     fn set_title(&mut self, title: &str) {
         self.window().set_title(title);
     }
 
-    fn set_background_appearance(&self, _background_appearance: WindowBackgroundAppearance) {
-        // TODO(mdeand): Add support for setting the background appearance.
+    fn set_background_appearance(&self, background_appearance: WindowBackgroundAppearance) {
+        let transparent = background_appearance != WindowBackgroundAppearance::Opaque;
+        self.0.background_appearance.set(background_appearance);
+        self.window().set_transparent(transparent);
+        self.window()
+            .set_blur(background_appearance == WindowBackgroundAppearance::Blurred);
+        if let Some(renderer) = self.0.renderer.get() {
+            renderer.borrow_mut().update_transparency(transparent);
+        }
     }
 
     fn minimize(&self) {
@@ -324,20 +460,50 @@ impl PlatformWindow for CrossWindow {
     }
 
     fn draw(&self, scene: &crate::Scene) {
+        let Some(renderer) = self.0.renderer.get() else {
+            return;
+        };
+        let result = renderer.borrow_mut().draw(scene);
+        match result {
+            DrawResult::Drawn => {}
+            DrawResult::Retry => {
+                self.0.force_render.set(true);
+                self.window().request_redraw();
+            }
+            DrawResult::Skipped => {
+                self.0
+                    .retry_draw_at
+                    .set(Some(Instant::now() + SKIPPED_FRAME_RETRY_DELAY));
+            }
+        }
+    }
+
+    fn capture_frame(
+        &self,
+        scene: &crate::Scene,
+    ) -> Option<futures::channel::oneshot::Receiver<anyhow::Result<image::RgbaImage>>> {
+        let renderer = self.0.renderer.get()?;
+        Some(renderer.borrow_mut().capture_frame(scene))
+    }
+
+    fn set_present_mode(&self, present_mode: PresentMode) {
         if let Some(renderer) = self.0.renderer.get() {
-            renderer.borrow().draw(scene);
+            renderer.borrow_mut().set_present_mode(present_mode);
         }
     }
 
+    fn last_frame_stats(&self) -> Option<crate::FrameStats> {
+        self.0.renderer.get()?.borrow().last_frame_stats()
+    }
+
     fn create_wgpu_surface(
         &self,
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
     ) -> Option<WgpuSurfaceHandle> {
-        let ctx = &self.0.wgpu_context;
-        let registry = ctx.surface_registry.clone();
-        let surface_id = registry.create(&ctx.device, width, height, format);
+        let registry = self.0.wgpu_context.borrow().surface_registry.clone();
+        let surface_id = registry.create(width, height, format);
 
         // Build the present trigger: sends a CrossEvent to wake the event loop
         // and request a redraw for this window.
@@ -360,8 +526,6 @@ impl PlatformWindow for CrossWindow {
             .get()
             .cloned();
         Some(WgpuSurfaceHandle::new(
-            ctx.device.clone(),
-            ctx.queue.clone(),
             surface_id,
             registry,
             present_trigger,
@@ -381,7 +545,12 @@ impl PlatformWindow for CrossWindow {
         None
     }
 
-    fn update_ime_position(&self, _bounds: crate::Bounds<crate::Pixels>) {}
+    fn update_ime_position(&self, bounds: crate::Bounds<crate::Pixels>) {
+        self.window().set_ime_cursor_area(
+            winit::dpi::LogicalPosition::new(bounds.origin.x.0, bounds.origin.y.0),
+            winit::dpi::LogicalSize::new(bounds.size.width.0, bounds.size.height.0),
+        );
+    }
 }
 
 impl raw_window_handle::HasDisplayHandle for CrossWindow {
@@ -399,3 +568,300 @@ impl raw_window_handle::HasWindowHandle for CrossWindow {
         self.window().window_handle()
     }
 }
+
+/// Whether a preedit of `text` is marked, replacing the text being composed if `composing`, or
+/// the selection otherwise.
+fn preedit_marks_text(text: &str, composing: bool) -> bool {
+    // NOTE(mdeand): winit clears the preedit text before committing, which mustn't replace the
+    // NOTE(mdeand): selection when nothing was being composed.
+    !text.is_empty() || composing
+}
+
+/// The range of the preedit `text` selected by winit's `cursor`, in the UTF-16 code units input
+/// handlers count. winit's cursor is a byte range instead, and `None` if it doesn't fall on
+/// character boundaries within the text.
+fn preedit_selected_range(text: &str, cursor: Option<(usize, usize)>) -> Option<Range<usize>> {
+    let utf16_offset = |offset: usize| {
+        text.get(..offset)
+            .map(|prefix| prefix.encode_utf16().count())
+    };
+    let (start, end) = cursor?;
+    Some(utf16_offset(start)?..utf16_offset(end)?)
+}
+
+/// Builds the attributes of the winit window opened for `params`.
+///
+/// Backends that don't support an option ignore it:
+///
+/// | Option | Windows | macOS | X11 | Wayland |
+/// |---|---|---|---|---|
+/// | `bounds.size`, `window_min_size`, `is_resizable` | Yes | Yes | Yes | Yes |
+/// | `bounds.origin`, `display_id` | Yes | Yes | Yes | No, the compositor places windows |
+/// | `kind` | Always on top, without a taskbar entry | Always on top | Always on top, with a utility or notification window type | No |
+/// | `is_minimizable` | Yes | Yes | No | No |
+/// | `is_movable` | No | No | No | No |
+/// | `focus` | Yes | Yes | Up to the window manager | Up to the compositor |
+/// | `show` | Yes | Yes | Yes | No |
+/// | `window_background` | Transparent, not blurred | Yes | Transparent, not blurred | Blurred with KDE's blur protocol only |
+/// | `app_id` | No | No | Yes, as `WM_CLASS` | Yes |
+/// | `titlebar.appears_transparent`, `tabbing_identifier` | No | Yes | No | No |
+/// | `titlebar.traffic_light_position` | No | No | No | No |
+pub(crate) fn window_attributes(
+    params: &WindowParams,
+    event_loop: &ActiveEventLoop,
+    displays: &DisplayRegistry,
+) -> WindowAttributes {
+    let display = params
+        .display_id
+        .and_then(|display_id| displays.monitor(event_loop.available_monitors(), display_id))
+        .map(|monitor| DisplayPlacement {
+            bounds: monitor_bounds(&monitor),
+            position: monitor.position(),
+            scale_factor: monitor.scale_factor(),
+        });
+    window_attributes_on_display(params, display.as_ref())
+}
+
+/// Where a display lies, in the global logical coordinates window bounds are given in and in
+/// the physical ones winit positions windows with.
+#[derive(Clone, Copy, Debug)]
+struct DisplayPlacement {
+    bounds: Bounds<Pixels>,
+    position: winit::dpi::PhysicalPosition<i32>,
+    scale_factor: f64,
+}
+
+/// Builds the attributes of the winit window opened for `params` on `display`, the display
+/// requested by `params.display_id` if it's connected.
+fn window_attributes_on_display(
+    params: &WindowParams,
+    display: Option<&DisplayPlacement>,
+) -> WindowAttributes {
+    let bounds = params.bounds;
+    let mut position: winit::dpi::Position =
+        winit::dpi::LogicalPosition::new(bounds.origin.x.0 as f64, bounds.origin.y.0 as f64).into();
+    // NOTE(mdeand): Bounds are in global coordinates. Bounds that don't lie on the requested
+    // NOTE(mdeand): display are taken to be relative to it. The position is scaled by that
+    // NOTE(mdeand): display, rather than by whichever display winit would pick for a logical one.
+    if let Some(display) = display {
+        let offset = if display.bounds.contains(&bounds.origin) {
+            bounds.origin - display.bounds.origin
+        } else {
+            bounds.origin
+        };
+        position = winit::dpi::PhysicalPosition::new(
+            display.position.x + (offset.x.0 as f64 * display.scale_factor).round() as i32,
+            display.position.y + (offset.y.0 as f64 * display.scale_factor).round() as i32,
+        )
+        .into();
+    }
+
+    let mut buttons = WindowButtons::all();
+    if !params.is_minimizable {
+        buttons.remove(WindowButtons::MINIMIZE);
+    }
+    if !params.is_resizable {
+        buttons.remove(WindowButtons::MAXIMIZE);
+    }
+
+    let mut attributes = winit::window::Window::default_attributes()
+        .with_title(
+            params
+                .titlebar
+                .as_ref()
+                .and_then(|t| t.title.as_ref())
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| "GPUI".into()),
+        )
+        .with_position(position)
+        .with_inner_size(winit::dpi::LogicalSize::new(
+            bounds.size.width.0 as f64,
+            bounds.size.height.0 as f64,
+        ))
+        .with_resizable(params.is_resizable)
+        .with_enabled_buttons(buttons)
+        .with_active(params.focus)
+        .with_visible(params.show)
+        .with_transparent(params.window_background != WindowBackgroundAppearance::Opaque)
+        .with_blur(params.window_background == WindowBackgroundAppearance::Blurred);
+
+    if let Some(min_size) = params.window_min_size {
+        attributes = attributes.with_min_inner_size(winit::dpi::LogicalSize::new(
+            min_size.width.0 as f64,
+            min_size.height.0 as f64,
+        ));
+    }
+
+    match params.kind {
+        WindowKind::Normal => {}
+        WindowKind::PopUp => {
+            attributes = attributes
+                .with_window_level(WindowLevel::AlwaysOnTop)
+                .with_decorations(false);
+        }
+        WindowKind::Floating => {
+            attributes = attributes.with_window_level(WindowLevel::AlwaysOnTop);
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use winit::platform::windows::WindowAttributesExtWindows as _;
+
+        attributes = attributes.with_skip_taskbar(params.kind != WindowKind::Normal);
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use winit::platform::macos::WindowAttributesExtMacOS as _;
+
+        if let Some(tabbing_identifier) = &params.tabbing_identifier {
+            attributes = attributes.with_tabbing_identifier(tabbing_identifier);
+        }
+        if params
+            .titlebar
+            .as_ref()
+            .is_some_and(|titlebar| titlebar.appears_transparent)
+        {
+            attributes = attributes
+                .with_titlebar_transparent(true)
+                .with_title_hidden(true)
+                .with_fullsize_content_view(true);
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    {
+        use winit::platform::x11::{WindowAttributesExtX11 as _, WindowType};
+
+        // NOTE(mdeand): X11 and Wayland share the name, which becomes the window's WM_CLASS on
+        // NOTE(mdeand): X11 and its app id on Wayland.
+        if let Some(app_id) = &params.app_id {
+            attributes = attributes.with_name(app_id, app_id);
+        }
+        match params.kind {
+            WindowKind::Normal => {}
+            WindowKind::PopUp => {
+                attributes = attributes.with_x11_window_type(vec![WindowType::Notification]);
+            }
+            WindowKind::Floating => {
+                attributes = attributes.with_x11_window_type(vec![WindowType::Utility]);
+            }
+        }
+    }
+
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point, px, size};
+    use winit::dpi::{LogicalPosition, PhysicalPosition, Position};
+
+    fn params(bounds: Bounds<Pixels>, kind: WindowKind) -> WindowParams {
+        WindowParams {
+            bounds,
+            titlebar: None,
+            kind,
+            is_movable: true,
+            is_resizable: true,
+            is_minimizable: true,
+            focus: true,
+            show: true,
+            display_id: None,
+            window_min_size: None,
+            window_background: WindowBackgroundAppearance::Opaque,
+            app_id: None,
+            tabbing_identifier: None,
+            present_mode: None,
+            max_frame_latency: None,
+        }
+    }
+
+    #[test]
+    fn test_preedit_cursors_are_mapped_to_utf16() {
+        // NOTE(mdeand): "é" is two bytes and one code unit, "𝄞" four bytes and two code units.
+        let text = "é𝄞a";
+        assert_eq!(preedit_selected_range(text, Some((0, 0))), Some(0..0));
+        assert_eq!(preedit_selected_range(text, Some((2, 6))), Some(1..3));
+        assert_eq!(preedit_selected_range(text, Some((7, 7))), Some(4..4));
+        assert_eq!(preedit_selected_range(text, None), None);
+    }
+
+    #[test]
+    fn test_preedit_cursors_off_character_boundaries_are_dropped() {
+        let text = "é𝄞a";
+        assert_eq!(preedit_selected_range(text, Some((1, 2))), None);
+        assert_eq!(preedit_selected_range(text, Some((2, 4))), None);
+        assert_eq!(preedit_selected_range(text, Some((7, 8))), None);
+        assert_eq!(preedit_selected_range("", Some((0, 1))), None);
+    }
+
+    #[test]
+    fn test_empty_preedits_only_clear_what_is_being_composed() {
+        assert!(!preedit_marks_text("", false));
+        assert!(preedit_marks_text("", true));
+        assert!(preedit_marks_text("か", false));
+        assert_eq!(preedit_selected_range("", Some((0, 0))), Some(0..0));
+    }
+
+    #[test]
+    fn test_origins_off_the_requested_display_are_relative_to_it() {
+        let display = DisplayPlacement {
+            bounds: Bounds::new(point(px(1920.), px(0.)), size(px(1280.), px(800.))),
+            position: PhysicalPosition::new(1920, 0),
+            scale_factor: 2.0,
+        };
+        let position = |origin| {
+            let params = params(
+                Bounds::new(origin, size(px(400.), px(300.))),
+                WindowKind::Normal,
+            );
+            window_attributes_on_display(&params, Some(&display)).position
+        };
+
+        assert_eq!(
+            position(point(px(2020.), px(100.))),
+            Some(Position::Physical(PhysicalPosition::new(2120, 200)))
+        );
+        assert_eq!(
+            position(point(px(100.), px(50.))),
+            Some(Position::Physical(PhysicalPosition::new(2120, 100)))
+        );
+
+        let params = params(
+            Bounds::new(point(px(100.), px(50.)), size(px(400.), px(300.))),
+            WindowKind::Normal,
+        );
+        assert_eq!(
+            window_attributes_on_display(&params, None).position,
+            Some(Position::Logical(LogicalPosition::new(100., 50.)))
+        );
+    }
+
+    #[test]
+    fn test_window_kinds_set_buttons_and_levels() {
+        let bounds = Bounds::new(point(px(0.), px(0.)), size(px(400.), px(300.)));
+
+        let attributes = window_attributes_on_display(&params(bounds, WindowKind::Normal), None);
+        assert_eq!(attributes.window_level, WindowLevel::Normal);
+        assert!(attributes.decorations);
+        assert_eq!(attributes.enabled_buttons, WindowButtons::all());
+
+        let attributes = window_attributes_on_display(&params(bounds, WindowKind::PopUp), None);
+        assert_eq!(attributes.window_level, WindowLevel::AlwaysOnTop);
+        assert!(!attributes.decorations);
+
+        let attributes = window_attributes_on_display(&params(bounds, WindowKind::Floating), None);
+        assert_eq!(attributes.window_level, WindowLevel::AlwaysOnTop);
+        assert!(attributes.decorations);
+
+        let mut fixed = params(bounds, WindowKind::Normal);
+        fixed.is_resizable = false;
+        fixed.is_minimizable = false;
+        let attributes = window_attributes_on_display(&fixed, None);
+        assert!(!attributes.resizable);
+        assert_eq!(attributes.enabled_buttons, WindowButtons::CLOSE);
+    }
+}
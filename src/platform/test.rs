@@ -1,9 +1,12 @@
 mod dispatcher;
 mod display;
 mod platform;
+mod snapshot;
 mod window;
 
 pub use dispatcher::*;
 pub(crate) use display::*;
 pub(crate) use platform::*;
+pub use snapshot::SnapshotTolerance;
+pub(crate) use snapshot::assert_snapshot;
 pub(crate) use window::*;
\ No newline at end of file
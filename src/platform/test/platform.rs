@@ -3,6 +3,7 @@ use crate::{
     DummyKeyboardMapper, ForegroundExecutor, Keymap, NoopTextSystem, Platform, PlatformDisplay,
     PlatformKeyboardLayout, PlatformKeyboardMapper, PlatformTextSystem, PromptButton,
     Task, TestDisplay, TestWindow, WindowAppearance, WindowParams,
+    platform::cross::offscreen_renderer::OffscreenBackend,
 };
 use anyhow::Result;
 use collections::VecDeque;
@@ -24,10 +25,15 @@ pub(crate) struct TestPlatform {
     active_display: Rc<dyn PlatformDisplay>,
     active_cursor: Mutex<CursorStyle>,
     current_clipboard_item: Mutex<Option<ClipboardItem>>,
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    current_primary_item: Mutex<Option<ClipboardItem>>,
     pub(crate) prompts: RefCell<TestPrompts>,
     pub opened_url: RefCell<Option<String>>,
     pub text_system: Arc<dyn PlatformTextSystem>,
     pub expect_restart: RefCell<Option<oneshot::Sender<Option<PathBuf>>>>,
+    // NOTE(mdeand): Only set once a test opts into rendering, most tests never look at pixels
+    // NOTE(mdeand): and shouldn't need an adapter.
+    offscreen_backend: RefCell<Option<OffscreenBackend>>,
     weak: Weak<Self>,
 }
 
@@ -57,12 +63,26 @@ impl TestPlatform {
             active_window: Default::default(),
             expect_restart: Default::default(),
             current_clipboard_item: Mutex::new(None),
+            #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+            current_primary_item: Mutex::new(None),
+            offscreen_backend: Default::default(),
             weak: weak.clone(),
             opened_url: Default::default(),
             text_system,
         })
     }
 
+    /// Renders windows opened from now on with the wgpu renderer, so their frames can be
+    /// captured. Falls back to a software adapter on machines without a GPU, or to the CPU
+    /// rasterizer with the `software-renderer` feature.
+    pub(crate) fn enable_rendering(&self) -> Result<()> {
+        if self.offscreen_backend.borrow().is_none() {
+            let backend = OffscreenBackend::new()?;
+            self.offscreen_backend.replace(Some(backend));
+        }
+        Ok(())
+    }
+
     pub(crate) fn simulate_new_path_selection(
         &self,
         select_path: impl FnOnce(&std::path::Path) -> Option<std::path::PathBuf>,
@@ -233,6 +253,7 @@ impl Platform for TestPlatform {
             params,
             self.weak.clone(),
             self.active_display.clone(),
+            self.offscreen_backend.borrow().clone(),
         );
         Ok(Box::new(window))
     }
@@ -320,6 +341,16 @@ impl Platform for TestPlatform {
         self.current_clipboard_item.lock().clone()
     }
 
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn write_to_primary(&self, item: ClipboardItem) {
+        *self.current_primary_item.lock() = Some(item);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.current_primary_item.lock().clone()
+    }
+
     fn write_credentials(&self, _url: &str, _username: &str, _password: &[u8]) -> Task<Result<()>> {
         Task::ready(Ok(()))
     }
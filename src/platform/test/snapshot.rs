@@ -0,0 +1,265 @@
+use anyhow::{Context as _, Result, bail};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Set this environment variable to write captured frames as the new golden images instead of
+/// comparing against them.
+const UPDATE_SNAPSHOTS_ENV: &str = "GPUI_UPDATE_SNAPSHOTS";
+
+/// How much a captured frame may differ from its golden image before a snapshot assertion
+/// fails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnapshotTolerance {
+    /// The largest difference in any channel for which two pixels are still considered equal.
+    pub channel_threshold: u8,
+    /// The fraction of pixels, between 0 and 1, that may differ.
+    pub max_mismatched_ratio: f32,
+}
+
+impl Default for SnapshotTolerance {
+    fn default() -> Self {
+        // NOTE(mdeand): Allow for small rounding differences between adapters, but not for
+        // NOTE(mdeand): anything a person could see.
+        Self {
+            channel_threshold: 2,
+            max_mismatched_ratio: 0.0,
+        }
+    }
+}
+
+/// The result of comparing a captured frame against its golden image.
+pub(crate) struct ImageComparison {
+    pub mismatched_pixels: usize,
+    pub total_pixels: usize,
+    /// The golden image, dimmed, with every mismatched pixel drawn in red.
+    pub diff: RgbaImage,
+}
+
+pub(crate) fn compare_images(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    channel_threshold: u8,
+) -> ImageComparison {
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    let mut mismatched_pixels = 0;
+
+    for (x, y, expected_pixel) in expected.enumerate_pixels() {
+        let matches = actual.get_pixel_checked(x, y).is_some_and(|actual_pixel| {
+            expected_pixel
+                .0
+                .iter()
+                .zip(actual_pixel.0)
+                .all(|(&a, b)| a.abs_diff(b) <= channel_threshold)
+        });
+
+        let diff_pixel = if matches {
+            let [r, g, b, _] = expected_pixel.0;
+            Rgba([r / 4, g / 4, b / 4, 255])
+        } else {
+            mismatched_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        };
+        diff.put_pixel(x, y, diff_pixel);
+    }
+
+    ImageComparison {
+        mismatched_pixels,
+        total_pixels: expected.width() as usize * expected.height() as usize,
+        diff,
+    }
+}
+
+/// The directory golden images are read from: `snapshots` in the crate under test.
+fn snapshot_dir() -> PathBuf {
+    std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join("snapshots")
+}
+
+/// Compares `actual` against the golden image stored as `<name>.png`, or writes it as the new
+/// golden image when `GPUI_UPDATE_SNAPSHOTS` is set.
+pub(crate) fn assert_snapshot(
+    name: &str,
+    actual: &RgbaImage,
+    tolerance: SnapshotTolerance,
+) -> Result<()> {
+    let update = std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some();
+    compare_snapshot(&snapshot_dir(), name, actual, tolerance, update)
+}
+
+/// Compares `actual` against the golden image `<name>.png` in `dir`.
+///
+/// A missing golden image is written from `actual`, but the comparison still fails so the new
+/// image gets looked at before it's checked in. On a mismatch, the captured frame and a diff
+/// image are written next to the golden image, as `<name>.actual.png` and `<name>.diff.png`.
+fn compare_snapshot(
+    dir: &Path,
+    name: &str,
+    actual: &RgbaImage,
+    tolerance: SnapshotTolerance,
+    update: bool,
+) -> Result<()> {
+    let golden_path = dir.join(format!("{name}.png"));
+
+    if update || !golden_path.exists() {
+        std::fs::create_dir_all(dir)?;
+        save_image(actual, &golden_path)?;
+        if update {
+            return Ok(());
+        }
+        bail!(
+            "no golden image for snapshot {name}, the captured frame was written to {}. \
+             Check that it looks right, and rerun",
+            golden_path.display()
+        );
+    }
+
+    let actual_path = dir.join(format!("{name}.actual.png"));
+    let expected = image::open(&golden_path)
+        .with_context(|| format!("failed to read {}", golden_path.display()))?
+        .into_rgba8();
+
+    if expected.dimensions() != actual.dimensions() {
+        save_image(actual, &actual_path)?;
+        bail!(
+            "snapshot {name} is {:?} but the captured frame is {:?}, see {}",
+            expected.dimensions(),
+            actual.dimensions(),
+            actual_path.display()
+        );
+    }
+
+    let comparison = compare_images(&expected, actual, tolerance.channel_threshold);
+    let mismatched_ratio = comparison.mismatched_pixels as f32 / comparison.total_pixels as f32;
+    if mismatched_ratio > tolerance.max_mismatched_ratio {
+        let diff_path = dir.join(format!("{name}.diff.png"));
+        save_image(actual, &actual_path)?;
+        save_image(&comparison.diff, &diff_path)?;
+        bail!(
+            "snapshot {name} differs in {} of {} pixels, see {} and {}",
+            comparison.mismatched_pixels,
+            comparison.total_pixels,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn save_image(image: &RgbaImage, path: &Path) -> Result<()> {
+    image
+        .save(path)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AppContext as _, Bounds, Context, IntoElement, ParentElement as _, Render, Styled as _,
+        TestAppContext, VisualTestContext, Window, WindowBounds, WindowOptions, div, point, px,
+        red, size,
+    };
+
+    struct Square;
+
+    impl Render for Square {
+        fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+            div()
+                .size_full()
+                .child(div().absolute().left(px(4.)).size(px(8.)).bg(red()))
+        }
+    }
+
+    /// A directory of its own in the system's temporary directory.
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("gpui-snapshots-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[gpui::test]
+    fn test_captured_frames_match_golden_images(cx: &mut TestAppContext) {
+        // NOTE(mdeand): Test windows fall back to the software renderer when it's built.
+        #[cfg(not(feature = "software-renderer"))]
+        if crate::platform::cross::render_context::WgpuContext::for_test().is_none() {
+            return;
+        }
+        cx.enable_rendering().unwrap();
+
+        let window = cx.update(|cx| {
+            cx.open_window(
+                WindowOptions {
+                    window_bounds: Some(WindowBounds::Windowed(Bounds::new(
+                        point(px(0.), px(0.)),
+                        size(px(16.), px(8.)),
+                    ))),
+                    ..Default::default()
+                },
+                |_, cx| cx.new(|_| Square),
+            )
+            .unwrap()
+        });
+        let mut cx = VisualTestContext::from_window(*window, cx);
+        cx.assert_snapshot("red_square");
+    }
+
+    #[test]
+    fn test_missing_golden_images_are_written() {
+        let dir = temp_dir();
+        let frame = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+
+        let error = compare_snapshot(&dir, "frame", &frame, SnapshotTolerance::default(), false)
+            .unwrap_err();
+        assert!(error.to_string().contains("no golden image"), "{error}");
+        let golden = image::open(dir.join("frame.png")).unwrap().into_rgba8();
+        assert_eq!(golden, frame);
+        compare_snapshot(&dir, "frame", &frame, SnapshotTolerance::default(), false).unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_mismatched_frames_are_written_next_to_golden_images() {
+        let dir = temp_dir();
+        let golden = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let mut frame = golden.clone();
+        frame.put_pixel(1, 0, Rgba([0, 0, 255, 255]));
+        compare_snapshot(&dir, "frame", &golden, SnapshotTolerance::default(), true).unwrap();
+
+        let error = compare_snapshot(&dir, "frame", &frame, SnapshotTolerance::default(), false)
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("differs in 1 of 4 pixels"),
+            "{error}"
+        );
+        let actual = image::open(dir.join("frame.actual.png")).unwrap();
+        assert_eq!(actual.into_rgba8(), frame);
+        let diff = image::open(dir.join("frame.diff.png"))
+            .unwrap()
+            .into_rgba8();
+        assert_eq!(diff.get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+
+        let tolerance = SnapshotTolerance {
+            max_mismatched_ratio: 0.25,
+            ..Default::default()
+        };
+        compare_snapshot(&dir, "frame", &frame, tolerance, false).unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_differences_within_threshold_match() {
+        let expected = RgbaImage::from_pixel(2, 2, Rgba([100, 100, 100, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, Rgba([102, 98, 100, 255]));
+        actual.put_pixel(1, 1, Rgba([110, 100, 100, 255]));
+
+        let comparison = compare_images(&expected, &actual, 2);
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.total_pixels, 4);
+        assert_eq!(comparison.diff.get_pixel(1, 1), &Rgba([255, 0, 0, 255]));
+        assert_eq!(comparison.diff.get_pixel(0, 0), &Rgba([25, 25, 25, 255]));
+    }
+}
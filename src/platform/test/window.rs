@@ -1,10 +1,15 @@
 use crate::{
-    AnyWindowHandle, AtlasKey, AtlasTextureId, AtlasTile, Bounds, DispatchEventResult, GpuSpecs,
-    Pixels, PlatformAtlas, PlatformDisplay, PlatformInput, PlatformInputHandler, PlatformWindow,
-    Point, PromptButton, RequestFrameOptions, Size, TestPlatform, TileId, WindowAppearance,
-    WindowBackgroundAppearance, WindowBounds, WindowControlArea, WindowParams,
+    AnyWindowHandle, AtlasKey, AtlasTextureId, AtlasTile, Bounds, DevicePixels,
+    DispatchEventResult, GpuSpecs, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput,
+    PlatformInputHandler, PlatformWindow, Point, PromptButton, RequestFrameOptions, Scene, Size,
+    TestPlatform, TileId, WindowAppearance, WindowBackgroundAppearance, WindowBounds,
+    WindowControlArea, WindowParams,
+    platform::cross::offscreen_renderer::{OffscreenBackend, OffscreenRenderer},
 };
+use anyhow::Result;
 use collections::HashMap;
+use futures::channel::oneshot;
+use image::RgbaImage;
 use parking_lot::Mutex;
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
 use std::{
@@ -20,6 +25,7 @@ pub(crate) struct TestWindowState {
     pub(crate) edited: bool,
     platform: Weak<TestPlatform>,
     sprite_atlas: Arc<dyn PlatformAtlas>,
+    renderer: Option<OffscreenRenderer>,
     pub(crate) should_close_handler: Option<Box<dyn FnMut() -> bool>>,
     hit_test_window_control_callback: Option<Box<dyn FnMut() -> Option<WindowControlArea>>>,
     input_callback: Option<Box<dyn FnMut(PlatformInput) -> DispatchEventResult>>,
@@ -56,13 +62,24 @@ impl TestWindow {
         params: WindowParams,
         platform: Weak<TestPlatform>,
         display: Rc<dyn PlatformDisplay>,
+        offscreen_backend: Option<OffscreenBackend>,
     ) -> Self {
+        let (sprite_atlas, renderer): (Arc<dyn PlatformAtlas>, _) = match offscreen_backend {
+            Some(backend) => {
+                let (renderer, sprite_atlas) =
+                    OffscreenRenderer::new(&backend, device_size(params.bounds.size));
+                (sprite_atlas, Some(renderer))
+            }
+            None => (Arc::new(TestAtlas::new()), None),
+        };
+
         Self(Rc::new(Mutex::new(TestWindowState {
             bounds: params.bounds,
             display,
             platform,
             handle,
-            sprite_atlas: Arc::new(TestAtlas::new()),
+            sprite_atlas,
+            renderer,
             title: Default::default(),
             edited: false,
             should_close_handler: None,
@@ -84,6 +101,9 @@ impl TestWindow {
             return;
         };
         lock.bounds.size = size;
+        if let Some(renderer) = lock.renderer.as_mut() {
+            renderer.update_drawable_size(device_size(size));
+        }
         drop(lock);
         callback(size, scale_factor);
         self.0.lock().resize_callback = Some(callback);
@@ -131,6 +151,9 @@ impl PlatformWindow for TestWindow {
     fn resize(&mut self, size: Size<Pixels>) {
         let mut lock = self.0.lock();
         lock.bounds.size = size;
+        if let Some(renderer) = lock.renderer.as_mut() {
+            renderer.update_drawable_size(device_size(size));
+        }
     }
 
     fn scale_factor(&self) -> f32 {
@@ -266,7 +289,15 @@ impl PlatformWindow for TestWindow {
 
     fn on_appearance_changed(&self, _callback: Box<dyn FnMut()>) {}
 
-    fn draw(&self, _scene: &crate::Scene) {}
+    fn draw(&self, scene: &Scene) {
+        if let Some(renderer) = self.0.lock().renderer.as_mut() {
+            renderer.draw(scene);
+        }
+    }
+
+    fn capture_frame(&self, scene: &Scene) -> Option<oneshot::Receiver<Result<RgbaImage>>> {
+        Some(self.0.lock().renderer.as_mut()?.capture_frame(scene))
+    }
 
     fn sprite_atlas(&self) -> sync::Arc<dyn crate::PlatformAtlas> {
         self.0.lock().sprite_atlas.clone()
@@ -287,10 +318,16 @@ impl PlatformWindow for TestWindow {
     fn update_ime_position(&self, _bounds: Bounds<Pixels>) {}
 
     fn gpu_specs(&self) -> Option<GpuSpecs> {
-        None
+        Some(self.0.lock().renderer.as_ref()?.gpu_specs())
     }
 }
 
+/// The size of the texture frames are rendered into, given test windows' fixed scale factor.
+fn device_size(size: Size<Pixels>) -> Size<DevicePixels> {
+    size.scale(2.0)
+        .map(|dimension| DevicePixels(dimension.0.round().max(1.0) as i32))
+}
+
 pub(crate) struct TestAtlasState {
     next_id: u32,
     tiles: HashMap<AtlasKey, AtlasTile>,
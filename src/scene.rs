@@ -2,68 +2,200 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    AtlasTextureId, AtlasTile, Background, Bounds, ContentMask, Corners, Edges, Hsla, Pixels,
-    Point, Radians, ScaledPixels, Size, bounds_tree::BoundsTree,
+    AtlasTextureId, AtlasTile, Background, Bounds, ColorSpace, ContentMask, Corners, Edges, Hsla,
+    LinearColorStop, MAX_SHADER_QUAD_UNIFORMS, Pixels, Point, Radians, ScaledPixels, SharedString,
+    Size, bounds_tree::BoundsTree, color::BackgroundTag,
     platform::cross::surface_registry::SurfaceId, point,
 };
 use std::{
     fmt::Debug,
+    hash::{Hash, Hasher},
     iter::Peekable,
     ops::{Add, Range, Sub},
     slice,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
+mod dump;
+
+pub use dump::*;
+
 #[allow(non_camel_case_types, unused)]
 pub(crate) type PathVertex_ScaledPixels = PathVertex<ScaledPixels>;
 
 pub(crate) type DrawOrder = u32;
 
+/// Scenes are numbered from one, so renderers can tell which frame damage is relative to.
+static NEXT_SCENE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Where a frame's scene differs from the scene of the frame before it.
+#[derive(Clone, Debug)]
+pub(crate) struct Damage {
+    /// The id of the scene this one was compared with.
+    pub since: u64,
+    /// The regions whose pixels may have changed, which is everything any primitive painted
+    /// in only one of the two scenes covers. Primitives painting volatile content (surfaces
+    /// and shader quads) and blurs sampling the regions aren't included.
+    pub bounds: Vec<Bounds<ScaledPixels>>,
+}
+
 #[derive(Default)]
 pub(crate) struct Scene {
+    /// Identifies a frame's scene once it was finished, `0` before that.
+    pub(crate) id: u64,
+    /// How the scene differs from the frame's previous scene, `None` if it wasn't compared with
+    /// one, in which case every pixel has to be redrawn.
+    pub(crate) damage: Option<Damage>,
     pub(crate) paint_operations: Vec<PaintOperation>,
     primitive_bounds: BoundsTree<ScaledPixels>,
     layer_stack: Vec<DrawOrder>,
+    /// For each layer on the stack, from the outermost, the index of the paint operation that
+    /// started it if it's isolated.
+    layer_isolation_stack: Vec<Option<usize>>,
+    /// The isolated layers being painted, from the outermost. Primitives are inserted into the
+    /// scene of the innermost one until it is popped.
+    isolated_layer_stack: Vec<Layer>,
     pub(crate) shadows: Vec<Shadow>,
+    pub(crate) blurs: Vec<Blur>,
     pub(crate) quads: Vec<Quad>,
     pub(crate) paths: Vec<Path<ScaledPixels>>,
     pub(crate) underlines: Vec<Underline>,
     pub(crate) monochrome_sprites: Vec<MonochromeSprite>,
     pub(crate) polychrome_sprites: Vec<PolychromeSprite>,
     pub(crate) surfaces: Vec<PaintSurface>,
+    pub(crate) shader_quads: Vec<PaintShaderQuad>,
+    pub(crate) layers: Vec<Layer>,
+    /// The stops of the gradients quads and paths are filled with, including those in isolated
+    /// layers, which only ever use the stops of the root scene.
+    pub(crate) gradient_stops: Vec<LinearColorStop>,
 }
 
 impl Scene {
     pub fn clear(&mut self) {
+        self.id = 0;
+        self.damage = None;
         self.paint_operations.clear();
         self.primitive_bounds.clear();
         self.layer_stack.clear();
+        self.layer_isolation_stack.clear();
+        self.isolated_layer_stack.clear();
         self.paths.clear();
         self.shadows.clear();
+        self.blurs.clear();
         self.quads.clear();
         self.underlines.clear();
         self.monochrome_sprites.clear();
         self.polychrome_sprites.clear();
         self.surfaces.clear();
+        self.shader_quads.clear();
+        self.layers.clear();
+        self.gradient_stops.clear();
     }
 
     pub fn len(&self) -> usize {
         self.paint_operations.len()
     }
 
-    pub fn push_layer(&mut self, bounds: Bounds<ScaledPixels>) {
-        let order = self.primitive_bounds.insert(bounds);
-        self.layer_stack.push(order);
+    /// Keeps the gradient stops of `background` with the scene, returning the background
+    /// quads and paths are filled with.
+    pub(crate) fn push_background(&mut self, background: &Background) -> SceneBackground {
+        SceneBackground::new(background, &mut self.gradient_stops)
+    }
+
+    /// Starts a layer within `bounds`. Isolated layers are painted into a scene of their own,
+    /// which is composited as a whole onto what's painted beneath it.
+    pub fn push_layer(&mut self, bounds: Bounds<ScaledPixels>, isolation: Option<LayerIsolation>) {
+        self.layer_isolation_stack
+            .push(isolation.is_some().then_some(self.paint_operations.len()));
         self.paint_operations
-            .push(PaintOperation::StartLayer(bounds));
+            .push(PaintOperation::StartLayer(bounds, isolation.clone()));
+        if let Some(isolation) = isolation {
+            self.isolated_layer_stack.push(Layer {
+                order: 0,
+                clip: bounds,
+                bounds: Bounds::default(),
+                opacity: isolation.opacity,
+                blend_mode: isolation.blend_mode,
+                mask: isolation.mask,
+                blur_radius: isolation.blur_radius,
+                scene: Scene::default(),
+            });
+        } else {
+            let scene = self.innermost_scene();
+            let order = scene.primitive_bounds.insert(bounds);
+            scene.layer_stack.push(order);
+        }
     }
 
     pub fn pop_layer(&mut self) {
-        self.layer_stack.pop();
         self.paint_operations.push(PaintOperation::EndLayer);
+        let Some(Some(start_ix)) = self.layer_isolation_stack.pop() else {
+            self.innermost_scene().layer_stack.pop();
+            return;
+        };
+
+        let Some(mut layer) = self.isolated_layer_stack.pop() else {
+            return;
+        };
+        // NOTE(mdeand): The blur spreads the layer's primitives over three standard deviations
+        // NOTE(mdeand): past their bounds.
+        if layer.blur_radius > ScaledPixels(0.) && !layer.bounds.is_empty() {
+            layer.bounds = layer
+                .bounds
+                .dilate(layer.blur_radius * 3.)
+                .intersect(&layer.clip);
+        }
+        if let Some(mask) = &layer.mask {
+            layer.bounds = layer.bounds.intersect(&mask.clipped_bounds());
+        }
+        // NOTE(mdeand): The layer is only drawn where it's painted, so that's all a change to it
+        // NOTE(mdeand): damages. Replaying it within these bounds paints the same layer again.
+        if let Some(PaintOperation::StartLayer(bounds, _)) = self.paint_operations.get_mut(start_ix)
+        {
+            *bounds = layer.bounds;
+        }
+        if layer.bounds.is_empty() {
+            return;
+        }
+        layer.scene.finish();
+        let parent = self.innermost_scene();
+        layer.order = parent
+            .layer_stack
+            .last()
+            .copied()
+            .unwrap_or_else(|| parent.primitive_bounds.insert(layer.bounds));
+        let bounds = layer.bounds;
+        parent.layers.push(layer);
+        self.extend_isolated_layer_bounds(bounds);
+    }
+
+    /// The scene primitives are currently inserted into, which is the scene of the innermost
+    /// isolated layer if there is one.
+    fn innermost_scene(&mut self) -> &mut Scene {
+        if self.isolated_layer_stack.is_empty() {
+            return self;
+        }
+        &mut self.isolated_layer_stack.last_mut().unwrap().scene
+    }
+
+    /// Grows the innermost isolated layer, if any, to cover `bounds` within its clip.
+    fn extend_isolated_layer_bounds(&mut self, bounds: Bounds<ScaledPixels>) {
+        let Some(layer) = self.isolated_layer_stack.last_mut() else {
+            return;
+        };
+        let bounds = bounds.intersect(&layer.clip);
+        if bounds.is_empty() {
+            return;
+        }
+        layer.bounds = if layer.bounds.is_empty() {
+            bounds
+        } else {
+            layer.bounds.union(&bounds)
+        };
     }
 
     pub fn insert_primitive(&mut self, primitive: impl Into<Primitive>) {
-        let mut primitive = primitive.into();
+        let primitive = primitive.into();
         let clipped_bounds = primitive
             .bounds()
             .intersect(&primitive.content_mask().bounds);
@@ -72,6 +204,18 @@ impl Scene {
             return;
         }
 
+        self.paint_operations
+            .push(PaintOperation::Primitive(primitive.clone()));
+        self.extend_isolated_layer_bounds(clipped_bounds);
+        self.innermost_scene()
+            .insert_clipped_primitive(primitive, clipped_bounds);
+    }
+
+    fn insert_clipped_primitive(
+        &mut self,
+        mut primitive: Primitive,
+        clipped_bounds: Bounds<ScaledPixels>,
+    ) {
         let order = self
             .layer_stack
             .last()
@@ -82,6 +226,10 @@ impl Scene {
                 shadow.order = order;
                 self.shadows.push(shadow.clone());
             }
+            Primitive::Blur(blur) => {
+                blur.order = order;
+                self.blurs.push(blur.clone());
+            }
             Primitive::Quad(quad) => {
                 quad.order = order;
                 self.quads.push(quad.clone());
@@ -107,16 +255,29 @@ impl Scene {
                 surface.order = order;
                 self.surfaces.push(surface.clone());
             }
+            Primitive::ShaderQuad(quad) => {
+                quad.order = order;
+                self.shader_quads.push(quad.clone());
+            }
         }
-        self.paint_operations
-            .push(PaintOperation::Primitive(primitive));
     }
 
     pub fn replay(&mut self, range: Range<usize>, prev_scene: &Scene) {
         for operation in &prev_scene.paint_operations[range] {
             match operation {
-                PaintOperation::Primitive(primitive) => self.insert_primitive(primitive.clone()),
-                PaintOperation::StartLayer(bounds) => self.push_layer(*bounds),
+                PaintOperation::Primitive(primitive) => {
+                    let mut primitive = primitive.clone();
+                    // NOTE(mdeand): The stops of the previous scene are gone with it.
+                    if let Some(background) = primitive.background_mut() {
+                        let stops = background.stops(&prev_scene.gradient_stops);
+                        background.stops_offset = self.gradient_stops.len() as u32;
+                        self.gradient_stops.extend_from_slice(stops);
+                    }
+                    self.insert_primitive(primitive)
+                }
+                PaintOperation::StartLayer(bounds, isolation) => {
+                    self.push_layer(*bounds, isolation.clone())
+                }
                 PaintOperation::EndLayer => self.pop_layer(),
             }
         }
@@ -124,6 +285,7 @@ impl Scene {
 
     pub fn finish(&mut self) {
         self.shadows.sort_by_key(|shadow| shadow.order);
+        self.blurs.sort_by_key(|blur| blur.order);
         self.quads.sort_by_key(|quad| quad.order);
         self.paths.sort_by_key(|path| path.order);
         self.underlines.sort_by_key(|underline| underline.order);
@@ -132,6 +294,19 @@ impl Scene {
         self.polychrome_sprites
             .sort_by_key(|sprite| (sprite.order, sprite.tile.tile_id));
         self.surfaces.sort_by_key(|surface| surface.order);
+        self.shader_quads
+            .sort_by_key(|quad| (quad.order, quad.source.hash));
+        self.layers.sort_by_key(|layer| layer.order);
+    }
+
+    /// Numbers the scene of a finished frame and records where it differs from the scene of
+    /// the frame it replaces.
+    pub(crate) fn track_damage(&mut self, prev_scene: &Scene) {
+        self.id = NEXT_SCENE_ID.fetch_add(1, Ordering::Relaxed);
+        self.damage = (prev_scene.id != 0).then(|| Damage {
+            since: prev_scene.id,
+            bounds: damaged_bounds(prev_scene, self),
+        });
     }
 
     pub(crate) fn batches(&self) -> impl Iterator<Item = PrimitiveBatch<'_>> {
@@ -139,6 +314,9 @@ impl Scene {
             shadows: &self.shadows,
             shadows_start: 0,
             shadows_iter: self.shadows.iter().peekable(),
+            blurs: &self.blurs,
+            blurs_start: 0,
+            blurs_iter: self.blurs.iter().peekable(),
             quads: &self.quads,
             quads_start: 0,
             quads_iter: self.quads.iter().peekable(),
@@ -157,69 +335,215 @@ impl Scene {
             surfaces: &self.surfaces,
             surfaces_start: 0,
             surfaces_iter: self.surfaces.iter().peekable(),
+            shader_quads: &self.shader_quads,
+            shader_quads_start: 0,
+            shader_quads_iter: self.shader_quads.iter().peekable(),
+            layers: &self.layers,
+            layers_start: 0,
+            layers_iter: self.layers.iter().peekable(),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Default)]
-pub(crate) enum PrimitiveKind {
+/// The kinds of primitives a scene is made of, which renderers draw in batches of one kind.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub enum PrimitiveKind {
+    /// Box shadows.
     Shadow,
+    /// Backdrop and element blurs.
+    Blur,
+    /// Rectangles, including their borders and backgrounds.
     #[default]
     Quad,
+    /// Filled vector paths.
     Path,
+    /// Straight and wavy underlines.
     Underline,
+    /// Single-colored sprites, such as glyphs.
     MonochromeSprite,
+    /// Multicolored sprites, such as images and emoji.
     PolychromeSprite,
+    /// Externally rendered surfaces.
     Surface,
+    /// Quads shaded by custom WGSL.
+    ShaderQuad,
+    /// Isolated layers, composited onto the frame at once.
+    Layer,
 }
 
+#[derive(PartialEq)]
 pub(crate) enum PaintOperation {
     Primitive(Primitive),
-    StartLayer(Bounds<ScaledPixels>),
+    StartLayer(Bounds<ScaledPixels>, Option<LayerIsolation>),
     EndLayer,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub(crate) enum Primitive {
     Shadow(Shadow),
+    Blur(Blur),
     Quad(Quad),
     Path(Path<ScaledPixels>),
     Underline(Underline),
     MonochromeSprite(MonochromeSprite),
     PolychromeSprite(PolychromeSprite),
     Surface(PaintSurface),
+    ShaderQuad(PaintShaderQuad),
 }
 
 impl Primitive {
-    pub fn bounds(&self) -> &Bounds<ScaledPixels> {
+    /// The bounds the primitive covers on screen, after its transformation.
+    pub fn bounds(&self) -> Bounds<ScaledPixels> {
         match self {
-            Primitive::Shadow(shadow) => &shadow.bounds,
-            Primitive::Quad(quad) => &quad.bounds,
-            Primitive::Path(path) => &path.bounds,
-            Primitive::Underline(underline) => &underline.bounds,
-            Primitive::MonochromeSprite(sprite) => &sprite.bounds,
-            Primitive::PolychromeSprite(sprite) => &sprite.bounds,
-            Primitive::Surface(surface) => &surface.bounds,
+            // NOTE(mdeand): The blur fades out over three standard deviations past the bounds.
+            Primitive::Shadow(shadow) => shadow
+                .transformation
+                .apply_to_bounds(&shadow.bounds.dilate(shadow.blur_radius * 3.)),
+            Primitive::Blur(blur) => blur.bounds,
+            Primitive::Quad(quad) => quad.transformation.apply_to_bounds(&quad.bounds),
+            Primitive::Path(path) => path.bounds,
+            Primitive::Underline(underline) => {
+                underline.transformation.apply_to_bounds(&underline.bounds)
+            }
+            Primitive::MonochromeSprite(sprite) => {
+                sprite.transformation.apply_to_bounds(&sprite.bounds)
+            }
+            Primitive::PolychromeSprite(sprite) => {
+                sprite.transformation.apply_to_bounds(&sprite.bounds)
+            }
+            Primitive::Surface(surface) => surface.bounds,
+            Primitive::ShaderQuad(quad) => quad.bounds,
         }
     }
 
     pub fn content_mask(&self) -> &ContentMask<ScaledPixels> {
         match self {
             Primitive::Shadow(shadow) => &shadow.content_mask,
+            Primitive::Blur(blur) => &blur.content_mask,
             Primitive::Quad(quad) => &quad.content_mask,
             Primitive::Path(path) => &path.content_mask,
             Primitive::Underline(underline) => &underline.content_mask,
             Primitive::MonochromeSprite(sprite) => &sprite.content_mask,
             Primitive::PolychromeSprite(sprite) => &sprite.content_mask,
             Primitive::Surface(surface) => &surface.content_mask,
+            Primitive::ShaderQuad(quad) => &quad.content_mask,
+        }
+    }
+
+    /// The background the primitive is filled with, if it has one.
+    fn background(&self) -> Option<&SceneBackground> {
+        match self {
+            Primitive::Quad(quad) => Some(&quad.background),
+            Primitive::Path(path) => Some(&path.color),
+            _ => None,
         }
     }
+
+    fn background_mut(&mut self) -> Option<&mut SceneBackground> {
+        match self {
+            Primitive::Quad(quad) => Some(&mut quad.background),
+            Primitive::Path(path) => Some(&mut path.color),
+            _ => None,
+        }
+    }
+}
+
+/// Whether an operation of the previous scene, whose gradient stops are `prev_stops`, paints
+/// the same as one of the current scene, whose gradient stops are `stops`.
+fn same_operation(
+    prev: &PaintOperation,
+    prev_stops: &[LinearColorStop],
+    operation: &PaintOperation,
+    stops: &[LinearColorStop],
+) -> bool {
+    if prev != operation {
+        return false;
+    }
+    let (PaintOperation::Primitive(prev), PaintOperation::Primitive(primitive)) = (prev, operation)
+    else {
+        return true;
+    };
+    prev.background()
+        .zip(primitive.background())
+        .is_none_or(|(prev, background)| prev.stops(prev_stops) == background.stops(stops))
+}
+
+/// Compares the paint operations of two scenes, returning the bounds of those that differ.
+///
+/// Frames mostly repaint what they painted before in the same order, so only the operations
+/// between the longest common prefix and suffix are considered changed.
+fn damaged_bounds(prev_scene: &Scene, scene: &Scene) -> Vec<Bounds<ScaledPixels>> {
+    let prev_operations = &prev_scene.paint_operations[..];
+    let operations = &scene.paint_operations[..];
+    let same = |prev: &PaintOperation, operation: &PaintOperation| {
+        same_operation(
+            prev,
+            &prev_scene.gradient_stops,
+            operation,
+            &scene.gradient_stops,
+        )
+    };
+    let prefix_len = prev_operations
+        .iter()
+        .zip(operations)
+        .take_while(|(prev, operation)| same(*prev, *operation))
+        .count();
+    let suffix_len = prev_operations[prefix_len..]
+        .iter()
+        .rev()
+        .zip(operations[prefix_len..].iter().rev())
+        .take_while(|(prev, operation)| same(*prev, *operation))
+        .count();
+
+    let mut bounds = Vec::new();
+    for operations in [prev_operations, operations] {
+        let changed = prefix_len..operations.len() - suffix_len;
+        // NOTE(mdeand): A changed layer boundary changes how the whole layer is composited, so
+        // NOTE(mdeand): the bounds of the layers around the changed operations are tracked.
+        // NOTE(mdeand): Blurred layers spread their primitives over three standard deviations,
+        // NOTE(mdeand): so how far the primitives within them spread is tracked as well.
+        let mut layer_stack = Vec::new();
+        for (ix, operation) in operations[..changed.end].iter().enumerate() {
+            let operation_bounds = match operation {
+                PaintOperation::Primitive(primitive) => {
+                    let bounds = primitive
+                        .bounds()
+                        .intersect(&primitive.content_mask().bounds);
+                    match layer_stack.last() {
+                        Some((_, spread)) if *spread > ScaledPixels(0.) => bounds.dilate(*spread),
+                        _ => bounds,
+                    }
+                }
+                PaintOperation::StartLayer(layer_bounds, isolation) => {
+                    let spread = layer_stack
+                        .last()
+                        .map_or(ScaledPixels(0.), |(_, spread)| *spread)
+                        + isolation
+                            .as_ref()
+                            .map_or(ScaledPixels(0.), |isolation| isolation.blur_radius * 3.);
+                    layer_stack.push((*layer_bounds, spread));
+                    *layer_bounds
+                }
+                PaintOperation::EndLayer => layer_stack
+                    .pop()
+                    .map(|(layer_bounds, _)| layer_bounds)
+                    .unwrap_or_default(),
+            };
+            if changed.contains(&ix) && !operation_bounds.is_empty() {
+                bounds.push(operation_bounds);
+            }
+        }
+    }
+    bounds
 }
 
 struct BatchIterator<'a> {
     shadows: &'a [Shadow],
     shadows_start: usize,
     shadows_iter: Peekable<slice::Iter<'a, Shadow>>,
+    blurs: &'a [Blur],
+    blurs_start: usize,
+    blurs_iter: Peekable<slice::Iter<'a, Blur>>,
     quads: &'a [Quad],
     quads_start: usize,
     quads_iter: Peekable<slice::Iter<'a, Quad>>,
@@ -238,6 +562,12 @@ struct BatchIterator<'a> {
     surfaces: &'a [PaintSurface],
     surfaces_start: usize,
     surfaces_iter: Peekable<slice::Iter<'a, PaintSurface>>,
+    shader_quads: &'a [PaintShaderQuad],
+    shader_quads_start: usize,
+    shader_quads_iter: Peekable<slice::Iter<'a, PaintShaderQuad>>,
+    layers: &'a [Layer],
+    layers_start: usize,
+    layers_iter: Peekable<slice::Iter<'a, Layer>>,
 }
 
 impl<'a> Iterator for BatchIterator<'a> {
@@ -249,6 +579,7 @@ impl<'a> Iterator for BatchIterator<'a> {
                 self.shadows_iter.peek().map(|s| s.order),
                 PrimitiveKind::Shadow,
             ),
+            (self.blurs_iter.peek().map(|b| b.order), PrimitiveKind::Blur),
             (self.quads_iter.peek().map(|q| q.order), PrimitiveKind::Quad),
             (self.paths_iter.peek().map(|q| q.order), PrimitiveKind::Path),
             (
@@ -267,6 +598,14 @@ impl<'a> Iterator for BatchIterator<'a> {
                 self.surfaces_iter.peek().map(|s| s.order),
                 PrimitiveKind::Surface,
             ),
+            (
+                self.shader_quads_iter.peek().map(|q| q.order),
+                PrimitiveKind::ShaderQuad,
+            ),
+            (
+                self.layers_iter.peek().map(|l| l.order),
+                PrimitiveKind::Layer,
+            ),
         ];
         orders_and_kinds.sort_by_key(|(order, kind)| (order.unwrap_or(u32::MAX), *kind));
 
@@ -295,6 +634,20 @@ impl<'a> Iterator for BatchIterator<'a> {
                     &self.shadows[shadows_start..shadows_end],
                 ))
             }
+            PrimitiveKind::Blur => {
+                let blurs_start = self.blurs_start;
+                let mut blurs_end = blurs_start + 1;
+                self.blurs_iter.next();
+                while self
+                    .blurs_iter
+                    .next_if(|blur| (blur.order, batch_kind) < max_order_and_kind)
+                    .is_some()
+                {
+                    blurs_end += 1;
+                }
+                self.blurs_start = blurs_end;
+                Some(PrimitiveBatch::Blurs(&self.blurs[blurs_start..blurs_end]))
+            }
             PrimitiveKind::Quad => {
                 let quads_start = self.quads_start;
                 let mut quads_end = quads_start + 1;
@@ -397,6 +750,38 @@ impl<'a> Iterator for BatchIterator<'a> {
                     &self.surfaces[surfaces_start..surfaces_end],
                 ))
             }
+            PrimitiveKind::ShaderQuad => {
+                let quads_start = self.shader_quads_start;
+                let mut quads_end = quads_start + 1;
+                self.shader_quads_iter.next();
+                while self
+                    .shader_quads_iter
+                    .next_if(|quad| (quad.order, batch_kind) < max_order_and_kind)
+                    .is_some()
+                {
+                    quads_end += 1;
+                }
+                self.shader_quads_start = quads_end;
+                Some(PrimitiveBatch::ShaderQuads(
+                    &self.shader_quads[quads_start..quads_end],
+                ))
+            }
+            PrimitiveKind::Layer => {
+                let layers_start = self.layers_start;
+                let mut layers_end = layers_start + 1;
+                self.layers_iter.next();
+                while self
+                    .layers_iter
+                    .next_if(|layer| (layer.order, batch_kind) < max_order_and_kind)
+                    .is_some()
+                {
+                    layers_end += 1;
+                }
+                self.layers_start = layers_end;
+                Some(PrimitiveBatch::Layers(
+                    &self.layers[layers_start..layers_end],
+                ))
+            }
         }
     }
 }
@@ -404,6 +789,7 @@ impl<'a> Iterator for BatchIterator<'a> {
 #[derive(Debug)]
 pub(crate) enum PrimitiveBatch<'a> {
     Shadows(&'a [Shadow]),
+    Blurs(&'a [Blur]),
     Quads(&'a [Quad]),
     Paths(&'a [Path<ScaledPixels>]),
     Underlines(&'a [Underline]),
@@ -416,19 +802,55 @@ pub(crate) enum PrimitiveBatch<'a> {
         sprites: &'a [PolychromeSprite],
     },
     Surfaces(&'a [PaintSurface]),
+    ShaderQuads(&'a [PaintShaderQuad]),
+    Layers(&'a [Layer]),
+}
+
+impl PrimitiveBatch<'_> {
+    pub(crate) fn kind(&self) -> PrimitiveKind {
+        match self {
+            PrimitiveBatch::Shadows(_) => PrimitiveKind::Shadow,
+            PrimitiveBatch::Blurs(_) => PrimitiveKind::Blur,
+            PrimitiveBatch::Quads(_) => PrimitiveKind::Quad,
+            PrimitiveBatch::Paths(_) => PrimitiveKind::Path,
+            PrimitiveBatch::Underlines(_) => PrimitiveKind::Underline,
+            PrimitiveBatch::MonochromeSprites { .. } => PrimitiveKind::MonochromeSprite,
+            PrimitiveBatch::PolychromeSprites { .. } => PrimitiveKind::PolychromeSprite,
+            PrimitiveBatch::Surfaces(_) => PrimitiveKind::Surface,
+            PrimitiveBatch::ShaderQuads(_) => PrimitiveKind::ShaderQuad,
+            PrimitiveBatch::Layers(_) => PrimitiveKind::Layer,
+        }
+    }
+
+    /// How many primitives the batch draws.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            PrimitiveBatch::Shadows(shadows) => shadows.len(),
+            PrimitiveBatch::Blurs(blurs) => blurs.len(),
+            PrimitiveBatch::Quads(quads) => quads.len(),
+            PrimitiveBatch::Paths(paths) => paths.len(),
+            PrimitiveBatch::Underlines(underlines) => underlines.len(),
+            PrimitiveBatch::MonochromeSprites { sprites, .. } => sprites.len(),
+            PrimitiveBatch::PolychromeSprites { sprites, .. } => sprites.len(),
+            PrimitiveBatch::Surfaces(surfaces) => surfaces.len(),
+            PrimitiveBatch::ShaderQuads(quads) => quads.len(),
+            PrimitiveBatch::Layers(layers) => layers.len(),
+        }
+    }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq)]
 #[repr(C)]
 pub(crate) struct Quad {
     pub order: DrawOrder,
     pub border_style: BorderStyle,
     pub bounds: Bounds<ScaledPixels>,
     pub content_mask: ContentMask<ScaledPixels>,
-    pub background: Background,
+    pub background: SceneBackground,
     pub border_color: Hsla,
     pub corner_radii: Corners<ScaledPixels>,
     pub border_widths: Edges<ScaledPixels>,
+    pub transformation: TransformationMatrix,
 }
 
 impl From<Quad> for Primitive {
@@ -437,7 +859,77 @@ impl From<Quad> for Primitive {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A [`Background`] as quads and paths hold it, with its gradient stops kept in the root
+/// scene's `gradient_stops` rather than inline, so the primitives stay small.
+///
+/// Two backgrounds are equal when everything but where their stops are is, since the same
+/// stops end up at different offsets from one frame to the next. Their stops have to be
+/// compared separately.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub(crate) struct SceneBackground {
+    pub tag: BackgroundTag,
+    pub color_space: ColorSpace,
+    pub solid: Hsla,
+    pub gradient_angle_or_pattern_height: f32,
+    pub stops_offset: u32,
+    pub gradient_center: Point<f32>,
+    pub stop_count: u32,
+    pub pad: u32, // align to 8 bytes
+}
+
+impl SceneBackground {
+    /// Appends the stops of `background` to `gradient_stops`, returning the background that
+    /// refers to them.
+    pub(crate) fn new(background: &Background, gradient_stops: &mut Vec<LinearColorStop>) -> Self {
+        let stops = background.gradient_stops();
+        let stops_offset = gradient_stops.len() as u32;
+        gradient_stops.extend_from_slice(stops);
+        Self {
+            tag: background.tag,
+            color_space: background.color_space,
+            solid: background.solid,
+            gradient_angle_or_pattern_height: background.gradient_angle_or_pattern_height,
+            stops_offset,
+            gradient_center: background.gradient_center,
+            stop_count: stops.len() as u32,
+            pad: 0,
+        }
+    }
+
+    /// The stops of the background within the root scene's `gradient_stops`.
+    pub(crate) fn stops<'a>(&self, gradient_stops: &'a [LinearColorStop]) -> &'a [LinearColorStop] {
+        let start = self.stops_offset as usize;
+        gradient_stops
+            .get(start..start + self.stop_count as usize)
+            .unwrap_or_default()
+    }
+}
+
+impl Default for SceneBackground {
+    fn default() -> Self {
+        Hsla::default().into()
+    }
+}
+
+impl From<Hsla> for SceneBackground {
+    fn from(color: Hsla) -> Self {
+        Self::new(&color.into(), &mut Vec::new())
+    }
+}
+
+impl PartialEq for SceneBackground {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+            && self.color_space == other.color_space
+            && self.solid == other.solid
+            && self.gradient_angle_or_pattern_height == other.gradient_angle_or_pattern_height
+            && self.gradient_center == other.gradient_center
+            && self.stop_count == other.stop_count
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub(crate) struct Underline {
     pub order: DrawOrder,
@@ -447,6 +939,7 @@ pub(crate) struct Underline {
     pub color: Hsla,
     pub thickness: ScaledPixels,
     pub wavy: u32,
+    pub transformation: TransformationMatrix,
 }
 
 impl From<Underline> for Primitive {
@@ -455,7 +948,7 @@ impl From<Underline> for Primitive {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[repr(C)]
 pub(crate) struct Shadow {
     pub order: DrawOrder,
@@ -464,6 +957,7 @@ pub(crate) struct Shadow {
     pub corner_radii: Corners<ScaledPixels>,
     pub content_mask: ContentMask<ScaledPixels>,
     pub color: Hsla,
+    pub transformation: TransformationMatrix,
 }
 
 impl From<Shadow> for Primitive {
@@ -472,6 +966,25 @@ impl From<Shadow> for Primitive {
     }
 }
 
+/// Blurs what has already been drawn within its rounded bounds, which is how both backdrop
+/// and element blurs are drawn: the former is inserted before the element paints, the latter
+/// after.
+#[derive(Debug, Clone, PartialEq)]
+#[repr(C)]
+pub(crate) struct Blur {
+    pub order: DrawOrder,
+    pub radius: ScaledPixels,
+    pub bounds: Bounds<ScaledPixels>,
+    pub corner_radii: Corners<ScaledPixels>,
+    pub content_mask: ContentMask<ScaledPixels>,
+}
+
+impl From<Blur> for Primitive {
+    fn from(blur: Blur) -> Self {
+        Primitive::Blur(blur)
+    }
+}
+
 /// The style of a border.
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[repr(C)]
@@ -483,6 +996,39 @@ pub enum BorderStyle {
     Dashed = 1,
 }
 
+/// How the colors of an element are mixed with the colors painted beneath it, like the CSS
+/// `mix-blend-mode` property.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[repr(u32)]
+pub enum BlendMode {
+    /// The element is painted over what's beneath it.
+    #[default]
+    Normal = 0,
+    /// Multiplies the colors, which always darkens them.
+    Multiply = 1,
+    /// Multiplies the complements of the colors, which always lightens them.
+    Screen = 2,
+    /// Multiplies or screens the colors, depending on the color beneath.
+    Overlay = 3,
+    /// Keeps the darker of the colors.
+    Darken = 4,
+    /// Keeps the lighter of the colors.
+    Lighten = 5,
+    /// Brightens the color beneath to reflect the element's color.
+    ColorDodge = 6,
+    /// Darkens the color beneath to reflect the element's color.
+    ColorBurn = 7,
+    /// Multiplies or screens the colors, depending on the element's color.
+    HardLight = 8,
+    /// Darkens or lightens the colors, depending on the element's color.
+    SoftLight = 9,
+    /// Subtracts the darker of the colors from the lighter one.
+    Difference = 10,
+    /// Like `Difference`, but with lower contrast.
+    Exclusion = 11,
+}
+
 /// A data type representing a 2 dimensional transformation that can be applied to an element.
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
@@ -578,6 +1124,66 @@ impl TransformationMatrix {
         }
         Point::new(output[0].into(), output[1].into())
     }
+
+    /// The transformation that undoes this one, or `None` if it collapses the plane onto a
+    /// line or a point.
+    pub fn inverse(&self) -> Option<Self> {
+        let [[a, b], [c, d]] = self.rotation_scale;
+        let determinant = a * d - b * c;
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+        let rotation_scale = [
+            [d / determinant, -b / determinant],
+            [-c / determinant, a / determinant],
+        ];
+        let [x, y] = self.translation;
+        Some(Self {
+            rotation_scale,
+            translation: [
+                -(rotation_scale[0][0] * x + rotation_scale[0][1] * y),
+                -(rotation_scale[1][0] * x + rotation_scale[1][1] * y),
+            ],
+        })
+    }
+
+    /// The same transformation, for coordinates scaled by `factor`. Transformations of
+    /// primitives are in device pixels, this converts them to and from logical pixels.
+    pub fn scale_translation(mut self, factor: f32) -> Self {
+        self.translation = self.translation.map(|component| component * factor);
+        self
+    }
+
+    /// The smallest axis aligned bounds containing the transformed `bounds`.
+    pub fn apply_to_bounds(&self, bounds: &Bounds<ScaledPixels>) -> Bounds<ScaledPixels> {
+        if *self == Self::unit() {
+            return *bounds;
+        }
+
+        let corners = [
+            bounds.origin,
+            bounds.top_right(),
+            bounds.bottom_left(),
+            bounds.bottom_right(),
+        ]
+        .map(|corner| {
+            let transformed = self.apply(Point::new(Pixels(corner.x.0), Pixels(corner.y.0)));
+            [transformed.x.0, transformed.y.0]
+        });
+        let min = corners
+            .iter()
+            .fold([f32::MAX; 2], |min, p| [min[0].min(p[0]), min[1].min(p[1])]);
+        let max = corners
+            .iter()
+            .fold([f32::MIN; 2], |max, p| [max[0].max(p[0]), max[1].max(p[1])]);
+        Bounds {
+            origin: point(ScaledPixels(min[0]), ScaledPixels(min[1])),
+            size: Size {
+                width: ScaledPixels(max[0] - min[0]),
+                height: ScaledPixels(max[1] - min[1]),
+            },
+        }
+    }
 }
 
 impl Default for TransformationMatrix {
@@ -586,7 +1192,7 @@ impl Default for TransformationMatrix {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[repr(C)]
 pub(crate) struct MonochromeSprite {
     pub order: DrawOrder,
@@ -604,7 +1210,7 @@ impl From<MonochromeSprite> for Primitive {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[repr(C)]
 pub(crate) struct PolychromeSprite {
     pub order: DrawOrder,
@@ -615,6 +1221,7 @@ pub(crate) struct PolychromeSprite {
     pub content_mask: ContentMask<ScaledPixels>,
     pub corner_radii: Corners<ScaledPixels>,
     pub tile: AtlasTile,
+    pub transformation: TransformationMatrix,
 }
 
 impl From<PolychromeSprite> for Primitive {
@@ -624,13 +1231,13 @@ impl From<PolychromeSprite> for Primitive {
 }
 
 /// The backing content for a painted surface.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum SurfaceContent {
     /// A WGPU surface managed by the SurfaceRegistry.
     Wgpu(SurfaceId),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) struct PaintSurface {
     pub order: DrawOrder,
     pub bounds: Bounds<ScaledPixels>,
@@ -644,18 +1251,112 @@ impl From<PaintSurface> for Primitive {
     }
 }
 
+/// The WGSL source of a shader quad, along with its hash, which pipelines are cached by.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ShaderQuadSource {
+    pub hash: u64,
+    pub wgsl: SharedString,
+}
+
+impl ShaderQuadSource {
+    pub fn new(wgsl: SharedString) -> Self {
+        let mut hasher = collections::FxHasher::default();
+        wgsl.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            wgsl,
+        }
+    }
+}
+
+/// A quad whose pixels are shaded by a user supplied WGSL function.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PaintShaderQuad {
+    pub order: DrawOrder,
+    pub bounds: Bounds<ScaledPixels>,
+    pub content_mask: ContentMask<ScaledPixels>,
+    pub source: ShaderQuadSource,
+    pub uniforms: [f32; MAX_SHADER_QUAD_UNIFORMS],
+}
+
+/// The uniforms a shader quad painted with `values` is drawn with. Values beyond
+/// [`MAX_SHADER_QUAD_UNIFORMS`] are dropped with a warning, and missing ones are zero.
+pub(crate) fn shader_quad_uniforms(values: &[f32]) -> [f32; MAX_SHADER_QUAD_UNIFORMS] {
+    if values.len() > MAX_SHADER_QUAD_UNIFORMS {
+        log::warn!(
+            "shader quad painted with {} uniforms, only the first {} are used",
+            values.len(),
+            MAX_SHADER_QUAD_UNIFORMS
+        );
+    }
+    let mut uniforms = [0.0; MAX_SHADER_QUAD_UNIFORMS];
+    for (uniform, value) in uniforms.iter_mut().zip(values) {
+        *uniform = *value;
+    }
+    uniforms
+}
+
+impl From<PaintShaderQuad> for Primitive {
+    fn from(quad: PaintShaderQuad) -> Self {
+        Primitive::ShaderQuad(quad)
+    }
+}
+
+/// How an isolated layer is composited onto what's painted beneath it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LayerIsolation {
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+    /// The path the layer is clipped to, if any.
+    pub mask: Option<Path<ScaledPixels>>,
+    /// The standard deviation of the blur the layer is filtered with, or zero.
+    pub blur_radius: ScaledPixels,
+}
+
+/// A subtree painted into a scene of its own, which renderers draw offscreen and composite
+/// onto the frame at once, so its primitives don't show through each other when it's faded.
+#[derive(Default)]
+pub(crate) struct Layer {
+    pub order: DrawOrder,
+    /// The bounds the layer's primitives may cover, which its painted bounds are clipped to.
+    pub clip: Bounds<ScaledPixels>,
+    /// The bounds covered by the layer's primitives.
+    pub bounds: Bounds<ScaledPixels>,
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+    /// The path the layer is clipped to, whose coverage the layer is multiplied by.
+    pub mask: Option<Path<ScaledPixels>>,
+    /// The standard deviation of the blur the layer is filtered with before it's clipped to its
+    /// mask, or zero.
+    pub blur_radius: ScaledPixels,
+    pub scene: Scene,
+}
+
+impl Debug for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Layer")
+            .field("order", &self.order)
+            .field("bounds", &self.bounds)
+            .field("opacity", &self.opacity)
+            .field("blend_mode", &self.blend_mode)
+            .field("mask", &self.mask)
+            .field("blur_radius", &self.blur_radius)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) struct PathId(pub(crate) usize);
 
 /// A line made up of a series of vertices and control points.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Path<P: Clone + Debug + Default + PartialEq> {
     pub(crate) id: PathId,
     pub(crate) order: DrawOrder,
     pub(crate) bounds: Bounds<P>,
     pub(crate) content_mask: ContentMask<P>,
     pub(crate) vertices: Vec<PathVertex<P>>,
-    pub(crate) color: Background,
+    pub(crate) color: SceneBackground,
     start: Point<P>,
     current: Point<P>,
     contour_count: usize,
@@ -778,7 +1479,6 @@ impl<T> Path<T>
 where
     T: Clone + Debug + Default + PartialEq + PartialOrd + Add<T, Output = T> + Sub<Output = T>,
 {
-    #[allow(unused)]
     pub(crate) fn clipped_bounds(&self) -> Bounds<T> {
         self.bounds.intersect(&self.content_mask.bounds)
     }
@@ -790,7 +1490,7 @@ impl From<Path<ScaledPixels>> for Primitive {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[repr(C)]
 pub(crate) struct PathVertex<P: Clone + Debug + Default + PartialEq> {
     pub(crate) xy_position: Point<P>,
@@ -807,3 +1507,174 @@ impl PathVertex<Pixels> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{blue, green, linear_color_stop, linear_gradient, px, red, size};
+
+    fn gradient_scene(prev_scene: &Scene, end: Hsla) -> Scene {
+        let bounds = Bounds::new(point(px(0.), px(0.)), size(px(10.), px(10.))).scale(1.0);
+        let mut scene = Scene::default();
+        let background = scene.push_background(&linear_gradient(
+            0.,
+            linear_color_stop(red(), 0.),
+            linear_color_stop(end, 1.),
+        ));
+        scene.insert_primitive(Quad {
+            bounds,
+            content_mask: ContentMask {
+                bounds,
+                ..Default::default()
+            },
+            background,
+            ..Default::default()
+        });
+        scene.finish();
+        scene.track_damage(prev_scene);
+        scene
+    }
+
+    #[test]
+    fn test_changed_gradient_stops_are_damaged() {
+        let first = gradient_scene(&Scene::default(), blue());
+        let second = gradient_scene(&first, blue());
+        assert!(second.damage.as_ref().unwrap().bounds.is_empty());
+
+        // NOTE(mdeand): Both the quad that was painted before and the one painted now are damaged.
+        let third = gradient_scene(&second, green());
+        assert_eq!(
+            third.damage.as_ref().unwrap().bounds,
+            [third.quads[0].bounds, third.quads[0].bounds]
+        );
+    }
+
+    #[test]
+    fn test_changes_within_blurred_layers_damage_what_the_blur_spreads_to() {
+        let blurred_scene = |prev_scene: &Scene, color: Hsla| {
+            let clip = Bounds::new(point(px(0.), px(0.)), size(px(100.), px(100.))).scale(1.0);
+            let mut scene = Scene::default();
+            scene.push_layer(
+                clip,
+                Some(LayerIsolation {
+                    opacity: 1.,
+                    blend_mode: BlendMode::Normal,
+                    mask: None,
+                    blur_radius: ScaledPixels(2.),
+                }),
+            );
+            scene.insert_primitive(Quad {
+                bounds: Bounds::new(point(px(40.), px(40.)), size(px(10.), px(10.))).scale(1.0),
+                content_mask: ContentMask {
+                    bounds: clip,
+                    ..Default::default()
+                },
+                background: color.into(),
+                ..Default::default()
+            });
+            scene.pop_layer();
+            scene.finish();
+            scene.track_damage(prev_scene);
+            scene
+        };
+
+        let first = blurred_scene(&Scene::default(), red());
+        let second = blurred_scene(&first, blue());
+        let blurred_bounds =
+            Bounds::new(point(px(34.), px(34.)), size(px(22.), px(22.))).scale(1.0);
+        assert_eq!(second.layers[0].bounds, blurred_bounds);
+        assert_eq!(
+            second.damage.as_ref().unwrap().bounds,
+            [blurred_bounds, blurred_bounds]
+        );
+    }
+
+    #[test]
+    fn test_isolated_layers_only_damage_what_they_paint() {
+        let mut prev_scene = Scene::default();
+        prev_scene.finish();
+        prev_scene.track_damage(&Scene::default());
+
+        let clip = Bounds::new(point(px(0.), px(0.)), size(px(100.), px(100.))).scale(1.0);
+        let bounds = Bounds::new(point(px(40.), px(40.)), size(px(10.), px(10.))).scale(1.0);
+        let mut scene = Scene::default();
+        scene.push_layer(
+            clip,
+            Some(LayerIsolation {
+                opacity: 0.5,
+                blend_mode: BlendMode::Normal,
+                mask: None,
+                blur_radius: ScaledPixels(0.),
+            }),
+        );
+        scene.insert_primitive(Quad {
+            bounds,
+            content_mask: ContentMask {
+                bounds: clip,
+                ..Default::default()
+            },
+            background: red().into(),
+            ..Default::default()
+        });
+        scene.pop_layer();
+        scene.finish();
+        scene.track_damage(&prev_scene);
+
+        assert_eq!(scene.layers[0].bounds, bounds);
+        assert!(matches!(
+            scene.paint_operations[0],
+            PaintOperation::StartLayer(layer_bounds, _) if layer_bounds == bounds
+        ));
+        assert_eq!(scene.damage.as_ref().unwrap().bounds, [bounds; 3]);
+    }
+
+    #[test]
+    fn test_replayed_primitives_keep_their_gradient_stops() {
+        let prev_scene = gradient_scene(&Scene::default(), blue());
+        let mut scene = Scene::default();
+        scene.push_background(&linear_gradient(
+            0.,
+            linear_color_stop(green(), 0.),
+            linear_color_stop(green(), 1.),
+        ));
+        scene.replay(0..prev_scene.len(), &prev_scene);
+
+        let background = &scene.quads[0].background;
+        assert_eq!(background.stops_offset, 2);
+        assert_eq!(
+            background.stops(&scene.gradient_stops),
+            prev_scene.quads[0]
+                .background
+                .stops(&prev_scene.gradient_stops)
+        );
+    }
+
+    #[test]
+    fn test_shader_quad_sources_are_cached_by_their_wgsl() {
+        let wgsl = "fn shade(input: ShaderQuadInput) -> vec4<f32> { return vec4(1.); }";
+        let source = ShaderQuadSource::new(wgsl.into());
+        assert_eq!(
+            ShaderQuadSource::new(SharedString::from(wgsl.to_string())).hash,
+            source.hash
+        );
+        assert_ne!(
+            ShaderQuadSource::new(wgsl.replace("1.", "0.").into()).hash,
+            source.hash
+        );
+    }
+
+    #[test]
+    fn test_shader_quad_uniforms_are_padded_and_truncated() {
+        let mut uniforms = [0.0; MAX_SHADER_QUAD_UNIFORMS];
+        uniforms[..2].copy_from_slice(&[1., 2.]);
+        assert_eq!(shader_quad_uniforms(&[1., 2.]), uniforms);
+
+        let values = (0..MAX_SHADER_QUAD_UNIFORMS + 4)
+            .map(|value| value as f32)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shader_quad_uniforms(&values),
+            values[..MAX_SHADER_QUAD_UNIFORMS]
+        );
+    }
+}
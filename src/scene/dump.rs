@@ -0,0 +1,990 @@
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AtlasTextureId, AtlasTextureKind, AtlasTile, Background, BlendMode, Blur, BorderStyle, Bounds,
+    ColorSpace, ContentMask, Corners, DevicePixels, Edges, Hsla, Layer, LinearColorStop,
+    MAX_SHADER_QUAD_UNIFORMS, MonochromeSprite, PaintShaderQuad, Path, PathId, PathVertex,
+    PolychromeSprite, PrimitiveBatch, Quad, ScaledPixels, Scene, SceneBackground, ShaderQuadSource,
+    Shadow, TileId, TransformationMatrix, Underline, color::BackgroundTag, conic_gradient,
+    linear_gradient_with_stops, point, radial_gradient,
+};
+
+/// A serializable snapshot of the primitives in a rendered frame, in the order they're drawn.
+///
+/// All positions are in device pixels. Colors are stored as `[h, s, l, a]`, so dumps round
+/// trip without losing precision.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneDump {
+    /// The primitives of the frame, in draw order.
+    pub primitives: Vec<PrimitiveDump>,
+}
+
+/// A single primitive in a [`SceneDump`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PrimitiveDump {
+    /// A blurred, rounded rectangle drawn beneath other primitives.
+    Shadow {
+        /// The draw order of the shadow.
+        order: u32,
+        /// The bounds of the shadow, before blurring and its transformation.
+        bounds: Bounds<f32>,
+        /// The bounds the shadow is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the shadow's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The corner radii, as `[top_left, top_right, bottom_right, bottom_left]`.
+        corner_radii: [f32; 4],
+        /// The radius of the blur.
+        blur_radius: f32,
+        /// The color of the shadow.
+        color: [f32; 4],
+        /// The row-major 2x2 rotation and scale of the shadow.
+        rotation_scale: [[f32; 2]; 2],
+        /// The translation of the shadow.
+        translation: [f32; 2],
+    },
+    /// A blur of everything drawn before it within its rounded bounds.
+    Blur {
+        /// The draw order of the blur.
+        order: u32,
+        /// The bounds of the blurred region.
+        bounds: Bounds<f32>,
+        /// The bounds the blur is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the blur's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The corner radii, as `[top_left, top_right, bottom_right, bottom_left]`.
+        corner_radii: [f32; 4],
+        /// The radius of the blur.
+        radius: f32,
+    },
+    /// A rectangle with an optional border and rounded corners.
+    Quad {
+        /// The draw order of the quad.
+        order: u32,
+        /// The bounds of the quad, before its transformation.
+        bounds: Bounds<f32>,
+        /// The bounds the quad is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the quad's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The fill of the quad.
+        background: BackgroundDump,
+        /// The color of the border.
+        border_color: [f32; 4],
+        /// Whether the border is dashed.
+        border_dashed: bool,
+        /// The border widths, as `[top, right, bottom, left]`.
+        border_widths: [f32; 4],
+        /// The corner radii, as `[top_left, top_right, bottom_right, bottom_left]`.
+        corner_radii: [f32; 4],
+        /// The row-major 2x2 rotation and scale of the quad.
+        rotation_scale: [[f32; 2]; 2],
+        /// The translation of the quad.
+        translation: [f32; 2],
+    },
+    /// A filled path, made of triangles.
+    Path {
+        /// The draw order of the path.
+        order: u32,
+        /// The bounds of the path.
+        bounds: Bounds<f32>,
+        /// The bounds the path is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the path's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The fill of the path.
+        color: BackgroundDump,
+        /// The vertices of the path's triangles.
+        vertices: Vec<PathVertexDump>,
+    },
+    /// A straight or wavy line beneath text.
+    Underline {
+        /// The draw order of the underline.
+        order: u32,
+        /// The bounds of the underline, before its transformation.
+        bounds: Bounds<f32>,
+        /// The bounds the underline is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the underline's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The color of the underline.
+        color: [f32; 4],
+        /// The thickness of the line.
+        thickness: f32,
+        /// Whether the line is wavy.
+        wavy: bool,
+        /// The row-major 2x2 rotation and scale of the underline.
+        rotation_scale: [[f32; 2]; 2],
+        /// The translation of the underline.
+        translation: [f32; 2],
+    },
+    /// A single-channel sprite from the atlas, such as a glyph, tinted with a color.
+    MonochromeSprite {
+        /// The draw order of the sprite.
+        order: u32,
+        /// The bounds of the sprite, before its transformation.
+        bounds: Bounds<f32>,
+        /// The bounds the sprite is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the sprite's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The color of the sprite.
+        color: [f32; 4],
+        /// The atlas tile the sprite is sampled from.
+        tile: SpriteTileDump,
+        /// The row-major 2x2 rotation and scale of the sprite.
+        rotation_scale: [[f32; 2]; 2],
+        /// The translation of the sprite.
+        translation: [f32; 2],
+    },
+    /// A full-color sprite from the atlas, such as an image or emoji.
+    PolychromeSprite {
+        /// The draw order of the sprite.
+        order: u32,
+        /// The bounds of the sprite, before its transformation.
+        bounds: Bounds<f32>,
+        /// The bounds the sprite is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the sprite's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The corner radii, as `[top_left, top_right, bottom_right, bottom_left]`.
+        corner_radii: [f32; 4],
+        /// Whether the sprite is drawn in grayscale.
+        grayscale: bool,
+        /// The opacity of the sprite.
+        opacity: f32,
+        /// The atlas tile the sprite is sampled from.
+        tile: SpriteTileDump,
+        /// The row-major 2x2 rotation and scale of the sprite.
+        rotation_scale: [[f32; 2]; 2],
+        /// The translation of the sprite.
+        translation: [f32; 2],
+    },
+    /// A surface whose contents are rendered outside of GPUI.
+    Surface {
+        /// The draw order of the surface.
+        order: u32,
+        /// The bounds of the surface.
+        bounds: Bounds<f32>,
+        /// The bounds the surface is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the surface's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+    },
+    /// A quad shaded by a user supplied WGSL function.
+    ShaderQuad {
+        /// The draw order of the quad.
+        order: u32,
+        /// The bounds of the quad.
+        bounds: Bounds<f32>,
+        /// The bounds the quad is clipped to.
+        content_mask: Bounds<f32>,
+        /// The radii of the corners of the quad's content mask, as
+        /// `[top_left, top_right, bottom_right, bottom_left]`.
+        #[serde(default)]
+        content_mask_corner_radii: [f32; 4],
+        /// The WGSL source the quad is shaded with.
+        source: String,
+        /// The uniforms the quad was painted with.
+        uniforms: [f32; MAX_SHADER_QUAD_UNIFORMS],
+    },
+    /// A group of primitives drawn offscreen and composited at once.
+    Layer {
+        /// The draw order of the layer.
+        order: u32,
+        /// The bounds covered by the layer's primitives.
+        bounds: Bounds<f32>,
+        /// The opacity the layer is composited with.
+        opacity: f32,
+        /// How the layer's colors are mixed with the colors beneath it.
+        blend_mode: BlendMode,
+        /// The path the layer is clipped to, which is always a [`PrimitiveDump::Path`].
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mask: Option<Box<PrimitiveDump>>,
+        /// The standard deviation of the blur the layer is filtered with, or zero.
+        #[serde(default)]
+        blur_radius: f32,
+        /// The primitives of the layer, in draw order.
+        primitives: Vec<PrimitiveDump>,
+    },
+}
+
+/// The fill of a quad or path in a [`SceneDump`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BackgroundDump {
+    /// A single color.
+    Solid {
+        /// The color.
+        color: [f32; 4],
+    },
+    /// A linear gradient between color stops.
+    LinearGradient {
+        /// The angle of the gradient, in degrees.
+        angle: f32,
+        /// The color space the colors are interpolated in.
+        color_space: ColorSpace,
+        /// The color stops, as `([h, s, l, a], percentage)`.
+        stops: Vec<([f32; 4], f32)>,
+    },
+    /// A radial gradient between color stops.
+    RadialGradient {
+        /// The center of the gradient, relative to the bounds it fills.
+        center: [f32; 2],
+        /// The color space the colors are interpolated in.
+        color_space: ColorSpace,
+        /// The color stops, as `([h, s, l, a], percentage)`.
+        stops: Vec<([f32; 4], f32)>,
+    },
+    /// A conic gradient between color stops.
+    ConicGradient {
+        /// The angle the gradient starts at, in degrees.
+        angle: f32,
+        /// The center of the gradient, relative to the bounds it fills.
+        center: [f32; 2],
+        /// The color space the colors are interpolated in.
+        color_space: ColorSpace,
+        /// The color stops, as `([h, s, l, a], percentage)`.
+        stops: Vec<([f32; 4], f32)>,
+    },
+    /// Diagonal stripes of a single color.
+    PatternSlash {
+        /// The color of the stripes.
+        color: [f32; 4],
+        /// The packed width and interval of the stripes.
+        pattern_height: f32,
+    },
+}
+
+/// A vertex of a path in a [`SceneDump`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PathVertexDump {
+    /// The position of the vertex.
+    pub xy: [f32; 2],
+    /// The position on the quadratic curve the vertex belongs to.
+    pub st: [f32; 2],
+}
+
+/// The atlas tile a sprite is sampled from.
+///
+/// Tiles are only meaningful for the atlas that produced them, so dumps with sprites only
+/// replay correctly in the window they were taken from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteTileDump {
+    /// The index of the atlas texture.
+    pub texture: u32,
+    /// The tile within the texture.
+    pub tile: u32,
+    /// The padding around the tile's contents.
+    pub padding: u32,
+    /// The bounds of the tile within the texture.
+    pub bounds: Bounds<i32>,
+}
+
+impl SceneDump {
+    /// Serializes the dump as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scene dumps are always serializable")
+    }
+
+    /// Deserializes a dump written by [`SceneDump::to_json`].
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("invalid scene dump")
+    }
+
+    pub(crate) fn new(scene: &Scene) -> Self {
+        Self {
+            primitives: Self::dump_primitives(scene, &scene.gradient_stops),
+        }
+    }
+
+    /// Rebuilds a scene that renders the same as the one the dump was taken from.
+    pub(crate) fn to_scene(&self) -> Scene {
+        let mut gradient_stops = Vec::new();
+        let mut scene = Self::load_scene(&self.primitives, &mut gradient_stops);
+        scene.gradient_stops = gradient_stops;
+        scene
+    }
+
+    /// Dumps the primitives of `scene`, which may be the scene of a layer, whose gradients
+    /// are in the `gradient_stops` of the root scene.
+    fn dump_primitives(scene: &Scene, gradient_stops: &[LinearColorStop]) -> Vec<PrimitiveDump> {
+        let mut primitives = Vec::new();
+        for batch in scene.batches() {
+            match batch {
+                PrimitiveBatch::Shadows(shadows) => {
+                    primitives.extend(shadows.iter().map(|shadow| PrimitiveDump::Shadow {
+                        order: shadow.order,
+                        bounds: dump_bounds(&shadow.bounds),
+                        content_mask: dump_bounds(&shadow.content_mask.bounds),
+                        content_mask_corner_radii: dump_corners(&shadow.content_mask.corner_radii),
+                        corner_radii: dump_corners(&shadow.corner_radii),
+                        blur_radius: shadow.blur_radius.0,
+                        color: dump_color(shadow.color),
+                        rotation_scale: shadow.transformation.rotation_scale,
+                        translation: shadow.transformation.translation,
+                    }))
+                }
+                PrimitiveBatch::Blurs(blurs) => {
+                    primitives.extend(blurs.iter().map(|blur| PrimitiveDump::Blur {
+                        order: blur.order,
+                        bounds: dump_bounds(&blur.bounds),
+                        content_mask: dump_bounds(&blur.content_mask.bounds),
+                        content_mask_corner_radii: dump_corners(&blur.content_mask.corner_radii),
+                        corner_radii: dump_corners(&blur.corner_radii),
+                        radius: blur.radius.0,
+                    }))
+                }
+                PrimitiveBatch::Quads(quads) => {
+                    primitives.extend(quads.iter().map(|quad| PrimitiveDump::Quad {
+                        order: quad.order,
+                        bounds: dump_bounds(&quad.bounds),
+                        content_mask: dump_bounds(&quad.content_mask.bounds),
+                        content_mask_corner_radii: dump_corners(&quad.content_mask.corner_radii),
+                        background: BackgroundDump::new(&quad.background, gradient_stops),
+                        border_color: dump_color(quad.border_color),
+                        border_dashed: quad.border_style == BorderStyle::Dashed,
+                        border_widths: [
+                            quad.border_widths.top.0,
+                            quad.border_widths.right.0,
+                            quad.border_widths.bottom.0,
+                            quad.border_widths.left.0,
+                        ],
+                        corner_radii: dump_corners(&quad.corner_radii),
+                        rotation_scale: quad.transformation.rotation_scale,
+                        translation: quad.transformation.translation,
+                    }))
+                }
+                PrimitiveBatch::Paths(paths) => {
+                    primitives.extend(paths.iter().map(|path| dump_path(path, gradient_stops)))
+                }
+                PrimitiveBatch::Underlines(underlines) => {
+                    primitives.extend(underlines.iter().map(|underline| PrimitiveDump::Underline {
+                        order: underline.order,
+                        bounds: dump_bounds(&underline.bounds),
+                        content_mask: dump_bounds(&underline.content_mask.bounds),
+                        content_mask_corner_radii: dump_corners(
+                            &underline.content_mask.corner_radii,
+                        ),
+                        color: dump_color(underline.color),
+                        thickness: underline.thickness.0,
+                        wavy: underline.wavy != 0,
+                        rotation_scale: underline.transformation.rotation_scale,
+                        translation: underline.transformation.translation,
+                    }))
+                }
+                PrimitiveBatch::MonochromeSprites { sprites, .. } => {
+                    primitives.extend(sprites.iter().map(|sprite| {
+                        PrimitiveDump::MonochromeSprite {
+                            order: sprite.order,
+                            bounds: dump_bounds(&sprite.bounds),
+                            content_mask: dump_bounds(&sprite.content_mask.bounds),
+                            content_mask_corner_radii: dump_corners(
+                                &sprite.content_mask.corner_radii,
+                            ),
+                            color: dump_color(sprite.color),
+                            tile: SpriteTileDump::new(&sprite.tile),
+                            rotation_scale: sprite.transformation.rotation_scale,
+                            translation: sprite.transformation.translation,
+                        }
+                    }))
+                }
+                PrimitiveBatch::PolychromeSprites { sprites, .. } => {
+                    primitives.extend(sprites.iter().map(|sprite| {
+                        PrimitiveDump::PolychromeSprite {
+                            order: sprite.order,
+                            bounds: dump_bounds(&sprite.bounds),
+                            content_mask: dump_bounds(&sprite.content_mask.bounds),
+                            content_mask_corner_radii: dump_corners(
+                                &sprite.content_mask.corner_radii,
+                            ),
+                            corner_radii: dump_corners(&sprite.corner_radii),
+                            grayscale: sprite.grayscale,
+                            opacity: sprite.opacity,
+                            tile: SpriteTileDump::new(&sprite.tile),
+                            rotation_scale: sprite.transformation.rotation_scale,
+                            translation: sprite.transformation.translation,
+                        }
+                    }))
+                }
+                PrimitiveBatch::Surfaces(surfaces) => {
+                    primitives.extend(surfaces.iter().map(|surface| PrimitiveDump::Surface {
+                        order: surface.order,
+                        bounds: dump_bounds(&surface.bounds),
+                        content_mask: dump_bounds(&surface.content_mask.bounds),
+                        content_mask_corner_radii: dump_corners(&surface.content_mask.corner_radii),
+                    }))
+                }
+                PrimitiveBatch::ShaderQuads(quads) => {
+                    primitives.extend(quads.iter().map(|quad| PrimitiveDump::ShaderQuad {
+                        order: quad.order,
+                        bounds: dump_bounds(&quad.bounds),
+                        content_mask: dump_bounds(&quad.content_mask.bounds),
+                        content_mask_corner_radii: dump_corners(&quad.content_mask.corner_radii),
+                        source: quad.source.wgsl.to_string(),
+                        uniforms: quad.uniforms,
+                    }))
+                }
+                PrimitiveBatch::Layers(layers) => primitives.extend(layers.iter().map(|layer| {
+                    PrimitiveDump::Layer {
+                        order: layer.order,
+                        bounds: dump_bounds(&layer.bounds),
+                        opacity: layer.opacity,
+                        blend_mode: layer.blend_mode,
+                        mask: layer
+                            .mask
+                            .as_ref()
+                            .map(|mask| Box::new(dump_path(mask, gradient_stops))),
+                        blur_radius: layer.blur_radius.0,
+                        primitives: Self::dump_primitives(&layer.scene, gradient_stops),
+                    }
+                })),
+            }
+        }
+        primitives
+    }
+
+    /// Loads the primitives of a scene, appending their gradient stops to those of the root
+    /// scene.
+    fn load_scene(
+        primitives: &[PrimitiveDump],
+        gradient_stops: &mut Vec<LinearColorStop>,
+    ) -> Scene {
+        let mut scene = Scene::default();
+        for primitive in primitives {
+            match primitive {
+                PrimitiveDump::Shadow {
+                    order,
+                    bounds,
+                    content_mask,
+                    content_mask_corner_radii,
+                    corner_radii,
+                    blur_radius,
+                    color,
+                    rotation_scale,
+                    translation,
+                } => scene.shadows.push(Shadow {
+                    order: *order,
+                    blur_radius: ScaledPixels(*blur_radius),
+                    bounds: load_bounds(bounds),
+                    corner_radii: load_corners(corner_radii),
+                    content_mask: load_content_mask(content_mask, content_mask_corner_radii),
+                    color: load_color(color),
+                    transformation: TransformationMatrix {
+                        rotation_scale: *rotation_scale,
+                        translation: *translation,
+                    },
+                }),
+                PrimitiveDump::Blur {
+                    order,
+                    bounds,
+                    content_mask,
+                    content_mask_corner_radii,
+                    corner_radii,
+                    radius,
+                } => scene.blurs.push(Blur {
+                    order: *order,
+                    radius: ScaledPixels(*radius),
+                    bounds: load_bounds(bounds),
+                    corner_radii: load_corners(corner_radii),
+                    content_mask: load_content_mask(content_mask, content_mask_corner_radii),
+                }),
+                PrimitiveDump::Quad {
+                    order,
+                    bounds,
+                    content_mask,
+                    content_mask_corner_radii,
+                    background,
+                    border_color,
+                    border_dashed,
+                    border_widths,
+                    corner_radii,
+                    rotation_scale,
+                    translation,
+                } => {
+                    let [top, right, bottom, left] = border_widths.map(ScaledPixels);
+                    scene.quads.push(Quad {
+                        order: *order,
+                        border_style: if *border_dashed {
+                            BorderStyle::Dashed
+                        } else {
+                            BorderStyle::Solid
+                        },
+                        bounds: load_bounds(bounds),
+                        content_mask: load_content_mask(content_mask, content_mask_corner_radii),
+                        background: background.to_background(gradient_stops),
+                        border_color: load_color(border_color),
+                        corner_radii: load_corners(corner_radii),
+                        border_widths: Edges {
+                            top,
+                            right,
+                            bottom,
+                            left,
+                        },
+                        transformation: TransformationMatrix {
+                            rotation_scale: *rotation_scale,
+                            translation: *translation,
+                        },
+                    })
+                }
+                PrimitiveDump::Path { .. } => {
+                    if let Some(mut path) = load_path(primitive, gradient_stops) {
+                        path.id = PathId(scene.paths.len());
+                        scene.paths.push(path);
+                    }
+                }
+                PrimitiveDump::Underline {
+                    order,
+                    bounds,
+                    content_mask,
+                    content_mask_corner_radii,
+                    color,
+                    thickness,
+                    wavy,
+                    rotation_scale,
+                    translation,
+                } => scene.underlines.push(Underline {
+                    order: *order,
+                    pad: 0,
+                    bounds: load_bounds(bounds),
+                    content_mask: load_content_mask(content_mask, content_mask_corner_radii),
+                    color: load_color(color),
+                    thickness: ScaledPixels(*thickness),
+                    wavy: *wavy as u32,
+                    transformation: TransformationMatrix {
+                        rotation_scale: *rotation_scale,
+                        translation: *translation,
+                    },
+                }),
+                PrimitiveDump::MonochromeSprite {
+                    order,
+                    bounds,
+                    content_mask,
+                    content_mask_corner_radii,
+                    color,
+                    tile,
+                    rotation_scale,
+                    translation,
+                } => scene.monochrome_sprites.push(MonochromeSprite {
+                    order: *order,
+                    pad: 0,
+                    bounds: load_bounds(bounds),
+                    content_mask: load_content_mask(content_mask, content_mask_corner_radii),
+                    color: load_color(color),
+                    tile: tile.to_tile(AtlasTextureKind::Monochrome),
+                    transformation: TransformationMatrix {
+                        rotation_scale: *rotation_scale,
+                        translation: *translation,
+                    },
+                }),
+                PrimitiveDump::PolychromeSprite {
+                    order,
+                    bounds,
+                    content_mask,
+                    content_mask_corner_radii,
+                    corner_radii,
+                    grayscale,
+                    opacity,
+                    tile,
+                    rotation_scale,
+                    translation,
+                } => scene.polychrome_sprites.push(PolychromeSprite {
+                    order: *order,
+                    pad: 0,
+                    grayscale: *grayscale,
+                    opacity: *opacity,
+                    bounds: load_bounds(bounds),
+                    content_mask: load_content_mask(content_mask, content_mask_corner_radii),
+                    corner_radii: load_corners(corner_radii),
+                    tile: tile.to_tile(AtlasTextureKind::Polychrome),
+                    transformation: TransformationMatrix {
+                        rotation_scale: *rotation_scale,
+                        translation: *translation,
+                    },
+                }),
+                // NOTE(mdeand): Surfaces point at textures owned by whoever registered them,
+                // NOTE(mdeand): which don't outlive the frame they were painted in.
+                PrimitiveDump::Surface { .. } => {}
+                PrimitiveDump::ShaderQuad {
+                    order,
+                    bounds,
+                    content_mask,
+                    content_mask_corner_radii,
+                    source,
+                    uniforms,
+                } => scene.shader_quads.push(PaintShaderQuad {
+                    order: *order,
+                    bounds: load_bounds(bounds),
+                    content_mask: load_content_mask(content_mask, content_mask_corner_radii),
+                    source: ShaderQuadSource::new(source.clone().into()),
+                    uniforms: *uniforms,
+                }),
+                PrimitiveDump::Layer {
+                    order,
+                    bounds,
+                    opacity,
+                    blend_mode,
+                    mask,
+                    blur_radius,
+                    primitives,
+                } => scene.layers.push(Layer {
+                    order: *order,
+                    clip: load_bounds(bounds),
+                    bounds: load_bounds(bounds),
+                    opacity: *opacity,
+                    blend_mode: *blend_mode,
+                    mask: mask
+                        .as_deref()
+                        .and_then(|mask| load_path(mask, gradient_stops)),
+                    blur_radius: ScaledPixels(*blur_radius),
+                    scene: Self::load_scene(primitives, gradient_stops),
+                }),
+            }
+        }
+        scene.finish();
+        scene
+    }
+}
+
+impl BackgroundDump {
+    fn new(background: &SceneBackground, gradient_stops: &[LinearColorStop]) -> Self {
+        match background.tag {
+            BackgroundTag::Solid => BackgroundDump::Solid {
+                color: dump_color(background.solid),
+            },
+            BackgroundTag::LinearGradient => BackgroundDump::LinearGradient {
+                angle: background.gradient_angle_or_pattern_height,
+                color_space: background.color_space,
+                stops: dump_stops(background, gradient_stops),
+            },
+            BackgroundTag::RadialGradient => BackgroundDump::RadialGradient {
+                center: [background.gradient_center.x, background.gradient_center.y],
+                color_space: background.color_space,
+                stops: dump_stops(background, gradient_stops),
+            },
+            BackgroundTag::ConicGradient => BackgroundDump::ConicGradient {
+                angle: background.gradient_angle_or_pattern_height,
+                center: [background.gradient_center.x, background.gradient_center.y],
+                color_space: background.color_space,
+                stops: dump_stops(background, gradient_stops),
+            },
+            BackgroundTag::PatternSlash => BackgroundDump::PatternSlash {
+                color: dump_color(background.solid),
+                pattern_height: background.gradient_angle_or_pattern_height,
+            },
+        }
+    }
+
+    fn to_background(&self, gradient_stops: &mut Vec<LinearColorStop>) -> SceneBackground {
+        let background = match self {
+            BackgroundDump::Solid { color } => Background::from(load_color(color)),
+            BackgroundDump::LinearGradient {
+                angle,
+                color_space,
+                stops,
+            } => linear_gradient_with_stops(*angle, load_stops(stops)).color_space(*color_space),
+            BackgroundDump::RadialGradient {
+                center,
+                color_space,
+                stops,
+            } => radial_gradient(point(center[0], center[1]), load_stops(stops))
+                .color_space(*color_space),
+            BackgroundDump::ConicGradient {
+                angle,
+                center,
+                color_space,
+                stops,
+            } => conic_gradient(*angle, point(center[0], center[1]), load_stops(stops))
+                .color_space(*color_space),
+            BackgroundDump::PatternSlash {
+                color,
+                pattern_height,
+            } => Background {
+                tag: BackgroundTag::PatternSlash,
+                solid: load_color(color),
+                gradient_angle_or_pattern_height: *pattern_height,
+                ..Default::default()
+            },
+        };
+        SceneBackground::new(&background, gradient_stops)
+    }
+}
+
+impl SpriteTileDump {
+    fn new(tile: &AtlasTile) -> Self {
+        Self {
+            texture: tile.texture_id.index,
+            tile: tile.tile_id.0,
+            padding: tile.padding,
+            bounds: tile.bounds.map(|value| value.0),
+        }
+    }
+
+    fn to_tile(&self, kind: AtlasTextureKind) -> AtlasTile {
+        AtlasTile {
+            texture_id: AtlasTextureId {
+                index: self.texture,
+                kind,
+            },
+            tile_id: TileId(self.tile),
+            padding: self.padding,
+            bounds: self.bounds.map(DevicePixels),
+        }
+    }
+}
+
+fn dump_path(path: &Path<ScaledPixels>, gradient_stops: &[LinearColorStop]) -> PrimitiveDump {
+    PrimitiveDump::Path {
+        order: path.order,
+        bounds: dump_bounds(&path.bounds),
+        content_mask: dump_bounds(&path.content_mask.bounds),
+        content_mask_corner_radii: dump_corners(&path.content_mask.corner_radii),
+        color: BackgroundDump::new(&path.color, gradient_stops),
+        vertices: path
+            .vertices
+            .iter()
+            .map(|vertex| PathVertexDump {
+                xy: [vertex.xy_position.x.0, vertex.xy_position.y.0],
+                st: [vertex.st_position.x, vertex.st_position.y],
+            })
+            .collect(),
+    }
+}
+
+fn load_path(
+    primitive: &PrimitiveDump,
+    gradient_stops: &mut Vec<LinearColorStop>,
+) -> Option<Path<ScaledPixels>> {
+    let PrimitiveDump::Path {
+        order,
+        bounds,
+        content_mask,
+        content_mask_corner_radii,
+        color,
+        vertices,
+    } = primitive
+    else {
+        return None;
+    };
+    let content_mask = load_content_mask(content_mask, content_mask_corner_radii);
+    let mut path = Path::new(Default::default()).scale(1.0);
+    path.order = *order;
+    path.bounds = load_bounds(bounds);
+    path.color = color.to_background(gradient_stops);
+    path.vertices = vertices
+        .iter()
+        .map(|vertex| PathVertex {
+            xy_position: point(ScaledPixels(vertex.xy[0]), ScaledPixels(vertex.xy[1])),
+            st_position: point(vertex.st[0], vertex.st[1]),
+            content_mask: content_mask.clone(),
+        })
+        .collect();
+    path.content_mask = content_mask;
+    Some(path)
+}
+
+fn dump_bounds(bounds: &Bounds<ScaledPixels>) -> Bounds<f32> {
+    bounds.map(|value| value.0)
+}
+
+fn load_bounds(bounds: &Bounds<f32>) -> Bounds<ScaledPixels> {
+    bounds.map(ScaledPixels)
+}
+
+fn load_content_mask(bounds: &Bounds<f32>, corner_radii: &[f32; 4]) -> ContentMask<ScaledPixels> {
+    ContentMask {
+        bounds: load_bounds(bounds),
+        corner_radii: load_corners(corner_radii),
+    }
+}
+
+fn dump_corners(corners: &Corners<ScaledPixels>) -> [f32; 4] {
+    [
+        corners.top_left.0,
+        corners.top_right.0,
+        corners.bottom_right.0,
+        corners.bottom_left.0,
+    ]
+}
+
+fn load_corners(corners: &[f32; 4]) -> Corners<ScaledPixels> {
+    let [top_left, top_right, bottom_right, bottom_left] = corners.map(ScaledPixels);
+    Corners {
+        top_left,
+        top_right,
+        bottom_right,
+        bottom_left,
+    }
+}
+
+fn dump_color(color: Hsla) -> [f32; 4] {
+    [color.h, color.s, color.l, color.a]
+}
+
+fn load_color(color: &[f32; 4]) -> Hsla {
+    let [h, s, l, a] = *color;
+    Hsla { h, s, l, a }
+}
+
+fn dump_stops(
+    background: &SceneBackground,
+    gradient_stops: &[LinearColorStop],
+) -> Vec<([f32; 4], f32)> {
+    background
+        .stops(gradient_stops)
+        .iter()
+        .map(|stop| (dump_color(stop.color), stop.percentage))
+        .collect()
+}
+
+fn load_stops(stops: &[([f32; 4], f32)]) -> impl Iterator<Item = LinearColorStop> + '_ {
+    stops.iter().map(|(color, percentage)| LinearColorStop {
+        color: load_color(color),
+        percentage: *percentage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LayerIsolation, blue, linear_color_stop, linear_gradient, px, red, size, white};
+
+    #[test]
+    fn test_scene_dump_round_trips_through_json() {
+        let mut scene = Scene::default();
+        let background = scene.push_background(&linear_gradient(
+            45.,
+            linear_color_stop(red(), 0.),
+            linear_color_stop(blue(), 1.),
+        ));
+        scene.insert_primitive(Quad {
+            bounds: Bounds::new(point(px(1.), px(2.)), size(px(30.), px(40.))).scale(2.0),
+            content_mask: ContentMask {
+                bounds: Bounds::new(point(px(0.), px(0.)), size(px(50.), px(50.))).scale(2.0),
+                corner_radii: Corners::all(ScaledPixels(6.)),
+            },
+            background,
+            border_color: blue(),
+            border_widths: Edges::all(ScaledPixels(1.5)),
+            corner_radii: Corners::all(ScaledPixels(4.)),
+            ..Default::default()
+        });
+        scene.insert_primitive(Shadow {
+            order: 0,
+            blur_radius: ScaledPixels(3.),
+            bounds: Bounds::new(point(px(4.), px(4.)), size(px(8.), px(8.))).scale(1.0),
+            corner_radii: Corners::default(),
+            content_mask: ContentMask {
+                bounds: Bounds::new(point(px(0.), px(0.)), size(px(20.), px(20.))).scale(1.0),
+                ..Default::default()
+            },
+            color: red(),
+            transformation: TransformationMatrix::unit(),
+        });
+        scene.insert_primitive(PaintShaderQuad {
+            order: 0,
+            bounds: Bounds::new(point(px(20.), px(0.)), size(px(10.), px(10.))).scale(1.0),
+            content_mask: ContentMask {
+                bounds: Bounds::new(point(px(0.), px(0.)), size(px(50.), px(50.))).scale(1.0),
+                ..Default::default()
+            },
+            source: ShaderQuadSource::new(
+                "fn shade(input: ShaderQuadInput) -> vec4<f32> { return input.uniforms[0]; }"
+                    .into(),
+            ),
+            uniforms: [0.5; MAX_SHADER_QUAD_UNIFORMS],
+        });
+        let layer_bounds = Bounds::new(point(px(0.), px(0.)), size(px(50.), px(50.))).scale(2.0);
+        let mut mask = Path::new(point(px(0.), px(0.)));
+        mask.line_to(point(px(50.), px(0.)));
+        mask.line_to(point(px(0.), px(50.)));
+        let mut mask = mask.scale(2.0);
+        mask.content_mask.bounds = layer_bounds;
+        mask.color = white().into();
+        scene.push_layer(
+            layer_bounds,
+            Some(LayerIsolation {
+                opacity: 0.5,
+                blend_mode: BlendMode::Multiply,
+                mask: Some(mask),
+                blur_radius: ScaledPixels(0.),
+            }),
+        );
+        scene.insert_primitive(Quad {
+            bounds: Bounds::new(point(px(10.), px(10.)), size(px(20.), px(20.))).scale(2.0),
+            content_mask: ContentMask {
+                bounds: layer_bounds,
+                ..Default::default()
+            },
+            background: red().into(),
+            ..Default::default()
+        });
+        scene.pop_layer();
+        scene.finish();
+
+        let dump = SceneDump::new(&scene);
+        assert_eq!(dump.primitives.len(), 4);
+        assert!(matches!(dump.primitives[0], PrimitiveDump::Quad { .. }));
+        assert!(matches!(dump.primitives[1], PrimitiveDump::Shadow { .. }));
+        assert!(matches!(
+            dump.primitives[2],
+            PrimitiveDump::ShaderQuad { .. }
+        ));
+        assert!(
+            matches!(&dump.primitives[3], PrimitiveDump::Layer { primitives, mask, .. } if primitives.len() == 1 && mask.is_some())
+        );
+
+        let json = dump.to_json();
+        assert_eq!(SceneDump::from_json(&json).unwrap(), dump);
+        assert_eq!(SceneDump::new(&dump.to_scene()), dump);
+    }
+
+    #[test]
+    fn test_shader_quads_round_trip_with_their_source_and_uniforms() {
+        let mut uniforms = [0.; MAX_SHADER_QUAD_UNIFORMS];
+        uniforms[..3].copy_from_slice(&[0.25, 0.5, 1.]);
+        let quad = PaintShaderQuad {
+            order: 0,
+            bounds: Bounds::new(point(px(2.), px(4.)), size(px(10.), px(10.))).scale(2.0),
+            content_mask: ContentMask {
+                bounds: Bounds::new(point(px(0.), px(0.)), size(px(20.), px(20.))).scale(2.0),
+                corner_radii: Corners::all(ScaledPixels(3.)),
+            },
+            source: ShaderQuadSource::new(
+                "fn shade(input: ShaderQuadInput) -> vec4<f32> { return input.uniforms[0]; }"
+                    .into(),
+            ),
+            uniforms,
+        };
+        let mut scene = Scene::default();
+        scene.insert_primitive(quad.clone());
+        scene.finish();
+
+        let dump = SceneDump::from_json(&SceneDump::new(&scene).to_json()).unwrap();
+        let scene = dump.to_scene();
+        assert_eq!(scene.shader_quads.len(), 1);
+        assert_eq!(scene.shader_quads[0].source, quad.source);
+        assert_eq!(scene.shader_quads[0].uniforms, quad.uniforms);
+        assert_eq!(scene.shader_quads[0].bounds, quad.bounds);
+        assert_eq!(scene.shader_quads[0].content_mask, quad.content_mask);
+    }
+}
@@ -5,11 +5,12 @@ use std::{
 };
 
 use crate::{
-    AbsoluteLength, App, Background, BackgroundTag, BorderStyle, Bounds, ContentMask, Corners,
-    CornersRefinement, CursorStyle, DefiniteLength, DevicePixels, Edges, EdgesRefinement, Font,
-    FontFallbacks, FontFeatures, FontStyle, FontWeight, GridLocation, Hsla, Length, Pixels, Point,
-    PointRefinement, Rgba, SharedString, Size, SizeRefinement, Styled, TextRun, Window, black, phi,
-    point, quad, rems, size,
+    AbsoluteLength, App, Background, BackgroundTag, BlendMode, BorderStyle, Bounds, ContentMask,
+    Corners, CornersRefinement, CursorStyle, DefiniteLength, DevicePixels, Edges, EdgesRefinement,
+    Font, FontFallbacks, FontFeatures, FontStyle, FontWeight, GridLocation, Hsla, Length, Path,
+    PathBuilder, Pixels, Point, PointRefinement, Radians, Rgba, SharedString, Size, SizeRefinement,
+    Styled, TextRun, TransformationMatrix, Window, black, geometry::Negate as _, phi, point, quad,
+    relative, rems, size,
 };
 use collections::HashSet;
 use refineable::Refineable;
@@ -260,6 +261,46 @@ pub struct Style {
     /// The opacity of this element
     pub opacity: Option<f32>,
 
+    /// Whether this element and its children are painted into an offscreen layer when they're
+    /// faded, so that the opacity applies to the group as a whole rather than each child on its
+    /// own, like a CSS stacking context.
+    pub isolate: bool,
+
+    /// How the colors of this element and its children are mixed with what's painted beneath
+    /// them, like the CSS `mix-blend-mode` property.
+    pub mix_blend_mode: BlendMode,
+
+    /// The radius of the blur applied to whatever is painted behind this element, within its
+    /// bounds, like the CSS `backdrop-filter: blur()` property.
+    pub backdrop_blur: Option<Pixels>,
+
+    /// The radius of the blur applied to this element and its children, like the CSS
+    /// `filter: blur()` property.
+    pub blur: Option<Pixels>,
+
+    /// The shape this element and its children are clipped to, like the CSS `clip-path`
+    /// property.
+    pub clip_path: Option<ClipPath>,
+
+    /// The clockwise rotation of this element and its children around the transform origin,
+    /// like the CSS `rotate` property.
+    pub rotate: Option<Radians>,
+
+    /// How much this element and its children are scaled around the transform origin, like
+    /// the CSS `scale` property.
+    #[refineable]
+    pub scale: Size<f32>,
+
+    /// How far this element and its children are moved, like the CSS `translate` property.
+    /// Fractions are relative to the size of the element.
+    #[refineable]
+    pub translate: Point<DefiniteLength>,
+
+    /// The point this element is rotated and scaled around, like the CSS `transform-origin`
+    /// property. Fractions are relative to the size of the element.
+    #[refineable]
+    pub transform_origin: Point<DefiniteLength>,
+
     /// The grid columns of this element
     /// Equivalent to the Tailwind `grid-cols-<number>`
     pub grid_cols: Option<u16>,
@@ -316,6 +357,48 @@ pub struct BoxShadow {
     pub spread_radius: Pixels,
 }
 
+/// The possible values of the clip-path property. The shapes are placed within the bounds of
+/// the element they clip.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum ClipPath {
+    /// The ellipse filling the element's bounds, like the CSS `ellipse()` function.
+    Ellipse,
+    /// The polygon through the given points, which are fractions of the element's size, like
+    /// the CSS `polygon()` function.
+    Polygon(Vec<Point<f32>>),
+}
+
+impl ClipPath {
+    /// The outline of the shape when it's placed within `bounds`.
+    pub fn path(&self, bounds: Bounds<Pixels>) -> anyhow::Result<Path<Pixels>> {
+        let mut builder = PathBuilder::fill();
+        match self {
+            ClipPath::Ellipse => {
+                let radii = point(bounds.size.width / 2., bounds.size.height / 2.);
+                let left = point(bounds.left(), bounds.center().y);
+                let right = point(bounds.right(), bounds.center().y);
+                builder.move_to(left);
+                builder.arc_to(radii, Pixels::ZERO, false, true, right);
+                builder.arc_to(radii, Pixels::ZERO, false, true, left);
+            }
+            ClipPath::Polygon(points) => {
+                let mut points = points.iter().map(|point| {
+                    bounds.origin
+                        + crate::point(bounds.size.width * point.x, bounds.size.height * point.y)
+                });
+                if let Some(first) = points.next() {
+                    builder.move_to(first);
+                }
+                for point in points {
+                    builder.line_to(point);
+                }
+            }
+        }
+        builder.close();
+        builder.build()
+    }
+}
+
 /// How to handle whitespace in text
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum WhiteSpace {
@@ -564,42 +647,123 @@ impl Style {
             _ => {
                 let mut min = bounds.origin;
                 let mut max = bounds.bottom_right();
+                let mut border_widths = Edges::<Pixels>::default();
 
                 if self
                     .border_color
                     .is_some_and(|color| !color.is_transparent())
                 {
-                    min.x += self.border_widths.left.to_pixels(rem_size);
-                    max.x -= self.border_widths.right.to_pixels(rem_size);
-                    min.y += self.border_widths.top.to_pixels(rem_size);
-                    max.y -= self.border_widths.bottom.to_pixels(rem_size);
+                    border_widths = self.border_widths.to_pixels(rem_size);
+                    min.x += border_widths.left;
+                    max.x -= border_widths.right;
+                    min.y += border_widths.top;
+                    max.y -= border_widths.bottom;
                 }
 
-                let bounds = match (
+                let (bounds, corner_radii) = match (
                     self.overflow.x == Overflow::Visible,
                     self.overflow.y == Overflow::Visible,
                 ) {
                     // x and y both visible
                     (true, true) => return None,
                     // x visible, y hidden
-                    (true, false) => Bounds::from_corners(
-                        point(min.x, bounds.origin.y),
-                        point(max.x, bounds.bottom_right().y),
+                    (true, false) => (
+                        Bounds::from_corners(
+                            point(min.x, bounds.origin.y),
+                            point(max.x, bounds.bottom_right().y),
+                        ),
+                        Corners::default(),
                     ),
                     // x hidden, y visible
-                    (false, true) => Bounds::from_corners(
-                        point(bounds.origin.x, min.y),
-                        point(bounds.bottom_right().x, max.y),
+                    (false, true) => (
+                        Bounds::from_corners(
+                            point(bounds.origin.x, min.y),
+                            point(bounds.bottom_right().x, max.y),
+                        ),
+                        Corners::default(),
                     ),
                     // both hidden
-                    (false, false) => Bounds::from_corners(min, max),
+                    (false, false) => (
+                        Bounds::from_corners(min, max),
+                        self.padding_box_corner_radii(bounds, &border_widths, rem_size),
+                    ),
                 };
 
-                Some(ContentMask { bounds })
+                Some(ContentMask {
+                    bounds,
+                    corner_radii,
+                })
             }
         }
     }
 
+    /// The radii of the corners of the padding box, which are the outer corner radii shrunk by
+    /// the borders that meet at each corner.
+    fn padding_box_corner_radii(
+        &self,
+        bounds: Bounds<Pixels>,
+        border_widths: &Edges<Pixels>,
+        rem_size: Pixels,
+    ) -> Corners<Pixels> {
+        let outer_radii = self
+            .corner_radii
+            .to_pixels(rem_size)
+            .clamp_radii_for_quad_size(bounds.size);
+        let inner_radius =
+            |radius: Pixels, a: Pixels, b: Pixels| (radius - a.max(b)).max(Pixels::ZERO);
+        Corners {
+            top_left: inner_radius(outer_radii.top_left, border_widths.top, border_widths.left),
+            top_right: inner_radius(
+                outer_radii.top_right,
+                border_widths.top,
+                border_widths.right,
+            ),
+            bottom_right: inner_radius(
+                outer_radii.bottom_right,
+                border_widths.bottom,
+                border_widths.right,
+            ),
+            bottom_left: inner_radius(
+                outer_radii.bottom_left,
+                border_widths.bottom,
+                border_widths.left,
+            ),
+        }
+    }
+
+    /// The transformation this style applies to an element with the given bounds and its
+    /// children, in device pixels. Returns `None` if the element isn't transformed.
+    pub fn transformation(
+        &self,
+        bounds: Bounds<Pixels>,
+        rem_size: Pixels,
+        scale_factor: f32,
+    ) -> Option<TransformationMatrix> {
+        let rotate = self.rotate.unwrap_or_default();
+        if rotate.0 == 0. && self.scale == size(1., 1.) && self.translate == Point::default() {
+            return None;
+        }
+
+        let resolve = |length: Point<DefiniteLength>| {
+            point(
+                length.x.to_pixels(bounds.size.width.into(), rem_size),
+                length.y.to_pixels(bounds.size.height.into(), rem_size),
+            )
+        };
+        let origin = (bounds.origin + resolve(self.transform_origin)).scale(scale_factor);
+        let translate = resolve(self.translate).scale(scale_factor);
+
+        // NOTE(mdeand): Read from the bottom up: move the origin to zero, scale and rotate around
+        // NOTE(mdeand): it, then move it back and translate.
+        Some(
+            TransformationMatrix::unit()
+                .translate(origin + translate)
+                .rotate(rotate)
+                .scale(self.scale)
+                .translate(origin.negate()),
+        )
+    }
+
     /// Paints the background of an element styled with this style.
     pub fn paint(
         &self,
@@ -624,95 +788,106 @@ impl Style {
             .to_pixels(rem_size)
             .clamp_radii_for_quad_size(bounds.size);
 
-        window.paint_shadows(bounds, corner_radii, &self.box_shadow);
-
-        let background_color = self.background.as_ref().and_then(Fill::color);
-        if background_color.is_some_and(|color| !color.is_transparent()) {
-            let mut border_color = match background_color {
-                Some(color) => match color.tag {
-                    BackgroundTag::Solid => color.solid,
-                    BackgroundTag::LinearGradient => color
-                        .colors
-                        .first()
-                        .map(|stop| stop.color)
-                        .unwrap_or_default(),
-                    BackgroundTag::PatternSlash => color.solid,
-                },
-                None => Hsla::default(),
-            };
-            border_color.a = 0.;
-            window.paint_quad(quad(
-                bounds,
-                corner_radii,
-                background_color.unwrap_or_default(),
-                Edges::default(),
-                border_color,
-                self.border_style,
-            ));
+        // NOTE(mdeand): The backdrop is what's painted beneath the element, which includes
+        // NOTE(mdeand): neither its shadows nor anything its own blur applies to.
+        if let Some(radius) = self.backdrop_blur {
+            window.paint_blur(bounds, corner_radii, radius);
         }
 
-        continuation(window, cx);
-
-        if self.is_border_visible() {
-            let border_widths = self.border_widths.to_pixels(rem_size);
-            let max_border_width = border_widths.max();
-            let max_corner_radius = corner_radii.max();
-
-            let top_bounds = Bounds::from_corners(
-                bounds.origin,
-                bounds.top_right() + point(Pixels::ZERO, max_border_width.max(max_corner_radius)),
-            );
-            let bottom_bounds = Bounds::from_corners(
-                bounds.bottom_left() - point(Pixels::ZERO, max_border_width.max(max_corner_radius)),
-                bounds.bottom_right(),
-            );
-            let left_bounds = Bounds::from_corners(
-                top_bounds.bottom_left(),
-                bottom_bounds.origin + point(max_border_width, Pixels::ZERO),
-            );
-            let right_bounds = Bounds::from_corners(
-                top_bounds.bottom_right() - point(max_border_width, Pixels::ZERO),
-                bottom_bounds.top_right(),
-            );
-
-            let mut background = self.border_color.unwrap_or_default();
-            background.a = 0.;
-            let quad = quad(
-                bounds,
-                corner_radii,
-                background,
-                border_widths,
-                self.border_color.unwrap_or_default(),
-                self.border_style,
-            );
-
-            window.with_content_mask(Some(ContentMask { bounds: top_bounds }), |window| {
-                window.paint_quad(quad.clone());
-            });
-            window.with_content_mask(
-                Some(ContentMask {
-                    bounds: right_bounds,
-                }),
-                |window| {
-                    window.paint_quad(quad.clone());
-                },
-            );
-            window.with_content_mask(
-                Some(ContentMask {
-                    bounds: bottom_bounds,
-                }),
-                |window| {
-                    window.paint_quad(quad.clone());
-                },
-            );
-            window.with_content_mask(
-                Some(ContentMask {
-                    bounds: left_bounds,
-                }),
-                |window| {
-                    window.paint_quad(quad);
-                },
-            );
+        let paint = |window: &mut Window, cx: &mut App| {
+            window.paint_shadows(bounds, corner_radii, &self.box_shadow);
+
+            let background_color = self.background.as_ref().and_then(Fill::color);
+            if background_color.is_some_and(|color| !color.is_transparent()) {
+                let mut border_color = match background_color {
+                    Some(color) => match color.tag {
+                        BackgroundTag::Solid => color.solid,
+                        BackgroundTag::LinearGradient
+                        | BackgroundTag::RadialGradient
+                        | BackgroundTag::ConicGradient => color
+                            .gradient_stops()
+                            .first()
+                            .map(|stop| stop.color)
+                            .unwrap_or_default(),
+                        BackgroundTag::PatternSlash => color.solid,
+                    },
+                    None => Hsla::default(),
+                };
+                border_color.a = 0.;
+                window.paint_quad(quad(
+                    bounds,
+                    corner_radii,
+                    background_color.unwrap_or_default(),
+                    Edges::default(),
+                    border_color,
+                    self.border_style,
+                ));
+            }
+
+            continuation(window, cx);
+
+            if self.is_border_visible() {
+                let border_widths = self.border_widths.to_pixels(rem_size);
+                let max_border_width = border_widths.max();
+                let max_corner_radius = corner_radii.max();
+
+                let top_bounds = Bounds::from_corners(
+                    bounds.origin,
+                    bounds.top_right()
+                        + point(Pixels::ZERO, max_border_width.max(max_corner_radius)),
+                );
+                let bottom_bounds = Bounds::from_corners(
+                    bounds.bottom_left()
+                        - point(Pixels::ZERO, max_border_width.max(max_corner_radius)),
+                    bounds.bottom_right(),
+                );
+                let left_bounds = Bounds::from_corners(
+                    top_bounds.bottom_left(),
+                    bottom_bounds.origin + point(max_border_width, Pixels::ZERO),
+                );
+                let right_bounds = Bounds::from_corners(
+                    top_bounds.bottom_right() - point(max_border_width, Pixels::ZERO),
+                    bottom_bounds.top_right(),
+                );
+
+                let mut background = self.border_color.unwrap_or_default();
+                background.a = 0.;
+                let quad = quad(
+                    bounds,
+                    corner_radii,
+                    background,
+                    border_widths,
+                    self.border_color.unwrap_or_default(),
+                    self.border_style,
+                );
+
+                for side_bounds in [top_bounds, right_bounds, bottom_bounds, left_bounds] {
+                    let content_mask = ContentMask {
+                        bounds: side_bounds,
+                        ..Default::default()
+                    };
+                    window.with_content_mask(Some(content_mask), |window| {
+                        window.paint_quad(quad.clone());
+                    });
+                }
+            }
+        };
+        let paint = |window: &mut Window, cx: &mut App| match self.blur {
+            Some(radius) => window.paint_blurred_layer(bounds, radius, |window| paint(window, cx)),
+            None => paint(window, cx),
+        };
+        // NOTE(mdeand): Like in CSS, the element is clipped after it's blurred.
+        match self
+            .clip_path
+            .as_ref()
+            .map(|clip_path| clip_path.path(bounds))
+        {
+            Some(Ok(path)) => window.with_clip_path(path, |window| paint(window, cx)),
+            Some(Err(error)) => {
+                log::error!("failed to build a clip path: {error:?}");
+                paint(window, cx);
+            }
+            None => paint(window, cx),
         }
 
         #[cfg(debug_assertions)]
@@ -769,6 +944,15 @@ impl Default for Style {
             text: TextStyleRefinement::default(),
             mouse_cursor: None,
             opacity: None,
+            isolate: false,
+            mix_blend_mode: BlendMode::Normal,
+            backdrop_blur: None,
+            blur: None,
+            clip_path: None,
+            rotate: None,
+            scale: size(1., 1.),
+            translate: Point::default(),
+            transform_origin: point(relative(0.5), relative(0.5)),
             grid_rows: None,
             grid_cols: None,
             grid_location: None,
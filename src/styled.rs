@@ -1,9 +1,9 @@
 use crate::{
-    self as gpui, AbsoluteLength, AlignContent, AlignItems, BorderStyle, CursorStyle,
-    DefiniteLength, Display, Fill, FlexDirection, FlexWrap, Font, FontFeatures, FontStyle,
-    FontWeight, GridPlacement, Hsla, JustifyContent, Length, SharedString, StrikethroughStyle,
-    StyleRefinement, TextAlign, TextOverflow, TextStyleRefinement, UnderlineStyle, WhiteSpace, px,
-    relative, rems,
+    self as gpui, AbsoluteLength, AlignContent, AlignItems, BlendMode, BorderStyle, ClipPath,
+    CursorStyle, DefiniteLength, Display, Fill, FlexDirection, FlexWrap, Font, FontFeatures,
+    FontStyle, FontWeight, GridPlacement, Hsla, JustifyContent, Length, Pixels, Radians,
+    SharedString, StrikethroughStyle, StyleRefinement, TextAlign, TextOverflow,
+    TextStyleRefinement, UnderlineStyle, WhiteSpace, px, relative, rems,
 };
 pub use gpui_macros::{
     border_style_methods, box_shadow_style_methods, cursor_style_methods, margin_style_methods,
@@ -673,6 +673,105 @@ pub trait Styled: Sized {
         self
     }
 
+    /// Fades this element and its children as a group, painting them into an offscreen layer
+    /// so that overlapping children don't show through each other.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/isolation)
+    fn isolate(mut self) -> Self {
+        self.style().isolate = Some(true);
+        self
+    }
+
+    /// Sets how the colors of this element and its children are mixed with what's painted
+    /// beneath them.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/mix-blend-mode)
+    fn mix_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.style().mix_blend_mode = Some(blend_mode);
+        self
+    }
+
+    /// Blurs whatever is painted behind this element, within its rounded bounds.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/backdrop-filter)
+    fn backdrop_blur(mut self, radius: impl Into<Pixels>) -> Self {
+        self.style().backdrop_blur = Some(radius.into());
+        self
+    }
+
+    /// Blurs this element and its children, within its rounded bounds.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/filter-function/blur)
+    fn blur(mut self, radius: impl Into<Pixels>) -> Self {
+        self.style().blur = Some(radius.into());
+        self
+    }
+
+    /// Clips this element and its children to the given shape, painting them into an
+    /// offscreen layer.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/clip-path)
+    fn clip_path(mut self, clip_path: ClipPath) -> Self {
+        self.style().clip_path = Some(clip_path);
+        self
+    }
+
+    /// Rotates this element and its children clockwise around the transform origin.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/rotate)
+    fn rotate(mut self, angle: impl Into<Radians>) -> Self {
+        self.style().rotate = Some(angle.into());
+        self
+    }
+
+    /// Scales this element and its children around the transform origin.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/scale)
+    fn scale(self, scale: f32) -> Self {
+        self.scale_x(scale).scale_y(scale)
+    }
+
+    /// Scales this element and its children horizontally around the transform origin.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/scale)
+    fn scale_x(mut self, scale: f32) -> Self {
+        self.style().scale.width = Some(scale);
+        self
+    }
+
+    /// Scales this element and its children vertically around the transform origin.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/scale)
+    fn scale_y(mut self, scale: f32) -> Self {
+        self.style().scale.height = Some(scale);
+        self
+    }
+
+    /// Moves this element and its children, without affecting layout. Fractions are relative
+    /// to the size of the element.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/translate)
+    fn translate(self, x: impl Into<DefiniteLength>, y: impl Into<DefiniteLength>) -> Self {
+        self.translate_x(x).translate_y(y)
+    }
+
+    /// Moves this element and its children horizontally, without affecting layout.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/translate)
+    fn translate_x(mut self, x: impl Into<DefiniteLength>) -> Self {
+        self.style().translate.x = Some(x.into());
+        self
+    }
+
+    /// Moves this element and its children vertically, without affecting layout.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/translate)
+    fn translate_y(mut self, y: impl Into<DefiniteLength>) -> Self {
+        self.style().translate.y = Some(y.into());
+        self
+    }
+
+    /// Sets the point this element is rotated and scaled around. Fractions are relative to the
+    /// size of the element, and it defaults to its center.
+    /// [Docs](https://developer.mozilla.org/en-US/docs/Web/CSS/transform-origin)
+    fn transform_origin(
+        mut self,
+        x: impl Into<DefiniteLength>,
+        y: impl Into<DefiniteLength>,
+    ) -> Self {
+        self.style().transform_origin.x = Some(x.into());
+        self.style().transform_origin.y = Some(y.into());
+        self
+    }
+
     /// Sets the grid columns of this element.
     fn grid_cols(mut self, cols: u16) -> Self {
         self.style().grid_cols = Some(cols);
@@ -2,22 +2,22 @@
 use crate::Inspector;
 use crate::{
     Action, AnyDrag, AnyElement, AnyImageCache, AnyTooltip, AnyView, App, AppContext, Arena, Asset,
-    AsyncWindowContext, AvailableSpace, Background, BorderStyle, Bounds, BoxShadow, Capslock,
-    Context, Corners, CursorStyle, Decorations, DevicePixels, DispatchActionListener,
-    DispatchNodeId, DispatchTree, DisplayId, Edges, Effect, Entity, EntityId, EventEmitter,
-    FileDropEvent, FontId, Global, GlobalElementId, GlyphId, GpuSpecs, Hsla, InputHandler, IsZero,
-    KeyBinding, KeyContext, KeyDownEvent, KeyEvent, Keystroke, KeystrokeEvent, LayoutId,
-    LineLayoutIndex, Modifiers, ModifiersChangedEvent, MonochromeSprite, MouseButton, MouseEvent,
-    MouseMoveEvent, MouseUpEvent, Path, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput,
-    PlatformInputHandler, PlatformWindow, Point, PolychromeSprite, Priority, PromptButton,
-    PromptLevel, Quad, Render, RenderGlyphParams, RenderImage, RenderImageParams, RenderSvgParams,
-    Replay, ResizeEdge, SMOOTH_SVG_SCALE_FACTOR, SUBPIXEL_VARIANTS_X, SUBPIXEL_VARIANTS_Y,
-    ScaledPixels, Scene, Shadow, SharedString, Size, StrikethroughStyle, Style, SubscriberSet,
-    Subscription, SystemWindowTab, SystemWindowTabController, TabStopMap, TaffyLayoutEngine, Task,
-    TextStyle, TextStyleRefinement, TransformationMatrix, Underline, UnderlineStyle,
-    WindowAppearance, WindowBackgroundAppearance, WindowBounds, WindowControls, WindowDecorations,
-    WindowOptions, WindowParams, WindowTextSystem, point, prelude::*, px, rems, size,
-    transparent_black,
+    AsyncWindowContext, AvailableSpace, Background, BlendMode, Blur, BorderStyle, Bounds,
+    BoxShadow, Capslock, Context, Corners, CursorStyle, Decorations, DevicePixels,
+    DispatchActionListener, DispatchNodeId, DispatchTree, DisplayId, Edges, Effect, Entity,
+    EntityId, EventEmitter, FileDropEvent, FontId, Global, GlobalElementId, GlyphId, GpuSpecs,
+    Hsla, InputHandler, IsZero, KeyBinding, KeyContext, KeyDownEvent, KeyEvent, Keystroke,
+    KeystrokeEvent, LayerIsolation, LayoutId, LineLayoutIndex, Modifiers, ModifiersChangedEvent,
+    MonochromeSprite, MouseButton, MouseEvent, MouseMoveEvent, MouseUpEvent, Path, PathBuilder,
+    Pixels, PlatformAtlas, PlatformDisplay, PlatformInput, PlatformInputHandler, PlatformWindow,
+    Point, PolychromeSprite, Priority, PromptButton, PromptLevel, Quad, Render, RenderGlyphParams,
+    RenderImage, RenderImageParams, RenderSvgParams, Replay, ResizeEdge, SMOOTH_SVG_SCALE_FACTOR,
+    SUBPIXEL_VARIANTS_X, SUBPIXEL_VARIANTS_Y, ScaledPixels, Scene, Shadow, SharedString, Size,
+    StrikethroughStyle, Style, SubscriberSet, Subscription, SystemWindowTab,
+    SystemWindowTabController, TabStopMap, TaffyLayoutEngine, Task, TextStyle, TextStyleRefinement,
+    TransformationMatrix, Underline, UnderlineStyle, WindowAppearance, WindowBackgroundAppearance,
+    WindowBounds, WindowControls, WindowDecorations, WindowOptions, WindowParams, WindowTextSystem,
+    point, prelude::*, px, rems, size, transparent_black, white,
 };
 use anyhow::{Context as _, Result, anyhow};
 use collections::{FxHashMap, FxHashSet};
@@ -40,7 +40,7 @@ use std::{
     hash::{Hash, Hasher},
     marker::PhantomData,
     mem,
-    ops::{DerefMut, Range},
+    ops::{Add, DerefMut, Div, Range, Sub},
     rc::Rc,
     sync::{
         Arc, Weak,
@@ -155,6 +155,10 @@ impl WindowInvalidator {
         self.inner.borrow().draw_phase == DrawPhase::None
     }
 
+    pub fn painting(&self) -> bool {
+        self.inner.borrow().draw_phase == DrawPhase::Paint
+    }
+
     #[track_caller]
     pub fn debug_assert_paint(&self) {
         debug_assert!(
@@ -539,6 +543,9 @@ pub struct Hitbox {
     pub content_mask: ContentMask<Pixels>,
     /// Flags that specify hitbox behavior.
     pub behavior: HitboxBehavior,
+    /// The transformation of the element that inserted the hitbox, in logical pixels. The
+    /// bounds and content mask are in the space it maps onto the window.
+    pub transformation: TransformationMatrix,
 }
 
 impl Hitbox {
@@ -569,6 +576,20 @@ impl Hitbox {
     pub fn should_handle_scroll(&self, window: &Window) -> bool {
         self.id.should_handle_scroll(window)
     }
+
+    /// Checks whether the given point in window coordinates falls within the hitbox's bounds,
+    /// after undoing the transformation of the element that inserted it.
+    pub fn contains(&self, point: &Point<Pixels>) -> bool {
+        self.local_position(*point)
+            .is_some_and(|point| self.bounds.contains(&point))
+    }
+
+    fn local_position(&self, point: Point<Pixels>) -> Option<Point<Pixels>> {
+        if self.transformation == TransformationMatrix::unit() {
+            return Some(point);
+        }
+        Some(self.transformation.inverse()?.apply(point))
+    }
 }
 
 /// How the hitbox affects mouse behavior.
@@ -658,6 +679,13 @@ pub(crate) struct TooltipRequest {
     tooltip: AnyTooltip,
 }
 
+/// The transformation applied to the elements being drawn, in device pixels, along with the
+/// content mask on screen when it was applied.
+pub(crate) struct ElementTransformation {
+    matrix: TransformationMatrix,
+    content_mask: ContentMask<ScaledPixels>,
+}
+
 pub(crate) struct DeferredDraw {
     current_view: EntityId,
     priority: usize,
@@ -782,6 +810,9 @@ impl Frame {
         let mut set_hover_hitbox_count = false;
         let mut hit_test = HitTest::default();
         for hitbox in self.hitboxes.iter().rev() {
+            let Some(position) = hitbox.local_position(position) else {
+                continue;
+            };
             let bounds = hitbox.bounds.intersect(&hitbox.content_mask.bounds);
             if bounds.contains(&position) {
                 hit_test.ids.push(hitbox.id);
@@ -818,6 +849,7 @@ impl Frame {
         }
 
         self.scene.finish();
+        self.scene.track_damage(&prev_frame.scene);
     }
 }
 
@@ -850,6 +882,7 @@ pub struct Window {
     pub(crate) rendered_entity_stack: Vec<EntityId>,
     pub(crate) element_offset_stack: Vec<Point<Pixels>>,
     pub(crate) element_opacity: f32,
+    pub(crate) element_transformation: Option<ElementTransformation>,
     pub(crate) content_mask_stack: Vec<ContentMask<Pixels>>,
     pub(crate) requested_autoscroll: Option<Bounds<Pixels>>,
     pub(crate) image_cache_stack: Vec<AnyImageCache>,
@@ -1003,6 +1036,8 @@ impl Window {
             window_min_size,
             window_decorations,
             tabbing_identifier,
+            present_mode,
+            max_frame_latency,
         } = options;
 
         let window_bounds = window_bounds.unwrap_or_else(|| default_bounds(display_id, cx));
@@ -1019,7 +1054,11 @@ impl Window {
                 show,
                 display_id,
                 window_min_size,
+                window_background,
+                app_id: app_id.clone(),
                 tabbing_identifier,
+                present_mode,
+                max_frame_latency,
             },
         )?;
 
@@ -1095,6 +1134,12 @@ impl Window {
                     measure("frame duration", || {
                         handle
                             .update(&mut cx, |_, window, cx| {
+                                // NOTE(mdeand): Forced frames follow a loss of the GPU, which
+                                // NOTE(mdeand): took every atlas tile with it. Views can't be
+                                // NOTE(mdeand): reused since their primitives refer to them.
+                                if request_frame_options.force_render {
+                                    window.refresh();
+                                }
                                 let arena_clear_needed = window.draw(cx);
                                 window.present();
                                 // drop the arena elements after present to reduce latency
@@ -1273,6 +1318,7 @@ impl Window {
             element_offset_stack: Vec::new(),
             content_mask_stack: Vec::new(),
             element_opacity: 1.0,
+            element_transformation: None,
             requested_autoscroll: None,
             rendered_frame: Frame::new(DispatchTree::new(cx.keymap.clone(), cx.actions.clone())),
             next_frame: Frame::new(DispatchTree::new(cx.keymap.clone(), cx.actions.clone())),
@@ -1327,13 +1373,38 @@ pub(crate) struct DispatchEventResult {
 }
 
 /// Indicates which region of the window is visible. Content falling outside of this mask will not be
-/// rendered. Currently, only rectangular content masks are supported, but we give the mask its own type
-/// to leave room to support more complex shapes in the future.
+/// rendered. A mask is a rectangle whose corners may be rounded, so that children of a rounded
+/// container with hidden overflow don't bleed past its corners.
+///
+/// Masks are created with [`ContentMask::new`] or [`ContentMask::rounded`]. Since they gained
+/// rounded corners, they can no longer be built with a `ContentMask { bounds }` literal outside of
+/// this crate.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
 #[repr(C)]
 pub struct ContentMask<P: Clone + Debug + Default + PartialEq> {
     /// The bounds
     pub bounds: Bounds<P>,
+    /// The radii of the mask's corners
+    pub corner_radii: Corners<P>,
+}
+
+impl<P: Clone + Debug + Default + PartialEq> ContentMask<P> {
+    /// Creates a mask clipping to the given bounds, with square corners.
+    pub fn new(bounds: Bounds<P>) -> Self {
+        Self {
+            bounds,
+            corner_radii: Corners::default(),
+        }
+    }
+
+    /// Creates a mask clipping to the given bounds, with corners rounded by the given radii.
+    pub fn rounded(bounds: Bounds<P>, corner_radii: Corners<P>) -> Self {
+        Self {
+            bounds,
+            corner_radii,
+        }
+    }
 }
 
 impl ContentMask<Pixels> {
@@ -1341,13 +1412,174 @@ impl ContentMask<Pixels> {
     pub fn scale(&self, factor: f32) -> ContentMask<ScaledPixels> {
         ContentMask {
             bounds: self.bounds.scale(factor),
+            corner_radii: self.corner_radii.scale(factor),
+        }
+    }
+
+    /// This mask, which lies within `outer`, with each corner that a rounded corner of `outer`
+    /// would cut into rounded just enough to stay within it, so that clipping to this mask also
+    /// clips to `outer`. Returns `None` when a corner would need a larger radius than the mask's
+    /// size allows, in which case only an offscreen layer can clip to both.
+    pub(crate) fn rounded_within(&self, outer: &Self) -> Option<Self> {
+        let corner_radius = |corner: Point<Pixels>,
+                             outer_corner: Point<Pixels>,
+                             radius: Pixels,
+                             outer_radius: Pixels| {
+            // NOTE(mdeand): A corner inset by `dx` and `dy` stays within a rounded corner of
+            // NOTE(mdeand): radius `r` once its own circle is, which takes a radius of at least
+            // NOTE(mdeand): `r - dx - dy - sqrt(2 dx dy)`. Its curve then touches that corner's.
+            let dx = (corner.x - outer_corner.x).abs().0;
+            let dy = (corner.y - outer_corner.y).abs().0;
+            radius.max(px(outer_radius.0 - dx - dy - (2. * dx * dy).sqrt()))
+        };
+        let corner_radii = Corners {
+            top_left: corner_radius(
+                self.bounds.origin,
+                outer.bounds.origin,
+                self.corner_radii.top_left,
+                outer.corner_radii.top_left,
+            ),
+            top_right: corner_radius(
+                self.bounds.top_right(),
+                outer.bounds.top_right(),
+                self.corner_radii.top_right,
+                outer.corner_radii.top_right,
+            ),
+            bottom_right: corner_radius(
+                self.bounds.bottom_right(),
+                outer.bounds.bottom_right(),
+                self.corner_radii.bottom_right,
+                outer.corner_radii.bottom_right,
+            ),
+            bottom_left: corner_radius(
+                self.bounds.bottom_left(),
+                outer.bounds.bottom_left(),
+                self.corner_radii.bottom_left,
+                outer.corner_radii.bottom_left,
+            ),
+        };
+        if corner_radii
+            .clone()
+            .clamp_radii_for_quad_size(self.bounds.size)
+            != corner_radii
+        {
+            return None;
         }
+        Some(ContentMask {
+            bounds: self.bounds,
+            corner_radii,
+        })
+    }
+
+    /// The outline of the rounded rectangle the mask clips to.
+    fn path(&self) -> Result<Path<Pixels>> {
+        let bounds = self.bounds;
+        let radii = self.corner_radii;
+        let mut builder = PathBuilder::fill();
+        let arc_to = |builder: &mut PathBuilder, radius: Pixels, to: Point<Pixels>| {
+            if radius > Pixels::ZERO {
+                builder.arc_to(point(radius, radius), Pixels::ZERO, false, true, to);
+            }
+        };
+        builder.move_to(point(bounds.left() + radii.top_left, bounds.top()));
+        builder.line_to(point(bounds.right() - radii.top_right, bounds.top()));
+        arc_to(
+            &mut builder,
+            radii.top_right,
+            point(bounds.right(), bounds.top() + radii.top_right),
+        );
+        builder.line_to(point(bounds.right(), bounds.bottom() - radii.bottom_right));
+        arc_to(
+            &mut builder,
+            radii.bottom_right,
+            point(bounds.right() - radii.bottom_right, bounds.bottom()),
+        );
+        builder.line_to(point(bounds.left() + radii.bottom_left, bounds.bottom()));
+        arc_to(
+            &mut builder,
+            radii.bottom_left,
+            point(bounds.left(), bounds.bottom() - radii.bottom_left),
+        );
+        builder.line_to(point(bounds.left(), bounds.top() + radii.top_left));
+        arc_to(
+            &mut builder,
+            radii.top_left,
+            point(bounds.left() + radii.top_left, bounds.top()),
+        );
+        builder.close();
+        builder.build()
     }
+}
 
+impl<P> ContentMask<P>
+where
+    P: Add<P, Output = P>
+        + Sub<P, Output = P>
+        + Div<f32, Output = P>
+        + Ord
+        + Copy
+        + Debug
+        + Default
+        + PartialEq,
+{
     /// Intersect the content mask with the given content mask.
+    ///
+    /// Each corner of the intersection keeps the radius of whichever mask it came from, so nested
+    /// rounded masks clip to the tighter of the two corners.
     pub fn intersect(&self, other: &Self) -> Self {
         let bounds = self.bounds.intersect(&other.bounds);
-        ContentMask { bounds }
+        let corner_radius = |corner: Point<P>,
+                             corner_of: fn(&Bounds<P>) -> Point<P>,
+                             self_radius: P,
+                             other_radius: P| {
+            let mut radius = P::default();
+            if corner == corner_of(&self.bounds) {
+                radius = radius.max(self_radius);
+            }
+            if corner == corner_of(&other.bounds) {
+                radius = radius.max(other_radius);
+            }
+            radius
+        };
+        let corner_radii = Corners {
+            top_left: corner_radius(
+                bounds.origin,
+                |bounds| bounds.origin,
+                self.corner_radii.top_left,
+                other.corner_radii.top_left,
+            ),
+            top_right: corner_radius(
+                bounds.top_right(),
+                Bounds::top_right,
+                self.corner_radii.top_right,
+                other.corner_radii.top_right,
+            ),
+            bottom_right: corner_radius(
+                bounds.bottom_right(),
+                Bounds::bottom_right,
+                self.corner_radii.bottom_right,
+                other.corner_radii.bottom_right,
+            ),
+            bottom_left: corner_radius(
+                bounds.bottom_left(),
+                Bounds::bottom_left,
+                self.corner_radii.bottom_left,
+                other.corner_radii.bottom_left,
+            ),
+        }
+        .clamp_radii_for_quad_size(bounds.size);
+        ContentMask {
+            bounds,
+            corner_radii,
+        }
+    }
+
+    /// Whether the mask has any rounded corners.
+    pub fn is_rounded(&self) -> bool {
+        self.corner_radii.top_left > P::default()
+            || self.corner_radii.top_right > P::default()
+            || self.corner_radii.bottom_right > P::default()
+            || self.corner_radii.bottom_left > P::default()
     }
 }
 
@@ -1996,6 +2228,11 @@ impl Window {
             self.rendered_frame.input_handlers.push(Some(input_handler));
         }
         if !cx.mode.skip_drawing() {
+            // NOTE(mdeand): Compacting the atlas moves tiles, which the primitives of the views
+            // NOTE(mdeand): painted before refer to, so none of them can be reused.
+            if self.sprite_atlas.compact() {
+                self.refreshing = true;
+            }
             self.draw_roots(cx);
         }
         self.dirty_views.clear();
@@ -2469,7 +2706,36 @@ impl Window {
     ) -> R {
         self.invalidator.debug_assert_paint_or_prepaint();
         if let Some(mask) = mask {
-            let mask = mask.intersect(&self.content_mask());
+            let parent_mask = self.content_mask();
+            let mask = mask.intersect(&parent_mask);
+
+            // NOTE(mdeand): A mask only keeps the corners it shares with the mask it's within, so
+            // NOTE(mdeand): its other corners are rounded to stay within that one's, and the
+            // NOTE(mdeand): shaders clip to both. Only corners too small for that are clipped in
+            // NOTE(mdeand): an offscreen layer instead.
+            let mask = if parent_mask.is_rounded() {
+                match mask.rounded_within(&parent_mask) {
+                    Some(mask) => mask,
+                    None if self.invalidator.painting() => match parent_mask.path() {
+                        Ok(path) => {
+                            return self.with_clip_path(path, |window| {
+                                window.content_mask_stack.push(mask);
+                                let result = f(window);
+                                window.content_mask_stack.pop();
+                                result
+                            });
+                        }
+                        Err(error) => {
+                            log::error!("failed to build a content mask's path: {error:?}");
+                            mask
+                        }
+                    },
+                    None => mask,
+                }
+            } else {
+                mask
+            };
+
             self.content_mask_stack.push(mask);
             let result = f(self);
             self.content_mask_stack.pop();
@@ -2511,16 +2777,27 @@ impl Window {
         result
     }
 
+    /// Fades everything painted by the given function. If `isolate` is set or it's blended
+    /// with what's beneath it, it's painted into an isolated layer which is faded as a whole,
+    /// see [`Window::paint_isolated_layer`].
     pub(crate) fn with_element_opacity<R>(
         &mut self,
         opacity: Option<f32>,
+        isolate: bool,
+        blend_mode: BlendMode,
         f: impl FnOnce(&mut Self) -> R,
     ) -> R {
         self.invalidator.debug_assert_paint_or_prepaint();
 
+        if blend_mode != BlendMode::Normal {
+            return self.paint_isolated_layer(opacity.unwrap_or(1.0), blend_mode, f);
+        }
         let Some(opacity) = opacity else {
             return f(self);
         };
+        if isolate && opacity < 1.0 {
+            return self.paint_isolated_layer(opacity, blend_mode, f);
+        }
 
         let previous_opacity = self.element_opacity;
         self.element_opacity = previous_opacity * opacity;
@@ -2529,6 +2806,59 @@ impl Window {
         result
     }
 
+    /// Transforms everything painted by the given function, along with its hitboxes. The
+    /// transformation is in device pixels and applies on top of the current one. Within the
+    /// function, content masks are in the space the transformation maps from.
+    pub fn with_element_transformation<R>(
+        &mut self,
+        transformation: Option<TransformationMatrix>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        self.invalidator.debug_assert_paint_or_prepaint();
+
+        let Some(transformation) =
+            transformation.filter(|transformation| *transformation != TransformationMatrix::unit())
+        else {
+            return f(self);
+        };
+
+        let scale_factor = self.scale_factor();
+        let screen_content_mask = self.scaled_content_mask();
+        let matrix = self.element_transformation().compose(transformation);
+        // NOTE(mdeand): Content masks are axis aligned, so within a rotated element the best
+        // NOTE(mdeand): we can do is the bounding box of the mask mapped back into its space.
+        // NOTE(mdeand): Primitives are still clipped to the exact mask on screen.
+        let local_content_mask = match matrix.inverse() {
+            Some(inverse) => {
+                let bounds = inverse.apply_to_bounds(&screen_content_mask.bounds);
+                ContentMask {
+                    bounds: Bounds {
+                        origin: point(
+                            px(bounds.origin.x.0 / scale_factor),
+                            px(bounds.origin.y.0 / scale_factor),
+                        ),
+                        size: size(
+                            px(bounds.size.width.0 / scale_factor),
+                            px(bounds.size.height.0 / scale_factor),
+                        ),
+                    },
+                    ..Default::default()
+                }
+            }
+            None => ContentMask::default(),
+        };
+
+        let previous_transformation = self.element_transformation.replace(ElementTransformation {
+            matrix,
+            content_mask: screen_content_mask,
+        });
+        self.content_mask_stack.push(local_content_mask);
+        let result = f(self);
+        self.content_mask_stack.pop();
+        self.element_transformation = previous_transformation;
+        result
+    }
+
     /// Perform prepaint on child elements in a "retryable" manner, so that any side effects
     /// of prepaints can be discarded before prepainting again. This is used to support autoscroll
     /// where we need to prepaint children to detect the autoscroll bounds, then adjust the
@@ -2628,6 +2958,33 @@ impl Window {
         self.element_opacity
     }
 
+    /// Obtain the current element transformation, in device pixels. This method should only be
+    /// called during element drawing.
+    pub(crate) fn element_transformation(&self) -> TransformationMatrix {
+        self.invalidator.debug_assert_paint_or_prepaint();
+        self.element_transformation
+            .as_ref()
+            .map_or_else(TransformationMatrix::unit, |transformation| {
+                transformation.matrix
+            })
+    }
+
+    /// The current content mask in device pixels on screen, which is what primitives are
+    /// clipped to.
+    fn scaled_content_mask(&self) -> ContentMask<ScaledPixels> {
+        let content_mask = self.content_mask().scale(self.scale_factor());
+        match &self.element_transformation {
+            // NOTE(mdeand): Rounded corners don't survive an arbitrary transformation, so only the
+            // NOTE(mdeand): mask in effect when the element was transformed keeps its radii.
+            Some(transformation) => ContentMask {
+                bounds: transformation.matrix.apply_to_bounds(&content_mask.bounds),
+                ..Default::default()
+            }
+            .intersect(&transformation.content_mask),
+            None => content_mask,
+        }
+    }
+
     /// Obtain the current content mask. This method should only be called during element drawing.
     pub fn content_mask(&self) -> ContentMask<Pixels> {
         self.invalidator.debug_assert_paint_or_prepaint();
@@ -2639,6 +2996,7 @@ impl Window {
                     origin: Point::default(),
                     size: self.viewport_size,
                 },
+                ..Default::default()
             })
     }
 
@@ -2862,12 +3220,13 @@ impl Window {
         self.invalidator.debug_assert_paint();
 
         let scale_factor = self.scale_factor();
-        let content_mask = self.content_mask();
-        let clipped_bounds = bounds.intersect(&content_mask.bounds);
+        let content_mask = self.scaled_content_mask();
+        let clipped_bounds = self
+            .element_transformation()
+            .apply_to_bounds(&bounds.scale(scale_factor))
+            .intersect(&content_mask.bounds);
         if !clipped_bounds.is_empty() {
-            self.next_frame
-                .scene
-                .push_layer(clipped_bounds.scale(scale_factor));
+            self.next_frame.scene.push_layer(clipped_bounds, None);
         }
 
         let result = f(self);
@@ -2879,6 +3238,105 @@ impl Window {
         result
     }
 
+    /// Paints everything painted by the given function into an offscreen layer, which is
+    /// composited onto what's beneath it at once with the given opacity and blend mode, like a
+    /// CSS stacking context. Unlike fading each primitive on its own, overlapping children
+    /// don't show through each other.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_isolated_layer<R>(
+        &mut self,
+        opacity: f32,
+        blend_mode: BlendMode,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        self.invalidator.debug_assert_paint();
+
+        let isolation = LayerIsolation {
+            opacity: self.element_opacity * opacity,
+            blend_mode,
+            mask: None,
+            blur_radius: ScaledPixels(0.),
+        };
+        let bounds = self.scaled_content_mask().bounds;
+        self.paint_layer_with_isolation(bounds, isolation, f)
+    }
+
+    /// Paints everything painted by the given function into an offscreen layer, which is
+    /// blurred with the given radius as a whole before it's composited onto what's beneath it,
+    /// like the CSS `filter: blur()` property. The blur bleeds up to three times the radius past
+    /// the given bounds, beyond which the layer is clipped.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_blurred_layer<R>(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        radius: Pixels,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        self.invalidator.debug_assert_paint();
+
+        if radius <= Pixels::ZERO {
+            return f(self);
+        }
+
+        let scale_factor = self.scale_factor();
+        let blur_radius = radius.scale(scale_factor);
+        let bounds = self
+            .element_transformation()
+            .apply_to_bounds(&bounds.scale(scale_factor))
+            .dilate(blur_radius * 3.)
+            .intersect(&self.scaled_content_mask().bounds);
+        let isolation = LayerIsolation {
+            opacity: self.element_opacity,
+            blend_mode: BlendMode::Normal,
+            mask: None,
+            blur_radius,
+        };
+        self.paint_layer_with_isolation(bounds, isolation, f)
+    }
+
+    /// Paint the contents of the given function clipped to the given path, which is positioned
+    /// like the paths passed to [`Self::paint_path`]. The contents are painted into an offscreen
+    /// layer, so prefer [`ContentMask`]s with rounded corners where they're enough.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn with_clip_path<R>(&mut self, path: Path<Pixels>, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.invalidator.debug_assert_paint();
+
+        let mut mask = self.scale_path(path);
+        mask.color = white().into();
+        let isolation = LayerIsolation {
+            opacity: self.element_opacity,
+            blend_mode: BlendMode::Normal,
+            mask: Some(mask),
+            blur_radius: ScaledPixels(0.),
+        };
+        let bounds = self.scaled_content_mask().bounds;
+        self.paint_layer_with_isolation(bounds, isolation, f)
+    }
+
+    /// Paints the contents of the given function into an isolated layer, whose primitives may
+    /// cover the given bounds.
+    fn paint_layer_with_isolation<R>(
+        &mut self,
+        bounds: Bounds<ScaledPixels>,
+        isolation: LayerIsolation,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        self.next_frame.scene.push_layer(bounds, Some(isolation));
+
+        // NOTE(mdeand): The layer applies the opacity when it's composited, so what is painted
+        // NOTE(mdeand): into it must be opaque.
+        let previous_opacity = self.element_opacity;
+        self.element_opacity = 1.0;
+        let result = f(self);
+        self.element_opacity = previous_opacity;
+
+        self.next_frame.scene.pop_layer();
+        result
+    }
+
     /// Paint one or more drop shadows into the scene for the next frame at the current z-index.
     ///
     /// This method should only be called as part of the paint phase of element drawing.
@@ -2891,21 +3349,59 @@ impl Window {
         self.invalidator.debug_assert_paint();
 
         let scale_factor = self.scale_factor();
-        let content_mask = self.content_mask();
+        let content_mask = self.scaled_content_mask();
         let opacity = self.element_opacity();
+        let transformation = self.element_transformation();
         for shadow in shadows {
             let shadow_bounds = (bounds + shadow.offset).dilate(shadow.spread_radius);
             self.next_frame.scene.insert_primitive(Shadow {
                 order: 0,
                 blur_radius: shadow.blur_radius.scale(scale_factor),
                 bounds: shadow_bounds.scale(scale_factor),
-                content_mask: content_mask.scale(scale_factor),
+                content_mask: content_mask.clone(),
                 corner_radii: corner_radii.scale(scale_factor),
                 color: shadow.color.opacity(opacity),
+                transformation,
             });
         }
     }
 
+    /// Paint a blur of everything painted so far within the given bounds into the scene for the
+    /// next frame at the current z-index. Painting it before an element's background gives a
+    /// backdrop blur. Use [`Self::paint_blurred_layer`] to blur the element itself.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_blur(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        corner_radii: Corners<Pixels>,
+        radius: Pixels,
+    ) {
+        self.invalidator.debug_assert_paint();
+
+        if radius <= Pixels::ZERO {
+            return;
+        }
+
+        // NOTE(mdeand): Blurs are drawn axis aligned, so a transformed one covers the bounding
+        // NOTE(mdeand): box of its bounds on screen.
+        let scale_factor = self.scale_factor();
+        let content_mask = self.scaled_content_mask();
+        let transformation = self.element_transformation();
+        let corner_radii = if transformation == TransformationMatrix::unit() {
+            corner_radii.scale(scale_factor)
+        } else {
+            Corners::default()
+        };
+        self.next_frame.scene.insert_primitive(Blur {
+            order: 0,
+            radius: radius.scale(scale_factor),
+            bounds: transformation.apply_to_bounds(&bounds.scale(scale_factor)),
+            corner_radii,
+            content_mask,
+        });
+    }
+
     /// Paint one or more quads into the scene for the next frame at the current stacking context.
     /// Quads are colored rectangular regions with an optional background, border, and corner radius.
     /// see [`fill`], [`outline`], and [`quad`] to construct this type.
@@ -2919,17 +3415,23 @@ impl Window {
         self.invalidator.debug_assert_paint();
 
         let scale_factor = self.scale_factor();
-        let content_mask = self.content_mask();
+        let content_mask = self.scaled_content_mask();
         let opacity = self.element_opacity();
+        let transformation = self.element_transformation();
+        let background = self
+            .next_frame
+            .scene
+            .push_background(&quad.background.opacity(opacity));
         self.next_frame.scene.insert_primitive(Quad {
             order: 0,
             bounds: quad.bounds.scale(scale_factor),
-            content_mask: content_mask.scale(scale_factor),
-            background: quad.background.opacity(opacity),
+            content_mask,
+            background,
             border_color: quad.border_color.opacity(opacity),
             corner_radii: quad.corner_radii.scale(scale_factor),
             border_widths: quad.border_widths.scale(scale_factor),
             border_style: quad.border_style,
+            transformation,
         });
     }
 
@@ -2939,15 +3441,34 @@ impl Window {
     pub fn paint_path(&mut self, mut path: Path<Pixels>, color: impl Into<Background>) {
         self.invalidator.debug_assert_paint();
 
-        let scale_factor = self.scale_factor();
-        let content_mask = self.content_mask();
         let opacity = self.element_opacity();
-        path.content_mask = content_mask;
         let color: Background = color.into();
-        path.color = color.opacity(opacity);
-        self.next_frame
+        path.color = self
+            .next_frame
             .scene
-            .insert_primitive(path.scale(scale_factor));
+            .push_background(&color.opacity(opacity));
+        let path = self.scale_path(path);
+        self.next_frame.scene.insert_primitive(path);
+    }
+
+    /// Scales the path into device pixels on screen, clipped to the current content mask.
+    fn scale_path(&self, path: Path<Pixels>) -> Path<ScaledPixels> {
+        let transformation = self.element_transformation();
+        let mut path = path.scale(self.scale_factor());
+        path.content_mask = self.scaled_content_mask();
+        // NOTE(mdeand): Paths are rasterized from their vertices, so they're transformed here
+        // NOTE(mdeand): rather than on the GPU.
+        if transformation != TransformationMatrix::unit() {
+            for vertex in &mut path.vertices {
+                let position = transformation.apply(point(
+                    px(vertex.xy_position.x.0),
+                    px(vertex.xy_position.y.0),
+                ));
+                vertex.xy_position = point(ScaledPixels(position.x.0), ScaledPixels(position.y.0));
+            }
+            path.bounds = transformation.apply_to_bounds(&path.bounds);
+        }
+        path
     }
 
     /// Paint an underline into the scene for the next frame at the current z-index.
@@ -2971,17 +3492,19 @@ impl Window {
             origin,
             size: size(width, height),
         };
-        let content_mask = self.content_mask();
+        let content_mask = self.scaled_content_mask();
         let element_opacity = self.element_opacity();
+        let transformation = self.element_transformation();
 
         self.next_frame.scene.insert_primitive(Underline {
             order: 0,
             pad: 0,
             bounds: bounds.scale(scale_factor),
-            content_mask: content_mask.scale(scale_factor),
+            content_mask,
             color: style.color.unwrap_or_default().opacity(element_opacity),
             thickness: style.thickness.scale(scale_factor),
             wavy: if style.wavy { 1 } else { 0 },
+            transformation,
         });
     }
 
@@ -3002,17 +3525,19 @@ impl Window {
             origin,
             size: size(width, height),
         };
-        let content_mask = self.content_mask();
+        let content_mask = self.scaled_content_mask();
         let opacity = self.element_opacity();
+        let transformation = self.element_transformation();
 
         self.next_frame.scene.insert_primitive(Underline {
             order: 0,
             pad: 0,
             bounds: bounds.scale(scale_factor),
-            content_mask: content_mask.scale(scale_factor),
+            content_mask,
             thickness: style.thickness.scale(scale_factor),
             color: style.color.unwrap_or_default().opacity(opacity),
             wavy: 0,
+            transformation,
         });
     }
 
@@ -3064,7 +3589,8 @@ impl Window {
                 origin: glyph_origin.map(|px| px.floor()) + raster_bounds.origin.map(Into::into),
                 size: tile.bounds.size.map(Into::into),
             };
-            let content_mask = self.content_mask().scale(scale_factor);
+            let content_mask = self.scaled_content_mask();
+            let transformation = self.element_transformation();
             self.next_frame.scene.insert_primitive(MonochromeSprite {
                 order: 0,
                 pad: 0,
@@ -3072,7 +3598,7 @@ impl Window {
                 content_mask,
                 color: color.opacity(element_opacity),
                 tile,
-                transformation: TransformationMatrix::unit(),
+                transformation,
             });
         }
         Ok(())
@@ -3121,8 +3647,9 @@ impl Window {
                 origin: glyph_origin.map(|px| px.floor()) + raster_bounds.origin.map(Into::into),
                 size: tile.bounds.size.map(Into::into),
             };
-            let content_mask = self.content_mask().scale(scale_factor);
+            let content_mask = self.scaled_content_mask();
             let opacity = self.element_opacity();
+            let transformation = self.element_transformation();
 
             self.next_frame.scene.insert_primitive(PolychromeSprite {
                 order: 0,
@@ -3133,6 +3660,7 @@ impl Window {
                 content_mask,
                 tile,
                 opacity,
+                transformation,
             });
         }
         Ok(())
@@ -3175,7 +3703,7 @@ impl Window {
         else {
             return Ok(());
         };
-        let content_mask = self.content_mask().scale(scale_factor);
+        let content_mask = self.scaled_content_mask();
         let svg_bounds = Bounds {
             origin: bounds.center()
                 - Point::new(
@@ -3197,7 +3725,7 @@ impl Window {
             content_mask,
             color: color.opacity(element_opacity),
             tile,
-            transformation,
+            transformation: self.element_transformation().compose(transformation),
         });
 
         Ok(())
@@ -3236,9 +3764,10 @@ impl Window {
                 )))
             })?
             .expect("Callback above only returns Some");
-        let content_mask = self.content_mask().scale(scale_factor);
+        let content_mask = self.scaled_content_mask();
         let corner_radii = corner_radii.scale(scale_factor);
         let opacity = self.element_opacity();
+        let transformation = self.element_transformation();
 
         self.next_frame.scene.insert_primitive(PolychromeSprite {
             order: 0,
@@ -3251,6 +3780,7 @@ impl Window {
             corner_radii,
             tile,
             opacity,
+            transformation,
         });
         Ok(())
     }
@@ -3271,8 +3801,10 @@ impl Window {
 
         if !std::env::var("GPUI_BENCHMARK").is_ok() {
             let scale_factor = self.scale_factor();
-            let bounds = bounds.scale(scale_factor);
-            let content_mask = self.content_mask().scale(scale_factor);
+            let bounds = self
+                .element_transformation()
+                .apply_to_bounds(&bounds.scale(scale_factor));
+            let content_mask = self.scaled_content_mask();
             self.next_frame.scene.insert_primitive(PaintSurface {
                 order: 0,
                 bounds,
@@ -3282,6 +3814,36 @@ impl Window {
         }
     }
 
+    /// Paint a quad into the scene for the next frame at the current z-index, whose pixels are
+    /// shaded by the given WGSL source. See [`crate::shader_quad`] for what the source has to
+    /// define. Uniforms beyond
+    /// [`MAX_SHADER_QUAD_UNIFORMS`](crate::MAX_SHADER_QUAD_UNIFORMS) are ignored.
+    ///
+    /// This method should only be called as part of the paint phase of element drawing.
+    pub fn paint_shader_quad(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        source: SharedString,
+        uniforms: &[f32],
+    ) {
+        use crate::scene::{PaintShaderQuad, ShaderQuadSource, shader_quad_uniforms};
+
+        self.invalidator.debug_assert_paint();
+
+        let scale_factor = self.scale_factor();
+        let bounds = self
+            .element_transformation()
+            .apply_to_bounds(&bounds.scale(scale_factor));
+        let content_mask = self.scaled_content_mask();
+        self.next_frame.scene.insert_primitive(PaintShaderQuad {
+            order: 0,
+            bounds,
+            content_mask,
+            source: ShaderQuadSource::new(source),
+            uniforms: shader_quad_uniforms(uniforms),
+        });
+    }
+
     /// Create a double-buffered WGPU surface handle for external GPU rendering.
     ///
     /// Returns `None` on platforms that don't use the WGPU renderer.
@@ -3407,6 +3969,9 @@ impl Window {
         self.invalidator.debug_assert_prepaint();
 
         let content_mask = self.content_mask();
+        let transformation = self
+            .element_transformation()
+            .scale_translation(1.0 / self.scale_factor());
         let mut id = self.next_hitbox_id;
         self.next_hitbox_id = self.next_hitbox_id.next();
         let hitbox = Hitbox {
@@ -3414,6 +3979,7 @@ impl Window {
             bounds,
             content_mask,
             behavior,
+            transformation,
         };
         self.next_frame.hitboxes.push(hitbox.clone());
         hitbox
@@ -4537,6 +5103,60 @@ impl Window {
         self.platform_window.gpu_specs()
     }
 
+    /// Changes how the window's frames are presented to the display, e.g. to trade power
+    /// usage for latency while the user is drawing.
+    pub fn set_present_mode(&self, present_mode: crate::PresentMode) {
+        self.platform_window.set_present_mode(present_mode);
+    }
+
+    /// Returns what it took to draw the window's last measured frame: the batches drawn for
+    /// each kind of primitive, the bytes of instance data and atlas uploads, and how long the
+    /// GPU took when `GPUI_GPU_TIMING` is set and the device supports timestamp queries.
+    ///
+    /// GPU times are read back a frame or more after the frame was drawn. Returns `None`
+    /// on platforms that don't measure their frames.
+    pub fn last_frame_stats(&self) -> Option<crate::FrameStats> {
+        self.platform_window.last_frame_stats()
+    }
+
+    /// Limits how many bytes of GPU memory the window's sprite atlas keeps for rasterized
+    /// glyphs, icons and images, or lifts the limit when `None`. The tiles that weren't drawn
+    /// for the longest time are evicted once another texture would exceed it, and rasterized
+    /// again when they're painted. Atlases are limited to 256 MiB by default.
+    pub fn set_atlas_budget(&self, budget: Option<u64>) {
+        self.sprite_atlas.set_budget(budget);
+    }
+
+    /// Returns how much memory the window's sprite atlas uses, or `None` on platforms that
+    /// don't keep track.
+    pub fn atlas_stats(&self) -> Option<crate::AtlasStats> {
+        self.sprite_atlas.stats()
+    }
+
+    /// Captures the contents of the window's last rendered frame as an image.
+    ///
+    /// The frame is rendered again offscreen, so this works for windows that are
+    /// hidden, occluded or headless. Rendering is submitted before this returns, but
+    /// the GPU is waited on in the background. Fails on platforms that can't read back
+    /// what they render.
+    pub fn capture_frame(&self, cx: &App) -> Task<Result<image::RgbaImage>> {
+        let Some(receiver) = self
+            .platform_window
+            .capture_frame(&self.rendered_frame.scene)
+        else {
+            return Task::ready(Err(anyhow!(
+                "capturing frames is not supported on this platform"
+            )));
+        };
+        cx.background_spawn(async move { receiver.await? })
+    }
+
+    /// Returns the primitives of the window's last rendered frame, for debugging or for
+    /// diffing against another frame.
+    pub fn scene_dump(&self) -> crate::SceneDump {
+        crate::SceneDump::new(&self.rendered_frame.scene)
+    }
+
     /// Perform titlebar double-click action.
     /// This is macOS specific.
     pub fn titlebar_double_click(&self) {
@@ -5272,3 +5892,117 @@ pub fn outline(
         border_style,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClipPath, PrimitiveDump, TestAppContext, div, red};
+
+    fn rounded_mask() -> ContentMask<Pixels> {
+        ContentMask {
+            bounds: Bounds::new(point(px(0.), px(0.)), size(px(100.), px(100.))),
+            corner_radii: Corners::all(px(20.)),
+        }
+    }
+
+    fn inset_mask(inset: f32, corner_radius: f32) -> ContentMask<Pixels> {
+        ContentMask {
+            bounds: Bounds::new(
+                point(px(inset), px(inset)),
+                size(px(100. - 2. * inset), px(100. - 2. * inset)),
+            ),
+            corner_radii: Corners::all(px(corner_radius)),
+        }
+        .intersect(&rounded_mask())
+    }
+
+    #[test]
+    fn test_inset_masks_are_rounded_within_rounded_corners() {
+        let parent = rounded_mask();
+        let rounded = inset_mask(4., 0.).rounded_within(&parent).unwrap();
+        // NOTE(mdeand): 20 - 4 - 4 - sqrt(2 * 4 * 4)
+        let radius = 20. - 8. - 32f32.sqrt();
+        assert!((rounded.corner_radii.top_left.0 - radius).abs() < 1e-4);
+        assert_eq!(rounded.bounds, inset_mask(4., 0.).bounds);
+
+        // NOTE(mdeand): Past the curve of the corner, or rounded like it, nothing changes.
+        let past = inset_mask(10., 0.);
+        assert_eq!(past.rounded_within(&parent), Some(past));
+        let round = inset_mask(4., 16.);
+        assert_eq!(round.rounded_within(&parent), Some(round));
+
+        let flush = ContentMask::new(Bounds::new(point(px(0.), px(0.)), size(px(50.), px(100.))))
+            .intersect(&parent);
+        assert_eq!(flush.corner_radii.top_left, px(20.));
+        assert_eq!(flush.rounded_within(&parent), Some(flush));
+
+        let small = ContentMask::new(Bounds::new(point(px(2.), px(2.)), size(px(6.), px(6.))));
+        assert_eq!(small.rounded_within(&parent), None);
+    }
+
+    fn inset_view(inset: f32, inner_size: f32) -> impl Render {
+        struct Inset(f32, f32);
+
+        impl Render for Inset {
+            fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+                div()
+                    .size(px(100.))
+                    .rounded(px(20.))
+                    .overflow_hidden()
+                    .p(px(self.0))
+                    .child(
+                        div()
+                            .size(px(self.1))
+                            .overflow_hidden()
+                            .child(div().size_full().bg(red())),
+                    )
+            }
+        }
+
+        Inset(inset, inner_size)
+    }
+
+    #[gpui::test]
+    fn test_inset_masks_are_clipped_to_rounded_corners_in_the_shaders(cx: &mut TestAppContext) {
+        let (_, cx) = cx.add_window_view(|_, _| inset_view(4., 92.));
+        let dump = cx.scene_dump();
+        assert!(
+            !dump
+                .primitives
+                .iter()
+                .any(|primitive| matches!(primitive, PrimitiveDump::Layer { .. }))
+        );
+        assert!(dump.primitives.iter().any(|primitive| matches!(
+            primitive,
+            PrimitiveDump::Quad { content_mask_corner_radii: [radius, ..], .. } if *radius > 0.
+        )));
+    }
+
+    #[gpui::test]
+    fn test_masks_too_small_to_round_are_clipped_in_a_layer(cx: &mut TestAppContext) {
+        let (_, cx) = cx.add_window_view(|_, _| inset_view(2., 6.));
+        let dump = cx.scene_dump();
+        assert!(dump.primitives.iter().any(|primitive| matches!(
+            primitive,
+            PrimitiveDump::Layer { mask: Some(_), primitives, .. } if primitives.len() == 1
+        )));
+    }
+
+    #[gpui::test]
+    fn test_clip_paths_clip_elements_in_a_layer(cx: &mut TestAppContext) {
+        struct Clipped;
+
+        impl Render for Clipped {
+            fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+                div().size(px(100.)).clip_path(ClipPath::Ellipse).bg(red())
+            }
+        }
+
+        let (_, cx) = cx.add_window_view(|_, _| Clipped);
+        let dump = cx.scene_dump();
+        assert!(dump.primitives.iter().any(|primitive| matches!(
+            primitive,
+            PrimitiveDump::Layer { mask: Some(_), primitives, .. } if primitives.len() == 1
+        )));
+    }
+}